@@ -0,0 +1,43 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+// Files of a directory, sorted so that the generated table is stable
+fn sorted_entries(dir: &Path) -> Vec<PathBuf> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir).map(|d| d.filter_map(|e| e.ok().map(|e| e.path())).collect()).unwrap_or_default();
+    entries.sort();
+    entries
+}
+
+// Generate the table of the references embedded with the embedded-references
+// feature, from the `embedded_references/<api_level>/c_sdk_build_<device>.<artifact>`
+// files. Adding a release is a matter of adding its directory. The table is
+// empty without the feature.
+fn main() {
+    let dir = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("embedded_references");
+    println!("cargo:rerun-if-changed={}", dir.display());
+
+    let mut table = String::from("pub static EMBEDDED_REFERENCES: &[EmbeddedReference] = &[\n");
+    if env::var_os("CARGO_FEATURE_EMBEDDED_REFERENCES").is_some() {
+        for level_dir in sorted_entries(&dir).into_iter().filter(|p| p.is_dir()) {
+            let api_level = level_dir.file_name().unwrap().to_string_lossy().into_owned();
+            for file in sorted_entries(&level_dir) {
+                let name = file.file_name().unwrap().to_string_lossy().into_owned();
+                let Some((device, artifact)) = name.strip_prefix("c_sdk_build_").and_then(|n| n.split_once('.')) else {
+                    panic!("unexpected embedded reference {}, expected c_sdk_build_<device>.<artifact>", file.display());
+                };
+                table.push_str(&format!(
+                    "    EmbeddedReference {{ api_level: {:?}, device: {:?}, artifact: {:?}, contents: include_str!({:?}) }},\n",
+                    api_level,
+                    device,
+                    artifact,
+                    file.display().to_string()
+                ));
+            }
+        }
+    }
+    table.push_str("];\n");
+    let out = Path::new(&env::var("OUT_DIR").unwrap()).join("embedded_references.rs");
+    fs::write(out, table).unwrap();
+}
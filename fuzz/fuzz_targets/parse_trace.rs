@@ -0,0 +1,25 @@
+#![no_main]
+
+use cbpx::{
+    files::{parse_defines, render_defines},
+    identifier::is_valid_define_name,
+    trace::{command_word, define_overrides, find_compile_lines, parse_compile_line, parse_includes, parse_source},
+};
+use libfuzzer_sys::fuzz_target;
+
+// Arbitrary make output must not panic the trace parser, and every define it
+// yields with a valid name must read back the same from its .defines line
+fuzz_target!(|trace: &[u8]| {
+    let _ = find_compile_lines(trace, false);
+    let Ok(lines) = find_compile_lines(trace, true) else {
+        return;
+    };
+    for line in &lines {
+        let _ = (command_word(line), parse_includes(line), parse_source(line), define_overrides(line));
+        let (defines, _) = parse_compile_line(line);
+        for define in defines.iter().filter(|d| is_valid_define_name(&d.name)) {
+            let rendered = render_defines(std::slice::from_ref(define));
+            assert_eq!(parse_defines(&rendered).as_deref(), Ok(std::slice::from_ref(define)), "{}", rendered);
+        }
+    }
+});
@@ -0,0 +1,66 @@
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+// API level declared by the app against the one of the SDK, with where each
+// value was read from
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiLevelCheck {
+    pub app: Option<String>,
+    pub app_source: Option<String>,
+    pub sdk: Option<String>,
+    pub sdk_source: Option<String>,
+}
+
+impl ApiLevelCheck {
+    // Only known values can mismatch
+    pub fn is_mismatch(&self) -> bool {
+        matches!((&self.app, &self.sdk), (Some(app), Some(sdk)) if app != sdk)
+    }
+
+    pub fn describe(&self) -> String {
+        let value = |v: &Option<String>, source: &Option<String>| match (v, source) {
+            (Some(v), Some(source)) => format!("{} (from {})", v, source),
+            _ => "unknown".to_string(),
+        };
+        format!("app API level {}, SDK API level {}", value(&self.app, &self.app_source), value(&self.sdk, &self.sdk_source))
+    }
+}
+
+// Value of a `NAME = value` assignment in a Makefile or TOML text, with
+// `:=`, `?=` and quotes accepted
+fn assignment(contents: &str, name: &str) -> Option<String> {
+    contents.lines().find_map(|line| {
+        let rest = line.trim().strip_prefix(name)?.trim_start();
+        let value = rest.strip_prefix(":=").or_else(|| rest.strip_prefix("?=")).or_else(|| rest.strip_prefix('='))?;
+        let value = value.split('#').next().unwrap_or("").trim().trim_matches('"');
+        Some(value.to_string()).filter(|v| !v.is_empty())
+    })
+}
+
+// API level declared by the app: the `api_level` of ledger_app.toml, or the
+// API_LEVEL variable assigned in its Makefile
+pub fn app_api_level(makefile: &Path, app_path: &Path) -> Option<(String, String)> {
+    let manifest = app_path.join("ledger_app.toml");
+    if let Some(level) = fs::read_to_string(&manifest).ok().and_then(|c| assignment(&c, "api_level")) {
+        return Some((level, manifest.display().to_string()));
+    }
+    let level = fs::read_to_string(makefile).ok().and_then(|c| assignment(&c, "API_LEVEL"))?;
+    Some((level, makefile.display().to_string()))
+}
+
+// API level of the SDK: its `api_level` file, or the API_LEVEL define
+// extracted from the trace
+pub fn sdk_api_level(sdk: &Path, define: Option<&str>) -> Option<(String, String)> {
+    let file = sdk.join("api_level");
+    if let Ok(contents) = fs::read_to_string(&file) {
+        return Some((contents.trim().to_string(), file.display().to_string()));
+    }
+    define.map(|d| (d.to_string(), "the API_LEVEL define".to_string()))
+}
+
+pub fn check_api_level(app: Option<(String, String)>, sdk: Option<(String, String)>) -> ApiLevelCheck {
+    let (app, app_source) = app.unzip();
+    let (sdk, sdk_source) = sdk.unzip();
+    ApiLevelCheck { app, app_source, sdk, sdk_source }
+}
@@ -0,0 +1,111 @@
+use std::{
+    fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+use regex::Regex;
+
+// Directories of build products, never copied: the point of the copy is not
+// to read them while the app is being built
+const SKIPPED_DIRS: [&str; 5] = [".git", "build", "bin", "debug", "target"];
+
+// Files needed with their contents at parse time: Makefiles and the icons
+// some rules convert when the Makefile is read
+fn is_parsed(name: &str) -> bool {
+    name.starts_with("Makefile")
+        || [".mk", ".toml", ".gif", ".png", ".bmp", ".py"].iter().any(|extension| name.ends_with(extension))
+}
+
+// Copy the skeleton of the app into `dest`: the files needed at parse time,
+// and an empty placeholder for every other file, as a dry run only checks
+// that the sources exist
+pub fn copy_app_skeleton(app: &Path, dest: &Path) -> io::Result<()> {
+    fs::create_dir_all(dest)?;
+    for entry in fs::read_dir(app)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let (path, target) = (entry.path(), dest.join(&name));
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            if !SKIPPED_DIRS.contains(&name.as_str()) {
+                copy_app_skeleton(&path, &target)?;
+            }
+        } else if is_parsed(&name) {
+            fs::copy(&path, &target)?;
+        } else {
+            fs::write(&target, "")?;
+        }
+    }
+    Ok(())
+}
+
+// Files make reported missing in its error output, relative to the
+// directory it ran in or absolute
+pub fn missing_files(stderr: &str) -> Vec<String> {
+    let patterns = [r"No rule to make target '([^']+)'", r"^(?:[^:\s]+:\d+: )?([^:\s]+): No such file or directory"];
+    let mut files = Vec::new();
+    for pattern in patterns {
+        let re = Regex::new(pattern).expect("valid pattern");
+        for line in stderr.lines() {
+            if let Some(file) = re.captures(line).map(|c| c[1].to_string()) {
+                if !files.contains(&file) {
+                    files.push(file);
+                }
+            }
+        }
+    }
+    files
+}
+
+// Copy a file or directory reported missing from the app into its copy.
+// Returns whether something was copied, paths outside of the app being left
+// alone.
+pub fn copy_missing(app: &Path, copy: &Path, make_dir: &Path, file: &str) -> io::Result<bool> {
+    let path = make_dir.join(file);
+    let Ok(relative) = path.strip_prefix(copy) else {
+        return Ok(false);
+    };
+    if relative.components().any(|c| !matches!(c, Component::Normal(_))) {
+        return Ok(false);
+    }
+    let (source, target) = (app.join(relative), copy.join(relative));
+    if source.is_dir() {
+        copy_dir(&source, &target)?;
+    } else if source.is_file() {
+        fs::create_dir_all(target.parent().unwrap_or(copy))?;
+        fs::copy(&source, &target)?;
+    } else {
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+fn copy_dir(source: &Path, target: &Path) -> io::Result<()> {
+    fs::create_dir_all(target)?;
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        let path: PathBuf = entry.path();
+        if entry.file_type()?.is_dir() {
+            copy_dir(&path, &target.join(entry.file_name()))?;
+        } else {
+            fs::copy(&path, target.join(entry.file_name()))?;
+        }
+    }
+    Ok(())
+}
+
+// Replace every occurrence of `from` in a trace which may not be valid UTF-8
+pub fn replace_bytes(bytes: &[u8], from: &[u8], to: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut rest = bytes;
+    while !from.is_empty() {
+        let Some(index) = rest.windows(from.len()).position(|w| w == from) else {
+            break;
+        };
+        out.extend_from_slice(&rest[..index]);
+        out.extend_from_slice(to);
+        rest = &rest[index + from.len()..];
+    }
+    out.extend_from_slice(rest);
+    out
+}
@@ -0,0 +1,91 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{files::Define, trace::shell_words};
+
+// Defines carrying the application flags, the first one found winning
+const FLAG_DEFINES: [&str; 2] = ["APP_FLAGS_APP_LOAD_PARAMS", "APP_FLAGS"];
+// Option of the ledgerblue load command and of install_params.py
+const LOAD_OPTION: &str = "--appFlags";
+
+// APPLICATION_FLAG_* bits of the OS, by name
+const FLAG_BITS: [(&str, u32); 14] = [
+    ("main", 0x1),
+    ("signed", 0x2),
+    ("issuer", 0x4),
+    ("bolos_ux", 0x8),
+    ("derive_master", 0x10),
+    ("shared_nvram", 0x20),
+    ("global_pin", 0x40),
+    ("debug", 0x80),
+    ("autoboot", 0x100),
+    ("bolos_settings", 0x200),
+    ("custom_ca", 0x400),
+    ("library", 0x800),
+    ("no_run", 0x1000),
+    ("enabled", 0x8000),
+];
+
+// Application flags of the app. All fields are empty when the flags could
+// not be located, which records them as unknown.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppFlags {
+    // Value in hexadecimal, e.g. `0x240`
+    #[serde(default)]
+    pub value: Option<String>,
+    // Where the value was found: `--appFlags` for the trace, or the define
+    #[serde(default)]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub flags: BTreeMap<String, bool>,
+    // Bits set without a known name, in hexadecimal
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unknown_bits: Option<String>,
+}
+
+impl AppFlags {
+    pub fn is_known(&self) -> bool {
+        self.value.is_some()
+    }
+}
+
+// `0x240` or `576`, possibly quoted or parenthesized
+fn parse_value(text: &str) -> Option<u32> {
+    let text = text.trim_matches(|c: char| c == '"' || c == '\'' || c == '\\' || c == '(' || c == ')' || c.is_whitespace());
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+pub fn decode_app_flags(value: u32, source: &str) -> AppFlags {
+    let known = FLAG_BITS.iter().fold(0, |mask, (_, bit)| mask | bit);
+    AppFlags {
+        value: Some(format!("{:#x}", value)),
+        source: Some(source.to_string()),
+        flags: FLAG_BITS.iter().map(|(name, bit)| (name.to_string(), value & bit != 0)).collect(),
+        unknown_bits: Some(value & !known).filter(|bits| *bits != 0).map(|bits| format!("{:#x}", bits)),
+    }
+}
+
+// Flags given to `--appFlags` on a line of the trace, or else by a define
+pub fn find_app_flags(trace: &str, defines: &[Define]) -> AppFlags {
+    for line in trace.lines().filter(|l| l.contains(LOAD_OPTION)) {
+        let words = shell_words(line);
+        let value = words.iter().enumerate().find_map(|(i, word)| match word.strip_prefix(LOAD_OPTION)? {
+            "" => words.get(i + 1).copied(),
+            rest => rest.strip_prefix('='),
+        });
+        if let Some(value) = value.and_then(parse_value) {
+            return decode_app_flags(value, LOAD_OPTION);
+        }
+    }
+    FLAG_DEFINES
+        .iter()
+        .find_map(|name| {
+            let define = defines.iter().find(|d| d.name == *name)?;
+            Some(decode_app_flags(parse_value(define.value.as_deref()?)?, name))
+        })
+        .unwrap_or_default()
+}
@@ -0,0 +1,75 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+// Files included by a Makefile line, for the `include`, `-include` and
+// `sinclude` directives
+fn included_files(line: &str) -> Vec<&str> {
+    let mut words = line.split_whitespace();
+    match words.next() {
+        Some("include" | "-include" | "sinclude") => words.collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn is_sdk_path(path: &str) -> bool {
+    path.contains("$(BOLOS_SDK)") || path.contains("${BOLOS_SDK}")
+}
+
+// Whether a Makefile includes one of the SDK, directly or through a file it
+// includes. Included files are followed one level deep and only when their
+// path is plain, without variables, relative paths resolving against the
+// directory of the Makefile as make runs there.
+pub fn includes_sdk_makefile(makefile: &Path) -> Result<bool, String> {
+    let contents =
+        fs::read_to_string(makefile).map_err(|e| format!("Failed to read {}: {}", makefile.display(), e))?;
+    let includes = contents.lines().flat_map(included_files).collect::<Vec<_>>();
+    if includes.iter().any(|path| is_sdk_path(path)) {
+        return Ok(true);
+    }
+    let dir = makefile.parent().unwrap_or(Path::new("."));
+    Ok(includes
+        .iter()
+        .filter(|path| !path.contains('$'))
+        .filter_map(|path| fs::read_to_string(dir.join(path)).ok())
+        .any(|contents| contents.lines().flat_map(included_files).any(is_sdk_path)))
+}
+
+// Substitute the SDK and target variables of an included path, None when it
+// keeps other variables make alone can resolve
+fn resolve_include(path: &str, bolos_sdk: &Path, target: &str) -> Option<String> {
+    let sdk = bolos_sdk.display().to_string();
+    let path = path
+        .replace("$(BOLOS_SDK)", &sdk)
+        .replace("${BOLOS_SDK}", &sdk)
+        .replace("$(TARGET)", target)
+        .replace("${TARGET}", target);
+    (!path.contains('$') && !path.contains(['*', '?', '['])).then_some(path)
+}
+
+// The Makefile and the files it includes, recursively, in the order they are
+// met. Paths are resolved with BOLOS_SDK and TARGET substituted, relative ones
+// against the directory make runs in. Includes of other variables and files
+// which don't exist, such as optional `-include` ones, are left out.
+pub fn makefile_includes(makefile: &Path, bolos_sdk: &Path, target: &str) -> Vec<PathBuf> {
+    let make_dir = makefile.parent().unwrap_or(Path::new("."));
+    let mut files = vec![makefile.to_path_buf()];
+    let mut next = 0;
+    while let Some(file) = files.get(next).cloned() {
+        next += 1;
+        let Ok(contents) = fs::read_to_string(&file) else {
+            continue;
+        };
+        for path in contents.lines().flat_map(included_files) {
+            let Some(path) = resolve_include(path, bolos_sdk, target) else {
+                continue;
+            };
+            let path = make_dir.join(path);
+            if path.is_file() && !files.contains(&path) {
+                files.push(path);
+            }
+        }
+    }
+    files
+}
@@ -0,0 +1,50 @@
+use regex::Regex;
+
+use crate::{
+    files::Define,
+    filter::{FilterEntry, FilterSet},
+    unit::{merge_units, parse_compile_unit, Strategy},
+};
+
+// Defines of the compile lines before any filtering, the first value of a
+// define winning
+pub fn raw_defines(compile_lines: &[String]) -> Vec<Define> {
+    let units = compile_lines.iter().map(|line| parse_compile_unit(line)).collect::<Vec<_>>();
+    merge_units(&units, Strategy::Union).0
+}
+
+// Filter entries matching none of the defines, which the SDK may no longer
+// emit
+pub fn stale_entries<'a>(filters: &'a FilterSet, defines: &[Define]) -> Vec<&'a FilterEntry> {
+    filters.entries.iter().filter(|e| !defines.iter().any(|d| filters.find(&d.name) == Some(*e))).collect()
+}
+
+// Why a define looks app-specific rather than SDK-intrinsic, None when it
+// doesn't. `app_name` is the value of APPNAME, which is kept on purpose.
+fn app_specific_reason(define: &Define, app_name: Option<&str>, version: &Regex) -> Option<&'static str> {
+    let value = define.value.as_deref()?;
+    if define.name == "APPNAME" {
+        return None;
+    }
+    let unquoted = value.trim_matches(|c| c == '"' || c == '\\' || c == '\'');
+    if app_name.is_some_and(|name| name == value) {
+        Some("same value as APPNAME")
+    } else if unquoted.starts_with('/') {
+        Some("absolute path")
+    } else if version.is_match(unquoted) {
+        Some("version string")
+    } else {
+        None
+    }
+}
+
+// Defines left unfiltered although they look app-specific, with the reason
+pub fn suspicious_defines<'a>(defines: &'a [Define], filters: &FilterSet) -> Vec<(&'a Define, &'static str)> {
+    let app_name = defines.iter().find(|d| d.name == "APPNAME").and_then(|d| d.value.as_deref());
+    let version = Regex::new(r"^v?\d+\.\d+(\.\d+)?([-+.][0-9A-Za-z.-]*)?$").expect("valid pattern");
+    defines
+        .iter()
+        .filter(|d| !filters.is_filtered(&d.name))
+        .filter_map(|d| Some((d, app_specific_reason(d, app_name, &version)?)))
+        .collect()
+}
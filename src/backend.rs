@@ -0,0 +1,333 @@
+use std::{
+    collections::BTreeMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use crate::{
+    app_copy::{copy_app_skeleton, copy_missing, missing_files, replace_bytes},
+    device::DeviceInfo,
+    host_build::compile_commands_trace,
+    makevars::{parse_query_output, query_arguments},
+    plan::{make_dir, makefile_args, MAKE_ARGS},
+    process::output_streaming,
+};
+
+// What a trace source is asked for: the trace of the app Makefile for a
+// device and an SDK
+pub struct TraceRequest<'a> {
+    pub device: &'a DeviceInfo,
+    pub app_path: &'a Path,
+    pub makefile: &'a Path,
+    pub bolos_sdk: &'a Path,
+    // Additional make arguments, such as variable assignments
+    pub make_args: &'a [String],
+}
+
+impl TraceRequest<'_> {
+    pub fn make_dir(&self) -> &Path {
+        make_dir(self.makefile)
+    }
+
+    pub fn makefile_args(&self) -> Vec<String> {
+        makefile_args(self.makefile)
+    }
+
+    pub fn env(&self) -> [(&'static str, String); 2] {
+        [("TARGET", self.device.target.to_string()), ("BOLOS_SDK", self.bolos_sdk.display().to_string())]
+    }
+}
+
+// Output of a trace source. A failed make is only reported when the trace has
+// no compile line, as make may fail after printing every compile command.
+#[derive(Debug)]
+pub struct Trace {
+    pub output: Vec<u8>,
+    pub failure: Option<String>,
+    // Standard error of make, for diagnostics
+    pub stderr: String,
+}
+
+// Source of the make trace. Sources are shared between the threads running
+// extractions, hence the Send and Sync bounds.
+pub trait TraceSource: fmt::Debug + Send + Sync {
+    // Produce the trace, calling `on_line` with every line as it is received
+    fn trace(&self, request: &TraceRequest, on_line: &mut dyn FnMut()) -> Result<Trace, String>;
+
+    // Final values of make variables, None for undefined ones
+    fn query_make_variables(
+        &self,
+        _request: &TraceRequest,
+        _names: &[String],
+    ) -> Result<BTreeMap<String, Option<String>>, String> {
+        Err("Capturing make variables is not supported by this trace source".to_string())
+    }
+
+    // Whether make runs with an explicit environment instead of inheriting
+    // the one of the caller
+    fn hermetic(&self) -> bool {
+        false
+    }
+
+    // Recorded trace the source reads rather than running make, an input of
+    // the extraction
+    fn recorded_trace(&self) -> Option<&Path> {
+        None
+    }
+}
+
+// Run a command producing a trace, `what` naming it in errors
+fn run_streaming(command: &mut Command, what: &str, on_line: &mut dyn FnMut()) -> Result<Trace, String> {
+    let output = output_streaming(command, |_| on_line()).map_err(|e| format!("Failed to execute {}: {}", what, e))?;
+    let failure = (!output.status.success()).then(|| {
+        format!("{} failed ({}): {}\n", what, output.status, String::from_utf8_lossy(&output.stderr).trim())
+    });
+    Ok(Trace { output: output.stdout, failure, stderr: String::from_utf8_lossy(&output.stderr).into_owned() })
+}
+
+// Run a make variable query, `what` naming the command in errors
+fn run_query(command: &mut Command, what: &str) -> Result<BTreeMap<String, Option<String>>, String> {
+    let output = command.output().map_err(|e| format!("Failed to execute {}: {}", what, e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} failed while querying variables ({}): {}",
+            what,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(parse_query_output(&output.stdout))
+}
+
+// Temporary directory of a single trace, `kind` naming its use. Every call
+// gets its own, concurrent extractions of the same device running in one
+// process.
+fn unique_temp_dir(kind: &str, request: &TraceRequest) -> PathBuf {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let call = NEXT.fetch_add(1, Ordering::Relaxed);
+    std::env::temp_dir().join(format!("cbpx-{}-{}-{}-{}", kind, request.device.target, std::process::id(), call))
+}
+
+// `make --trace --dry-run` run on this host
+#[derive(Debug, Clone, Default)]
+pub struct LocalMake {
+    // Only environment of make besides TARGET and BOLOS_SDK, the environment
+    // of the caller being inherited when None
+    pub hermetic_env: Option<BTreeMap<String, String>>,
+}
+
+impl LocalMake {
+    fn command(&self, request: &TraceRequest) -> Command {
+        let mut command = Command::new("make");
+        if let Some(env) = &self.hermetic_env {
+            command.env_clear().envs(env);
+        }
+        command.args(request.makefile_args()).current_dir(request.make_dir()).envs(request.env());
+        command
+    }
+}
+
+impl TraceSource for LocalMake {
+    fn trace(&self, request: &TraceRequest, on_line: &mut dyn FnMut()) -> Result<Trace, String> {
+        run_streaming(self.command(request).args(MAKE_ARGS).args(request.make_args), "make", on_line)
+    }
+
+    fn query_make_variables(
+        &self,
+        request: &TraceRequest,
+        names: &[String],
+    ) -> Result<BTreeMap<String, Option<String>>, String> {
+        run_query(self.command(request).args(request.make_args).args(query_arguments(names)), "make")
+    }
+
+    fn hermetic(&self) -> bool {
+        self.hermetic_env.is_some()
+    }
+}
+
+// make run in a container of `image`, such as the Ledger app builder. The
+// SDK and the app are mounted at the same paths as on the host so that the
+// paths of the trace are the host ones.
+#[derive(Debug, Clone)]
+pub struct DockerMake {
+    pub image: String,
+    // Docker client to run, `docker` from PATH by default
+    pub docker: PathBuf,
+}
+
+impl DockerMake {
+    pub fn new(image: impl Into<String>) -> Self {
+        DockerMake { image: image.into(), docker: PathBuf::from("docker") }
+    }
+
+    fn command(&self, request: &TraceRequest) -> Command {
+        let mut mounts = vec![request.bolos_sdk, request.app_path];
+        if !request.make_dir().starts_with(request.app_path) {
+            mounts.push(request.make_dir());
+        }
+        let mut command = Command::new(&self.docker);
+        command.args(["run", "--rm"]);
+        for mount in mounts {
+            command.arg("-v").arg(format!("{}:{}", mount.display(), mount.display()));
+        }
+        command.arg("-w").arg(request.make_dir());
+        for (name, value) in request.env() {
+            command.arg("-e").arg(format!("{}={}", name, value));
+        }
+        command.arg(&self.image).arg("make").args(request.makefile_args());
+        command
+    }
+}
+
+impl TraceSource for DockerMake {
+    fn trace(&self, request: &TraceRequest, on_line: &mut dyn FnMut()) -> Result<Trace, String> {
+        run_streaming(self.command(request).args(MAKE_ARGS).args(request.make_args), "docker make", on_line)
+    }
+
+    fn query_make_variables(
+        &self,
+        request: &TraceRequest,
+        names: &[String],
+    ) -> Result<BTreeMap<String, Option<String>>, String> {
+        run_query(self.command(request).args(request.make_args).args(query_arguments(names)), "docker make")
+    }
+}
+
+// Trace recorded beforehand, e.g. on another machine or by a remote build
+// service
+#[derive(Debug, Clone)]
+pub struct SavedTrace {
+    pub path: PathBuf,
+}
+
+impl TraceSource for SavedTrace {
+    fn trace(&self, _request: &TraceRequest, on_line: &mut dyn FnMut()) -> Result<Trace, String> {
+        let output =
+            fs::read(&self.path).map_err(|e| format!("Failed to read the trace {}: {}", self.path.display(), e))?;
+        output.split(|b| *b == b'\n').for_each(|_| on_line());
+        Ok(Trace { output, failure: None, stderr: String::new() })
+    }
+
+    fn recorded_trace(&self) -> Option<&Path> {
+        Some(&self.path)
+    }
+}
+
+// Host build of the unit tests or fuzzers, configured by cmake in a temporary
+// build directory. The trace is made of the compile lines of the compilation
+// database, nothing is built.
+#[derive(Debug, Clone)]
+pub struct HostCmake {
+    // cmake to run, `cmake` from PATH by default
+    pub cmake: PathBuf,
+}
+
+impl Default for HostCmake {
+    fn default() -> Self {
+        HostCmake { cmake: PathBuf::from("cmake") }
+    }
+}
+
+impl TraceSource for HostCmake {
+    fn trace(&self, request: &TraceRequest, on_line: &mut dyn FnMut()) -> Result<Trace, String> {
+        let build_dir = unique_temp_dir("host", request);
+        let _ = fs::remove_dir_all(&build_dir);
+        let mut command = Command::new(&self.cmake);
+        command.arg("-S").arg(request.make_dir()).arg("-B").arg(&build_dir);
+        command.arg("-DCMAKE_EXPORT_COMPILE_COMMANDS=ON").envs(request.env());
+        command.args(request.env().iter().map(|(name, value)| format!("-D{}={}", name, value)));
+        command.args(request.make_args.iter().map(|a| format!("-D{}", a)));
+        let mut trace = run_streaming(&mut command, "cmake", on_line)?;
+        let database = build_dir.join("compile_commands.json");
+        let commands = fs::read_to_string(&database);
+        let _ = fs::remove_dir_all(&build_dir);
+        trace.output = match (trace.failure.is_some(), commands) {
+            (true, _) => Vec::new(),
+            (false, Ok(contents)) => compile_commands_trace(&contents)?.into_bytes(),
+            (false, Err(e)) => return Err(format!("cmake did not write {}: {}", database.display(), e)),
+        };
+        Ok(trace)
+    }
+}
+
+// Number of times make is run again after copying the files it reported missing
+const COPY_RETRIES: usize = 5;
+
+// Another trace source run on a copy of the app skeleton in a temporary
+// directory, so that a build in progress in the app directory can't race with
+// the dry run. The paths of the copy are replaced by the app ones in the
+// trace.
+#[derive(Debug, Clone)]
+pub struct TempAppCopy {
+    pub inner: Arc<dyn TraceSource>,
+}
+
+impl TempAppCopy {
+    // Run `f` with the request moved to a fresh copy of the app, removed
+    // afterwards
+    fn with_copy<T>(
+        &self,
+        request: &TraceRequest,
+        f: impl FnOnce(&TraceRequest, &Path) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let relative = request.makefile.strip_prefix(request.app_path).map_err(|_| {
+            format!("{} is not in the app directory, it can't be copied", request.makefile.display())
+        })?;
+        let copy = unique_temp_dir("app", request);
+        let _ = fs::remove_dir_all(&copy);
+        copy_app_skeleton(request.app_path, &copy)
+            .map_err(|e| format!("Failed to copy {} to {}: {}", request.app_path.display(), copy.display(), e))?;
+        let makefile = copy.join(relative);
+        let moved = TraceRequest { app_path: &copy, makefile: &makefile, ..*request };
+        let result = f(&moved, &copy);
+        let _ = fs::remove_dir_all(&copy);
+        result
+    }
+}
+
+impl TraceSource for TempAppCopy {
+    fn trace(&self, request: &TraceRequest, on_line: &mut dyn FnMut()) -> Result<Trace, String> {
+        self.with_copy(request, |moved, copy| {
+            let mut trace = self.inner.trace(moved, on_line)?;
+            for _ in 0..COPY_RETRIES {
+                if trace.failure.is_none() {
+                    break;
+                }
+                let mut copied = false;
+                for file in missing_files(&trace.stderr) {
+                    copied |= copy_missing(request.app_path, copy, moved.make_dir(), &file)
+                        .map_err(|e| format!("Failed to copy {} from the app: {}", file, e))?;
+                }
+                if !copied {
+                    break;
+                }
+                trace = self.inner.trace(moved, on_line)?;
+            }
+            // make prints the absolute path of its directory, e.g. for $(CURDIR)
+            let app = request.app_path.canonicalize().unwrap_or_else(|_| request.app_path.to_path_buf());
+            trace.output = replace_bytes(&trace.output, copy.display().to_string().as_bytes(), app.display().to_string().as_bytes());
+            Ok(trace)
+        })
+    }
+
+    fn query_make_variables(
+        &self,
+        request: &TraceRequest,
+        names: &[String],
+    ) -> Result<BTreeMap<String, Option<String>>, String> {
+        self.with_copy(request, |moved, _| self.inner.query_make_variables(moved, names))
+    }
+
+    fn hermetic(&self) -> bool {
+        self.inner.hermetic()
+    }
+
+    fn recorded_trace(&self) -> Option<&Path> {
+        self.inner.recorded_trace()
+    }
+}
@@ -0,0 +1,50 @@
+use std::{fs, path::Path};
+
+use serde::Serialize;
+
+use crate::files::Define;
+
+// Makefile of the minimal app extracted as the baseline of the SDK features
+pub const BASELINE_MAKEFILE: &str = include_str!("baseline/Makefile");
+
+// Only source of the minimal app
+const BASELINE_SOURCE: &str = "int main(void) {\n    return 0;\n}\n";
+
+// Write the minimal app to `dir`
+pub fn write_baseline_app(dir: &Path) -> Result<(), String> {
+    let write = |path: &Path, contents: &str| {
+        fs::create_dir_all(path.parent().unwrap_or(dir))
+            .and_then(|_| fs::write(path, contents))
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    };
+    write(&dir.join("Makefile"), BASELINE_MAKEFILE)?;
+    write(&dir.join("src/main.c"), BASELINE_SOURCE)
+}
+
+// SDK define present on the SDK compile units of the app but not on the ones
+// of the baseline (enabled), or the other way round
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ToggledFeature {
+    pub name: String,
+    pub enabled: bool,
+}
+
+// Defines of the SDK compile units of an app differing by name from the ones
+// of the baseline, sorted by name
+pub fn toggled_features(sdk_defines: &[Define], baseline_defines: &[Define]) -> Vec<ToggledFeature> {
+    let has = |defines: &[Define], name: &str| defines.iter().any(|d| d.name == name);
+    let mut toggled = sdk_defines
+        .iter()
+        .filter(|d| !has(baseline_defines, &d.name))
+        .map(|d| ToggledFeature { name: d.name.clone(), enabled: true })
+        .chain(
+            baseline_defines
+                .iter()
+                .filter(|d| !has(sdk_defines, &d.name))
+                .map(|d| ToggledFeature { name: d.name.clone(), enabled: false }),
+        )
+        .collect::<Vec<_>>();
+    toggled.sort_by(|a, b| a.name.cmp(&b.name));
+    toggled.dedup();
+    toggled
+}
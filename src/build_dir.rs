@@ -0,0 +1,66 @@
+use std::path::{Component, Path, PathBuf};
+
+use crate::{portable::PathRoots, trace::flag_argument};
+
+// Path with its `.` and `..` components resolved without touching the
+// filesystem, the build directory of a dry run not existing
+pub fn lexical(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if matches!(out.components().next_back(), Some(Component::Normal(_))) => {
+                out.pop();
+            }
+            component => out.push(component),
+        }
+    }
+    out
+}
+
+// Root of the build directory layout of a compile line, e.g. `build/stax`: the
+// directory shared by the dependency file (-MF), the dependency targets (-MT,
+// -MQ) and the object (-o). None without dependency file.
+pub fn line_build_dir(line: &str) -> Option<PathBuf> {
+    let dep_file = flag_argument(line, "-MF")?;
+    let others = ["-MT", "-MQ", "-o"].into_iter().filter_map(|flag| flag_argument(line, flag));
+    let mut dirs = [dep_file].into_iter().chain(others).map(|p| lexical(Path::new(&p).parent().unwrap_or(Path::new(""))));
+    let first = dirs.next()?;
+    let common = dirs.fold(first, |common, dir| {
+        common.components().zip(dir.components()).take_while(|(a, b)| a == b).map(|(a, _)| a).collect()
+    });
+    Some(common)
+}
+
+// Build directory of the objects of compile lines without dependency file:
+// the directory shared by their objects (-o), as the app may have moved them
+// out of `build/` with BUILD_DIR or OBJ_DIR. None without object.
+pub fn objects_build_dir<'a>(lines: impl IntoIterator<Item = &'a str>) -> Option<PathBuf> {
+    let mut dirs = lines
+        .into_iter()
+        .filter_map(|line| flag_argument(line, "-o"))
+        .map(|p| lexical(Path::new(&p).parent().unwrap_or(Path::new(""))));
+    let first = dirs.next()?;
+    Some(dirs.fold(first, |common, dir| {
+        common.components().zip(dir.components()).take_while(|(a, b)| a == b).map(|(a, _)| a).collect()
+    }))
+}
+
+// Build directory relative to the app directory, `.` for the app directory
+// itself. `make_dir` is the directory make runs in, paths outside of the app
+// being made portable. The app directory is also matched in its canonical
+// form, make possibly running in it through a symbolic link.
+pub fn relative_to_app(build_dir: &Path, make_dir: &Path, app_path: &Path, roots: &PathRoots) -> String {
+    let build_dir = lexical(&make_dir.join(build_dir));
+    let app_dirs = [lexical(app_path)].into_iter().chain(app_path.canonicalize().ok());
+    match app_dirs.into_iter().find_map(|app| build_dir.strip_prefix(app).ok().map(Path::to_path_buf)) {
+        Some(relative) if relative.as_os_str().is_empty() => ".".to_string(),
+        Some(relative) => relative.display().to_string(),
+        None => roots.portable(&build_dir.display().to_string()),
+    }
+}
+
+// Build directory of a compile line relative to the app directory
+pub fn relative_build_dir(line: &str, make_dir: &Path, app_path: &Path, roots: &PathRoots) -> Option<String> {
+    Some(relative_to_app(&line_build_dir(line)?, make_dir, app_path, roots))
+}
@@ -0,0 +1,53 @@
+use std::{path::Path, process::Command};
+
+use crate::{compiler::CompilerInfo, files::Define};
+
+// Whether a flag changes the predefined macros of the compiler: the target,
+// arch, ABI, optimization, language standard and codegen flags
+pub fn is_builtin_flag(flag: &str) -> bool {
+    ["--target=", "-m", "-O", "-std=", "-f", "--sysroot="].iter().any(|p| flag.starts_with(p))
+}
+
+// Parse the `#define` dump of `<compiler> -dM -E`, sorted by name as the
+// compilers print it in no particular order. Function-like macros keep their
+// parameter list in the name.
+pub fn parse_define_dump(dump: &str) -> Vec<Define> {
+    let mut defines = dump
+        .lines()
+        .filter_map(|line| line.strip_prefix("#define "))
+        .map(|rest| {
+            let name_end = match rest.find(['(', ' ']) {
+                Some(i) if rest[i..].starts_with('(') => rest[i..].find(')').map_or(rest.len(), |j| i + j + 1),
+                Some(i) => i,
+                None => rest.len(),
+            };
+            let (name, value) = rest.split_at(name_end);
+            Define { name: name.to_string(), value: value.strip_prefix(' ').map(|v| v.to_string()) }
+        })
+        .collect::<Vec<_>>();
+    defines.sort_by(|a, b| a.name.cmp(&b.name));
+    defines
+}
+
+// Predefined macros of the compiler of the compile line for the target flags
+// of `cflags`. The compiler has to be installed on this host.
+pub fn query_builtin_defines(compiler: Option<&CompilerInfo>, cflags: &[String], cwd: &Path) -> Result<Vec<Define>, String> {
+    let compiler = compiler.ok_or("No compiler found on the compile line, builtin defines can't be queried")?;
+    let path = compiler.resolved_path.as_deref().ok_or_else(|| {
+        format!("Compiler {} is not installed on this host, builtin defines can't be queried", compiler.command)
+    })?;
+    let output = Command::new(path)
+        .args(cflags.iter().filter(|f| is_builtin_flag(f)))
+        .args(["-dM", "-E", "-x", "c", "/dev/null"])
+        .current_dir(cwd)
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", path, e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} failed to dump its builtin defines: {}",
+            path,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(parse_define_dump(&String::from_utf8_lossy(&output.stdout)))
+}
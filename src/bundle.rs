@@ -0,0 +1,122 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::Read,
+    path::{Component, Path, PathBuf},
+};
+
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+// Bundle of the files of a run: a gzipped ustar archive whose first entry is
+// the manifest, the other files following in path order. Every entry has the
+// same mtime, owner and mode, and the gzip header has no name nor mtime, so
+// that the same files give the same bytes.
+pub const MANIFEST_NAME: &str = "MANIFEST.json";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub generator: String,
+    // SHA-256 of every other file of the bundle, by path
+    pub files: BTreeMap<String, String>,
+}
+
+fn sha256(contents: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(contents))
+}
+
+// Uncompressed archive of the files, with their manifest
+pub fn bundle_archive(files: &[(String, Vec<u8>)], generator: &str) -> Result<Vec<u8>, String> {
+    let mut files = files.to_vec();
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+    if let Some(duplicate) = files.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+        return Err(format!("{} would be bundled twice", duplicate[0].0));
+    }
+    let manifest = BundleManifest {
+        generator: generator.to_string(),
+        files: files.iter().map(|(path, contents)| (path.clone(), sha256(contents))).collect(),
+    };
+    let manifest = serde_json::to_string_pretty(&manifest).expect("Failed to serialize manifest") + "\n";
+    let mut builder = tar::Builder::new(Vec::new());
+    let entries = files.iter().map(|(path, contents)| (path.as_str(), contents.as_slice()));
+    for (path, contents) in [(MANIFEST_NAME, manifest.as_bytes())].into_iter().chain(entries) {
+        let mut header = tar::Header::new_ustar();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_uid(0);
+        header.set_gid(0);
+        header.set_mtime(0);
+        header.set_entry_type(tar::EntryType::Regular);
+        builder.append_data(&mut header, path, contents).map_err(|e| format!("Failed to bundle {}: {}", path, e))?;
+    }
+    builder.into_inner().map_err(|e| format!("Failed to write the bundle archive: {}", e))
+}
+
+// Regular files of an uncompressed archive, in archive order
+pub fn archive_entries(archive: &[u8]) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let mut entries = Vec::new();
+    let mut archive = tar::Archive::new(archive);
+    for entry in archive.entries().map_err(|e| format!("Invalid archive: {}", e))? {
+        let mut entry = entry.map_err(|e| format!("Invalid archive entry: {}", e))?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry.path().map_err(|e| format!("Invalid archive entry path: {}", e))?;
+        let path = path.to_string_lossy().into_owned();
+        let mut contents = Vec::new();
+        entry.read_to_end(&mut contents).map_err(|e| format!("Truncated archive in {}: {}", path, e))?;
+        entries.push((path, contents));
+    }
+    Ok(entries)
+}
+
+pub fn write_bundle(path: &Path, files: &[(String, Vec<u8>)], generator: &str) -> Result<(), String> {
+    let archive = bundle_archive(files, generator)?;
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    std::io::Write::write_all(&mut encoder, &archive).map_err(|e| format!("Failed to compress bundle: {}", e))?;
+    let compressed = encoder.finish().map_err(|e| format!("Failed to compress bundle: {}", e))?;
+    fs::write(path, compressed).map_err(|e| format!("Failed to write bundle {}: {}", path.display(), e))
+}
+
+pub fn read_bundle(path: &Path) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let compressed = fs::read(path).map_err(|e| format!("Failed to read bundle {}: {}", path.display(), e))?;
+    let mut archive = Vec::new();
+    GzDecoder::new(compressed.as_slice())
+        .read_to_end(&mut archive)
+        .map_err(|e| format!("Failed to decompress bundle {}: {}", path.display(), e))?;
+    archive_entries(&archive)
+}
+
+// Differences between the entries of a bundle and its manifest, empty for a
+// bundle intact
+pub fn verify_bundle(entries: &[(String, Vec<u8>)]) -> Vec<String> {
+    let Some((_, manifest)) = entries.iter().find(|(path, _)| path == MANIFEST_NAME) else {
+        return vec![format!("No {} in the bundle", MANIFEST_NAME)];
+    };
+    let manifest: BundleManifest = match serde_json::from_slice(manifest) {
+        Ok(manifest) => manifest,
+        Err(e) => return vec![format!("Invalid {}: {}", MANIFEST_NAME, e)],
+    };
+    let mut problems = Vec::new();
+    for (path, hash) in &manifest.files {
+        match entries.iter().find(|(p, _)| p == path) {
+            None => problems.push(format!("{} is missing", path)),
+            Some((_, contents)) if sha256(contents) != *hash => problems.push(format!("{} does not match its hash", path)),
+            Some(_) => {}
+        }
+    }
+    for (path, _) in entries.iter().filter(|(p, _)| p != MANIFEST_NAME && !manifest.files.contains_key(p)) {
+        problems.push(format!("{} is not in the manifest", path));
+    }
+    problems
+}
+
+// Path of an entry below `dir`, refusing absolute paths and `..`
+pub fn entry_path(dir: &Path, entry: &str) -> Result<PathBuf, String> {
+    let relative = Path::new(entry);
+    if entry.is_empty() || !relative.components().all(|c| matches!(c, Component::Normal(_))) {
+        return Err(format!("Refusing to extract {}, outside of the target directory", entry));
+    }
+    Ok(dir.join(relative))
+}
@@ -0,0 +1,105 @@
+use std::path::{Path, PathBuf};
+
+use anstream::{eprintln, println};
+use serde::Serialize;
+
+use cbpx::{
+    capability::{capability_changes, capability_matrix, render_csv, CapabilityChange, CapabilityMatrix},
+    params::BuildParameters,
+};
+
+use crate::{color, filters::find_outputs};
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum MatrixFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct CapabilitiesArgs {
+    /// Directory searched recursively for the JSON outputs, one per device
+    #[arg(default_value = ".")]
+    output_dir: PathBuf,
+
+    /// How the matrix is printed
+    #[arg(long, value_enum, default_value_t = MatrixFormat::Text)]
+    format: MatrixFormat,
+
+    /// Previous snapshot, the capabilities gained or lost since it being
+    /// highlighted for the devices present in both
+    #[arg(long, value_name = "OLD_DIR")]
+    diff: Option<PathBuf>,
+}
+
+#[derive(Serialize)]
+struct MatrixReport<'a> {
+    #[serde(flatten)]
+    matrix: &'a CapabilityMatrix,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    changes: Option<&'a [CapabilityChange]>,
+}
+
+// Capability matrix of the outputs below `dir`
+fn load(dir: &Path) -> Result<CapabilityMatrix, String> {
+    let mut outputs = Vec::new();
+    find_outputs(dir, &mut outputs)?;
+    if outputs.is_empty() {
+        return Err(format!("No JSON output found in {}", dir.display()));
+    }
+    let mut devices: Vec<(String, BuildParameters)> = Vec::new();
+    for (path, params) in outputs {
+        if let Some((other, _)) = devices.iter().find(|(device, _)| *device == params.device) {
+            return Err(format!(
+                "{} has several outputs of {}, e.g. {}, point to a directory with one output per device",
+                dir.display(),
+                other,
+                path.display()
+            ));
+        }
+        devices.push((params.device.clone(), params));
+    }
+    Ok(capability_matrix(&devices.into_iter().collect()))
+}
+
+fn print_table(matrix: &CapabilityMatrix, changes: &[CapabilityChange]) {
+    let width = matrix.capabilities.keys().map(String::len).chain(["CAPABILITY".len()]).max().unwrap_or(0);
+    let header = matrix.devices.iter().map(|d| format!("{:<8}", d)).collect::<Vec<_>>().join("  ");
+    println!("{:<width$}  {}", "CAPABILITY", header.trim_end());
+    for name in matrix.capabilities.keys() {
+        let cells = matrix.devices.iter().map(|d| format!("{:<8}", if matrix.is_enabled(name, d) { "x" } else { "." }));
+        println!("{:<width$}  {}", name, cells.collect::<Vec<_>>().join("  ").trim_end());
+    }
+    for change in changes {
+        let line = format!("{} {} on {}", if change.gained { "+" } else { "-" }, change.capability, change.device);
+        println!("{}", color::diff_line(&line));
+    }
+}
+
+fn capabilities(args: &CapabilitiesArgs) -> Result<(), String> {
+    let matrix = load(&args.output_dir)?;
+    let changes = args.diff.as_deref().map(load).transpose()?.map(|old| capability_changes(&old, &matrix));
+    let change_list = changes.as_deref().unwrap_or_default();
+    match args.format {
+        MatrixFormat::Text => print_table(&matrix, change_list),
+        MatrixFormat::Json => {
+            let report = MatrixReport { matrix: &matrix, changes: changes.as_deref() };
+            println!("{}", serde_json::to_string_pretty(&report).expect("Failed to serialize capabilities"));
+        }
+        MatrixFormat::Csv => print!("{}", render_csv(&matrix, change_list)),
+    }
+    Ok(())
+}
+
+// Run the `capabilities` subcommand, returning 0 once the matrix is printed
+// and 1 on error
+pub fn run(args: &CapabilitiesArgs) -> i32 {
+    match capabilities(args) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+
+use crate::{
+    category::{categorize, Category},
+    multi_device::MultiDeviceParameters,
+};
+
+// Capability defines of the matrix are the curve and cryptography ones of the
+// define categorization, unlike the boolean ones of `rustflags::is_capability`
+pub fn is_crypto_capability(name: &str) -> bool {
+    matches!(categorize(name), Category::Curves | Category::Crypto)
+}
+
+// Devices on which each capability define is enabled
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CapabilityMatrix {
+    pub devices: Vec<String>,
+    pub capabilities: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl CapabilityMatrix {
+    pub fn is_enabled(&self, capability: &str, device: &str) -> bool {
+        self.capabilities.get(capability).is_some_and(|devices| devices.contains(device))
+    }
+}
+
+// Matrix of the capabilities of each device, devices kept in their order
+pub fn capability_matrix(params: &MultiDeviceParameters) -> CapabilityMatrix {
+    let capabilities = params
+        .matrix()
+        .into_iter()
+        .filter(|(name, _)| is_crypto_capability(name))
+        .map(|(name, values)| (name, values.into_iter().map(|v| v.device).collect()))
+        .collect();
+    CapabilityMatrix { devices: params.device_names(), capabilities }
+}
+
+// A capability enabled or disabled on a device since a previous snapshot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CapabilityChange {
+    pub capability: String,
+    pub device: String,
+    pub gained: bool,
+}
+
+// Changes of the devices present in both matrices, by capability then device
+pub fn capability_changes(old: &CapabilityMatrix, new: &CapabilityMatrix) -> Vec<CapabilityChange> {
+    let names = old.capabilities.keys().chain(new.capabilities.keys()).collect::<BTreeSet<_>>();
+    let mut changes = Vec::new();
+    for name in names {
+        for device in new.devices.iter().filter(|d| old.devices.contains(d)) {
+            let (before, after) = (old.is_enabled(name, device), new.is_enabled(name, device));
+            if before != after {
+                changes.push(CapabilityChange { capability: name.clone(), device: device.clone(), gained: after });
+            }
+        }
+    }
+    changes
+}
+
+// CSV of the matrix, `1` and `0` cells, or `gained` and `lost` for the
+// capabilities which changed
+pub fn render_csv(matrix: &CapabilityMatrix, changes: &[CapabilityChange]) -> String {
+    let mut csv = format!("capability,{}\n", matrix.devices.join(","));
+    let names = matrix.capabilities.keys().chain(changes.iter().map(|c| &c.capability)).collect::<BTreeSet<_>>();
+    for name in names {
+        let cells = matrix.devices.iter().map(|device| {
+            match changes.iter().find(|c| &c.capability == name && &c.device == device) {
+                Some(change) if change.gained => "gained",
+                Some(_) => "lost",
+                None if matrix.is_enabled(name, device) => "1",
+                None => "0",
+            }
+        });
+        csv.push_str(&format!("{},{}\n", name, cells.collect::<Vec<_>>().join(",")));
+    }
+    csv
+}
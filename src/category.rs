@@ -0,0 +1,122 @@
+// Grouping of the SDK defines by topic, used to present defines files in a
+// readable way. Rules are evaluated in order, the first matching pattern
+// wins; defines matching no rule fall in `Other`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Category {
+    Application,
+    Target,
+    Curves,
+    Crypto,
+    Io,
+    Display,
+    Other,
+}
+
+impl Category {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Category::Application => "Application",
+            Category::Target => "Target and SDK",
+            Category::Curves => "Curves",
+            Category::Crypto => "Cryptography",
+            Category::Io => "Input/Output",
+            Category::Display => "Display",
+            Category::Other => "Other",
+        }
+    }
+}
+
+const CATEGORY_RULES: [(Category, &[&str]); 6] = [
+    (
+        Category::Application,
+        &["APPNAME", "APPVERSION", "*_VERSION", "APP_*", "HAVE_SWAP", "HAVE_PENDING_REVIEW_SCREEN"],
+    ),
+    (Category::Target, &["API_LEVEL", "TARGET", "TARGET_*", "SDK_*"]),
+    (Category::Curves, &["HAVE_*_CURVE"]),
+    (
+        Category::Crypto,
+        &[
+            "HAVE_NES_CRYPT",
+            "HAVE_ST_AES",
+            "HAVE_CRC",
+            "HAVE_HASH",
+            "HAVE_RIPEMD160",
+            "HAVE_SHA*",
+            "HAVE_BLAKE2",
+            "HAVE_HMAC",
+            "HAVE_PBKDF2",
+            "HAVE_AES*",
+            "HAVE_MATH",
+            "HAVE_RNG*",
+            "HAVE_ECC*",
+            "HAVE_ECDH",
+            "HAVE_ECDSA",
+            "HAVE_EDDSA",
+            "HAVE_ECSCHNORR",
+            "HAVE_X25519",
+            "HAVE_X448",
+            "HAVE_CMAC",
+            "HAVE_LEDGER_PKI",
+        ],
+    ),
+    (
+        Category::Io,
+        &[
+            "HAVE_IO_*",
+            "HAVE_BLE*",
+            "BLE_*",
+            "HAVE_INAPP_BLE_PAIRING",
+            "HAVE_USB*",
+            "HAVE_L4_USBLIB",
+            "USB_*",
+            "HAVE_WEBUSB",
+            "WEBUSB_*",
+            "IO_*",
+            "OS_IO_*",
+            "USE_OS_IO_STACK",
+            "REVAMPED_IO",
+            "STANDARD_APP_SYNC_RAPDU",
+        ],
+    ),
+    (
+        Category::Display,
+        &[
+            "HAVE_NBGL",
+            "NBGL_*",
+            "HAVE_BAGL*",
+            "BAGL_*",
+            "HAVE_SE_*",
+            "SCREEN_*",
+            "HAVE_FONTS",
+            "HAVE_PIEZO_SOUND",
+            "HAVE_BATTERY",
+        ],
+    ),
+];
+
+pub fn categorize(name: &str) -> Category {
+    CATEGORY_RULES
+        .iter()
+        .find(|(_, patterns)| patterns.iter().any(|p| glob_match(p, name)))
+        .map(|(category, _)| *category)
+        .unwrap_or(Category::Other)
+}
+
+// Minimal glob matching where `*` matches any (possibly empty) sequence of
+// characters. Every other character matches itself.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == name,
+        Some((prefix, rest)) => {
+            let Some(remaining) = name.strip_prefix(prefix) else {
+                return false;
+            };
+            if rest.is_empty() {
+                return true;
+            }
+            (0..=remaining.len())
+                .filter(|i| remaining.is_char_boundary(*i))
+                .any(|i| glob_match(rest, &remaining[i..]))
+        }
+    }
+}
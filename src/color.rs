@@ -0,0 +1,65 @@
+use anstream::ColorChoice;
+use anstyle::{AnsiColor, Style};
+use cbpx::report::RunStatus;
+
+use crate::doctor::CheckStatus;
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default)]
+pub enum ColorWhen {
+    /// Color when writing to a terminal, unless NO_COLOR is set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+// Select the color choice of every output stream. The anstream print macros
+// strip the styles when colors are disabled.
+pub fn init(when: ColorWhen) {
+    let choice = match when {
+        ColorWhen::Auto => ColorChoice::Auto,
+        ColorWhen::Always => ColorChoice::Always,
+        ColorWhen::Never => ColorChoice::Never,
+    };
+    choice.write_global();
+}
+
+const GREEN: Style = AnsiColor::Green.on_default();
+const RED: Style = AnsiColor::Red.on_default();
+const YELLOW: Style = AnsiColor::Yellow.on_default();
+
+fn paint(style: Style, text: &str) -> String {
+    format!("{}{}{}", style.render(), text, style.render_reset())
+}
+
+pub fn error(text: &str) -> String {
+    paint(RED, text)
+}
+
+pub fn status(status: &RunStatus) -> String {
+    let style = match status {
+        RunStatus::Match => GREEN,
+        RunStatus::Unchanged | RunStatus::Skipped => YELLOW,
+        RunStatus::Mismatch | RunStatus::Error => RED,
+    };
+    paint(style, status.label())
+}
+
+pub fn check(status: CheckStatus) -> String {
+    let style = match status {
+        CheckStatus::Pass => GREEN,
+        CheckStatus::Warn => YELLOW,
+        CheckStatus::Fail => RED,
+    };
+    paint(style, status.label())
+}
+
+// Highlight a `+`, `-` or `~` prefixed diff line, other lines are left as is
+pub fn diff_line(line: &str) -> String {
+    match line.trim_start().chars().next() {
+        Some('+') => paint(GREEN, line),
+        Some('-') => paint(RED, line),
+        Some('~') => paint(YELLOW, line),
+        _ => line.to_string(),
+    }
+}
@@ -0,0 +1,34 @@
+use crate::{
+    files::render_defines,
+    multi_device::{DeviceDefines, MultiDeviceParameters},
+};
+
+// Guard macro of a device, given by its TARGET_NAME define
+// (`\"TARGET_STAX\"` gives `TARGET_STAX`)
+fn guard_macro(device: &DeviceDefines) -> Result<String, String> {
+    device
+        .defines
+        .iter()
+        .find(|d| d.name == "TARGET_NAME")
+        .and_then(|d| d.value.as_deref())
+        .map(|v| v.chars().filter(|c| *c != '"' && *c != '\\').collect::<String>())
+        .filter(|v| !v.is_empty())
+        .ok_or_else(|| format!("TARGET_NAME not found for device {}, cannot build its guard", device.device))
+}
+
+// Merge the defines of several devices into a single header. Defines common to
+// every device (same name and value) are emitted unguarded, the others under
+// an `#if defined(<TARGET_NAME>)` block per device.
+pub fn combined_header(params: &MultiDeviceParameters) -> Result<String, String> {
+    let mut header = String::from("// Generated by cbpx, do not edit\n#pragma once\n");
+    header.push_str(&format!("\n// Common to {}\n", params.device_names().join(", ")));
+    header.push_str(&render_defines(&params.common_defines()));
+
+    for device in &params.devices {
+        let guard = guard_macro(device)?;
+        header.push_str(&format!("\n#if defined({})\n", guard));
+        header.push_str(&render_defines(&params.device_specific(&device.device)));
+        header.push_str(&format!("#endif // {}\n", guard));
+    }
+    Ok(header)
+}
@@ -0,0 +1,115 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anstream::{eprintln, println};
+
+use cbpx::{
+    diff::{compare, read_renames, CompareOptions},
+    files::{read_cflags, read_defines},
+    params::BuildParameters,
+};
+
+use crate::color;
+
+#[derive(clap::Args, Debug)]
+pub struct CompareArgs {
+    /// Old parameters, as a .json output or a .defines/.cflags file
+    old: String,
+
+    /// New parameters, in the same forms as the old ones
+    new: String,
+
+    /// Print the differences as JSON
+    #[arg(long)]
+    json: bool,
+
+    /// Ignore the defines matching a glob pattern, can be given several times
+    #[arg(long, value_name = "PATTERN")]
+    ignore_define: Vec<String>,
+
+    /// Ignore the cflags matching a glob pattern, can be given several times
+    #[arg(long, value_name = "PATTERN")]
+    ignore_cflag: Vec<String>,
+
+    /// TOML file of known define renames, `OLD_NAME = "NEW_NAME"` pairs. A
+    /// matching removal and addition with equal values is reported as a
+    /// rename.
+    #[arg(long, value_name = "PATH")]
+    renames: Option<PathBuf>,
+
+    /// Don't count the renames from --renames as differences
+    #[arg(long, requires = "renames")]
+    allow_renames: bool,
+
+    /// Count a different order of the same include paths as a difference
+    #[arg(long)]
+    strict_include_order: bool,
+
+    /// Don't count the defines and cflags found on only some compile units,
+    /// per the occurrences of outputs extracted with --strategy union
+    #[arg(long)]
+    tolerate_partial: bool,
+}
+
+// Load parameters from a JSON output, or from a defines/cflags file pair in
+// which case the sibling file is optional
+pub fn load(target: &str) -> Result<BuildParameters, String> {
+    let path = Path::new(target);
+    let (defines, cflags) = match path.extension().and_then(|e| e.to_str()) {
+        Some("json") => {
+            let contents =
+                fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            return serde_json::from_str(&contents).map_err(|e| format!("{}: {}", path.display(), e));
+        }
+        Some("defines") => (path.to_path_buf(), path.with_extension("cflags")),
+        Some("cflags") => (path.with_extension("defines"), path.to_path_buf()),
+        _ => return Err(format!("{} is neither a .json, a .defines nor a .cflags file", target)),
+    };
+    if !path.exists() {
+        return Err(format!("{} does not exist", path.display()));
+    }
+    Ok(BuildParameters {
+        defines: Some(defines).filter(|p| p.exists()).map(|p| read_defines(&p)).transpose()?.unwrap_or_default(),
+        cflags: Some(cflags).filter(|p| p.exists()).map(|p| read_cflags(&p)).transpose()?.unwrap_or_default(),
+        ..Default::default()
+    })
+}
+
+// Run the `compare` subcommand, returning 0 when the parameters are
+// equivalent, 1 when they differ and 2 on error
+pub fn run(args: &CompareArgs) -> i32 {
+    let (old, new) = match load(&args.old).and_then(|old| Ok((old, load(&args.new)?))) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+    let renames = match args.renames.as_deref().map(read_renames).transpose() {
+        Ok(renames) => renames.unwrap_or_default(),
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+    let options = CompareOptions {
+        ignored_defines: args.ignore_define.clone(),
+        ignored_cflags: args.ignore_cflag.clone(),
+        renames,
+        allow_renames: args.allow_renames,
+        strict_include_order: args.strict_include_order,
+        tolerate_partial: args.tolerate_partial,
+    };
+    let diff = compare(&old, &new, &options);
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&diff).expect("Failed to serialize diff"));
+    } else {
+        for line in diff.lines() {
+            println!("{}", color::diff_line(&line));
+        }
+    }
+    if diff.is_empty() { 0 } else { 1 }
+}
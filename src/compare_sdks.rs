@@ -0,0 +1,83 @@
+use std::path::PathBuf;
+
+use anstream::{eprintln, println};
+
+use cbpx::{
+    device::parse_device,
+    diff::CompareOptions,
+    extract::ExtractOptions,
+    sdk_delta::sdk_delta,
+};
+
+use crate::color;
+
+#[derive(clap::Args, Debug)]
+pub struct CompareSdksArgs {
+    /// SDK checkout the app is currently built with
+    #[arg(long, value_name = "PATH")]
+    sdk_old: PathBuf,
+
+    /// SDK checkout to review, e.g. a release candidate
+    #[arg(long, value_name = "PATH")]
+    sdk_new: PathBuf,
+
+    /// Device to extract the parameters for
+    #[arg(short, long)]
+    device: String,
+
+    /// Path of the application to build
+    #[arg(short, long)]
+    app_path: PathBuf,
+
+    /// Print the SDKs and the differences as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+// Run the `compare-sdks` subcommand, returning 0 when both SDKs give the same
+// parameters, 1 when they differ and 2 on error
+pub fn run(args: &CompareSdksArgs) -> i32 {
+    let device = match parse_device(&args.device) {
+        Ok(device) => device,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+    // make runs in the app directory, relative SDK paths would resolve there
+    let [sdk_old, sdk_new, app_path] = match [&args.sdk_old, &args.sdk_new, &args.app_path].map(std::path::absolute) {
+        [Ok(sdk_old), Ok(sdk_new), Ok(app_path)] => [sdk_old, sdk_new, app_path],
+        _ => {
+            eprintln!("Failed to resolve the SDK and app paths");
+            return 2;
+        }
+    };
+    for sdk in [&sdk_old, &sdk_new] {
+        if !sdk.is_dir() {
+            eprintln!("SDK {} is not a directory", sdk.display());
+            return 2;
+        }
+    }
+    let old = ExtractOptions::new(sdk_old);
+    let new = ExtractOptions::new(sdk_new);
+    let delta = match sdk_delta(device, &app_path, &old, &new, &CompareOptions::default()) {
+        Ok(delta) => delta,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&delta).expect("Failed to serialize delta"));
+    } else {
+        println!("{}", delta.header());
+        for line in delta.diff.lines() {
+            println!("{}", color::diff_line(&line));
+        }
+        if delta.diff.is_empty() {
+            println!("No difference");
+        }
+    }
+    if delta.diff.is_empty() { 0 } else { 1 }
+}
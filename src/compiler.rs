@@ -0,0 +1,57 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::trace::command_word;
+
+// Compiler used by the SDK build, as found on the compile line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompilerInfo {
+    // First token of the compile line, verbatim
+    pub command: String,
+    // Location of the compiler on this host, if it could be found
+    pub resolved_path: Option<String>,
+    // First line of `<compiler> --version`
+    pub version: Option<String>,
+}
+
+// Resolve a compiler command the way make's shell would, relative commands
+// being searched in the SDK toolchain directories (CLANGPATH and GCCPATH, as
+// used by the SDK Makefiles) before PATH. `cwd` is the directory make runs in.
+fn resolve(command: &str, cwd: &Path) -> Option<PathBuf> {
+    if command.contains('/') {
+        let path = cwd.join(command);
+        return path.is_file().then_some(path);
+    }
+    let toolchain_dirs = ["CLANGPATH", "GCCPATH"]
+        .iter()
+        .filter_map(env::var_os)
+        .map(PathBuf::from);
+    let path_dirs = env::var_os("PATH").map(|p| env::split_paths(&p).collect::<Vec<_>>()).unwrap_or_default();
+    toolchain_dirs.chain(path_dirs).map(|dir| dir.join(command)).find(|p| p.is_file())
+}
+
+fn version(path: &Path) -> Option<String> {
+    let output = Command::new(path).arg("--version").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout).lines().next().map(|l| l.trim().to_string())
+}
+
+// Look a compiler command up on this host. A missing compiler only records
+// the command name.
+pub fn probe_compiler(command: &str, cwd: &Path) -> CompilerInfo {
+    let resolved = resolve(command, cwd);
+    let version = resolved.as_deref().and_then(version);
+    CompilerInfo { command: command.to_string(), resolved_path: resolved.map(|p| p.display().to_string()), version }
+}
+
+// Detect the compiler of a compile line, wrappers such as ccache skipped
+pub fn detect_compiler(compile_line: &str, cwd: &Path) -> Option<CompilerInfo> {
+    command_word(compile_line).map(|command| probe_compiler(command, cwd))
+}
@@ -0,0 +1,89 @@
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::{shrink::ShrinkThresholds, target_id::TargetExpectation};
+
+// Configuration file looked up from the current directory upwards, marking
+// the root of a references repository
+pub const CONFIG_FILE: &str = "cbpx.json";
+
+// Default name of the reference directory, relative to the configuration file
+pub const REFERENCE_DIR: &str = "references";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    // Relative to the directory of the configuration file
+    pub reference_dir: Option<PathBuf>,
+    // Expected TARGET_ID and TARGET_NAME per device name
+    #[serde(default)]
+    pub targets: BTreeMap<String, TargetExpectation>,
+    // Entry counts below which an extraction is considered truncated
+    #[serde(default)]
+    pub shrink: ShrinkThresholds,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Config, String> {
+        let contents = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        serde_json::from_str(&contents).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+// Configuration file of `start` or its parents
+fn find_config(start: &Path) -> Option<PathBuf> {
+    start.ancestors().map(|dir| dir.join(CONFIG_FILE)).find(|config| config.is_file())
+}
+
+// Configuration found from the current directory, then from the executable's
+// location, the default one when there is none
+pub fn load_config() -> Result<Config, String> {
+    let cwd = env::current_dir().map_err(|e| format!("Failed to get the current directory: {}", e))?;
+    let exe_dir = env::current_exe().ok().and_then(|exe| exe.parent().map(Path::to_path_buf));
+    match find_config(&cwd).or_else(|| exe_dir.and_then(|dir| find_config(&dir))) {
+        Some(config) => Config::load(&config),
+        None => Ok(Config::default()),
+    }
+}
+
+// Look for a configuration file or a reference directory in `start` and its
+// parents, the configuration file taking precedence in each directory
+fn search(start: &Path) -> Result<Option<PathBuf>, String> {
+    for dir in start.ancestors() {
+        let config = dir.join(CONFIG_FILE);
+        if config.is_file() {
+            let reference_dir = Config::load(&config)?.reference_dir.unwrap_or_else(|| PathBuf::from(REFERENCE_DIR));
+            return Ok(Some(dir.join(reference_dir)));
+        }
+        if dir.join(REFERENCE_DIR).is_dir() {
+            return Ok(Some(dir.join(REFERENCE_DIR)));
+        }
+    }
+    Ok(None)
+}
+
+// Absolute path of the reference directory: the explicit one when given,
+// otherwise the one found from the current directory, then from the
+// executable's location (e.g. `cargo run` in the references repository).
+// When none is found, `references` in the current directory is used so that
+// --update-references can create it and missing references are reported
+// with the absolute path tried.
+pub fn reference_dir(explicit: Option<&Path>) -> Result<PathBuf, String> {
+    let cwd = env::current_dir().map_err(|e| format!("Failed to get the current directory: {}", e))?;
+    if let Some(explicit) = explicit {
+        return Ok(cwd.join(explicit));
+    }
+    if let Some(found) = search(&cwd)? {
+        return Ok(found);
+    }
+    let exe_dir = env::current_exe().ok().and_then(|exe| exe.parent().map(Path::to_path_buf));
+    if let Some(found) = exe_dir.map(|dir| search(&dir)).transpose()?.flatten() {
+        return Ok(found);
+    }
+    Ok(cwd.join(REFERENCE_DIR))
+}
@@ -0,0 +1,69 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    trace::{is_link_line, parse_compile_line, parse_source},
+    unit::CompileUnit,
+};
+
+// Kind of a toolchain invocation of the trace
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InvocationKind {
+    C,
+    Assembly,
+    Link,
+}
+
+impl InvocationKind {
+    pub fn describe(self) -> &'static str {
+        match self {
+            InvocationKind::C => "C compiles",
+            InvocationKind::Assembly => "assembly",
+            InvocationKind::Link => "linking",
+        }
+    }
+}
+
+// Names of the defines passed to any invocation of each kind
+pub type KindDefines = BTreeMap<InvocationKind, BTreeSet<String>>;
+
+fn unit_kind(unit: &CompileUnit) -> InvocationKind {
+    match &unit.source {
+        Some(source) if source.ends_with(".s") || source.ends_with(".S") => InvocationKind::Assembly,
+        _ => InvocationKind::C,
+    }
+}
+
+// Define sets of the compile units and of the link lines of the trace
+pub fn kind_defines(units: &[CompileUnit], trace: &str) -> KindDefines {
+    let mut sets = KindDefines::new();
+    for unit in units {
+        sets.entry(unit_kind(unit)).or_default().extend(unit.defines.iter().map(|d| d.name.clone()));
+    }
+    for line in trace.lines().filter(|l| is_link_line(l) && parse_source(l).is_none()) {
+        let (defines, _) = parse_compile_line(line);
+        sets.entry(InvocationKind::Link).or_default().extend(defines.into_iter().map(|d| d.name));
+    }
+    sets
+}
+
+// Defines passed to one kind of invocation but not to another. Kinds without
+// any define (usually linking, as the driver doesn't need them) are not
+// compared, every define would be reported otherwise.
+pub fn asymmetries(sets: &KindDefines) -> Vec<String> {
+    let compared = sets.iter().filter(|(_, names)| !names.is_empty()).collect::<Vec<_>>();
+    let mut messages = Vec::new();
+    for (kind, names) in &compared {
+        for (other, other_names) in &compared {
+            if kind == other {
+                continue;
+            }
+            for name in names.difference(other_names) {
+                messages.push(format!("{} passed to {} but not to {}", name, kind.describe(), other.describe()));
+            }
+        }
+    }
+    messages
+}
@@ -0,0 +1,155 @@
+use crate::rust_target::UNKNOWN_RUST_TARGET;
+
+// Mapping between the supported devices, the TARGET passed to make and the
+// environment variable holding the path of the matching SDK checkout
+pub struct DeviceInfo {
+    pub name: &'static str,
+    pub target: &'static str,
+    pub sdk_env: &'static str,
+    // Rust target expected for the CPU of the device
+    pub rust_target: &'static str,
+    // TARGET_ID and TARGET_NAME defined by the SDK, empty when unknown
+    pub target_id: &'static str,
+    pub target_name: &'static str,
+}
+
+pub const DEVICES: [DeviceInfo; 5] = [
+    DeviceInfo {
+        name: "nanox",
+        target: "nanox",
+        sdk_env: "NANOX_SDK",
+        rust_target: "thumbv7m-none-eabi",
+        target_id: "0x33000004",
+        target_name: "TARGET_NANOX",
+    },
+    DeviceInfo {
+        name: "nanosplus",
+        target: "nanos2",
+        sdk_env: "NANOSP_SDK",
+        rust_target: "thumbv8m.main-none-eabi",
+        target_id: "0x33100004",
+        target_name: "TARGET_NANOS2",
+    },
+    DeviceInfo {
+        name: "stax",
+        target: "stax",
+        sdk_env: "STAX_SDK",
+        rust_target: "thumbv8m.main-none-eabi",
+        target_id: "0x33200004",
+        target_name: "TARGET_STAX",
+    },
+    DeviceInfo {
+        name: "flex",
+        target: "flex",
+        sdk_env: "FLEX_SDK",
+        rust_target: "thumbv8m.main-none-eabi",
+        target_id: "0x33300004",
+        target_name: "TARGET_FLEX",
+    },
+    DeviceInfo {
+        name: "apex_p",
+        target: "apex_p",
+        sdk_env: "APEX_P_SDK",
+        rust_target: "thumbv8m.main-none-eabi",
+        target_id: "0x33400004",
+        target_name: "TARGET_APEX_P",
+    },
+];
+
+impl DeviceInfo {
+    // Names the device is accepted under on the command line
+    pub fn aliases(&self) -> [&'static str; 2] {
+        [self.name, self.target]
+    }
+}
+
+pub fn find_device(name: &str) -> Option<&'static DeviceInfo> {
+    DEVICES.iter().find(|d| d.name == name)
+}
+
+pub fn find_device_by_target(target: &str) -> Option<&'static DeviceInfo> {
+    DEVICES.iter().find(|d| d.target == target)
+}
+
+// Device given either by its marketing name or by its TARGET. Every alias is
+// looked up in the whole table, so that one shared by two devices is reported
+// rather than resolved to the first of them.
+pub fn parse_device(name: &str) -> Result<&'static DeviceInfo, String> {
+    let matching = DEVICES.iter().filter(|d| d.aliases().contains(&name)).collect::<Vec<_>>();
+    match matching[..] {
+        [device] => Ok(device),
+        [] => Err(format!("Unsupported device {}. Supported devices are: {}, or their TARGET.", name, device_names())),
+        _ => Err(format!(
+            "Ambiguous device {}, it names {}",
+            name,
+            matching.iter().map(|d| d.name).collect::<Vec<_>>().join(" and ")
+        )),
+    }
+}
+
+// Device outside of the table, for targets not released yet. Its name is
+// the target. The entry lives for the whole process, as the built-in ones.
+pub fn custom_device(target: &str, sdk_env: &str) -> Result<&'static DeviceInfo, String> {
+    if let Ok(device) = parse_device(target) {
+        return Err(format!("{} is a built-in device, use --device {} instead of --custom-target", target, device.name));
+    }
+    let target: &'static str = Box::leak(target.to_string().into_boxed_str());
+    Ok(Box::leak(Box::new(DeviceInfo {
+        name: target,
+        target,
+        sdk_env: Box::leak(sdk_env.to_string().into_boxed_str()),
+        rust_target: UNKNOWN_RUST_TARGET,
+        target_id: "",
+        target_name: "",
+    })))
+}
+
+pub fn device_names() -> String {
+    DEVICES.iter().map(|d| d.name).collect::<Vec<_>>().join(", ")
+}
+
+// Environment overrides given to the make child process. The tool's own
+// environment is never modified so that inherited values stay intact.
+pub struct ChildEnv {
+    pub target: String,
+    pub bolos_sdk: String,
+    // Inherited values overridden by the ones above, to be reported
+    pub notices: Vec<String>,
+}
+
+// Build the child environment for `device`, `lookup` giving access to the
+// caller's environment. Precedence is:
+// - TARGET always comes from the device table,
+// - BOLOS_SDK comes from the inherited BOLOS_SDK when `inherit_env` is set,
+//   otherwise from the device-specific SDK variable.
+// A notice is recorded whenever an inherited value is overridden.
+pub fn child_env(
+    device: &DeviceInfo,
+    inherit_env: bool,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<ChildEnv, String> {
+    let inherited_target = lookup("TARGET");
+    let inherited_sdk = lookup("BOLOS_SDK");
+
+    let bolos_sdk = match (inherit_env, inherited_sdk.clone()) {
+        (true, Some(sdk)) => sdk,
+        (true, None) => {
+            return Err("--inherit-env requires BOLOS_SDK to be set in the environment".to_string())
+        }
+        (false, _) => lookup(device.sdk_env)
+            .ok_or_else(|| format!("{} is not set (required for device {})", device.sdk_env, device.name))?,
+    };
+
+    let mut notices = Vec::new();
+    if let Some(target) = inherited_target.filter(|t| t != device.target) {
+        notices.push(format!("inherited TARGET={} overridden with TARGET={}", target, device.target));
+    }
+    if let Some(sdk) = inherited_sdk.filter(|s| *s != bolos_sdk) {
+        notices.push(format!(
+            "inherited BOLOS_SDK={} overridden with BOLOS_SDK={} (from {}), use --inherit-env to keep it",
+            sdk, bolos_sdk, device.sdk_env
+        ));
+    }
+
+    Ok(ChildEnv { target: device.target.to_string(), bolos_sdk, notices })
+}
@@ -0,0 +1,263 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use regex::Regex;
+use serde::Serialize;
+
+use crate::{category::glob_match, files::Define, params::BuildParameters};
+
+// Options of `compare`, defines and cflags matching one of the glob patterns
+// are left out of the diff
+#[derive(Debug, Clone, Default)]
+pub struct CompareOptions {
+    pub ignored_defines: Vec<String>,
+    pub ignored_cflags: Vec<String>,
+    // Known renames of defines, old name to new name
+    pub renames: BTreeMap<String, String>,
+    // Whether renamed defines leave the defines unchanged
+    pub allow_renames: bool,
+    // Whether a different order of the same include paths is a difference
+    pub strict_include_order: bool,
+    // Whether defines and cflags of only some compile units, as recorded in
+    // the occurrences, leave the parameters unchanged
+    pub tolerate_partial: bool,
+}
+
+// Parse a rename mapping: a TOML document of `OLD_NAME = "NEW_NAME"` pairs
+pub fn parse_renames(contents: &str) -> Result<BTreeMap<String, String>, String> {
+    let renames: BTreeMap<String, String> = toml::from_str(contents).map_err(|e| e.to_string())?;
+    if let Some((old, new)) = renames.iter().find(|(old, new)| old.is_empty() || new.is_empty()) {
+        return Err(format!("empty define name in the rename of `{}` to `{}`", old, new));
+    }
+    Ok(renames)
+}
+
+pub fn read_renames(path: &Path) -> Result<BTreeMap<String, String>, String> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    parse_renames(&contents).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+// A define present on both sides with different values
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChangedDefine {
+    pub name: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+// A define removed and added under another name, with the same value
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RenamedDefine {
+    pub old: String,
+    pub new: String,
+}
+
+// A make variable captured on both sides with different values, None
+// standing for an undefined variable
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChangedMakeVar {
+    pub name: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+// Differences between two sets of parameters. Defines are matched by name and
+// cflags compared as sets, the order of either is not significant.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ParamsDiff {
+    pub added_defines: Vec<Define>,
+    pub removed_defines: Vec<Define>,
+    pub changed_defines: Vec<ChangedDefine>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub renamed_defines: Vec<RenamedDefine>,
+    // Whether the renames leave the defines unchanged
+    #[serde(skip)]
+    pub renames_allowed: bool,
+    pub added_cflags: Vec<String>,
+    pub removed_cflags: Vec<String>,
+    // Include paths are compared as ordered lists, the search order deciding
+    // which header shadows which
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub added_includes: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub removed_includes: Vec<String>,
+    // First pair of include paths kept on both sides whose order changed, the
+    // first one now coming before the second one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reordered_includes: Option<(String, String)>,
+    // Whether the reordering is a difference
+    #[serde(skip)]
+    pub include_order_strict: bool,
+    // Only the variables captured on both sides are compared
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub changed_make_vars: Vec<ChangedMakeVar>,
+    // Defines and cflags of the diff present on only some compile units of
+    // the side having them, as (units having them, units)
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub partial_defines: BTreeMap<String, (usize, usize)>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub partial_cflags: BTreeMap<String, (usize, usize)>,
+    // Whether the partial ones are left out of the differences
+    #[serde(skip)]
+    pub partial_tolerated: bool,
+}
+
+impl ParamsDiff {
+    pub fn is_empty(&self) -> bool {
+        !self.defines_changed() && !self.cflags_changed() && !self.includes_changed() && !self.make_vars_changed()
+    }
+
+    pub fn defines_changed(&self) -> bool {
+        let counted = |name: &String| !(self.partial_tolerated && self.partial_defines.contains_key(name));
+        self.added_defines.iter().chain(&self.removed_defines).any(|d| counted(&d.name))
+            || self.changed_defines.iter().any(|d| counted(&d.name))
+            || (!self.renamed_defines.is_empty() && !self.renames_allowed)
+    }
+
+    pub fn cflags_changed(&self) -> bool {
+        let counted = |flag: &&String| !(self.partial_tolerated && self.partial_cflags.contains_key(*flag));
+        self.added_cflags.iter().chain(&self.removed_cflags).any(|f| counted(&f))
+    }
+
+    pub fn includes_changed(&self) -> bool {
+        !self.added_includes.is_empty()
+            || !self.removed_includes.is_empty()
+            || (self.reordered_includes.is_some() && self.include_order_strict)
+    }
+
+    pub fn make_vars_changed(&self) -> bool {
+        !self.changed_make_vars.is_empty()
+    }
+
+    // Old and new value of a define which was added, removed or changed,
+    // `(undefined)` standing for an absent define and `(defined)` for one
+    // without a value
+    pub fn define_change(&self, name: &str) -> Option<(String, String)> {
+        let value = |v: &Option<String>| v.clone().unwrap_or_else(|| "(defined)".to_string());
+        let undefined = || "(undefined)".to_string();
+        if let Some(d) = self.added_defines.iter().find(|d| d.name == name) {
+            return Some((undefined(), value(&d.value)));
+        }
+        if let Some(d) = self.removed_defines.iter().find(|d| d.name == name) {
+            return Some((value(&d.value), undefined()));
+        }
+        self.changed_defines.iter().find(|d| d.name == name).map(|d| (value(&d.old), value(&d.new)))
+    }
+
+    // Names of the removed defines matching `pattern` over their whole name
+    pub fn removed_matching(&self, pattern: &Regex) -> Vec<&str> {
+        self.removed_defines
+            .iter()
+            .map(|d| d.name.as_str())
+            .filter(|name| pattern.find(name).is_some_and(|m| m.range() == (0..name.len())))
+            .collect()
+    }
+
+    // One line per difference, prefixed by `+`, `-` or `~`
+    pub fn lines(&self) -> Vec<String> {
+        let value = |v: &Option<String>| v.clone().unwrap_or_default();
+        let units = |partial: Option<&(usize, usize)>| partial.map_or(String::new(), |(n, of)| format!(" (on {}/{} units)", n, of));
+        let define = |name: &String| format!("{}{}", name, units(self.partial_defines.get(name)));
+        let cflag = |flag: &String| format!("{}{}", flag, units(self.partial_cflags.get(flag)));
+        let mut lines = Vec::new();
+        lines.extend(self.added_defines.iter().map(|d| format!("+ define {}", define(&d.name))));
+        lines.extend(
+            self.changed_defines
+                .iter()
+                .map(|d| format!("~ define {}: {} -> {}", define(&d.name), value(&d.old), value(&d.new))),
+        );
+        lines.extend(self.renamed_defines.iter().map(|d| format!("~ define {} renamed to {}", d.old, d.new)));
+        lines.extend(self.removed_defines.iter().map(|d| format!("- define {}", define(&d.name))));
+        lines.extend(self.added_cflags.iter().map(|f| format!("+ cflag {}", cflag(f))));
+        lines.extend(self.removed_cflags.iter().map(|f| format!("- cflag {}", cflag(f))));
+        lines.extend(self.added_includes.iter().map(|i| format!("+ include {}", i)));
+        lines.extend(self.reordered_includes.iter().map(|(a, b)| format!("~ include {} now searched before {}", a, b)));
+        lines.extend(self.removed_includes.iter().map(|i| format!("- include {}", i)));
+        let var = |v: &Option<String>| v.clone().unwrap_or_else(|| "(undefined)".to_string());
+        lines.extend(
+            self.changed_make_vars.iter().map(|v| format!("~ make {}: {} -> {}", v.name, var(&v.old), var(&v.new))),
+        );
+        lines
+    }
+}
+
+// Compute what changed from `old` to `new`. Only defines, cflags and
+// captured make variables are compared, the other fields are informative.
+pub fn compare(old: &BuildParameters, new: &BuildParameters, options: &CompareOptions) -> ParamsDiff {
+    let define_kept = |d: &&Define| !options.ignored_defines.iter().any(|p| glob_match(p, &d.name));
+    let cflag_kept = |f: &&String| !options.ignored_cflags.iter().any(|p| glob_match(p, f));
+    let old_defines = old.defines.iter().filter(define_kept).collect::<Vec<_>>();
+    let new_defines = new.defines.iter().filter(define_kept).collect::<Vec<_>>();
+
+    let mut diff = ParamsDiff::default();
+    for define in &new_defines {
+        match old_defines.iter().find(|d| d.name == define.name) {
+            None => diff.added_defines.push((*define).clone()),
+            Some(d) if d.value != define.value => diff.changed_defines.push(ChangedDefine {
+                name: define.name.clone(),
+                old: d.value.clone(),
+                new: define.value.clone(),
+            }),
+            Some(_) => {}
+        }
+    }
+    diff.removed_defines = old_defines
+        .iter()
+        .filter(|d| !new_defines.iter().any(|x| x.name == d.name))
+        .map(|d| (*d).clone())
+        .collect();
+    // A removal and an addition matching a known rename, with equal values,
+    // are a rename
+    for (old_name, new_name) in &options.renames {
+        let removed = diff.removed_defines.iter().position(|d| d.name == *old_name);
+        let added = diff.added_defines.iter().position(|d| d.name == *new_name);
+        if let (Some(removed), Some(added)) = (removed, added) {
+            if diff.removed_defines[removed].value == diff.added_defines[added].value {
+                diff.removed_defines.remove(removed);
+                diff.added_defines.remove(added);
+                diff.renamed_defines.push(RenamedDefine { old: old_name.clone(), new: new_name.clone() });
+            }
+        }
+    }
+    diff.renames_allowed = options.allow_renames;
+    diff.added_cflags =
+        new.cflags.iter().filter(cflag_kept).filter(|f| !old.cflags.contains(f)).cloned().collect();
+    diff.removed_cflags =
+        old.cflags.iter().filter(cflag_kept).filter(|f| !new.cflags.contains(f)).cloned().collect();
+    diff.added_includes = new.includes.iter().filter(|i| !old.includes.contains(i)).cloned().collect();
+    diff.removed_includes = old.includes.iter().filter(|i| !new.includes.contains(i)).cloned().collect();
+    let old_kept = old.includes.iter().filter(|i| new.includes.contains(i)).collect::<Vec<_>>();
+    let new_kept = new.includes.iter().filter(|i| old.includes.contains(i)).collect::<Vec<_>>();
+    diff.reordered_includes = old_kept.iter().zip(&new_kept).find(|(old, new)| old != new).map(|(old, new)| {
+        // `new` took the place of `old`, which now comes after it
+        (new.to_string(), old.to_string())
+    });
+    diff.include_order_strict = options.strict_include_order;
+    // Added entries are looked up on the new side, removed ones on the old one
+    let partial_define = |name: &str, params: &BuildParameters| params.occurrences.as_ref()?.partial_define(name);
+    let partial_cflag = |flag: &str, params: &BuildParameters| params.occurrences.as_ref()?.partial_cflag(flag);
+    let define_sides = diff.added_defines.iter().map(|d| (&d.name, new)).chain(diff.removed_defines.iter().map(|d| (&d.name, old)));
+    let changed = diff.changed_defines.iter().flat_map(|d| [(&d.name, new), (&d.name, old)]);
+    for (name, params) in define_sides.chain(changed) {
+        if let Some(partial) = partial_define(name, params) {
+            diff.partial_defines.entry(name.clone()).or_insert(partial);
+        }
+    }
+    let cflag_sides = diff.added_cflags.iter().map(|f| (f, new)).chain(diff.removed_cflags.iter().map(|f| (f, old)));
+    for (flag, params) in cflag_sides {
+        if let Some(partial) = partial_cflag(flag, params) {
+            diff.partial_cflags.insert(flag.clone(), partial);
+        }
+    }
+    diff.partial_tolerated = options.tolerate_partial;
+    for (name, value) in &new.metadata.make_vars {
+        match old.metadata.make_vars.get(name) {
+            Some(old_value) if old_value != value => diff.changed_make_vars.push(ChangedMakeVar {
+                name: name.clone(),
+                old: old_value.clone(),
+                new: value.clone(),
+            }),
+            _ => {}
+        }
+    }
+    diff
+}
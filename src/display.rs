@@ -0,0 +1,83 @@
+use serde::{Deserialize, Serialize};
+
+use crate::files::Define;
+
+// Defines giving each display parameter, the first one found winning
+const WIDTH_DEFINES: [&str; 2] = ["SCREEN_WIDTH", "BAGL_WIDTH"];
+const HEIGHT_DEFINES: [&str; 2] = ["SCREEN_HEIGHT", "BAGL_HEIGHT"];
+const SCREEN_SIZE_DEFINES: [(&str, &str); 2] = [("SCREEN_SIZE_NANO", "nano"), ("SCREEN_SIZE_WALLET", "wallet")];
+const UI_LIBRARY_DEFINES: [(&str, &str); 2] = [("HAVE_NBGL", "nbgl"), ("HAVE_BAGL", "bagl")];
+const EINK_DEFINE: &str = "HAVE_SE_EINK_DISPLAY";
+const FONT_PREFIX: &str = "HAVE_BAGL_FONT_";
+
+// Display defines expected on each device family, a missing one usually
+// meaning that the wrong UI library was selected
+const EXPECTED_DISPLAY_DEFINES: [(&[&str], &[&str]); 2] = [
+    (&["nanox", "nanosplus"], &["SCREEN_SIZE_NANO", "BAGL_WIDTH", "BAGL_HEIGHT"]),
+    (&["stax", "flex", "apex_p"], &["SCREEN_SIZE_WALLET", "HAVE_NBGL"]),
+];
+
+// Screen and font parameters of a device, from its display defines
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Display {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    // `nano` or `wallet`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub screen_size: Option<String>,
+    // `nbgl` or `bagl`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ui_library: Option<String>,
+    #[serde(default)]
+    pub eink: bool,
+    // Fonts built in, from the HAVE_BAGL_FONT_* defines, e.g. `INTER_REGULAR_24PX`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fonts: Vec<String>,
+}
+
+impl Display {
+    // Value of a field as printed by `inspect --get display.<field>`, lists
+    // being space-separated
+    pub fn get(&self, field: &str) -> Option<String> {
+        let value = serde_json::to_value(self).expect("Failed to serialize display");
+        match value.get(field)? {
+            serde_json::Value::String(text) => Some(text.clone()),
+            serde_json::Value::Array(items) => {
+                Some(items.iter().filter_map(|i| i.as_str()).collect::<Vec<_>>().join(" "))
+            }
+            other => Some(other.to_string()),
+        }
+    }
+}
+
+// Display parameters of a set of defines, None without any display define.
+// Sizes which are not plain numbers, possibly parenthesized, are left out.
+pub fn display_parameters(defines: &[Define]) -> Option<Display> {
+    let find = |name: &str| defines.iter().find(|d| d.name == name);
+    let number = |names: &[&str]| {
+        let value = names.iter().find_map(|name| find(name))?.value.as_deref()?;
+        value.trim().trim_start_matches('(').trim_end_matches(')').trim().parse().ok()
+    };
+    let flag = |table: &[(&str, &str)]| table.iter().find(|(name, _)| find(name).is_some()).map(|(_, v)| v.to_string());
+    let display = Display {
+        width: number(&WIDTH_DEFINES),
+        height: number(&HEIGHT_DEFINES),
+        screen_size: flag(&SCREEN_SIZE_DEFINES),
+        ui_library: flag(&UI_LIBRARY_DEFINES),
+        eink: find(EINK_DEFINE).is_some(),
+        fonts: defines.iter().filter_map(|d| d.name.strip_prefix(FONT_PREFIX)).map(str::to_string).collect(),
+    };
+    Some(display).filter(|d| *d != Display::default())
+}
+
+// Display defines expected on the device but missing from `defines`
+pub fn missing_display_defines(device: &str, defines: &[Define]) -> Vec<&'static str> {
+    EXPECTED_DISPLAY_DEFINES
+        .iter()
+        .filter(|(devices, _)| devices.contains(&device))
+        .flat_map(|(_, expected)| expected.iter().copied())
+        .filter(|name| !defines.iter().any(|d| d.name == *name))
+        .collect()
+}
@@ -0,0 +1,121 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anstream::println;
+
+use cbpx::{
+    api_level::sdk_api_level,
+    compiler::probe_compiler,
+    device::{DeviceInfo, DEVICES},
+    git::head_hash,
+    make::make_version,
+    sdk_layout::missing_sdk_files,
+};
+
+use crate::color;
+
+#[derive(clap::Args, Debug)]
+pub struct DoctorArgs {
+    /// Output directory to check write access to
+    #[arg(short, long, default_value = ".")]
+    output_dir: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl CheckStatus {
+    pub fn label(self) -> &'static str {
+        match self {
+            CheckStatus::Pass => "pass",
+            CheckStatus::Warn => "warn",
+            CheckStatus::Fail => "fail",
+        }
+    }
+}
+
+struct Check {
+    item: String,
+    status: CheckStatus,
+    detail: String,
+}
+
+fn check(item: &str, status: CheckStatus, detail: String) -> Check {
+    Check { item: item.to_string(), status, detail }
+}
+
+// The extraction is a dry run, so a missing compiler only loses the compiler
+// metadata
+fn compiler_check(command: &str) -> Check {
+    let cwd = env::current_dir().unwrap_or_default();
+    let info = probe_compiler(command, &cwd);
+    match (info.resolved_path, info.version) {
+        (Some(path), Some(version)) => check(command, CheckStatus::Pass, format!("{} ({})", version, path)),
+        (Some(path), None) => check(command, CheckStatus::Warn, format!("{} found but `--version` failed", path)),
+        (None, _) => check(command, CheckStatus::Warn, "not found, the compiler metadata will be empty".to_string()),
+    }
+}
+
+fn sdk_check(device: &DeviceInfo) -> Check {
+    let sdk_env = device.sdk_env;
+    let Ok(sdk) = env::var(sdk_env) else {
+        return check(sdk_env, CheckStatus::Warn, "not set, the device can't be extracted".to_string());
+    };
+    let path = Path::new(&sdk);
+    if !path.is_dir() {
+        return check(sdk_env, CheckStatus::Fail, format!("{} is not a directory", sdk));
+    }
+    let missing = missing_sdk_files(device, path);
+    if !missing.is_empty() {
+        return check(sdk_env, CheckStatus::Fail, format!("{} is incomplete, missing {}", sdk, missing.join(", ")));
+    }
+    let api_level = sdk_api_level(path, None).map_or("unknown API level".to_string(), |(l, _)| format!("API level {}", l));
+    match head_hash(path) {
+        Some(hash) => check(sdk_env, CheckStatus::Pass, format!("{} (git {}, {})", sdk, &hash[..hash.len().min(12)], api_level)),
+        None => check(sdk_env, CheckStatus::Warn, format!("{} is not a git checkout, stamps are disabled ({})", sdk, api_level)),
+    }
+}
+
+fn docker_check() -> Check {
+    match Command::new("docker").arg("--version").output() {
+        Ok(output) if output.status.success() => {
+            check("docker", CheckStatus::Pass, String::from_utf8_lossy(&output.stdout).trim().to_string())
+        }
+        _ => check("docker", CheckStatus::Warn, "not available".to_string()),
+    }
+}
+
+fn output_dir_check(output_dir: &Path) -> Check {
+    let probe = output_dir.join(".cbpx-doctor");
+    let written = fs::create_dir_all(output_dir).and_then(|_| fs::write(&probe, b"")).and_then(|_| fs::remove_file(&probe));
+    match written {
+        Ok(()) => check("output dir", CheckStatus::Pass, format!("{} is writable", output_dir.display())),
+        Err(e) => check("output dir", CheckStatus::Fail, format!("{} is not writable: {}", output_dir.display(), e)),
+    }
+}
+
+// Run the `doctor` subcommand, returning 1 when any check fails
+pub fn run(args: &DoctorArgs) -> i32 {
+    let mut checks = vec![match make_version() {
+        Ok(version) => check("make", CheckStatus::Pass, version),
+        Err(e) => check("make", CheckStatus::Fail, e),
+    }];
+    checks.push(compiler_check("clang"));
+    checks.push(compiler_check("arm-none-eabi-gcc"));
+    checks.extend(DEVICES.iter().map(sdk_check));
+    checks.push(docker_check());
+    checks.push(output_dir_check(&args.output_dir));
+
+    let width = checks.iter().map(|c| c.item.len()).max().unwrap_or(0);
+    for c in &checks {
+        println!("{}  {:<width$}  {}", color::check(c.status), c.item, c.detail);
+    }
+    if checks.iter().any(|c| c.status == CheckStatus::Fail) { 1 } else { 0 }
+}
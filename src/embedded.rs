@@ -0,0 +1,42 @@
+use std::{collections::BTreeSet, fs, path::Path};
+
+// Reference file of a released SDK, embedded with the embedded-references
+// feature
+pub struct EmbeddedReference {
+    pub api_level: &'static str,
+    pub device: &'static str,
+    pub artifact: &'static str,
+    pub contents: &'static str,
+}
+
+// Generated by build.rs from the embedded_references directory
+include!(concat!(env!("OUT_DIR"), "/embedded_references.rs"));
+
+// API levels of the embedded references
+pub fn embedded_api_levels() -> BTreeSet<&'static str> {
+    EMBEDDED_REFERENCES.iter().map(|r| r.api_level).collect()
+}
+
+// Check that references of an API level are embedded
+pub fn check_embedded_api_level(api_level: &str) -> Result<(), String> {
+    if EMBEDDED_REFERENCES.is_empty() {
+        return Err("cbpx was built without the embedded-references feature".to_string());
+    }
+    if !EMBEDDED_REFERENCES.iter().any(|r| r.api_level == api_level) {
+        let levels = embedded_api_levels().into_iter().collect::<Vec<_>>().join(", ");
+        return Err(format!("No embedded references for API level {} (available: {})", api_level, levels));
+    }
+    Ok(())
+}
+
+// Write the embedded references of a device for an API level to `dir`, named
+// `<base_name>.<artifact>` with the base name rendered from the name template
+pub fn write_embedded_references(api_level: &str, device: &str, dir: &Path, base_name: &str) -> Result<(), String> {
+    check_embedded_api_level(api_level)?;
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    for reference in EMBEDDED_REFERENCES.iter().filter(|r| r.api_level == api_level && r.device == device) {
+        let path = dir.join(format!("{}.{}", base_name, reference.artifact));
+        fs::write(&path, reference.contents).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    }
+    Ok(())
+}
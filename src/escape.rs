@@ -0,0 +1,60 @@
+use crate::files::Define;
+
+// Escaping applied to the words of the defines and cflags text outputs, for
+// consumers substituting them into a shell command or a Makefile
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Escaping {
+    /// POSIX shell quoting, words with special characters are single-quoted
+    Shell,
+    /// GNU make escaping as in .d files: `$$`, `\#` and `\ `
+    Make,
+    /// Words are written as found on the compile line
+    #[default]
+    None,
+}
+
+impl Escaping {
+    pub fn name(self) -> &'static str {
+        match self {
+            Escaping::Shell => "shell",
+            Escaping::Make => "make",
+            Escaping::None => "none",
+        }
+    }
+}
+
+// Characters which never need quoting in a shell word
+fn is_shell_safe(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "_-+=/.,:@%^".contains(c)
+}
+
+pub fn escape(word: &str, escaping: Escaping) -> String {
+    match escaping {
+        Escaping::Shell if !word.is_empty() && word.chars().all(is_shell_safe) => word.to_string(),
+        Escaping::Shell => format!("'{}'", word.replace('\'', "'\\''")),
+        Escaping::Make => word.replace('$', "$$").replace('#', "\\#").replace(' ', "\\ "),
+        Escaping::None => word.to_string(),
+    }
+}
+
+// Defines with their values escaped, names being plain identifiers
+pub fn escape_defines(defines: &[Define], escaping: Escaping) -> Vec<Define> {
+    defines
+        .iter()
+        .map(|d| Define { name: d.name.clone(), value: d.value.as_deref().map(|v| escape(v, escaping)) })
+        .collect()
+}
+
+pub fn escape_cflags(cflags: &[String], escaping: Escaping) -> Vec<String> {
+    cflags.iter().map(|f| escape(f, escaping)).collect()
+}
+
+// Header line of the text outputs recording the escaping applied, none
+// without escaping so that unescaped outputs are unchanged. `comment` is the
+// comment leader of the output format.
+pub fn header(escaping: Escaping, comment: &str) -> String {
+    match escaping {
+        Escaping::None => String::new(),
+        escaping => format!("{} Escaped for {} by cbpx\n", comment, escaping.name()),
+    }
+}
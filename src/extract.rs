@@ -0,0 +1,403 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Instant,
+};
+
+use regex::Regex;
+
+use crate::{
+    api_level::{app_api_level, check_api_level, sdk_api_level},
+    app_flags::find_app_flags,
+    build_dir::{objects_build_dir, relative_build_dir, relative_to_app},
+    builtin::query_builtin_defines,
+    backend::{LocalMake, TraceRequest, TraceSource},
+    compiler::detect_compiler,
+    consistency::{kind_defines, KindDefines},
+    device::DeviceInfo,
+    display::display_parameters,
+    files::Define,
+    filter::{move_large_defines, FilterSet, DEFAULT_MAX_DEFINE_LENGTH},
+    flags::{codegen_flags, is_codegen_flag, normalize_warnings, warning_flags},
+    git::modified_files,
+    host_paths::drop_host_paths,
+    identifier::{define_token, is_valid_define_name, OnInvalid},
+    linker::memory_layout,
+    naming::app_name_from_define,
+    params::{BuildParameters, Metadata, Metrics, SCHEMA_VERSION},
+    parse_issue::{compile_line_issues, ParseIssue},
+    plan::makefile_path,
+    portable::PathRoots,
+    quoting::{normalize_define_value, normalize_define_values},
+    rust_target::rust_target,
+    scope::{app_only, Scope},
+    trace::{
+        default_compiler_pattern, define_overrides, find_numbered_compile_lines, line_toolchain, no_compile_line_error,
+        parse_source,
+    },
+    unit::{merge_units, occurrences, parse_compile_unit, Strategy},
+};
+
+// Everything an extraction depends on besides the device and the app. The
+// SDK is given explicitly, the environment of the process is never read.
+#[derive(Debug, Clone)]
+pub struct ExtractOptions {
+    pub bolos_sdk: PathBuf,
+    // Makefile relative to the app path, `Makefile` when None
+    pub makefile: Option<PathBuf>,
+    // Additional make arguments, such as variable assignments
+    pub make_args: Vec<String>,
+    pub backend: Arc<dyn TraceSource>,
+    pub strategy: Strategy,
+    pub scope: Scope,
+    // Pattern of the compiler command of compile lines, wrappers skipped
+    pub compiler_pattern: Regex,
+    pub define_filters: FilterSet,
+    pub cflag_filters: FilterSet,
+    // Defines longer than this are moved to the metadata, None keeps them all
+    pub max_define_length: Option<usize>,
+    // Remove the codegen flags from the cflags
+    pub split_flags: bool,
+    pub drop_host_paths: bool,
+    // Keep only the effective flag of each warning
+    pub normalize_warnings: bool,
+    // Record the define set of each kind of invocation
+    pub check_consistency: bool,
+    pub capture_make_vars: Vec<String>,
+    // Query the predefined macros of the compiler for the target flags
+    pub builtin_defines: bool,
+    // Replace invalid UTF-8 in compile lines instead of failing
+    pub lossy: bool,
+    // Defines whose name is not a valid macro name fail the extraction or
+    // are left out
+    pub on_invalid: OnInvalid,
+    // List the units of each define and cflag in the occurrences, besides
+    // counting them
+    pub occurrence_units: bool,
+    // Keep the define values with the shell quoting of the compile lines
+    // rather than as the compiler receives them
+    pub raw_values: bool,
+    // Rewrite the define values which are paths below the SDK or the app, as
+    // the include paths always are
+    pub portable_paths: bool,
+}
+
+impl ExtractOptions {
+    // Options of a plain extraction with the default filters
+    pub fn new(bolos_sdk: impl Into<PathBuf>) -> Self {
+        ExtractOptions {
+            bolos_sdk: bolos_sdk.into(),
+            makefile: None,
+            make_args: Vec::new(),
+            backend: Arc::new(LocalMake::default()),
+            strategy: Strategy::First,
+            scope: Scope::All,
+            compiler_pattern: default_compiler_pattern(),
+            define_filters: FilterSet::default_defines(),
+            cflag_filters: FilterSet::default(),
+            max_define_length: Some(DEFAULT_MAX_DEFINE_LENGTH),
+            split_flags: false,
+            drop_host_paths: false,
+            normalize_warnings: false,
+            check_consistency: false,
+            capture_make_vars: Vec::new(),
+            builtin_defines: false,
+            lossy: false,
+            on_invalid: OnInvalid::Error,
+            occurrence_units: false,
+            raw_values: false,
+            portable_paths: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ExtractError {
+    // The trace source failed, e.g. make could not be run
+    Make(String),
+    // The trace has no compile line, `trace` is kept for bug reports
+    NoCompileLine { message: String, trace: Vec<u8> },
+    // The trace could not be decoded
+    Trace(String),
+    // The make variables could not be queried
+    MakeVariables(String),
+    // The compiler could not dump its builtin defines
+    BuiltinDefines(String),
+    // The app compile units could not be told apart from the SDK ones
+    Scope(String),
+    // Words of the compile lines could not be parsed, e.g. a define name is
+    // not a valid macro name
+    Parse(Vec<ParseIssue>),
+}
+
+impl fmt::Display for ExtractError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExtractError::Make(message)
+            | ExtractError::NoCompileLine { message, .. }
+            | ExtractError::Trace(message)
+            | ExtractError::MakeVariables(message)
+            | ExtractError::BuiltinDefines(message)
+            | ExtractError::Scope(message) => f.write_str(message),
+            ExtractError::Parse(issues) => {
+                for issue in issues {
+                    writeln!(f, "{}", issue)?;
+                }
+                f.write_str("Use --on-invalid skip to leave the invalid defines out")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ExtractError {}
+
+// Rewrite the define values which are paths below the SDK or the app,
+// returning the rewritten defines with their values as extracted
+fn portable_define_paths(defines: &mut [Define], roots: &PathRoots, make_dir: &Path) -> Vec<Define> {
+    let mut raw = Vec::new();
+    for define in defines {
+        let Some(portable) = define.value.as_deref().and_then(|v| roots.portable_define_value(v, make_dir)) else {
+            continue;
+        };
+        raw.push(define.clone());
+        define.value = Some(portable);
+    }
+    raw
+}
+
+// Extract the build parameters of an app for a device. Nothing is written
+// and no process-wide state is touched, so that extractions of different
+// devices can run concurrently.
+pub fn extract(device: &DeviceInfo, app_path: &Path, options: &ExtractOptions) -> Result<BuildParameters, ExtractError> {
+    extract_with_progress(device, app_path, options, || {})
+}
+
+// `extract`, calling `on_line` with every line of the trace as it is received
+pub fn extract_with_progress(
+    device: &DeviceInfo,
+    app_path: &Path,
+    options: &ExtractOptions,
+    mut on_line: impl FnMut(),
+) -> Result<BuildParameters, ExtractError> {
+    let sdk = options.bolos_sdk.as_path();
+    let makefile = makefile_path(app_path, options.makefile.as_deref());
+    let request =
+        TraceRequest { device, app_path, makefile: &makefile, bolos_sdk: sdk, make_args: &options.make_args };
+    let make_dir = request.make_dir();
+
+    let started = Instant::now();
+    let trace = options.backend.trace(&request, &mut on_line).map_err(ExtractError::Make)?;
+    let (trace, make_failure, make_stderr) = (trace.output, trace.failure, trace.stderr);
+    let make_ms = started.elapsed().as_millis() as u64;
+    let parse_started = Instant::now();
+
+    let numbered_lines =
+        find_numbered_compile_lines(&trace, options.lossy, &options.compiler_pattern).map_err(ExtractError::Trace)?;
+    let compile_lines = numbered_lines.iter().map(|(_, line)| line.clone()).collect::<Vec<_>>();
+    let Some(compile_line) = compile_lines.first() else {
+        let message = make_failure.unwrap_or_default() + &no_compile_line_error(&trace);
+        return Err(ExtractError::NoCompileLine { message, trace });
+    };
+    let trace_text = String::from_utf8_lossy(&trace);
+
+    let roots = PathRoots::new(sdk, app_path);
+    let compiler = detect_compiler(compile_line, make_dir);
+    let memory = memory_layout(&trace_text, make_dir, sdk).map(|mut memory| {
+        memory.linker_script = roots.portable(&memory.linker_script);
+        memory
+    });
+    let mut dropped_host_paths: Vec<String> = Vec::new();
+    let mut units = compile_lines
+        .iter()
+        .map(|line| {
+            if !options.drop_host_paths {
+                return parse_compile_unit(line);
+            }
+            let (line, dropped) = drop_host_paths(line, &roots);
+            dropped_host_paths.extend(dropped.into_iter().filter(|d| !dropped_host_paths.contains(d)).collect::<Vec<_>>());
+            parse_compile_unit(&line)
+        })
+        .collect::<Vec<_>>();
+    if !options.raw_values {
+        for unit in &mut units {
+            normalize_define_values(&mut unit.defines, normalize_define_value);
+        }
+    }
+    // Build directory of the first line, and of the other lines with the
+    // strategies combining them
+    let checked_lines = if options.strategy == Strategy::First { &compile_lines[..1] } else { &compile_lines[..] };
+    let line_build_dirs = checked_lines
+        .iter()
+        .filter_map(|line| Some((line, relative_build_dir(line, make_dir, app_path, &roots)?)))
+        .collect::<Vec<_>>();
+    // Without dependency files, the objects of every line tell where the build
+    // directory is
+    let build_dir = match line_build_dirs.first() {
+        Some((_, dir)) => Some(dir.clone()),
+        None => objects_build_dir(compile_lines.iter().map(String::as_str))
+            .map(|dir| relative_to_app(&dir, make_dir, app_path, &roots)),
+    };
+    let build_dir_conflicts = line_build_dirs
+        .iter()
+        .filter(|(_, dir)| Some(dir) != build_dir.as_ref())
+        .map(|(line, dir)| (parse_source(line).map(|s| roots.portable(&s)).unwrap_or_default(), dir.clone()))
+        .collect();
+    // Defines undone or redone by a -U, recorded once per distinct sequence
+    let mut overrides = Vec::new();
+    for line in &compile_lines {
+        for define_override in define_overrides(line) {
+            if !overrides.contains(&define_override) {
+                overrides.push(define_override);
+            }
+        }
+    }
+    let invocation_defines =
+        if options.check_consistency { kind_defines(&units, &trace_text) } else { KindDefines::new() };
+    let (mut defines, mut cflags, includes) = merge_units(&units, options.strategy);
+    let invalid_defines =
+        defines.iter().filter(|d| !is_valid_define_name(&d.name)).map(define_token).collect::<Vec<_>>();
+    // Issues of the lines the strategy combines, by first occurrence. Invalid
+    // defines which made it through the strategy fail the extraction, as does
+    // a `-D` whose name is in the next word, whatever became of it.
+    let mut parse_issues: Vec<ParseIssue> = Vec::new();
+    for (index, line) in &numbered_lines[..checked_lines.len()] {
+        for mut issue in compile_line_issues(device.name, index + 1, line) {
+            if !parse_issues.iter().any(|i| i.token == issue.token) {
+                issue.fatal = options.on_invalid == OnInvalid::Error
+                    && (issue.token == "-D" || invalid_defines.contains(&issue.token));
+                parse_issues.push(issue);
+            }
+        }
+    }
+    if parse_issues.iter().any(|i| i.fatal) {
+        return Err(ExtractError::Parse(parse_issues.into_iter().filter(|i| i.fatal).collect()));
+    }
+    defines.retain(|d| is_valid_define_name(&d.name));
+    let path_defines =
+        if options.portable_paths { portable_define_paths(&mut defines, &roots, make_dir) } else { Vec::new() };
+    let includes = includes.iter().map(|i| roots.portable(i)).collect::<Vec<_>>();
+    let mut app_only = match options.scope {
+        Scope::All => None,
+        Scope::App => Some(app_only(&units, make_dir, app_path, &roots).map_err(ExtractError::Scope)?),
+    };
+    let sources = units.iter().filter_map(|u| u.source.as_deref()).map(|s| roots.portable(s)).collect::<Vec<_>>();
+
+    // The app name, target identifiers, API level and builtin defines are
+    // taken before filtering
+    let define_value = |name: &str| defines.iter().find(|d| d.name == name).and_then(|d| d.value.clone());
+    let app_name = define_value("APPNAME").and_then(|v| app_name_from_define(&v));
+    let (target_id, target_name) = (define_value("TARGET_ID"), define_value("TARGET_NAME"));
+    let api_level = check_api_level(
+        app_api_level(&makefile, app_path),
+        sdk_api_level(sdk, define_value("API_LEVEL").as_deref()),
+    );
+    let rust_target = rust_target(&cflags).to_string();
+    let display = display_parameters(&defines);
+    let app_flags = find_app_flags(&trace_text, &defines);
+    let builtin_defines = if options.builtin_defines {
+        query_builtin_defines(compiler.as_ref(), &cflags, make_dir).map_err(ExtractError::BuiltinDefines)?
+    } else {
+        Vec::new()
+    };
+
+    defines.retain(|d| !options.define_filters.is_filtered(&d.name));
+    let moved_defines = match options.max_define_length {
+        Some(max_length) => move_large_defines(&mut defines, max_length),
+        None => Vec::new(),
+    };
+    cflags.retain(|f| !options.cflag_filters.is_filtered(f));
+    if let Some(app_only) = app_only.as_mut() {
+        app_only.defines.retain(|d| !options.define_filters.is_filtered(&d.name) && is_valid_define_name(&d.name));
+        app_only.cflags.retain(|f| !options.cflag_filters.is_filtered(f));
+        app_only.includes = app_only.includes.iter().map(|i| roots.portable(i)).collect();
+        if options.portable_paths {
+            portable_define_paths(&mut app_only.defines, &roots, make_dir);
+        }
+    }
+    let overridden_warnings = if options.normalize_warnings {
+        let (effective, overridden) = normalize_warnings(&cflags);
+        cflags = effective;
+        overridden
+    } else {
+        Vec::new()
+    };
+
+    // Only the union holds flags of some of the units
+    let occurrences = (options.strategy == Strategy::Union).then(|| {
+        let names = units
+            .iter()
+            .map(|u| u.source.as_deref().or(u.object.as_deref()).map(|s| roots.portable(s)).unwrap_or_default())
+            .collect::<Vec<_>>();
+        let mut occurrences = occurrences(&units, options.occurrence_units.then_some(&names[..]));
+        occurrences.defines.retain(|name, _| defines.iter().any(|d| d.name == *name));
+        occurrences.cflags.retain(|flag, _| cflags.contains(flag));
+        occurrences
+    });
+    let codegen = codegen_flags(&cflags);
+    if options.split_flags {
+        cflags.retain(|f| !is_codegen_flag(f));
+    }
+    // Warning flags, in their original order, for porting the SDK warning policy
+    let warnings = warning_flags(&cflags);
+
+    let make_vars = if options.capture_make_vars.is_empty() {
+        Default::default()
+    } else {
+        options.backend.query_make_variables(&request, &options.capture_make_vars).map_err(ExtractError::MakeVariables)?
+    };
+
+    let toolchain = line_toolchain(compile_line);
+    let metrics = Metrics {
+        make_ms,
+        trace_lines: trace.split(|b| *b == b'\n').filter(|l| !l.is_empty()).count(),
+        trace_bytes: trace.len(),
+        compile_lines: compile_lines.len(),
+        parse_ms: parse_started.elapsed().as_millis() as u64,
+    };
+    Ok(BuildParameters {
+        schema_version: SCHEMA_VERSION,
+        device: device.name.to_string(),
+        target: device.target.to_string(),
+        defines,
+        cflags,
+        includes,
+        sources,
+        warnings,
+        codegen,
+        raw_commands: compile_lines.clone(),
+        builtin_defines,
+        occurrences,
+        app_only,
+        make_stderr,
+        metrics,
+        metadata: Metadata {
+            app_name,
+            target_id,
+            target_name,
+            makefile: Some(roots.portable(&makefile.display().to_string())),
+            build_dir,
+            build_dir_conflicts,
+            compiler,
+            toolchain,
+            memory,
+            display,
+            app_flags,
+            make_vars,
+            sdk_dirty: modified_files(sdk).map(|f| !f.is_empty()),
+            hermetic: options.backend.hermetic(),
+            rust_target,
+            api_level: Some(api_level),
+            moved_defines,
+            path_defines,
+            dropped_host_paths,
+            overridden_warnings,
+            werror_stripped: Vec::new(),
+            define_overrides: overrides,
+            invalid_defines,
+            invocation_defines,
+            parse_issues,
+            applied_overrides: Vec::new(),
+        },
+    })
+}
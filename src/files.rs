@@ -0,0 +1,147 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+// A `-D` define as found on the compile line: `value` is None for `-DNAME`
+// and Some for `-DNAME=VALUE`, the value being possibly empty (`-DNAME=`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Define {
+    pub name: String,
+    pub value: Option<String>,
+}
+
+// Render defines the way they are stored in the `.defines` files, one
+// `#define NAME [VALUE]` per line. An empty value keeps its separating space
+// so that `-DNAME=` and `-DNAME` stay distinguishable.
+pub fn render_defines(defines: &[Define]) -> String {
+    let mut out = String::new();
+    for define in defines {
+        out.push_str("#define ");
+        out.push_str(&define.name);
+        if let Some(value) = &define.value {
+            out.push(' ');
+            out.push_str(value);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+pub fn render_cflags(cflags: &[String]) -> String {
+    cflags.iter().map(|flag| format!("{}\n", flag)).collect()
+}
+
+// Parse the contents of a `.defines` file back into defines. This is the
+// exact inverse of `render_defines`, `//` comment lines being ignored.
+pub fn parse_defines(contents: &str) -> Result<Vec<Define>, String> {
+    let mut defines = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with("//") {
+            continue;
+        }
+        let rest = line
+            .strip_prefix("#define ")
+            .ok_or_else(|| format!("line {}: expected `#define NAME [VALUE]`, got `{}`", index + 1, line))?;
+        let define = match rest.split_once(' ') {
+            Some((name, value)) => Define { name: name.to_string(), value: Some(value.to_string()) },
+            None => Define { name: rest.to_string(), value: None },
+        };
+        defines.push(define);
+    }
+    Ok(defines)
+}
+
+// Parse a file of one word per line, `#` comment lines being ignored
+pub fn parse_cflags(contents: &str) -> Vec<String> {
+    contents.lines().filter(|l| !l.trim().is_empty() && !l.starts_with('#')).map(|l| l.to_string()).collect()
+}
+
+// Normal form of the output files, produced when writing them and applied to
+// both sides of a comparison:
+// - CRLF and lone CR line endings become LF,
+// - trailing whitespace is stripped from every line, except with
+//   `keep_trailing_spaces` (`.defines` files, where the space following the
+//   name marks an empty value),
+// - a non-empty text ends with exactly one newline, an empty one stays empty.
+pub fn normalize(text: &str, keep_trailing_spaces: bool) -> String {
+    let text = text.replace("\r\n", "\n").replace('\r', "\n");
+    let mut out = String::new();
+    for line in text.trim_end_matches('\n').split('\n') {
+        out.push_str(if keep_trailing_spaces { line } else { line.trim_end() });
+        out.push('\n');
+    }
+    if out.trim().is_empty() {
+        out.clear();
+    }
+    out
+}
+
+// Normal form of an output file given its extension
+pub fn normalize_output(extension: &str, text: &str) -> String {
+    normalize(text, extension == "defines" || extension.ends_with(".defines"))
+}
+
+// Normalized contents of an output file
+pub fn read_output(path: &Path) -> Result<String, String> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let extension = path.extension().map(|e| e.to_string_lossy().into_owned()).unwrap_or_default();
+    Ok(normalize_output(&extension, &contents))
+}
+
+pub fn read_defines(path: &Path) -> Result<Vec<Define>, String> {
+    let contents = read_output(path)?;
+    parse_defines(&contents).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+pub fn read_cflags(path: &Path) -> Result<Vec<String>, String> {
+    Ok(parse_cflags(&read_output(path)?))
+}
+
+// Render make variables as stored in the `.makevars` files, `NAME=VALUE` per
+// line and only `NAME` for an undefined variable
+pub fn render_make_vars(vars: &BTreeMap<String, Option<String>>) -> String {
+    let mut out = String::new();
+    for (name, value) in vars {
+        out.push_str(name);
+        if let Some(value) = value {
+            out.push('=');
+            out.push_str(value);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+pub fn parse_make_vars(contents: &str) -> BTreeMap<String, Option<String>> {
+    contents
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| match l.split_once('=') {
+            Some((name, value)) => (name.to_string(), Some(value.to_string())),
+            None => (l.to_string(), None),
+        })
+        .collect()
+}
+
+pub fn read_make_vars(path: &Path) -> Result<BTreeMap<String, Option<String>>, String> {
+    Ok(parse_make_vars(&read_output(path)?))
+}
+
+// Write a file unless it already has these contents, so that its mtime only
+// changes with its contents. The file is replaced atomically through a
+// temporary sibling, readers never see it half-written. Returns whether it
+// was written.
+pub fn write_if_changed(path: &Path, contents: &[u8]) -> Result<bool, String> {
+    if fs::read(path).is_ok_and(|existing| existing == contents) {
+        return Ok(false);
+    }
+    let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let temporary = path.with_file_name(format!(".{}.{}.tmp", name, std::process::id()));
+    fs::write(&temporary, contents)
+        .and_then(|_| fs::rename(&temporary, path))
+        .map_err(|e| {
+            let _ = fs::remove_file(&temporary);
+            format!("Failed to write {}: {}", path.display(), e)
+        })?;
+    Ok(true)
+}
@@ -0,0 +1,183 @@
+use std::{fs, path::Path};
+
+use crate::{category::glob_match, files::Define};
+
+// Defines filtered out of the outputs by default, as they are app-specific
+// rather than SDK-intrinsic. Commented entries were considered and kept.
+const DEFAULT_DEFINES: [(&str, &str); 6] = [
+    // "APPNAME",
+    // "HAVE_SWAP",
+    // "PRINTF(...)",
+    ("MAJOR_VERSION", "app version, changes with every release"),
+    ("MINOR_VERSION", "app version, changes with every release"),
+    ("PATCH_VERSION", "app version, changes with every release"),
+    // "API_LEVEL",
+    // "TARGET",
+    // "TARGET_NAME",
+    ("APPVERSION", "app version, changes with every release"),
+    // "SDK_NAME",
+    ("SDK_VERSION", "SDK version, changes with every SDK commit"),
+    ("SDK_HASH", "SDK version, changes with every SDK commit"),
+    // "HAVE_NES_CRYPT",
+    // "HAVE_ST_AES",
+    // "NATIVE_LITTLE_ENDIAN",
+    // "HAVE_CRC",
+    // "HAVE_HASH",
+    // "HAVE_RIPEMD160",
+    // "HAVE_SHA224",
+    // "HAVE_SHA256",
+    // "HAVE_SHA3",
+    // "HAVE_SHA384",
+    // "HAVE_SHA512",
+    // "HAVE_SHA512_WITH_BLOCK_ALT_METHOD",
+    // "HAVE_SHA512_WITH_BLOCK_ALT_METHOD_M0",
+    // "HAVE_BLAKE2",
+    // "HAVE_HMAC",
+    // "HAVE_PBKDF2",
+    // "HAVE_AES",
+    // "HAVE_MATH",
+    // "HAVE_RNG",
+    // "HAVE_RNG_RFC6979",
+    // "HAVE_RNG_SP800_90A",
+    // "HAVE_ECC",
+    // "HAVE_ECC_WEIERSTRASS",
+    // "HAVE_ECC_TWISTED_EDWARDS",
+    // "HAVE_ECC_MONTGOMERY",
+    // "HAVE_SECP256K1_CURVE",
+    // "HAVE_SECP256R1_CURVE",
+    // "HAVE_SECP384R1_CURVE",
+    // "HAVE_SECP521R1_CURVE",
+    // "HAVE_FR256V1_CURVE",
+    // "HAVE_STARK256_CURVE",
+    // "HAVE_BRAINPOOL_P256R1_CURVE",
+    // "HAVE_BRAINPOOL_P256T1_CURVE",
+    // "HAVE_BRAINPOOL_P320R1_CURVE",
+    // "HAVE_BRAINPOOL_P320T1_CURVE",
+    // "HAVE_BRAINPOOL_P384R1_CURVE",
+    // "HAVE_BRAINPOOL_P384T1_CURVE",
+    // "HAVE_BRAINPOOL_P512R1_CURVE",
+    // "HAVE_BRAINPOOL_P512T1_CURVE",
+    // "HAVE_BLS12_381_G1_CURVE",
+    // "HAVE_CV25519_CURVE",
+    // "HAVE_CV448_CURVE",
+    // "HAVE_ED25519_CURVE",
+    // "HAVE_ED448_CURVE",
+    // "HAVE_ECDH",
+    // "HAVE_ECDSA",
+    // "HAVE_EDDSA",
+    // "HAVE_ECSCHNORR",
+    // "HAVE_X25519",
+    // "HAVE_X448",
+    // "HAVE_AES_GCM",
+    // "HAVE_CMAC",
+    // "HAVE_AES_SIV",
+];
+
+// Defines always moved out of the defines output into the metadata, on top of
+// the ones longer than the maximum length. APP_INSTALL_PARAMS_DATA is a long
+// hex blob differing for every app version.
+const MOVED_DEFINES: [&str; 1] = ["APP_INSTALL_PARAMS_DATA"];
+
+// Default maximum length of the value of a define kept in the defines output
+pub const DEFAULT_MAX_DEFINE_LENGTH: usize = 256;
+
+// Remove from `defines` and return the ones to be recorded in the metadata
+// instead: the MOVED_DEFINES and the ones whose value exceeds `max_length`
+pub fn move_large_defines(defines: &mut Vec<Define>, max_length: usize) -> Vec<Define> {
+    let (moved, kept) = std::mem::take(defines).into_iter().partition(|d| {
+        MOVED_DEFINES.contains(&d.name.as_str()) || d.value.as_ref().is_some_and(|v| v.len() > max_length)
+    });
+    *defines = kept;
+    moved
+}
+
+// Cflags carrying no build parameter, for consumers rebuilding their own
+// command lines. They are not filtered out of the outputs by default.
+const DEFAULT_CFLAGS: [(&str, &str); 17] = [
+    ("-c", "compile-only mode, not a build parameter"),
+    ("-Wall", "warning policy of the SDK Makefile"),
+    ("-Wextra", "warning policy of the SDK Makefile"),
+    ("-Wno-main", "warning policy of the SDK Makefile"),
+    ("-Werror=int-to-pointer-cast", "warning policy of the SDK Makefile"),
+    ("-Wno-error=int-conversion", "warning policy of the SDK Makefile"),
+    ("-Wimplicit-fallthrough", "warning policy of the SDK Makefile"),
+    ("-Wvla", "warning policy of the SDK Makefile"),
+    ("-Wundef", "warning policy of the SDK Makefile"),
+    ("-Wshadow", "warning policy of the SDK Makefile"),
+    ("-Wformat=2", "warning policy of the SDK Makefile"),
+    ("-Wformat-security", "warning policy of the SDK Makefile"),
+    ("-Wwrite-strings", "warning policy of the SDK Makefile"),
+    ("-MMD", "dependency file generation"),
+    ("-MT", "dependency file generation"),
+    ("-MF", "dependency file generation"),
+    ("-o", "output file of the compile line"),
+];
+
+// A name or glob pattern (`*` matching any sequence) and why it is filtered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterEntry {
+    pub pattern: String,
+    pub explanation: String,
+}
+
+// Set of defines or cflags to leave out of the outputs
+#[derive(Debug, Clone, Default)]
+pub struct FilterSet {
+    pub entries: Vec<FilterEntry>,
+}
+
+impl FilterSet {
+    fn from_table(table: &[(&str, &str)]) -> Self {
+        let entries = table
+            .iter()
+            .map(|(pattern, explanation)| FilterEntry {
+                pattern: pattern.to_string(),
+                explanation: explanation.to_string(),
+            })
+            .collect();
+        FilterSet { entries }
+    }
+
+    pub fn default_defines() -> Self {
+        Self::from_table(&DEFAULT_DEFINES)
+    }
+
+    pub fn default_cflags() -> Self {
+        Self::from_table(&DEFAULT_CFLAGS)
+    }
+
+    // Read a filter file: one pattern per line, optionally followed by
+    // `# explanation`. Empty lines and lines starting with `#` are ignored.
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let entries = contents
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(|l| {
+                let (pattern, explanation) = l.split_once('#').unwrap_or((l, ""));
+                let explanation = match explanation.trim() {
+                    "" => format!("listed in {}", path.display()),
+                    explanation => explanation.to_string(),
+                };
+                FilterEntry { pattern: pattern.trim().to_string(), explanation }
+            })
+            .collect();
+        Ok(FilterSet { entries })
+    }
+
+    // Entries of both sets, the ones of `self` first
+    pub fn merge(mut self, other: FilterSet) -> Self {
+        self.entries.extend(other.entries);
+        self
+    }
+
+    // The first entry filtering `name` out, if any
+    pub fn find(&self, name: &str) -> Option<&FilterEntry> {
+        self.entries.iter().find(|e| glob_match(&e.pattern, name))
+    }
+
+    pub fn is_filtered(&self, name: &str) -> bool {
+        self.find(name).is_some()
+    }
+}
@@ -0,0 +1,104 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anstream::{eprintln, println};
+
+use cbpx::{
+    audit::{raw_defines, stale_entries, suspicious_defines},
+    filter::FilterSet,
+    params::BuildParameters,
+};
+
+#[derive(clap::Args, Debug)]
+pub struct FiltersArgs {
+    #[command(subcommand)]
+    command: FiltersCommand,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum FiltersCommand {
+    /// Report the filter entries matching no define and the unfiltered
+    /// defines looking app-specific, from the compile lines recorded in the
+    /// JSON outputs, without running make
+    Audit(AuditArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct AuditArgs {
+    /// Directory searched recursively for the JSON outputs of every device
+    #[arg(default_value = ".")]
+    output_dir: PathBuf,
+
+    /// File of additional define filters, as given to --filter-defines
+    #[arg(long, value_name = "PATH")]
+    filter_defines: Option<PathBuf>,
+}
+
+// JSON outputs below `dir`, other JSON files such as batch reports skipped
+pub fn find_outputs(dir: &Path, outputs: &mut Vec<(PathBuf, BuildParameters)>) -> Result<(), String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+    let mut paths = entries.filter_map(|e| e.ok().map(|e| e.path())).collect::<Vec<_>>();
+    paths.sort();
+    for path in paths {
+        if path.is_dir() {
+            find_outputs(&path, outputs)?;
+        } else if path.extension().is_some_and(|e| e == "json") {
+            let params = fs::read_to_string(&path).ok().and_then(|c| serde_json::from_str::<BuildParameters>(&c).ok());
+            if let Some(params) = params.filter(|p| !p.raw_commands.is_empty()) {
+                outputs.push((path, params));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn audit(args: &AuditArgs) -> Result<(), String> {
+    let extra = args.filter_defines.as_deref().map(FilterSet::from_file).transpose()?;
+    let filters = FilterSet::default_defines().merge(extra.unwrap_or_default());
+    let mut outputs = Vec::new();
+    find_outputs(&args.output_dir, &mut outputs)?;
+    if outputs.is_empty() {
+        return Err(format!("No JSON output with compile lines found in {}", args.output_dir.display()));
+    }
+
+    // Defines of every output, and the devices each suspicious define is on
+    let mut defines = Vec::new();
+    let mut suspicious: BTreeMap<(String, &str), Vec<String>> = BTreeMap::new();
+    for (_, params) in &outputs {
+        let unfiltered = raw_defines(&params.raw_commands);
+        for (define, reason) in suspicious_defines(&unfiltered, &filters) {
+            let devices = suspicious.entry((define.name.clone(), reason)).or_default();
+            if !devices.contains(&params.device) {
+                devices.push(params.device.clone());
+            }
+        }
+        defines.extend(unfiltered);
+    }
+
+    println!("Audited {} output(s) in {}", outputs.len(), args.output_dir.display());
+    for entry in stale_entries(&filters, &defines) {
+        println!("stale filter entry: {} ({})", entry.pattern, entry.explanation);
+    }
+    for ((name, reason), devices) in suspicious {
+        println!("unfiltered define {} looks app-specific ({}), on {}", name, reason, devices.join(", "));
+    }
+    Ok(())
+}
+
+// Run the `filters` subcommand, returning 0 once the report is printed and 1
+// on error
+pub fn run(args: &FiltersArgs) -> i32 {
+    let result = match &args.command {
+        FiltersCommand::Audit(args) => audit(args),
+    };
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
@@ -0,0 +1,87 @@
+// Classification of the compiler flags found on the compile line
+
+use serde::{Deserialize, Serialize};
+
+// Warning flags are `-W<name>`, `-Wno-<name>`, `-Werror` and `-Werror=<name>`
+// (and their `-Wno-error=` counterparts), plus `-w` and the pedantic flags.
+// `-Wl,`, `-Wa,` and `-Wp,` forward options to other tools and are not
+// warnings.
+pub fn is_warning_flag(flag: &str) -> bool {
+    if flag == "-w" || flag.starts_with("-pedantic") {
+        return true;
+    }
+    match flag.strip_prefix("-W") {
+        Some(rest) => !rest.is_empty() && !["l,", "a,", "p,"].iter().any(|p| rest.starts_with(p)),
+        None => false,
+    }
+}
+
+// Warning flags of `cflags`, in their original order
+pub fn warning_flags(cflags: &[String]) -> Vec<String> {
+    cflags.iter().filter(|f| is_warning_flag(f)).cloned().collect()
+}
+
+// `-Werror` and `-Werror=<name>`, which turn warnings into errors
+pub fn is_werror_flag(flag: &str) -> bool {
+    flag == "-Werror" || flag.starts_with("-Werror=")
+}
+
+// Split the -Werror flags out of `cflags`, as (kept, stripped) in their
+// original order
+pub fn strip_werror(cflags: &[String]) -> (Vec<String>, Vec<String>) {
+    cflags.iter().cloned().partition(|f| !is_werror_flag(f))
+}
+
+// Warning controlled by a warning flag: `foo` for `-Wfoo`, `-Wno-foo` and
+// `-Wfoo=2`, `error=foo` for `-Werror=foo` and `-Wno-error=foo`, `error` for
+// `-Werror` and `-Wno-error`. `-w`, the pedantic flags and the forwarding
+// flags control nothing that another flag overrides.
+fn warning_key(flag: &str) -> Option<&str> {
+    if !is_warning_flag(flag) || flag == "-w" || flag.starts_with("-pedantic") {
+        return None;
+    }
+    let name = &flag[2..];
+    let name = name.strip_prefix("no-").unwrap_or(name);
+    if name.starts_with("error=") {
+        return Some(name);
+    }
+    Some(name.split_once('=').map_or(name, |(name, _)| name))
+}
+
+// Warning flag removed by the normalization, for being followed by `by` for
+// the same warning
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OverriddenWarning {
+    pub flag: String,
+    pub by: String,
+}
+
+// Keep only the last flag of each warning, which is the effective one as the
+// compiler reads the command line in order, e.g. `-Wno-foo` of an app after
+// the `-Wfoo` of the SDK. Group flags such as `-Wall` are not expanded, the
+// warnings they enable are overridden by the specific flags whatever their
+// order. The other flags are kept in place.
+pub fn normalize_warnings(cflags: &[String]) -> (Vec<String>, Vec<OverriddenWarning>) {
+    let mut effective: Vec<String> = Vec::new();
+    let mut overridden = Vec::new();
+    for flag in cflags {
+        if let Some(key) = warning_key(flag) {
+            if let Some(index) = effective.iter().position(|f| warning_key(f) == Some(key)) {
+                overridden.push(OverriddenWarning { flag: effective.remove(index), by: flag.clone() });
+            }
+        }
+        effective.push(flag.clone());
+    }
+    (effective, overridden)
+}
+
+// Codegen flags (`-f<name>`, `-fno-<name>`) affect the ABI and code generation
+// and must be mirrored when compiling additional C code against the SDK
+pub fn is_codegen_flag(flag: &str) -> bool {
+    flag.len() > 2 && flag.starts_with("-f")
+}
+
+// Codegen flags of `cflags`, in their original order
+pub fn codegen_flags(cflags: &[String]) -> Vec<String> {
+    cflags.iter().filter(|f| is_codegen_flag(f)).cloned().collect()
+}
@@ -0,0 +1,54 @@
+use crate::files::Define;
+
+// Emission rules of the .defines and .cflags files. Version 1 is the original
+// format, the defines in compile-line order and no header. Version 2 sorts the
+// defines by name and starts with a `format: 2` comment, which the parsers
+// ignore like any comment.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum FormatVersion {
+    #[default]
+    #[value(name = "1")]
+    V1,
+    #[value(name = "2")]
+    V2,
+}
+
+impl FormatVersion {
+    pub fn number(self) -> u32 {
+        match self {
+            FormatVersion::V1 => 1,
+            FormatVersion::V2 => 2,
+        }
+    }
+
+    // Header line of a file, `comment` being its comment leader
+    pub fn header(self, comment: &str) -> String {
+        match self {
+            FormatVersion::V1 => String::new(),
+            version => format!("{} format: {}\n", comment, version.number()),
+        }
+    }
+
+    // Defines in the order of this version
+    pub fn order_defines(self, defines: &[Define]) -> Vec<Define> {
+        let mut defines = defines.to_vec();
+        if self == FormatVersion::V2 {
+            defines.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+        defines
+    }
+}
+
+// Format version of an existing file from its first line, version 1 having
+// no header
+pub fn file_format_version(contents: &str) -> Result<FormatVersion, String> {
+    let first = contents.lines().next().unwrap_or("");
+    let Some(version) = ["// format: ", "# format: "].iter().find_map(|prefix| first.strip_prefix(prefix)) else {
+        return Ok(FormatVersion::V1);
+    };
+    match version.trim() {
+        "1" => Ok(FormatVersion::V1),
+        "2" => Ok(FormatVersion::V2),
+        other => Err(format!("Unsupported format version {}, this cbpx writes versions 1 and 2", other)),
+    }
+}
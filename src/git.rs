@@ -0,0 +1,61 @@
+use std::{path::Path, process::Command};
+
+use crate::output_lock::LOCK_SUFFIX;
+
+// Run git in `dir`, returning its trimmed output. Directories which are not
+// git checkouts, or hosts without git, give None.
+fn git(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git").arg("-C").arg(dir).args(args).output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+pub fn head_hash(dir: &Path) -> Option<String> {
+    git(dir, &["rev-parse", "HEAD"])
+}
+
+// URL of the `origin` remote of the checkout, or else of its first remote.
+// None for checkouts without any.
+pub fn remote_url(dir: &Path) -> Option<String> {
+    let remote = git(dir, &["remote"])?.lines().min_by_key(|r| *r != "origin")?.to_string();
+    git(dir, &["remote", "get-url", &remote])
+}
+
+// Version of the checkout as given by `git describe`, as the SDK Makefiles do
+pub fn describe(dir: &Path) -> Option<String> {
+    git(dir, &["describe", "--tags", "--always", "--dirty"])
+}
+
+// `git status --porcelain` output, empty for a clean checkout
+pub fn status_porcelain(dir: &Path) -> Option<String> {
+    git(dir, &["status", "--porcelain"])
+}
+
+// Locally modified (or untracked) files of a checkout, as `XY path` status
+// lines. None when the directory is not a git checkout.
+pub fn modified_files(dir: &Path) -> Option<Vec<String>> {
+    Some(status_porcelain(dir)?.lines().map(str::to_string).collect())
+}
+
+// Whether a modified file can change the build parameters: Makefiles and
+// headers
+pub fn affects_build(status_line: &str) -> bool {
+    let path = status_line.get(3..).unwrap_or(status_line);
+    let name = path.rsplit('/').next().unwrap_or(path);
+    name.starts_with("Makefile") || name.ends_with(".mk") || name.ends_with(".h")
+}
+
+// Stage `path` of the checkout `dir`, deletions included
+pub fn add(dir: &Path, path: &Path) -> Result<(), String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["add", "-A", "--"])
+        .arg(path)
+        .arg(format!(":(exclude,glob)**/*{}", LOCK_SUFFIX))
+        .output()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("git add {} failed: {}", path.display(), String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(())
+}
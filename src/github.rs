@@ -0,0 +1,36 @@
+use std::{fs::OpenOptions, io::Write, path::Path};
+
+// Step output in the format of the GITHUB_OUTPUT file: `name=value`, or the
+// heredoc syntax for values spanning several lines
+pub fn format_output(name: &str, value: &str) -> String {
+    if !value.contains('\n') {
+        return format!("{}={}\n", name, value);
+    }
+    let mut delimiter = "CBPX_EOF".to_string();
+    while value.contains(&delimiter) {
+        delimiter.push('_');
+    }
+    format!("{}<<{}\n{}\n{}\n", name, delimiter, value.trim_end_matches('\n'), delimiter)
+}
+
+// Output name for a label such as a device or `app device cell`, characters
+// other than ASCII alphanumerics, `-` and `_` being replaced by `_`
+pub fn output_name(label: &str) -> String {
+    label.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' }).collect()
+}
+
+// Append the outputs to the GITHUB_OUTPUT file when given, or print them
+// otherwise
+pub fn write_outputs(outputs: &[(String, String)], file: Option<&Path>) -> Result<(), String> {
+    let text = outputs.iter().map(|(name, value)| format_output(name, value)).collect::<String>();
+    let Some(file) = file else {
+        print!("{}", text);
+        return Ok(());
+    };
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(file)
+        .and_then(|mut f| f.write_all(text.as_bytes()))
+        .map_err(|e| format!("Failed to write GitHub outputs to {}: {}", file.display(), e))
+}
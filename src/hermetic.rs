@@ -0,0 +1,37 @@
+use std::collections::BTreeMap;
+
+use crate::trace::shell_words;
+
+// Variables of the caller kept by --hermetic besides the ones of --keep-env,
+// TARGET and BOLOS_SDK being set explicitly
+pub const HERMETIC_VARIABLES: [&str; 2] = ["PATH", "HOME"];
+
+// Variables whose flags end up on the compile lines when inherited, e.g.
+// through a `CFLAGS += ...` of a Makefile
+pub const FLAG_VARIABLES: [&str; 2] = ["CFLAGS", "CPPFLAGS"];
+
+// Environment of make with --hermetic: the set variables among the default
+// ones and `keep`, `lookup` giving access to the caller's environment
+pub fn hermetic_env(keep: &[String], lookup: impl Fn(&str) -> Option<String>) -> BTreeMap<String, String> {
+    HERMETIC_VARIABLES
+        .iter()
+        .copied()
+        .chain(keep.iter().map(String::as_str))
+        .filter_map(|name| Some((name.to_string(), lookup(name)?)))
+        .collect()
+}
+
+// Inherited flag variables whose value appears verbatim on a compile line,
+// as (name, value) pairs: the flags likely come from the environment of the
+// caller and won't be reproduced elsewhere
+pub fn leaked_variables(compile_lines: &[String], lookup: impl Fn(&str) -> Option<String>) -> Vec<(String, String)> {
+    FLAG_VARIABLES
+        .iter()
+        .filter_map(|name| Some((name.to_string(), lookup(name)?)))
+        .filter(|(_, value)| {
+            let flags = shell_words(value);
+            !flags.is_empty()
+                && compile_lines.iter().any(|line| shell_words(line).windows(flags.len()).any(|words| words == flags))
+        })
+        .collect()
+}
@@ -0,0 +1,45 @@
+use std::{
+    io::{BufRead, BufReader},
+    process::{Command, Stdio},
+    thread,
+};
+
+use anstream::{eprintln, println};
+
+// Run a post hook through `sh -c` with `env` added to its environment, its
+// standard output and error being streamed line by line behind `prefix`
+fn run_hook(command: &str, env: &[(&str, String)], prefix: &str) -> Result<(), String> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .envs(env.iter().map(|(name, value)| (name, value)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run post hook `{}`: {}", command, e))?;
+    let stderr = child.stderr.take().expect("stderr is piped");
+    let stderr_prefix = prefix.to_string();
+    let stderr_printer = thread::spawn(move || {
+        for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+            eprintln!("{} {}", stderr_prefix, line);
+        }
+    });
+    for line in BufReader::new(child.stdout.take().expect("stdout is piped")).lines().map_while(Result::ok) {
+        println!("{} {}", prefix, line);
+    }
+    stderr_printer.join().expect("stderr printer panicked");
+    let status = child.wait().map_err(|e| format!("Failed to wait for post hook `{}`: {}", command, e))?;
+    if !status.success() {
+        return Err(format!("Post hook `{}` failed ({})", command, status));
+    }
+    Ok(())
+}
+
+// Run the post hooks in order, the first failing one stopping the others
+pub fn run_hooks(hooks: &[String], env: &[(&str, String)]) -> Result<(), String> {
+    for (index, command) in hooks.iter().enumerate() {
+        run_hook(command, env, &format!("[hook {}]", index + 1))?;
+    }
+    Ok(())
+}
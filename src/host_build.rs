@@ -0,0 +1,87 @@
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::escape::{escape, Escaping};
+
+// Build whose compile lines are extracted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum BuildKind {
+    /// The device firmware, built by the app Makefile
+    #[default]
+    Firmware,
+    /// The host-side unit tests or fuzzers, configured with cmake
+    HostTests,
+}
+
+impl BuildKind {
+    // Suffix appended to the base name of the output and reference files, so
+    // that both builds have their own artifact set
+    pub fn suffix(self) -> &'static str {
+        match self {
+            BuildKind::Firmware => "",
+            BuildKind::HostTests => "_host",
+        }
+    }
+}
+
+// Directories of the app holding its host build, in the order they are tried
+pub const HOST_TEST_DIRS: [&str; 2] = ["fuzzing", "unit-tests"];
+
+// Host compilers are usually called through `cc` or `c++`
+pub const HOST_COMPILER_PATTERN: &str = r"(^|/)[^/]*(clang|cc|\+\+)[^/]*$";
+
+// CMakeLists.txt of the host build, relative to the app path: the one of
+// `configured`, or of the first of HOST_TEST_DIRS having one
+pub fn host_cmake_lists(app_path: &Path, configured: Option<&Path>) -> Result<PathBuf, String> {
+    if let Some(dir) = configured {
+        let lists = dir.join("CMakeLists.txt");
+        if !app_path.join(&lists).is_file() {
+            return Err(format!("{} has no CMakeLists.txt", app_path.join(dir).display()));
+        }
+        return Ok(lists);
+    }
+    HOST_TEST_DIRS
+        .iter()
+        .map(|dir| Path::new(dir).join("CMakeLists.txt"))
+        .find(|lists| app_path.join(lists).is_file())
+        .ok_or_else(|| {
+            format!(
+                "{} has no host build, none of {} has a CMakeLists.txt (use --host-tests-dir)",
+                app_path.display(),
+                HOST_TEST_DIRS.map(|d| format!("{}/", d)).join(", ")
+            )
+        })
+}
+
+// Argument of a compile_commands.json entry as a shell word. Only the value
+// of a define is quoted, the tokenizer looking for `-D` at the word start.
+fn shell_argument(argument: &str) -> String {
+    match argument.strip_prefix("-D").and_then(|d| d.split_once('=')) {
+        Some((name, value)) => format!("-D{}={}", name, escape(value, Escaping::Shell)),
+        None => escape(argument, Escaping::Shell),
+    }
+}
+
+// Compile lines of a compile_commands.json database, one per line. Entries
+// given as an argument list are shell-quoted so that the trace tokenizer
+// reads them back.
+pub fn compile_commands_trace(contents: &str) -> Result<String, String> {
+    let entries: Vec<Value> = serde_json::from_str(contents).map_err(|e| format!("Invalid compile_commands.json: {}", e))?;
+    let mut trace = String::new();
+    for (index, entry) in entries.iter().enumerate() {
+        let line = match (entry.get("command").and_then(Value::as_str), entry.get("arguments").and_then(Value::as_array)) {
+            (Some(command), _) => command.to_string(),
+            (None, Some(arguments)) => arguments
+                .iter()
+                .map(|a| a.as_str().map(shell_argument))
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| format!("Entry {} of compile_commands.json has a non-string argument", index + 1))?
+                .join(" "),
+            (None, None) => return Err(format!("Entry {} of compile_commands.json has no command", index + 1)),
+        };
+        trace.push_str(&line);
+        trace.push('\n');
+    }
+    Ok(trace)
+}
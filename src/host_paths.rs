@@ -0,0 +1,51 @@
+use crate::{portable::PathRoots, trace::shell_words};
+
+// Flags taking a path, either joined (`-I/usr/include`, `--sysroot=/usr`) or
+// as the next word (`-isystem /usr/include`). Longer prefixes come first.
+const PATH_FLAGS: [&str; 10] =
+    ["--sysroot=", "--sysroot", "-idirafter", "-isystem", "-imacros", "-include", "-iquote", "-I", "-L", "-B"];
+
+// Path given to a flag in its joined form, or the flag expecting the path as
+// the next word
+enum PathFlag<'a> {
+    Joined(&'a str),
+    Separated,
+}
+
+fn path_flag(word: &str) -> Option<PathFlag<'_>> {
+    let prefix = PATH_FLAGS.iter().find(|p| word.starts_with(*p))?;
+    match &word[prefix.len()..] {
+        "" => Some(PathFlag::Separated),
+        path => Some(PathFlag::Joined(path)),
+    }
+}
+
+// Absolute path outside both the SDK and the app directory, e.g. a host
+// sysroot injected by a toolchain wrapper. Relative paths are relative to the
+// app and never host paths.
+fn is_host_path(path: &str, roots: &PathRoots) -> bool {
+    let path = path.trim_matches(|c| c == '"' || c == '\'');
+    path.starts_with('/') && roots.portable(path) == path
+}
+
+// Remove the flags whose path is a host path from a compile line, returning
+// the remaining line and the dropped flags with their path
+pub fn drop_host_paths(line: &str, roots: &PathRoots) -> (String, Vec<String>) {
+    let mut kept = Vec::new();
+    let mut dropped = Vec::new();
+    let mut words = shell_words(line).into_iter();
+    while let Some(word) = words.next() {
+        match path_flag(word) {
+            Some(PathFlag::Joined(path)) if is_host_path(path, roots) => dropped.push(word.to_string()),
+            Some(PathFlag::Separated) => {
+                let operand = words.next();
+                match operand {
+                    Some(path) if is_host_path(path, roots) => dropped.push(format!("{} {}", word, path)),
+                    _ => kept.extend([Some(word), operand].into_iter().flatten()),
+                }
+            }
+            _ => kept.push(word),
+        }
+    }
+    (kept.join(" "), dropped)
+}
@@ -0,0 +1,58 @@
+use crate::files::Define;
+
+// What happens to a define whose name is not a valid macro name
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OnInvalid {
+    /// Fail the extraction, naming the offending token
+    #[default]
+    Error,
+    /// Leave the define out, with a warning
+    Skip,
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// Define name without the backslashes escaping its characters for the shell,
+// `PRINTF\(...\)` giving the function-like macro `PRINTF(...)`
+pub fn unescape_define_name(name: &str) -> String {
+    let mut out = String::new();
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        out.push(if c == '\\' { chars.next().unwrap_or(c) } else { c });
+    }
+    out
+}
+
+// Whether `name` is an object-like macro name, or a function-like one such as
+// `PRINTF(...)` or `MAX(a, b)`. The backslashes make prints before the
+// parentheses are ignored.
+pub fn is_valid_define_name(name: &str) -> bool {
+    let name = name.replace("\\(", "(").replace("\\)", ")");
+    let Some((macro_name, parameters)) = name.split_once('(') else {
+        return is_identifier(&name);
+    };
+    let Some(parameters) = parameters.strip_suffix(')') else {
+        return false;
+    };
+    if !is_identifier(macro_name) {
+        return false;
+    }
+    if parameters.trim().is_empty() {
+        return true;
+    }
+    let parameters = parameters.split(',').map(str::trim).collect::<Vec<_>>();
+    let (last, named) = parameters.split_last().expect("split yields an item");
+    named.iter().all(|p| is_identifier(p)) && (is_identifier(last) || *last == "...")
+}
+
+// The `-D` token of a define, as found on the compile line
+pub fn define_token(define: &Define) -> String {
+    match &define.value {
+        Some(value) => format!("-D{}={}", define.name, value),
+        None => format!("-D{}", define.name),
+    }
+}
@@ -0,0 +1,73 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    app_makefile::makefile_includes,
+    device::DeviceInfo,
+    extract::{extract, ExtractError, ExtractOptions},
+    params::BuildParameters,
+    plan::makefile_path,
+};
+
+// SDK Makefiles a target build reads, whether or not the app includes them
+// through paths cbpx can resolve
+const SDK_MAKEFILES: [&str; 2] = ["Makefile.defines", "Makefile.rules"];
+
+// Files an extraction depends on, for a build script to rerun when one of
+// them changes
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExtractInputs {
+    // The app Makefile, the fragments it includes and the SDK Makefiles
+    pub makefiles: Vec<PathBuf>,
+    // Recorded trace of an offline extraction
+    pub trace: Option<PathBuf>,
+    // Environment variables selecting the SDK and the target
+    pub env_vars: Vec<String>,
+}
+
+impl ExtractInputs {
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        self.makefiles.iter().chain(&self.trace).map(PathBuf::as_path)
+    }
+}
+
+// Inputs of the extraction of an app for a device. The included Makefile
+// fragments are found by following the `include` lines, see
+// `makefile_includes`: a fragment included through another variable than
+// BOLOS_SDK and TARGET is missed.
+pub fn extract_inputs(device: &DeviceInfo, app_path: &Path, options: &ExtractOptions) -> ExtractInputs {
+    let sdk = options.bolos_sdk.as_path();
+    let mut makefiles = makefile_includes(&makefile_path(app_path, options.makefile.as_deref()), sdk, device.target);
+    for name in SDK_MAKEFILES {
+        let path = sdk.join(name);
+        if path.is_file() && !makefiles.contains(&path) {
+            makefiles.push(path);
+        }
+    }
+    let env_vars = [device.sdk_env, "BOLOS_SDK", "TARGET"].map(String::from).to_vec();
+    ExtractInputs { makefiles, trace: options.backend.recorded_trace().map(Path::to_path_buf), env_vars }
+}
+
+// `extract`, with the files the extraction depended on
+pub fn extract_with_inputs(
+    device: &DeviceInfo,
+    app_path: &Path,
+    options: &ExtractOptions,
+) -> Result<(BuildParameters, ExtractInputs), ExtractError> {
+    let params = extract(device, app_path, options)?;
+    Ok((params, extract_inputs(device, app_path, options)))
+}
+
+// Cargo instructions rerunning a build script when an input changes
+pub fn rerun_if_changed_lines(inputs: &ExtractInputs) -> Vec<String> {
+    let files = inputs.files().map(|path| format!("cargo:rerun-if-changed={}", path.display()));
+    let env_vars = inputs.env_vars.iter().map(|name| format!("cargo:rerun-if-env-changed={}", name));
+    files.chain(env_vars).collect()
+}
+
+// Print the instructions of `rerun_if_changed_lines`, from a build script
+#[cfg(feature = "build-support")]
+pub fn emit_rerun_if_changed(inputs: &ExtractInputs) {
+    for line in rerun_if_changed_lines(inputs) {
+        println!("{}", line);
+    }
+}
@@ -0,0 +1,235 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use regex::Regex;
+
+use cbpx::{
+    category::{categorize, Category},
+    config::reference_dir,
+    device::{parse_device, DeviceInfo},
+    display::display_parameters,
+    files::{read_cflags, read_defines, Define},
+    filter::FilterSet,
+    naming::{NameTemplate, DEFAULT_NAME_TEMPLATE},
+    rust_target::rust_target,
+};
+
+#[derive(clap::Args, Debug)]
+pub struct InspectArgs {
+    /// Defines or cflags file to inspect, or the name of a device
+    target: Option<String>,
+
+    /// Device whose generated files are inspected
+    #[arg(short, long, conflicts_with = "target")]
+    device: Option<String>,
+
+    /// Inspect the reference files of the device instead of the generated ones
+    #[arg(long)]
+    reference: bool,
+
+    /// Directory of the reference files, found like for the extraction when not given
+    #[arg(long, value_name = "PATH", requires = "reference")]
+    reference_dir: Option<PathBuf>,
+
+    /// Base name of the files of the device, as given to the extraction. The
+    /// placeholders other than {device} and {variant} match any value.
+    #[arg(long, default_value = DEFAULT_NAME_TEMPLATE)]
+    name_template: String,
+
+    /// Variant label substituted for {variant} in the name template
+    #[arg(long, default_value = "")]
+    variant: String,
+
+    /// Print the value of a single define, exiting with 1 if it is absent.
+    /// `rust_target` prints the Rust target matching the cflags, and
+    /// `display.<field>` a display parameter such as `display.width`.
+    #[arg(long, conflicts_with = "grep")]
+    get: Option<String>,
+
+    /// Print the defines and cflags matching a regular expression
+    #[arg(long)]
+    grep: Option<String>,
+
+    /// Explain whether a define or cflag is filtered out by default, and why
+    #[arg(long, value_name = "NAME", exclusive = true, allow_hyphen_values = true)]
+    explain_filter: Option<String>,
+}
+
+// Print why `name` is filtered, exiting with 1 if it isn't
+fn explain_filter(name: &str) -> i32 {
+    let sets = [
+        ("define", FilterSet::default_defines(), "filtered out of the defines"),
+        ("cflag", FilterSet::default_cflags(), "in the default cflags filter, not applied unless given with --filter-cflags"),
+    ];
+    let mut found = false;
+    for (kind, set, effect) in &sets {
+        if let Some(entry) = set.find(name) {
+            println!("{} {} is {} (pattern {}): {}", kind, name, effect, entry.pattern, entry.explanation);
+            found = true;
+        }
+    }
+    if !found {
+        println!("{} is not filtered by default", name);
+    }
+    if found { 0 } else { 1 }
+}
+
+// Base name of the files of a device in `dir`, rendered from the name template.
+// With placeholders known only to the extraction, such as {app}, the name is
+// the one of the single defines file of the directory matching the template.
+fn device_base_name(args: &InspectArgs, device: &DeviceInfo, dir: &Path) -> Result<String, String> {
+    let template = NameTemplate::parse(&args.name_template)?;
+    let pattern = template.pattern(|placeholder| match placeholder {
+        "device" => Some(format!("(?:{}|{})", regex::escape(device.name), regex::escape(device.target))),
+        "variant" => Some(regex::escape(&args.variant)),
+        _ => None,
+    });
+    let mut names = fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.strip_suffix(".defines").map(str::to_string))
+        .filter(|name| pattern.is_match(name))
+        .collect::<Vec<_>>();
+    names.sort();
+    match names.len() {
+        0 => Err(format!("No defines file of {} named after `{}` in {}", device.name, args.name_template, dir.display())),
+        1 => Ok(names.remove(0)),
+        _ => Err(format!(
+            "Several defines files of {} are named after `{}` in {}: {}, give the file to inspect",
+            device.name,
+            args.name_template,
+            dir.display(),
+            names.join(", ")
+        )),
+    }
+}
+
+// Resolve the inspected target to a defines/cflags file pair. Either file may
+// be missing when a single file was given explicitly.
+fn resolve_files(args: &InspectArgs) -> Result<(Option<PathBuf>, Option<PathBuf>), String> {
+    let target = args
+        .device
+        .as_deref()
+        .or(args.target.as_deref())
+        .ok_or("Nothing to inspect, give a file or --device")?;
+
+    if let Ok(device) = parse_device(target) {
+        let dir = if args.reference { reference_dir(args.reference_dir.as_deref())? } else { PathBuf::from(".") };
+        let base_name = device_base_name(args, device, &dir)?;
+        let defines = dir.join(format!("{}.defines", base_name));
+        let cflags = dir.join(format!("{}.cflags", base_name));
+        return Ok((Some(defines), Some(cflags)));
+    }
+    if args.device.is_some() {
+        return Err(format!("Unknown device {}", target));
+    }
+
+    // A file was given, pick up its sibling if it exists
+    let path = Path::new(target);
+    let (defines, cflags) = match path.extension().and_then(|e| e.to_str()) {
+        Some("defines") => (path.to_path_buf(), path.with_extension("cflags")),
+        Some("cflags") => (path.with_extension("defines"), path.to_path_buf()),
+        _ => return Err(format!("{} is neither a device, a .defines nor a .cflags file", target)),
+    };
+    if !path.exists() {
+        return Err(format!("{} does not exist", path.display()));
+    }
+    Ok((Some(defines).filter(|p| p.exists()), Some(cflags).filter(|p| p.exists())))
+}
+
+fn print_table(defines: &[Define], cflags: &[String]) {
+    let width = defines.iter().map(|d| d.name.len()).max().unwrap_or(0);
+
+    let mut grouped: Vec<(Category, &Define)> = defines.iter().map(|d| (categorize(&d.name), d)).collect();
+    grouped.sort_by_key(|(category, _)| *category);
+
+    let mut current = None;
+    for (category, define) in grouped {
+        if current != Some(category) {
+            if current.is_some() {
+                println!();
+            }
+            println!("{}:", category.label());
+            current = Some(category);
+        }
+        println!("  {:<width$}  {}", define.name, define.value.as_deref().unwrap_or(""));
+    }
+
+    if !cflags.is_empty() {
+        if current.is_some() {
+            println!();
+        }
+        println!("Cflags:");
+        for flag in cflags {
+            println!("  {}", flag);
+        }
+    }
+}
+
+// Run the `inspect` subcommand, returning the process exit code
+pub fn run(args: &InspectArgs) -> i32 {
+    if let Some(name) = &args.explain_filter {
+        return explain_filter(name);
+    }
+
+    let (defines, cflags) = match resolve_files(args).and_then(|(defines_path, cflags_path)| {
+        let defines = defines_path.map(|p| read_defines(&p)).transpose()?.unwrap_or_default();
+        let cflags = cflags_path.map(|p| read_cflags(&p)).transpose()?.unwrap_or_default();
+        Ok((defines, cflags))
+    }) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+
+    if args.get.as_deref() == Some("rust_target") {
+        println!("{}", rust_target(&cflags));
+        return 0;
+    }
+
+    if let Some(field) = args.get.as_deref().and_then(|name| name.strip_prefix("display.")) {
+        return match display_parameters(&defines).and_then(|display| display.get(field)) {
+            Some(value) => {
+                println!("{}", value);
+                0
+            }
+            None => 1,
+        };
+    }
+
+    if let Some(name) = &args.get {
+        return match defines.iter().find(|d| &d.name == name) {
+            Some(define) => {
+                println!("{}", define.value.as_deref().unwrap_or(""));
+                0
+            }
+            None => 1,
+        };
+    }
+
+    if let Some(pattern) = &args.grep {
+        let re = match Regex::new(pattern) {
+            Ok(re) => re,
+            Err(e) => {
+                eprintln!("Invalid regular expression: {}", e);
+                return 2;
+            }
+        };
+        for define in defines.iter().filter(|d| re.is_match(&d.name)) {
+            match &define.value {
+                Some(value) => println!("{} {}", define.name, value),
+                None => println!("{}", define.name),
+            }
+        }
+        for flag in cflags.iter().filter(|f| re.is_match(f)) {
+            println!("{}", flag);
+        }
+        return 0;
+    }
+
+    print_table(&defines, &cflags);
+    0
+}
@@ -0,0 +1,96 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+// Name of the index of the outputs installed into an app
+pub const INDEX_NAME: &str = "index.json";
+
+// Outputs of a device installed into an app, with what they were generated
+// from
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstalledDevice {
+    // Output file names, relative to the install directory
+    pub files: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_level: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sdk_hash: Option<String>,
+}
+
+// Index written next to the installed outputs. It has no timestamp, so that
+// an unchanged extraction leaves it unchanged.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstallIndex {
+    pub generator: String,
+    pub schema_version: u32,
+    pub devices: BTreeMap<String, InstalledDevice>,
+}
+
+// Install directory of an app: `relative` below the app directory, which
+// must be a plain relative path staying inside of it, not the app directory
+// itself
+pub fn install_dir(app_path: &Path, relative: &Path) -> Result<PathBuf, String> {
+    let plain = relative.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+    if !plain || !relative.components().any(|c| matches!(c, Component::Normal(_))) {
+        return Err(format!(
+            "{} must be a directory below the app directory, without `..`",
+            relative.display()
+        ));
+    }
+    let dir = app_path.join(relative);
+    // A symbolic link could still lead outside of the app
+    if let (Ok(resolved), Ok(app)) = (dir.canonicalize(), app_path.canonicalize()) {
+        if !resolved.starts_with(&app) || resolved == app {
+            return Err(format!("{} resolves to {}, outside of the app directory", dir.display(), resolved.display()));
+        }
+    }
+    Ok(dir)
+}
+
+// Whether an index entry names a file directly in the install directory,
+// the only files ever deleted
+fn is_plain_file_name(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    matches!((components.next(), components.next()), (Some(Component::Normal(_)), None))
+}
+
+// Update the index of `dir` with the devices of this run, the other devices
+// being kept. The files a device had in the previous index and no longer has
+// are removed, returning their paths. Entries naming anything else than a
+// file of `dir` are refused rather than removed.
+pub fn update_index(
+    dir: &Path,
+    generator: &str,
+    schema_version: u32,
+    devices: BTreeMap<String, InstalledDevice>,
+) -> Result<Vec<PathBuf>, String> {
+    let path = dir.join(INDEX_NAME);
+    let mut index = match fs::read_to_string(&path) {
+        Ok(contents) => serde_json::from_str::<InstallIndex>(&contents).map_err(|e| format!("{}: {}", path.display(), e))?,
+        Err(_) => InstallIndex::default(),
+    };
+    let mut removed = Vec::new();
+    for (device, installed) in devices {
+        let stale = index.devices.get(&device).map(|d| d.files.clone()).unwrap_or_default();
+        for name in stale.iter().filter(|name| !installed.files.contains(name)) {
+            if !is_plain_file_name(name) || name == INDEX_NAME {
+                return Err(format!("Refusing to delete {}, listed in {} but not a file of {}", name, path.display(), dir.display()));
+            }
+            let file = dir.join(name);
+            if file.is_file() {
+                fs::remove_file(&file).map_err(|e| format!("Failed to remove {}: {}", file.display(), e))?;
+                removed.push(file);
+            }
+        }
+        index.devices.insert(device, installed);
+    }
+    index.generator = generator.to_string();
+    index.schema_version = schema_version;
+    let json = serde_json::to_string_pretty(&index).expect("Failed to serialize the index") + "\n";
+    fs::write(&path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(removed)
+}
@@ -0,0 +1,119 @@
+use std::fmt::Write;
+
+use crate::report::{BatchReport, ComparisonStatus, RunReport, RunStatus};
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+// Name of the suite of a run: the app, device and matrix cell, and the
+// abbreviated SDK hash
+fn suite_name(run: &RunReport) -> String {
+    let mut name = format!("{} {}", run.app, run.device);
+    if !run.cell.is_empty() {
+        name.push_str(&format!(" {}", run.cell));
+    }
+    if let Some(hash) = &run.sdk_hash {
+        name.push_str(&format!(" (SDK {})", &hash[..hash.len().min(12)]));
+    }
+    name
+}
+
+// Diff lines of an artifact, e.g. `+ define HAVE_NFC` for the defines
+fn artifact_diff(run: &RunReport, artifact: &str) -> Vec<String> {
+    let kind = match artifact {
+        "defines" => " define ",
+        "cflags" => " cflag ",
+        "makevars" => " make ",
+        _ => return Vec::new(),
+    };
+    run.diff.iter().flat_map(|d| d.lines()).filter(|l| l.contains(kind)).collect()
+}
+
+// Test cases of a run: one per compared artifact, or a single one when the
+// run didn't get to the comparison
+fn test_cases(run: &RunReport, suite: &str) -> (String, usize, usize, usize, usize) {
+    let (mut failures, mut errors, mut skipped) = (0, 0, 0);
+    let mut xml = String::new();
+    let mut case = |name: &str, body: Option<String>| {
+        match body {
+            None => writeln!(xml, "    <testcase classname=\"{}\" name=\"{}\"/>", escape(suite), escape(name)),
+            Some(body) => writeln!(
+                xml,
+                "    <testcase classname=\"{}\" name=\"{}\">\n      {}\n    </testcase>",
+                escape(suite),
+                escape(name),
+                body
+            ),
+        }
+        .expect("writing to a String");
+    };
+
+    if run.comparisons.is_empty() {
+        let message = escape(&run.messages.join("\n"));
+        let body = match run.status {
+            RunStatus::Error => {
+                errors += 1;
+                Some(format!("<error message=\"{}\">{}</error>", message, message))
+            }
+            RunStatus::Skipped | RunStatus::Unchanged => {
+                skipped += 1;
+                Some(format!("<skipped message=\"{}\"/>", message))
+            }
+            RunStatus::Match | RunStatus::Mismatch => None,
+        };
+        case("extraction", body);
+        return (xml, 1, failures, errors, skipped);
+    }
+
+    for comparison in &run.comparisons {
+        let body = match comparison.status {
+            ComparisonStatus::Match | ComparisonStatus::Updated => None,
+            ComparisonStatus::Skipped => {
+                skipped += 1;
+                Some("<skipped message=\"not compared\"/>".to_string())
+            }
+            ComparisonStatus::Missing => {
+                failures += 1;
+                let message = format!("Reference {} is missing", comparison.reference);
+                Some(format!("<failure message=\"{}\"/>", escape(&message)))
+            }
+            ComparisonStatus::Mismatch => {
+                failures += 1;
+                let message = format!("Current {} file does not match {}", comparison.artifact, comparison.reference);
+                let details = artifact_diff(run, &comparison.artifact).join("\n");
+                Some(format!("<failure message=\"{}\">{}</failure>", escape(&message), escape(&details)))
+            }
+        };
+        case(&comparison.artifact, body);
+    }
+    (xml, run.comparisons.len(), failures, errors, skipped)
+}
+
+// JUnit XML report of a run, with a test suite per app, device and matrix
+// cell and a test case per compared artifact
+pub fn junit_xml(report: &BatchReport) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites name=\"cbpx\">\n");
+    for run in &report.runs {
+        let suite = suite_name(run);
+        let (cases, tests, failures, errors, skipped) = test_cases(run, &suite);
+        writeln!(
+            xml,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\">",
+            escape(&suite),
+            tests,
+            failures,
+            errors,
+            skipped
+        )
+        .expect("writing to a String");
+        xml.push_str(&cases);
+        xml.push_str("  </testsuite>\n");
+    }
+    xml.push_str("</testsuites>\n");
+    xml
+}
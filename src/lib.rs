@@ -0,0 +1,68 @@
+// Library side of cbpx: extraction of the build parameters of Ledger C SDK
+// apps from the make trace, and comparison of the extracted parameters
+pub mod api_level;
+pub mod app_copy;
+pub mod app_flags;
+pub mod app_makefile;
+pub mod audit;
+pub mod backend;
+pub mod baseline;
+pub mod build_dir;
+pub mod builtin;
+pub mod bundle;
+pub mod capability;
+pub mod category;
+pub mod combined;
+pub mod compiler;
+pub mod config;
+pub mod consistency;
+pub mod device;
+pub mod diff;
+pub mod display;
+pub mod embedded;
+pub mod escape;
+pub mod extract;
+pub mod files;
+pub mod filter;
+pub mod flags;
+pub mod format_version;
+pub mod git;
+pub mod github;
+pub mod hermetic;
+pub mod host_build;
+pub mod host_paths;
+pub mod identifier;
+pub mod inputs;
+pub mod install;
+pub mod junit;
+pub mod linker;
+pub mod lint;
+pub mod make;
+pub mod makevars;
+pub mod matrix;
+pub mod meson;
+pub mod migrate;
+pub mod multi_device;
+pub mod naming;
+pub mod output_lock;
+pub mod overrides;
+pub mod params;
+pub mod parse_issue;
+pub mod plan;
+pub mod portable;
+pub mod rust_target;
+pub mod rustflags;
+pub mod scope;
+pub mod sdk_delta;
+pub mod sdk_layout;
+pub mod shrink;
+pub mod spdx;
+pub mod process;
+pub mod properties;
+pub mod quoting;
+pub mod report;
+pub mod stamp;
+pub mod target_id;
+pub mod tool_version;
+pub mod trace;
+pub mod unit;
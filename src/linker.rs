@@ -0,0 +1,129 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+// Memory layout of the device, taken from the MEMORY command of the linker
+// script. When the script cannot be parsed only its path is recorded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryLayout {
+    pub linker_script: String,
+    pub regions: Vec<MemoryRegion>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryRegion {
+    pub name: String,
+    pub attributes: String,
+    // Evaluated values, absent when the expression is more than a number
+    pub origin: Option<u64>,
+    pub length: Option<u64>,
+    // Expressions as written in the script
+    pub origin_expr: String,
+    pub length_expr: String,
+}
+
+// Linker script given with `-T script`, `-Tscript` or `-Wl,-T,script` on any
+// line of the trace (the link command in practice)
+pub fn find_linker_script(trace: &str) -> Option<String> {
+    for line in trace.lines() {
+        let mut words = line.split_whitespace();
+        while let Some(word) = words.next() {
+            let script = match word {
+                "-T" => words.next().map(str::to_string),
+                _ if word.starts_with("-Wl,") => {
+                    let args = word["-Wl,".len()..].split(',').collect::<Vec<_>>();
+                    args.iter().position(|a| a.starts_with("-T")).and_then(|i| match args[i] {
+                        "-T" => args.get(i + 1).map(|s| s.to_string()),
+                        a => Some(a[2..].to_string()),
+                    })
+                }
+                _ => word.strip_prefix("-T").filter(|s| !s.is_empty()).map(str::to_string),
+            };
+            if script.is_some() {
+                return script;
+            }
+        }
+    }
+    None
+}
+
+// Resolve a script path from the trace: absolute paths as is, relative ones
+// against the app directory (where make runs) and then the SDK
+pub fn resolve_linker_script(script: &str, app: &Path, sdk: &Path) -> Option<PathBuf> {
+    let path = Path::new(script);
+    if path.is_absolute() {
+        return path.is_file().then(|| path.to_path_buf());
+    }
+    [app.join(path), sdk.join(path)].into_iter().find(|p| p.is_file())
+}
+
+// Evaluate `0x...`, decimal and `K`/`M` suffixed numbers
+fn parse_number(expr: &str) -> Option<u64> {
+    let expr = expr.trim();
+    let (digits, multiplier) = match expr.chars().last()? {
+        'K' | 'k' => (&expr[..expr.len() - 1], 1024),
+        'M' | 'm' => (&expr[..expr.len() - 1], 1024 * 1024),
+        _ => (expr, 1),
+    };
+    let value = match digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok()?,
+        None => digits.parse::<u64>().ok()?,
+    };
+    value.checked_mul(multiplier)
+}
+
+// Parse the regions of the MEMORY command, e.g.
+// `FLASH (rx) : ORIGIN = 0xc0de0000, LENGTH = 400K`
+pub fn parse_memory_regions(script: &str) -> Vec<MemoryRegion> {
+    let Some(start) = script.find("MEMORY") else {
+        return Vec::new();
+    };
+    let rest = &script[start..];
+    let (Some(open), Some(close)) = (rest.find('{'), rest.find('}')) else {
+        return Vec::new();
+    };
+    if close < open {
+        return Vec::new();
+    }
+
+    let mut regions = Vec::new();
+    for line in rest[open + 1..close].lines() {
+        let Some((head, tail)) = line.split_once(':') else {
+            continue;
+        };
+        let (name, attributes) = match head.split_once('(') {
+            Some((name, attributes)) => (name.trim(), attributes.trim_end().trim_end_matches(')')),
+            None => (head.trim(), ""),
+        };
+        let field = |aliases: [&str; 3]| {
+            tail.split(',')
+                .filter_map(|part| part.split_once('='))
+                .find(|(k, _)| aliases.contains(&k.trim()))
+                .map(|(_, v)| v.trim().to_string())
+        };
+        let (Some(origin_expr), Some(length_expr)) = (field(["ORIGIN", "org", "o"]), field(["LENGTH", "len", "l"])) else {
+            continue;
+        };
+        regions.push(MemoryRegion {
+            name: name.to_string(),
+            attributes: attributes.to_string(),
+            origin: parse_number(&origin_expr),
+            length: parse_number(&length_expr),
+            origin_expr,
+            length_expr,
+        });
+    }
+    regions
+}
+
+pub fn memory_layout(trace: &str, app: &Path, sdk: &Path) -> Option<MemoryLayout> {
+    let script = find_linker_script(trace)?;
+    let regions = resolve_linker_script(&script, app, sdk)
+        .and_then(|path| fs::read_to_string(path).ok())
+        .map(|contents| parse_memory_regions(&contents))
+        .unwrap_or_default();
+    Some(MemoryLayout { linker_script: script, regions })
+}
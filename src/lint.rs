@@ -0,0 +1,95 @@
+use std::{collections::BTreeMap, fmt, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{device::parse_device, params::BuildParameters};
+
+// Defines an app expects from its build, on every device and per device.
+// Device tables are keyed by marketing name or TARGET.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LintPolicy {
+    #[serde(default)]
+    pub required: Vec<String>,
+    #[serde(default)]
+    pub forbidden: Vec<String>,
+    #[serde(default)]
+    pub devices: BTreeMap<String, DevicePolicy>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DevicePolicy {
+    #[serde(default)]
+    pub required: Vec<String>,
+    #[serde(default)]
+    pub forbidden: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ViolationKind {
+    Missing,
+    Forbidden,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Violation {
+    pub device: String,
+    pub define: String,
+    pub kind: ViolationKind,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            ViolationKind::Missing => write!(f, "{}: required define {} is missing", self.device, self.define),
+            ViolationKind::Forbidden => write!(f, "{}: forbidden define {} is defined", self.device, self.define),
+        }
+    }
+}
+
+// Parse a policy: a TOML document of `required` and `forbidden` arrays of
+// define names, at the top level and in `[devices.<device>]` tables. Device
+// tables are keyed by marketing name once parsed.
+pub fn parse_policy(contents: &str) -> Result<LintPolicy, String> {
+    let mut policy: LintPolicy = toml::from_str(contents).map_err(|e| e.to_string())?;
+    let mut devices = BTreeMap::<String, DevicePolicy>::new();
+    for (device, specific) in std::mem::take(&mut policy.devices) {
+        let device = devices.entry(parse_device(&device)?.name.to_string()).or_default();
+        device.required.extend(specific.required);
+        device.forbidden.extend(specific.forbidden);
+    }
+    policy.devices = devices;
+    Ok(policy)
+}
+
+pub fn read_policy(path: &Path) -> Result<LintPolicy, String> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    parse_policy(&contents).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+impl LintPolicy {
+    // Required and forbidden defines of a device, the device-specific ones
+    // following the common ones
+    pub fn for_device(&self, device: &str) -> DevicePolicy {
+        let mut policy = DevicePolicy { required: self.required.clone(), forbidden: self.forbidden.clone() };
+        let specific = self.devices.iter().filter(|(name, _)| parse_device(name).is_ok_and(|d| d.name == device));
+        for (_, specific) in specific {
+            policy.required.extend(specific.required.iter().cloned());
+            policy.forbidden.extend(specific.forbidden.iter().cloned());
+        }
+        policy
+    }
+}
+
+// Violations of the policy by the extracted parameters of a device
+pub fn lint(policy: &LintPolicy, params: &BuildParameters) -> Vec<Violation> {
+    let policy = policy.for_device(&params.device);
+    let defined = |name: &String| params.defines.iter().any(|d| d.name == *name);
+    let violation = |define: &String, kind| Violation { device: params.device.clone(), define: define.clone(), kind };
+    let missing = policy.required.iter().filter(|name| !defined(name)).map(|name| violation(name, ViolationKind::Missing));
+    let forbidden =
+        policy.forbidden.iter().filter(|name| defined(name)).map(|name| violation(name, ViolationKind::Forbidden));
+    missing.chain(forbidden).collect()
+}
@@ -0,0 +1,137 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+    thread,
+};
+
+use anstream::{eprintln, println};
+
+use cbpx::{
+    backend::{LocalMake, TraceRequest, TraceSource},
+    device::{child_env, parse_device, DeviceInfo, DEVICES},
+    extract::{extract, ExtractOptions},
+    filter::FilterSet,
+    lint::{lint, read_policy},
+    params::BuildParameters,
+    plan::makefile_path,
+};
+
+use crate::{color, compare::load};
+
+#[derive(clap::Args, Debug)]
+pub struct LintArgs {
+    /// TOML policy of the required and forbidden defines, with
+    /// `[devices.<device>]` tables for the device-specific ones
+    policy: PathBuf,
+
+    /// JSON outputs of earlier extractions to check, can be given several
+    /// times
+    #[arg(long, value_name = "PATH", required_unless_present = "app_path", conflicts_with = "app_path")]
+    params: Vec<String>,
+
+    /// App to extract and check, on the devices of its SUPPORTED_DEVICES
+    /// whose SDK variable is set unless --device is given
+    #[arg(short, long)]
+    app_path: Option<PathBuf>,
+
+    /// Device to extract, by marketing name or TARGET, can be given several
+    /// times
+    #[arg(short, long, requires = "app_path")]
+    device: Vec<String>,
+
+    /// Print the violations as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+// Devices of the SUPPORTED_DEVICES variable of the app Makefile whose SDK
+// variable is set, any SDK being enough to evaluate the Makefile
+fn declared_devices(app_path: &Path) -> Result<Vec<&'static DeviceInfo>, String> {
+    let lookup = |name: &str| env::var(name).ok();
+    let (device, env) = DEVICES
+        .iter()
+        .find_map(|d| child_env(d, false, lookup).ok().map(|env| (d, env)))
+        .ok_or("No SDK environment variable is set")?;
+    let makefile = makefile_path(app_path, None);
+    let request =
+        TraceRequest { device, app_path, makefile: &makefile, bolos_sdk: Path::new(&env.bolos_sdk), make_args: &[] };
+    let variables = LocalMake::default().query_make_variables(&request, &["SUPPORTED_DEVICES".to_string()])?;
+    let declared = variables
+        .get("SUPPORTED_DEVICES")
+        .cloned()
+        .flatten()
+        .ok_or("SUPPORTED_DEVICES is not defined by the app Makefile, give --device")?;
+    Ok(declared
+        .split_whitespace()
+        .filter_map(|target| parse_device(target).ok())
+        .filter(|device| child_env(device, false, lookup).is_ok())
+        .collect())
+}
+
+// Extract the app on every device concurrently. The defines are not filtered,
+// so that the policy sees every define of the build.
+fn extract_devices(app_path: &Path, devices: &[&'static DeviceInfo]) -> Result<Vec<BuildParameters>, String> {
+    let lookup = |name: &str| env::var(name).ok();
+    let options = devices
+        .iter()
+        .map(|device| {
+            let mut options = ExtractOptions::new(child_env(device, false, lookup)?.bolos_sdk);
+            options.define_filters = FilterSet::default();
+            Ok(options)
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    thread::scope(|scope| {
+        let runs = devices
+            .iter()
+            .zip(&options)
+            .map(|(device, options)| scope.spawn(move || extract(device, app_path, options)))
+            .collect::<Vec<_>>();
+        runs.into_iter()
+            .zip(devices)
+            .map(|(run, device)| {
+                run.join().expect("extraction thread panicked").map_err(|e| format!("{}: {}", device.name, e))
+            })
+            .collect()
+    })
+}
+
+fn parameters(args: &LintArgs) -> Result<Vec<BuildParameters>, String> {
+    let Some(app_path) = &args.app_path else {
+        return args.params.iter().map(|path| load(path)).collect();
+    };
+    let app_path = std::path::absolute(app_path).map_err(|e| format!("Failed to resolve the app path: {}", e))?;
+    let devices = if args.device.is_empty() {
+        declared_devices(&app_path)?
+    } else {
+        args.device.iter().map(|name| parse_device(name)).collect::<Result<Vec<_>, _>>()?
+    };
+    if devices.is_empty() {
+        return Err("No device to extract, set the SDK variable of a device of SUPPORTED_DEVICES".to_string());
+    }
+    extract_devices(&app_path, &devices)
+}
+
+// Run the `lint` subcommand, returning 0 when every device follows the
+// policy, 1 when some don't and 2 on error
+pub fn run(args: &LintArgs) -> i32 {
+    let checked = read_policy(&args.policy).and_then(|policy| Ok((policy, parameters(args)?)));
+    let (policy, device_params) = match checked {
+        Ok(checked) => checked,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+    let violations = device_params.iter().flat_map(|params| lint(&policy, params)).collect::<Vec<_>>();
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&violations).expect("Failed to serialize violations"));
+    } else {
+        for violation in &violations {
+            println!("{}", color::error(&violation.to_string()));
+        }
+        let devices = device_params.iter().map(|p| p.device.as_str()).collect::<Vec<_>>().join(", ");
+        println!("{} violation(s) of {} on {}", violations.len(), args.policy.display(), devices);
+    }
+    if violations.is_empty() { 0 } else { 1 }
+}
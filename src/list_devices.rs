@@ -0,0 +1,49 @@
+use std::{env, path::Path};
+
+use serde::Serialize;
+
+use cbpx::device::DEVICES;
+
+#[derive(clap::Args, Debug)]
+pub struct ListDevicesArgs {
+    /// Print the devices as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Serialize)]
+struct DeviceEntry {
+    name: &'static str,
+    target: &'static str,
+    sdk_env: &'static str,
+    sdk_path: Option<String>,
+    sdk_exists: bool,
+}
+
+// Run the `list-devices` subcommand, returning the process exit code
+pub fn run(args: &ListDevicesArgs) -> i32 {
+    let entries = DEVICES
+        .iter()
+        .map(|d| {
+            let sdk_path = env::var(d.sdk_env).ok();
+            let sdk_exists = sdk_path.as_deref().is_some_and(|p| Path::new(p).is_dir());
+            DeviceEntry { name: d.name, target: d.target, sdk_env: d.sdk_env, sdk_path, sdk_exists }
+        })
+        .collect::<Vec<_>>();
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&entries).expect("Failed to serialize devices"));
+        return 0;
+    }
+
+    println!("{:<10} {:<8} {:<11} SDK", "DEVICE", "TARGET", "SDK_ENV");
+    for entry in &entries {
+        let status = match (&entry.sdk_path, entry.sdk_exists) {
+            (None, _) => "not set".to_string(),
+            (Some(path), true) => path.clone(),
+            (Some(path), false) => format!("{} (missing directory)", path),
+        };
+        println!("{:<10} {:<8} {:<11} {}", entry.name, entry.target, entry.sdk_env, status);
+    }
+    0
+}
@@ -1,209 +1,2067 @@
-use std::{env, fs::File, io::Write, path::Path, process::Command};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env, fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use anstream::{eprintln, println};
+use clap::{Parser, Subcommand, ValueEnum};
+use regex::Regex;
+
+mod capabilities;
+mod color;
+mod compare;
+mod compare_sdks;
+mod doctor;
+mod filters;
+mod hooks;
+mod inspect;
+mod lint_defines;
+mod list_devices;
+mod migrate_references;
+mod progress;
+mod query;
+mod run_log;
+mod serve;
+mod unbundle;
+mod verify_bundle;
+
+use color::ColorWhen;
+use indicatif::ProgressBar;
+use hooks::run_hooks;
+use progress::Progress;
+use run_log::{RunLog, RunLogs};
+
+use cbpx::{
+    audit::{raw_defines, stale_entries, suspicious_defines},
+    baseline::{toggled_features, write_baseline_app, ToggledFeature},
+    bundle::write_bundle,
+    combined::combined_header,
+    config::{load_config, reference_dir, Config},
+    consistency::asymmetries,
+    escape::{escape_cflags, escape_defines, header, Escaping},
+    format_version::{file_format_version, FormatVersion},
+    backend::{DockerMake, HostCmake, LocalMake, SavedTrace, TempAppCopy, TraceRequest, TraceSource},
+    extract::{extract_with_progress, ExtractError, ExtractOptions},
+    device::{child_env, custom_device, parse_device, DeviceInfo, DEVICES},
+    diff::{compare, read_renames, CompareOptions, ParamsDiff},
+    display::missing_display_defines,
+    embedded::{check_embedded_api_level, write_embedded_references},
+    git::{add as git_add, affects_build, describe, head_hash, modified_files, remote_url},
+    github::{output_name, write_outputs},
+    host_build::{host_cmake_lists, BuildKind, HOST_COMPILER_PATTERN},
+    hermetic::{hermetic_env, leaked_variables},
+    identifier::{unescape_define_name, OnInvalid},
+    install::{install_dir, update_index, InstalledDevice},
+    junit::junit_xml,
+    filter::{FilterSet, DEFAULT_MAX_DEFINE_LENGTH},
+    flags::strip_werror,
+    files::{
+        normalize_output, parse_cflags, parse_defines, parse_make_vars, read_cflags, read_defines, read_make_vars,
+        read_output, render_cflags, render_defines, render_make_vars, write_if_changed,
+    },
+    make::make_version,
+    meson::{render_meson, MachineFile},
+    matrix::{cells, parse_axis, MatrixAxis, MatrixCell},
+    naming::{FilenameStyle, NameContext, NameTemplate, DEFAULT_NAME_TEMPLATE},
+    output_lock::OutputLock,
+    overrides::{apply_overrides, read_overrides, OVERRIDES_FILE},
+    params::{BuildParameters, Metadata, SCHEMA_VERSION},
+    plan::{makefile_path, Plan, PlanOptions, COMPARED_ARTIFACTS},
+    portable::PathRoots,
+    properties::render_properties,
+    quoting::{normalize_define_values, normalize_stored_value},
+    rust_target::{host_machine, UNKNOWN_RUST_TARGET},
+    rustflags::{capability_cfgs, render_rustflags},
+    report::{BatchReport, Comparison, ComparisonStatus, RunReport, RunStatus, REPORT_SCHEMA_VERSION},
+    scope::{sdk_unit_defines, Scope},
+    spdx::{provenance_document, Provenance},
+    stamp::{inputs_digest, stamp_key, StampFile},
+    target_id::check_target,
+    tool_version::{predates, read_tool_version, write_tool_version},
+    trace::{default_compiler_pattern, DEFAULT_COMPILER_PATTERN},
+    unit::{parse_compile_unit, Strategy},
+};
 
-use clap::Parser;
 // This program is used to extract build parameters from the Ledger C SDK
 // It runs the `make --trace --dry-run` command and processes the output to extract
 // the defines and cflags used in the build process.
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Commands>,
+
+    /// When to color the output
+    #[arg(long, global = true, value_enum, default_value_t = ColorWhen::Auto)]
+    color: ColorWhen,
+
+    #[command(flatten)]
+    args: Args,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Pretty-print or query existing defines/cflags files
+    Inspect(inspect::InspectArgs),
+    /// List the supported devices and the SDK environment variable of each
+    ListDevices(list_devices::ListDevicesArgs),
+    /// Compare two sets of extracted parameters
+    Compare(compare::CompareArgs),
+    /// Extract the parameters of an app with two SDK checkouts and print how
+    /// they differ
+    CompareSdks(compare_sdks::CompareSdksArgs),
+    /// Diagnose the environment the extraction runs in
+    Doctor(doctor::DoctorArgs),
+    /// Check the define filters against existing outputs
+    Filters(filters::FiltersArgs),
+    /// Answer a single question about the differences between two sets of
+    /// parameters, exiting with 0 whatever the answer
+    Query(query::QueryArgs),
+    /// Print which curve and cryptography defines are enabled on which device
+    Capabilities(capabilities::CapabilitiesArgs),
+    /// Check the defines of an app against a policy of required and
+    /// forbidden ones, per device
+    Lint(lint_defines::LintArgs),
+    /// Rewrite existing reference files in place with normalizations, a
+    /// backup being kept
+    MigrateReferences(migrate_references::MigrateReferencesArgs),
+    /// Extract a bundle written with --bundle, checking it against its manifest
+    Unbundle(unbundle::UnbundleArgs),
+    /// Check the files of a bundle written with --bundle against its manifest
+    VerifyBundle(verify_bundle::VerifyBundleArgs),
+    /// Answer extraction requests of a long-running client, as JSON lines on
+    /// the standard input and output
+    Serve(serve::ServeArgs),
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum DeviceSource {
+    /// The SUPPORTED_DEVICES variable of the app Makefile
+    FromMakefile,
+}
+
+// Artifact written to the standard output with --stdout
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Emit {
+    Defines,
+    Cflags,
+    Includes,
+    Json,
+    Properties,
+    Meson,
+}
+
+// Output format written next to the text outputs
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    /// Java .properties file of the defines and cflags
+    Properties,
+    /// Meson machine file for cross builds, as <name>.ini
+    Meson,
+}
+
+impl Emit {
+    fn extension(self) -> &'static str {
+        match self {
+            Emit::Defines => "defines",
+            Emit::Cflags => "cflags",
+            Emit::Includes => "includes",
+            Emit::Json => "json",
+            Emit::Properties => "properties",
+            Emit::Meson => "ini",
+        }
+    }
+}
+
+#[derive(clap::Args, Debug)]
 struct Args {
-    /// Name of the application to build
+    /// Path of the application to build, can be given several times
+    #[arg(short, long, required_unless_present = "app_list")]
+    app_path: Vec<PathBuf>,
+
+    /// Makefile to build with instead of <app-path>/Makefile, relative to the
+    /// app path. Make runs in its directory.
+    #[arg(long, value_name = "PATH")]
+    makefile: Option<PathBuf>,
+
+    /// Run make even when the Makefile doesn't include one of $(BOLOS_SDK),
+    /// for setups doing it in a way the check doesn't follow
+    #[arg(long)]
+    skip_makefile_check: bool,
+
+    /// File listing application paths, one per line
+    #[arg(long)]
+    app_list: Option<PathBuf>,
+
+    /// Device to extract the parameters for, by marketing name (nanosplus) or
+    /// TARGET (nanos2), can be given several times
+    #[arg(short, long, required_unless_present_any = ["devices", "all_devices", "custom_target"])]
+    device: Vec<String>,
+
+    /// TARGET of a device missing from the built-in table, e.g. one not
+    /// released yet. The outputs are named after it and its references are
+    /// only compared when they exist.
+    #[arg(long, requires = "custom_sdk_env", conflicts_with_all = ["device", "devices", "all_devices"])]
+    custom_target: Option<String>,
+
+    /// Environment variable holding the SDK path of the --custom-target device
+    #[arg(long, value_name = "VAR", requires = "custom_target")]
+    custom_sdk_env: Option<String>,
+
+    /// Extract the parameters for every supported device
+    #[arg(long, conflicts_with_all = ["device", "devices"])]
+    all_devices: bool,
+
+    /// Write a header merging the defines of every extracted device, the
+    /// device-specific ones being guarded by their TARGET_NAME
+    #[arg(long, value_name = "PATH")]
+    emit_combined_header: Option<PathBuf>,
+
+    /// Write the `--cfg csdk_have_*` flags of the boolean HAVE_* defines, and
+    /// a `--check-cfg` line declaring the cfgs of every extracted device, for
+    /// RUSTFLAGS or the rustflags of .cargo/config.toml. {device} in the path
+    /// is replaced by the device name, it is required with several devices.
+    #[arg(long, value_name = "PATH")]
+    emit_rustflags: Option<String>,
+
+    /// Write an SPDX document of what produced the defines and cflags: the SDK
+    /// (remote, commit and API level) and the toolchain as packages, the files
+    /// with their checksums. {device} in the path is replaced by the device
+    /// name, it is required with several devices.
+    #[arg(long, value_name = "PATH")]
+    emit_provenance_spdx: Option<String>,
+
+    /// Where to read the list of devices from
+    #[arg(long, value_enum)]
+    devices: Option<DeviceSource>,
+
+    /// Extract requested devices even when the app Makefile doesn't declare them
+    #[arg(long)]
+    force: bool,
+
+    /// Write the outputs into this directory of the app instead, e.g.
+    /// `ledger_sdk_params` to commit them with the app, along with an
+    /// index.json listing the devices they were generated for
+    #[arg(long, value_name = "RELATIVE_DIR", conflicts_with_all = ["output_dir", "stdout"])]
+    install_into_app: Option<PathBuf>,
+
+    /// Stage the install directory with git add
+    #[arg(long, requires = "install_into_app")]
+    git_add: bool,
+
+    /// Directory receiving the outputs. With several applications, outputs are
+    /// organized as <output-dir>/<app>/<device>/ next to a report.json
     #[arg(short, long)]
-    app_path: String,
+    output_dir: Option<PathBuf>,
+
+    /// Use the caller's BOLOS_SDK as-is instead of the device-specific SDK variable
+    #[arg(long)]
+    inherit_env: bool,
+
+    /// Run make with PATH, HOME, TARGET, BOLOS_SDK and the variables of
+    /// --keep-env as its only environment, so that variables such as CFLAGS
+    /// can't leak into the parameters
+    #[arg(long, conflicts_with_all = ["docker_image", "trace_file"])]
+    hermetic: bool,
+
+    /// Variable of the caller's environment kept with --hermetic, can be
+    /// given several times
+    #[arg(long, value_name = "VAR", requires = "hermetic")]
+    keep_env: Vec<String>,
+
+    /// Replace invalid UTF-8 in the make trace instead of failing
+    #[arg(long)]
+    lossy: bool,
+
+    /// Write the define values with the shell quoting of the compile lines,
+    /// e.g. `\"1.2.3\"`, instead of as the compiler receives them with string
+    /// literals in canonical C syntax
+    #[arg(long)]
+    raw_values: bool,
+
+    /// Rewrite the define values which are paths below the SDK or the app
+    /// directory as `${BOLOS_SDK}/...` and `${APP_DIR}/...`, as the include
+    /// paths always are. The values as extracted are kept in the metadata.
+    #[arg(long)]
+    portable_paths: bool,
+
+    /// What to do with a `-D` whose name is not a valid C macro name, which
+    /// would not compile once written to the header
+    #[arg(long, value_enum, default_value_t = OnInvalid::Error)]
+    on_invalid: OnInvalid,
+
+    /// Base name of the output and reference files, accepting the {app},
+    /// {device}, {variant}, {api_level} and {toolchain} placeholders
+    #[arg(long, default_value = DEFAULT_NAME_TEMPLATE)]
+    name_template: String,
+
+    /// Name of the device substituted for {device} in the name template.
+    /// --device accepts both.
+    #[arg(long, value_enum, default_value_t = FilenameStyle::Marketing)]
+    filename_style: FilenameStyle,
+
+    /// Variant label substituted for {variant} in the name template
+    #[arg(long, default_value = "")]
+    variant: String,
+
+    /// Leave the -f codegen flags out of the cflags output, they are always
+    /// available in the .codegenflags output
+    #[arg(long)]
+    split_flags: bool,
+
+    /// Make variables whose final values are recorded in the metadata and a
+    /// .makevars output, compared with the .makevars reference when it exists
+    #[arg(long, value_name = "NAME,...", value_delimiter = ',')]
+    capture_make_vars: Vec<String>,
+
+    /// Pattern of the compiler command of compile lines, matched against the
+    /// first word of every trace line once wrappers (ccache, env VAR=...) are
+    /// skipped. By default any clang or gcc.
+    #[arg(long, value_name = "REGEX", value_parser = parse_compiler_pattern)]
+    compiler_pattern: Option<Regex>,
+
+    /// How the parameters of the compile lines of the trace are combined
+    #[arg(long, value_enum, default_value_t = Strategy::First)]
+    strategy: Strategy,
+
+    /// With --strategy union, list the compile units of each define and
+    /// cflag in the occurrences of the JSON output, besides counting them
+    #[arg(long)]
+    occurrence_units: bool,
+
+    /// With --strategy union, report the defines and cflags differing from
+    /// the reference but found on only some compile units without failing
+    #[arg(long)]
+    tolerate_partial: bool,
+
+    /// Compile units extracted from. With `app`, the defines, cflags and
+    /// include paths of the app sources that the SDK sources don't have are
+    /// also written to `.app-only.*` files, which are not compared with the
+    /// references
+    #[arg(long, value_enum, default_value_t = Scope::All)]
+    scope: Scope,
+
+    /// Build extracted from. `host-tests` configures the cmake build of the
+    /// app unit tests or fuzzers and writes `<name>_host.*` files, compared
+    /// with references of the same names.
+    #[arg(long, value_enum, default_value_t = BuildKind::Firmware, conflicts_with_all = ["docker_image", "hermetic"])]
+    build_kind: BuildKind,
+
+    /// Directory of the host build relative to the app path, by default the
+    /// first of `fuzzing` and `unit-tests` having a CMakeLists.txt
+    #[arg(long, value_name = "PATH")]
+    host_tests_dir: Option<PathBuf>,
+
+    /// Directory of the reference files, by default the `references`
+    /// directory or the one set in the cbpx.json file found in the current
+    /// directory or its parents
+    #[arg(long, value_name = "PATH")]
+    reference_dir: Option<PathBuf>,
+
+    /// Compare with the references embedded in cbpx, `builtin:<api_level>`
+    /// (requires the embedded-references feature)
+    #[arg(long, value_name = "SELECTOR", value_parser = parse_reference, conflicts_with_all = ["reference_dir", "update_references"])]
+    reference: Option<String>,
+
+    /// Fail when the API level declared by the app differs from the one of
+    /// the SDK, instead of warning
+    #[arg(long)]
+    require_api_level_match: bool,
+
+    /// Write the artifact selected with --emit to the standard output instead
+    /// of writing files and comparing them with the references
+    #[arg(long, requires = "emit", conflicts_with_all = ["update_references", "print_plan", "emit_combined_header"])]
+    stdout: bool,
+
+    /// Seconds to wait for another extraction writing the outputs of the
+    /// same device into the same output directory
+    #[arg(long, value_name = "SECONDS", default_value_t = 300)]
+    lock_timeout: u64,
+
+    /// Artifact written by --stdout
+    #[arg(long, value_enum, requires = "stdout")]
+    emit: Vec<Emit>,
+
+    /// Additional output format, can be given several times
+    #[arg(long, value_enum)]
+    format: Vec<Format>,
+
+    /// TOML file of known define renames, `OLD_NAME = "NEW_NAME"` pairs,
+    /// reported as renames when comparing with the references
+    #[arg(long, value_name = "PATH")]
+    renames: Option<PathBuf>,
+
+    /// Don't fail on the renames from --renames
+    #[arg(long, requires = "renames")]
+    allow_renames: bool,
+
+    /// Accept an extraction with much fewer defines or cflags than the
+    /// reference, or than the minimums of the configuration file
+    #[arg(long)]
+    allow_shrink: bool,
+
+    /// Fail when the include paths are the same as the reference ones but
+    /// in another order, instead of warning
+    #[arg(long)]
+    strict_include_order: bool,
+
+    /// Format of the .defines and .cflags files: 1 keeps the compile line
+    /// order, 2 sorts the defines and starts with a `format: 2` header
+    #[arg(long, value_enum, default_value_t = FormatVersion::V1)]
+    format_version: FormatVersion,
+
+    /// Also write the .v1.defines, .v2.defines, .v1.cflags and .v2.cflags
+    /// variants, while references move from one format to the other
+    #[arg(long, conflicts_with = "stdout")]
+    emit_both_versions: bool,
+
+    /// Compare the outputs with their references byte for byte, instead of
+    /// ignoring line endings, trailing whitespace and trailing newlines
+    #[arg(long)]
+    byte_exact: bool,
+
+    /// Append api_level, sdk_hash, sdk_version, defines_changed,
+    /// cflags_changed and the status of every run to the file named by
+    /// GITHUB_OUTPUT, or print them when it is not set
+    #[arg(long, conflicts_with = "stdout")]
+    github_output: bool,
+
+    /// Fail instead of warning when the SDK checkout has local modifications
+    #[arg(long)]
+    require_clean_sdk: bool,
+
+    /// Run make on a copy of the app Makefiles and icons in a temporary
+    /// directory, the sources being empty placeholders, so that a build in
+    /// progress in the app directory can't interfere. Files make reports
+    /// missing are copied before running it again.
+    #[arg(long, conflicts_with = "trace_file")]
+    copy_app_to_temp: bool,
+
+    /// Run make in a container of this image (e.g. the Ledger app builder)
+    /// instead of on this host, the SDK and the app being mounted at the same
+    /// paths
+    #[arg(long, value_name = "IMAGE")]
+    docker_image: Option<String>,
+
+    /// Extract from a trace recorded beforehand instead of running make
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["docker_image", "matrix", "capture_make_vars"])]
+    trace_file: Option<PathBuf>,
+
+    /// Copy the outputs over the reference files instead of comparing them
+    #[arg(long)]
+    update_references: bool,
+
+    /// Command run through `sh -c` after every extraction writing outputs,
+    /// with CSDK_OUTPUT_DIR, CSDK_DEVICE and CSDK_MATCH (`true` when the
+    /// outputs match the references) set. Can be given several times, the
+    /// hooks running in order. A failing hook fails the run.
+    #[arg(long, value_name = "COMMAND", conflicts_with_all = ["stdout", "print_plan"])]
+    post_hook: Vec<String>,
+
+    /// Report the SDK defines the app enables or disables against a minimal
+    /// app: `run` extracts the minimal app with the same SDK and writes its
+    /// JSON output to baseline_<device>.json in the output directory,
+    /// otherwise PATH is such a JSON output, {device} being replaced
+    #[arg(long, value_name = "run|PATH", conflicts_with_all = ["stdout", "print_plan"])]
+    baseline: Option<String>,
+
+    /// Print what would be executed and written, without running anything.
+    /// The exit code tells whether the plan is executable.
+    #[arg(long)]
+    print_plan: bool,
+
+    /// Run the extraction for each value of a make variable (e.g. DEBUG=0,1),
+    /// can be given several times to extract every combination
+    #[arg(long, value_parser = parse_axis)]
+    matrix: Vec<MatrixAxis>,
+
+    /// Escaping applied to the defines and cflags text outputs, recorded in
+    /// a header comment of the files
+    #[arg(long, value_enum, default_value_t = Escaping::None)]
+    escape_for: Escaping,
+
+    /// Longer define values are moved out of the defines output into the
+    /// metadata of the JSON output, as is APP_INSTALL_PARAMS_DATA
+    #[arg(long, value_name = "LENGTH", default_value_t = DEFAULT_MAX_DEFINE_LENGTH)]
+    max_define_length: usize,
+
+    /// Keep every define in the defines output, whatever its length
+    #[arg(long)]
+    keep_large_defines: bool,
 
+    /// Remove the flags with a path outside of both the SDK and the app, such
+    /// as host sysroot includes injected by a toolchain wrapper
+    #[arg(long)]
+    drop_host_paths: bool,
+
+    /// Keep only the effective flag of each warning, the last one of the
+    /// command line (e.g. `-Wno-foo` of the app after `-Wfoo` of the SDK),
+    /// the overridden flags being recorded in the metadata
+    #[arg(long)]
+    normalize_warnings: bool,
+
+    /// Leave -Werror and -Werror=* out of the cflags of the outputs meant for
+    /// other builds (.properties), where newer compilers turn them into
+    /// failures. The .cflags output and the references keep them, the
+    /// stripped flags are recorded in the metadata.
+    #[arg(long)]
+    strip_werror: bool,
+
+    /// Report defines passed to C compiles but not to assembly or linking, or
+    /// the other way round. The define set of each kind is recorded in the
+    /// metadata of the JSON output.
+    #[arg(long)]
+    check_consistency: bool,
+
+    /// Also write the predefined macros of the compiler for the target flags
+    /// of the compile line (e.g. __ARM_ARCH) to a .builtin.defines output.
+    /// The compiler must be installed on this host.
+    #[arg(long, conflicts_with = "docker_image")]
+    include_builtin_defines: bool,
+
+    /// File of additional define patterns to filter out, one per line with
+    /// an optional `# explanation`
+    #[arg(long, value_name = "PATH")]
+    filter_defines: Option<PathBuf>,
+
+    /// File of cflag patterns to filter out, in the same format. No cflag is
+    /// filtered by default.
+    #[arg(long, value_name = "PATH")]
+    filter_cflags: Option<PathBuf>,
+
+    /// Write every diagnostic of the runs to this file, whatever the console
+    /// verbosity. With `auto`, each device has its own log,
+    /// <output-dir>/extractor_<device>.log.
+    #[arg(long, value_name = "PATH|auto", conflicts_with = "print_plan")]
+    log_file: Option<PathBuf>,
+
+    /// Report details about the environment, such as the make version
+    #[arg(short, long, conflicts_with = "quiet")]
+    verbose: bool,
+
+    /// Don't report progress. Progress is only reported when the standard
+    /// output is a terminal.
     #[arg(short, long)]
-    device: String,
-}
-
-const FILTERED_DEFINES: [&str; 7] = [
-    // "APPNAME",
-    // "HAVE_SWAP",
-    // "PRINTF\\(...\\)",
-    "MAJOR_VERSION",
-    "MINOR_VERSION",
-    "PATCH_VERSION",
-    // "API_LEVEL",
-    // "TARGET",
-    // "TARGET_NAME",
-    "APPVERSION",
-    // "SDK_NAME",
-    "SDK_VERSION",
-    "SDK_HASH",
-    // "HAVE_NES_CRYPT",
-    // "HAVE_ST_AES",
-    // "NATIVE_LITTLE_ENDIAN",
-    // "HAVE_CRC",
-    // "HAVE_HASH",
-    // "HAVE_RIPEMD160",
-    // "HAVE_SHA224",
-    // "HAVE_SHA256",
-    // "HAVE_SHA3",
-    // "HAVE_SHA384",
-    // "HAVE_SHA512",
-    // "HAVE_SHA512_WITH_BLOCK_ALT_METHOD",
-    // "HAVE_SHA512_WITH_BLOCK_ALT_METHOD_M0",
-    // "HAVE_BLAKE2",
-    // "HAVE_HMAC",
-    // "HAVE_PBKDF2",
-    // "HAVE_AES",
-    // "HAVE_MATH",
-    // "HAVE_RNG",
-    // "HAVE_RNG_RFC6979",
-    // "HAVE_RNG_SP800_90A",
-    // "HAVE_ECC",
-    // "HAVE_ECC_WEIERSTRASS",
-    // "HAVE_ECC_TWISTED_EDWARDS",
-    // "HAVE_ECC_MONTGOMERY",
-    // "HAVE_SECP256K1_CURVE",
-    // "HAVE_SECP256R1_CURVE",
-    // "HAVE_SECP384R1_CURVE",
-    // "HAVE_SECP521R1_CURVE",
-    // "HAVE_FR256V1_CURVE",
-    // "HAVE_STARK256_CURVE",
-    // "HAVE_BRAINPOOL_P256R1_CURVE",
-    // "HAVE_BRAINPOOL_P256T1_CURVE",
-    // "HAVE_BRAINPOOL_P320R1_CURVE",
-    // "HAVE_BRAINPOOL_P320T1_CURVE",
-    // "HAVE_BRAINPOOL_P384R1_CURVE",
-    // "HAVE_BRAINPOOL_P384T1_CURVE",
-    // "HAVE_BRAINPOOL_P512R1_CURVE",
-    // "HAVE_BRAINPOOL_P512T1_CURVE",
-    // "HAVE_BLS12_381_G1_CURVE",
-    // "HAVE_CV25519_CURVE",
-    // "HAVE_CV448_CURVE",
-    // "HAVE_ED25519_CURVE",
-    // "HAVE_ED448_CURVE",
-    // "HAVE_ECDH",
-    // "HAVE_ECDSA",
-    // "HAVE_EDDSA",
-    // "HAVE_ECSCHNORR",
-    // "HAVE_X25519",
-    // "HAVE_X448",
-    // "HAVE_AES_GCM",
-    // "HAVE_CMAC",
-    // "HAVE_AES_SIV",
-    "APP_INSTALL_PARAMS_DATA",
-];
-
-const _FILTERED_CFLAGS: [&str; 17] = [
-    "-c",
-    "-Wall",
-    "-Wextra",
-    "-Wno-main",
-    "-Werror=int-to-pointer-cast",
-    "-Wno-error=int-conversion",
-    "-Wimplicit-fallthrough",
-    "-Wvla",
-    "-Wundef",
-    "-Wshadow",
-    "-Wformat=2",
-    "-Wformat-security",
-    "-Wwrite-strings",
-    "-MMD",
-    "-MT",
-    "-MF",
-    "-o",
-];
+    quiet: bool,
+
+    /// Write the comparison results as JUnit XML, with a test case per
+    /// device and compared artifact
+    #[arg(long, value_name = "PATH")]
+    junit: Option<PathBuf>,
+
+    /// Package every file written by the runs, with the run report holding
+    /// the diffs and the SDK revisions, into a reproducible .tar.gz along
+    /// with a manifest of their hashes.
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["stdout", "print_plan"])]
+    bundle: Option<PathBuf>,
+
+    /// Stamp file recording the inputs of successful matching runs, runs
+    /// whose inputs are unchanged since then skip make entirely. Ignored with
+    /// --post-hook, as the hooks must run every time.
+    #[arg(long, value_name = "PATH")]
+    stamp_file: Option<PathBuf>,
+
+    /// Ignore the stamp file
+    #[arg(long)]
+    no_stamp: bool,
+}
 
 fn main() {
-    let args = Args::parse();
-    let cur_dir = env::current_dir().expect("Failed to get current directory");
+    let cli = Cli::parse();
+    color::init(cli.color);
+    match &cli.command {
+        Some(Commands::Inspect(args)) => std::process::exit(inspect::run(args)),
+        Some(Commands::ListDevices(args)) => std::process::exit(list_devices::run(args)),
+        Some(Commands::Lint(args)) => std::process::exit(lint_defines::run(args)),
+        Some(Commands::Compare(args)) => std::process::exit(compare::run(args)),
+        Some(Commands::CompareSdks(args)) => std::process::exit(compare_sdks::run(args)),
+        Some(Commands::Doctor(args)) => std::process::exit(doctor::run(args)),
+        Some(Commands::Filters(args)) => std::process::exit(filters::run(args)),
+        Some(Commands::Query(args)) => std::process::exit(query::run(args)),
+        Some(Commands::Capabilities(args)) => std::process::exit(capabilities::run(args)),
+        Some(Commands::MigrateReferences(args)) => std::process::exit(migrate_references::run(args)),
+        Some(Commands::Unbundle(args)) => std::process::exit(unbundle::run(args)),
+        Some(Commands::VerifyBundle(args)) => std::process::exit(verify_bundle::run(args)),
+        Some(Commands::Serve(args)) => std::process::exit(serve::run(args)),
+        None => std::process::exit(run(&cli.args)),
+    }
+}
 
-    let path = Path::new(&args.app_path);
-    env::set_current_dir(path).expect("Failed to set current directory");
+// Collect the application paths given on the command line and in --app-list.
+// Empty lines and lines starting with `#` in the list file are ignored.
+fn app_paths(args: &Args) -> Result<Vec<PathBuf>, String> {
+    let mut paths = args.app_path.clone();
+    if let Some(list) = &args.app_list {
+        let contents =
+            fs::read_to_string(list).map_err(|e| format!("Failed to read app list {}: {}", list.display(), e))?;
+        paths.extend(
+            contents
+                .lines()
+                .map(str::trim)
+                .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                .map(PathBuf::from),
+        );
+    }
+    Ok(paths)
+}
+
+// Canonical path of an app, checking that it has a Makefile
+fn resolve_app_path(path: &Path, makefile: Option<&Path>) -> Result<PathBuf, String> {
+    let resolved = path.canonicalize().map_err(|e| format!("App path {} does not exist: {}", path.display(), e))?;
+    let makefile = makefile_path(&resolved, makefile);
+    if !makefile.is_file() {
+        return Err(format!("Makefile {} not found", makefile.display()));
+    }
+    Ok(resolved)
+}
 
-    match args.device.as_str() {
-        "nanox" => {
-            env::set_var("TARGET", "nanox");
-            env::set_var("BOLOS_SDK", env::var("NANOX_SDK").unwrap());
+fn parse_compiler_pattern(pattern: &str) -> Result<Regex, String> {
+    Regex::new(pattern).map_err(|e| format!("invalid compiler pattern: {}", e))
+}
+
+// API level of a `builtin:<api_level>` reference selector
+fn parse_reference(selector: &str) -> Result<String, String> {
+    match selector.strip_prefix("builtin:") {
+        Some(api_level) if !api_level.is_empty() => Ok(api_level.to_string()),
+        _ => Err("expected builtin:<api_level>".to_string()),
+    }
+}
+
+// Write the rustflags of every device, the check-cfg line declaring the cfgs
+// of all of them
+fn write_rustflags(template: &str, device_params: &[BuildParameters]) -> Result<(), String> {
+    if device_params.len() > 1 && !template.contains("{device}") {
+        return Err("--emit-rustflags needs {device} in its path with several devices".to_string());
+    }
+    let known = device_params.iter().flat_map(|p| capability_cfgs(&p.defines)).collect::<BTreeSet<_>>();
+    for params in device_params {
+        let path = template.replace("{device}", &params.device);
+        write_if_changed(Path::new(&path), render_rustflags(&capability_cfgs(&params.defines), &known).as_bytes())?;
+    }
+    Ok(())
+}
+
+// App, SDK checkout and output files of the default cell of a device
+type DeviceRun = (String, Option<PathBuf>, Vec<PathBuf>);
+
+// Write the SPDX provenance of every device. The creation time is the one of
+// SOURCE_DATE_EPOCH when it is set, for reproducible documents.
+fn write_provenance(
+    template: &str,
+    device_params: &[BuildParameters],
+    device_runs: &BTreeMap<String, DeviceRun>,
+) -> Result<(), String> {
+    if device_params.len() > 1 && !template.contains("{device}") {
+        return Err("--emit-provenance-spdx needs {device} in its path with several devices".to_string());
+    }
+    let created = env::var("SOURCE_DATE_EPOCH").ok().and_then(|s| s.parse().ok()).unwrap_or_else(|| {
+        std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_secs())
+    });
+    let generator = format!("cbpx-{}", env!("CARGO_PKG_VERSION"));
+    for params in device_params {
+        let Some((app, sdk, outputs)) = device_runs.get(&params.device) else {
+            continue;
+        };
+        let mut files = Vec::new();
+        for output in outputs.iter().filter(|p| p.extension().is_some_and(|e| e == "defines" || e == "cflags")) {
+            let contents = fs::read(output).map_err(|e| format!("Failed to read {}: {}", output.display(), e))?;
+            files.push((output.file_name().unwrap_or_default().to_string_lossy().into_owned(), contents));
         }
-        "nanosplus" => {
-            env::set_var("TARGET", "nanos2");
-            env::set_var("BOLOS_SDK", env::var("NANOSP_SDK").unwrap());
+        let sdk_hash = sdk.as_deref().and_then(head_hash);
+        let sdk_remote = sdk.as_deref().and_then(remote_url);
+        let metadata = &params.metadata;
+        let document = provenance_document(&Provenance {
+            name: &format!("{}-{}", app, params.device),
+            generator: &generator,
+            created,
+            sdk_remote: sdk_remote.as_deref(),
+            sdk_hash: sdk_hash.as_deref(),
+            api_level: metadata.api_level.as_ref().and_then(|c| c.sdk.as_deref()),
+            toolchain: metadata.toolchain.map(|t| t.name()),
+            toolchain_version: metadata.compiler.as_ref().and_then(|c| c.version.as_deref()),
+            files: &files,
+            overrides: &metadata.applied_overrides,
+        });
+        let path = template.replace("{device}", &params.device);
+        let json = serde_json::to_string_pretty(&document).expect("Failed to serialize provenance") + "\n";
+        fs::write(&path, json).map_err(|e| format!("Failed to write provenance {}: {}", path, e))?;
+    }
+    Ok(())
+}
+
+// Files written by the runs, named by their path in the output directory or
+// else by their file name, and the report of the runs
+fn bundle_files(
+    args: &Args,
+    output_dir: &Path,
+    report: &BatchReport,
+    logs: &RunLogs,
+    device_params: &[BuildParameters],
+) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let mut paths = report.runs.iter().flat_map(|r| r.outputs.iter().map(PathBuf::from)).collect::<Vec<_>>();
+    paths.extend(args.emit_combined_header.clone());
+    if let Some(template) = &args.emit_rustflags {
+        paths.extend(device_params.iter().map(|p| PathBuf::from(template.replace("{device}", &p.device))));
+    }
+    if let Some(template) = &args.emit_provenance_spdx {
+        paths.extend(device_params.iter().map(|p| PathBuf::from(template.replace("{device}", &p.device))));
+    }
+    paths.extend(args.junit.clone());
+    paths.extend(logs.keys().cloned());
+    let mut paths = paths.into_iter().filter_map(|p| std::path::absolute(p).ok()).collect::<Vec<_>>();
+    paths.sort();
+    paths.dedup();
+
+    let mut files = Vec::new();
+    for path in paths {
+        let name = match path.strip_prefix(output_dir) {
+            Ok(relative) => relative.to_string_lossy().into_owned(),
+            Err(_) => path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default(),
+        };
+        let contents = fs::read(&path).map_err(|e| format!("Failed to read {} for the bundle: {}", path.display(), e))?;
+        files.push((name, contents));
+    }
+    if !files.iter().any(|(name, _)| name == "report.json") {
+        let json = serde_json::to_string_pretty(report).expect("Failed to serialize report") + "\n";
+        files.push(("report.json".to_string(), json.into_bytes()));
+    }
+    Ok(files)
+}
+
+// Refuse to write the outputs of an app inside its own directory, where they
+// would show up as untracked files, unless --output-dir points there
+fn check_output_dir(args: &Args, output_dir: &Path, app_path: &Path) -> Result<(), String> {
+    let resolved = output_dir.canonicalize().unwrap_or_else(|_| output_dir.to_path_buf());
+    if args.output_dir.is_none() && args.install_into_app.is_none() && resolved.starts_with(app_path) {
+        return Err(format!(
+            "Refusing to write the outputs into the app directory {}, give --output-dir to write them there intentionally",
+            app_path.display()
+        ));
+    }
+    Ok(())
+}
+
+// Output directory of an app: its install directory with --install-into-app,
+// otherwise the output directory, or its subdirectory named after the app
+// with several apps
+fn app_output_dir(args: &Args, output_dir: &Path, app: &str, app_path: &Path, batch: bool) -> Result<PathBuf, String> {
+    match &args.install_into_app {
+        Some(relative) => install_dir(app_path, relative),
+        None if batch => Ok(output_dir.join(app)),
+        None => Ok(output_dir.to_path_buf()),
+    }
+}
+
+// Record the outputs installed into an app in the index of the install
+// directory, and stage the directory with --git-add
+fn install(
+    args: &Args,
+    app_path: &Path,
+    dir: &Path,
+    installed: BTreeMap<String, InstalledDevice>,
+) -> Result<(), String> {
+    let generator = format!("cbpx {}", env!("CARGO_PKG_VERSION"));
+    let count = installed.len();
+    let removed = update_index(dir, &generator, SCHEMA_VERSION, installed)?;
+    println!("Installed the outputs of {} device(s) into {}", count, dir.display());
+    for file in removed {
+        println!("Removed stale output {}", file.display());
+    }
+    if args.git_add {
+        git_add(app_path, dir)?;
+    }
+    Ok(())
+}
+
+fn dir_name(path: &Path) -> String {
+    path.canonicalize()
+        .ok()
+        .and_then(|p| p.file_name().map(|n| n.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+// Devices to extract for an app, and the ones skipped with the reason why
+struct DeviceSelection {
+    devices: Vec<&'static DeviceInfo>,
+    skipped: Vec<(&'static DeviceInfo, String)>,
+}
+
+// Select the devices to extract for an app. With --devices from-makefile, the
+// devices come from the SUPPORTED_DEVICES variable of the app Makefile (as SDK
+// target names): declared devices without SDK are skipped, and requested
+// devices which are not declared are skipped unless --force is given.
+fn select_devices(args: &Args, app_path: &Path, requested: &[&'static DeviceInfo]) -> Result<DeviceSelection, String> {
+    let Some(DeviceSource::FromMakefile) = args.devices else {
+        return Ok(DeviceSelection { devices: requested.to_vec(), skipped: Vec::new() });
+    };
+
+    // Any SDK is enough to evaluate the app Makefile
+    let lookup = |name: &str| env::var(name).ok();
+    let (query_device, query_env) = DEVICES
+        .iter()
+        .find_map(|d| child_env(d, args.inherit_env, lookup).ok().map(|env| (d, env)))
+        .ok_or("--devices from-makefile requires at least one SDK environment variable to be set")?;
+    let makefile = makefile_path(app_path, args.makefile.as_deref());
+    let request = TraceRequest {
+        device: query_device,
+        app_path,
+        makefile: &makefile,
+        bolos_sdk: Path::new(&query_env.bolos_sdk),
+        make_args: &[],
+    };
+    let variables = trace_source(args).query_make_variables(&request, &["SUPPORTED_DEVICES".to_string()])?;
+    let declared_targets = variables
+        .get("SUPPORTED_DEVICES")
+        .cloned()
+        .flatten()
+        .ok_or("SUPPORTED_DEVICES is not defined by the app Makefile")?;
+
+    let mut declared = Vec::new();
+    for target in declared_targets.split_whitespace() {
+        match parse_device(target) {
+            Ok(device) => declared.push(device),
+            Err(_) => eprintln!("Warning: unknown device {} in SUPPORTED_DEVICES, skipping", target),
         }
-        "stax" => {
-            env::set_var("TARGET", "stax");
-            env::set_var("BOLOS_SDK", env::var("STAX_SDK").unwrap());
+    }
+
+    let mut selection = DeviceSelection { devices: Vec::new(), skipped: Vec::new() };
+    if requested.is_empty() {
+        for device in declared {
+            if child_env(device, args.inherit_env, lookup).is_ok() {
+                selection.devices.push(device);
+            } else {
+                let reason = format!("declared in SUPPORTED_DEVICES but {} is not set", device.sdk_env);
+                selection.skipped.push((device, reason));
+            }
         }
-        "flex" => {
-            env::set_var("TARGET", "flex");
-            env::set_var("BOLOS_SDK", env::var("FLEX_SDK").unwrap());
+        return Ok(selection);
+    }
+
+    for device in requested.iter().copied() {
+        if args.force || declared.iter().any(|x| x.name == device.name) {
+            selection.devices.push(device);
+        } else {
+            let reason = "not declared in SUPPORTED_DEVICES (use --force to extract it)".to_string();
+            selection.skipped.push((device, reason));
         }
-        "apex_p" => {
-            env::set_var("TARGET", "apex_p");
-            env::set_var("BOLOS_SDK", env::var("APEX_P_SDK").unwrap());
+    }
+    Ok(selection)
+}
+
+// Options affecting the outputs or whether they are accepted, part of the
+// digest of the stamps
+fn options_fingerprint(args: &Args) -> String {
+    format!(
+        "{}|{}|{}|{:?}|{:?}|{}|{}|{}|{:?}|{:?}|{:?}|{:?}|{}|{:?}|{:?}|{:?}|{}|{}|{}|{}|{}|{:?}|{}|{}|{:?}|{}|{}|{:?}|{:?}|{:?}",
+        args.name_template,
+        args.variant,
+        args.split_flags,
+        (args.lossy, args.raw_values, args.portable_paths),
+        args.on_invalid,
+        args.inherit_env,
+        args.hermetic,
+        args.keep_env.join(","),
+        (args.strategy, args.occurrence_units, args.tolerate_partial),
+        args.scope,
+        (args.build_kind, &args.host_tests_dir),
+        args.format,
+        args.capture_make_vars.join(","),
+        args.filter_defines,
+        args.filter_cflags,
+        (args.escape_for, args.format_version, args.emit_both_versions),
+        args.max_define_length,
+        args.keep_large_defines,
+        args.check_consistency,
+        args.byte_exact,
+        args.drop_host_paths,
+        (args.normalize_warnings, args.strip_werror),
+        args.include_builtin_defines,
+        args.compiler_pattern.as_ref().map_or(DEFAULT_COMPILER_PATTERN, Regex::as_str),
+        args.renames,
+        args.allow_renames,
+        args.strict_include_order,
+        (args.allow_shrink, args.require_api_level_match, args.require_clean_sdk),
+        args.reference,
+        // Trace source, the build kind selecting the host build
+        (&args.docker_image, &args.trace_file, args.copy_app_to_temp)
+    )
+}
+
+// Print how the defines and cflags of each matrix cell differ from the ones of
+// the default cell
+fn print_matrix_summary(device: &DeviceInfo, cell_params: &[(&MatrixCell, BuildParameters)]) {
+    let Some((default_cell, default_params)) = cell_params.iter().find(|(cell, _)| cell.is_default) else {
+        return;
+    };
+    for (cell, params) in cell_params.iter().filter(|(cell, _)| !cell.is_default) {
+        let lines = compare(default_params, params, &CompareOptions::default()).lines();
+        println!("{} {} vs {}: {} difference(s)", device.name, cell.label(), default_cell.label(), lines.len());
+        for line in lines {
+            println!("  {}", color::diff_line(&line));
         }
-        _ => panic!("Unsupported device type. Supported types are: nanox, nanosplus, stax, flex."),
     }
+}
 
-    let output = Command::new("make")
-        .args(["--trace", "--dry-run"])
-        .output()
-        .expect("Failed to execute command");
+// Table of the status and metrics of every run, aligned on the longest
+// entries
+fn print_summary(report: &BatchReport) {
+    let width = |column: fn(&RunReport) -> &str, title: &str| {
+        report.runs.iter().map(|r| column(r).len()).chain([title.len()]).max().unwrap_or(0)
+    };
+    let app_width = width(|r| &r.app, "APP");
+    let device_width = width(|r| &r.device, "DEVICE");
+    let cell_width = width(|r| &r.cell, "CELL");
+    println!(
+        "{:<app_width$}  {:<device_width$}  {:<cell_width$}  {:>8}  {:>11}  {:>8}  {:>9}  STATUS",
+        "APP", "DEVICE", "CELL", "MAKE", "TRACE", "COMPILES", "UNCHANGED"
+    );
+    for run in &report.runs {
+        let (make, trace, compiles) = match &run.metrics {
+            Some(m) => (format!("{} ms", m.make_ms), format!("{} lines", m.trace_lines), m.compile_lines.to_string()),
+            None => ("-".to_string(), "-".to_string(), "-".to_string()),
+        };
+        // Files not rewritten, out of the written ones
+        let unchanged = match run.outputs.len() {
+            0 => "-".to_string(),
+            n => format!("{}/{}", run.unchanged_outputs.len(), n),
+        };
+        println!(
+            "{:<app_width$}  {:<device_width$}  {:<cell_width$}  {:>8}  {:>11}  {:>8}  {:>9}  {}",
+            run.app,
+            run.device,
+            run.cell,
+            make,
+            trace,
+            compiles,
+            unchanged,
+            color::status(&run.status)
+        );
+    }
+}
 
-    let s_out = String::from_utf8_lossy(&output.stdout);
+// Overrides of the apps applied by every run, which the summary table can't
+// show
+fn print_overrides(report: &BatchReport) {
+    for run in report.runs.iter().filter(|r| !r.applied_overrides.is_empty()) {
+        let label = format!("{} {} {}", run.app, run.device, run.cell);
+        println!("{}: {} override(s) of {}", label.trim_end(), run.applied_overrides.len(), OVERRIDES_FILE);
+        for line in &run.applied_overrides {
+            println!("  {}", line);
+        }
+    }
+}
 
-    env::set_current_dir(cur_dir).expect("Failed to reset current directory");
+// Run the extraction for every requested app and device, returning the
+// process exit code. A failing app doesn't stop the others, the exit code
+// reflects whether any of them failed or mismatched its references.
+fn run(args: &Args) -> i32 {
+    let name_template = NameTemplate::parse(&args.name_template).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
 
-    let mut define_file = File::create(format!("./c_sdk_build_{}.defines", args.device.as_str()))
-        .expect("Failed to create file");
+    let app_paths = app_paths(args).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
 
-    let mut cflags_file = File::create(format!("./c_sdk_build_{}.cflags", args.device.as_str()))
-        .expect("Failed to create cflags file");
+    let requested = if let (Some(target), Some(sdk_env)) = (&args.custom_target, &args.custom_sdk_env) {
+        vec![custom_device(target, sdk_env).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        })]
+    } else if args.all_devices {
+        DEVICES.iter().collect::<Vec<_>>()
+    } else {
+        let mut devices = Vec::new();
+        for name in &args.device {
+            let device = parse_device(name).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            });
+            // nanos2 and nanosplus are the same device
+            if !devices.iter().any(|d: &&DeviceInfo| d.name == device.name) {
+                devices.push(device);
+            }
+        }
+        devices
+    };
 
-    for line in s_out.lines() {
-        //println!("Processing line: {}", line);
-        if line.contains("clang -c") {
-            line.split_whitespace().for_each(|word| {
-                if word.starts_with("-D"){
-                    // Write the word to the file, removing the "-D" prefix
-                    let v = word.trim_start_matches("-D").split('=').collect::<Vec<&str>>();
-                    let bool = FILTERED_DEFINES.iter().any(|&x| x == v[0]);
-                    if !bool {
-                        write!(define_file, "#define ").unwrap();
-                        match v.len() {
-                            1 => write!(define_file, "{}", v[0]).unwrap(),
-                            2 => write!(define_file, "{} {}", v[0], v[1]).unwrap(),
-                            _ => panic!("Unexpected format for define: {}", word),
+    if args.stdout && args.emit.len() > 1 {
+        eprintln!("--stdout writes a single artifact, --emit can only be given once with it");
+        std::process::exit(1);
+    }
+    if args.stdout && (app_paths.len() > 1 || requested.len() > 1 || args.devices.is_some() || !args.matrix.is_empty()) {
+        eprintln!("--stdout requires a single app, device and matrix cell, the outputs of several runs would be interleaved");
+        std::process::exit(1);
+    }
+
+    if args.scope == Scope::App && args.strategy == Strategy::First {
+        eprintln!("--scope app needs the compile lines of both the app and the SDK, use --strategy intersection or union");
+        std::process::exit(1);
+    }
+
+    if args.baseline.as_deref() == Some("run") && args.trace_file.is_some() {
+        eprintln!("--baseline run needs make, give the JSON output of a baseline extraction with --trace-file");
+        std::process::exit(1);
+    }
+
+    if args.trace_file.is_some() && (app_paths.len() > 1 || requested.len() > 1 || args.devices.is_some()) {
+        eprintln!("--trace-file requires a single app and device, the trace is the one of a single extraction");
+        std::process::exit(1);
+    }
+
+    if args.emit_combined_header.is_some() && app_paths.len() > 1 {
+        eprintln!("--emit-combined-header is only supported with a single app");
+        std::process::exit(1);
+    }
+    if args.emit_rustflags.is_some() && app_paths.len() > 1 {
+        eprintln!("--emit-rustflags is only supported with a single app");
+        std::process::exit(1);
+    }
+    if args.emit_provenance_spdx.is_some() && app_paths.len() > 1 {
+        eprintln!("--emit-provenance-spdx is only supported with a single app");
+        std::process::exit(1);
+    }
+
+    if !args.print_plan {
+        match make_version() {
+            Ok(version) if args.verbose => eprintln!("Using {}", version),
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let filters = filters(args).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+
+    // Embedded references are written to a temporary directory once the names
+    // of the outputs are known, and removed at the end
+    let builtin_dir = args.reference.as_ref().map(|api_level| {
+        check_embedded_api_level(api_level).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+        env::temp_dir().join(format!("cbpx-builtin-{}-{}", api_level, std::process::id()))
+    });
+    let reference_dir = match &builtin_dir {
+        Some(dir) => dir.clone(),
+        None => reference_dir(args.reference_dir.as_deref()).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }),
+    };
+    let config = load_config().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+
+    let plan_options = PlanOptions {
+        inherit_env: args.inherit_env,
+        name_template: &name_template,
+        filename_style: args.filename_style,
+        variant: &args.variant,
+        reference_dir: &reference_dir,
+        makefile: args.makefile.as_deref(),
+        check_sdk: args.trace_file.is_none(),
+        check_makefile: args.trace_file.is_none() && !args.skip_makefile_check,
+    };
+    let cells = cells(&args.matrix);
+
+    let batch = app_paths.len() > 1;
+    // Every output is written through absolute paths resolved up front
+    let output_dir = std::path::absolute(args.output_dir.as_deref().unwrap_or(Path::new("."))).unwrap_or_else(|e| {
+        eprintln!("Failed to resolve the output directory: {}", e);
+        std::process::exit(1);
+    });
+
+    // Stamps can't be used when fresh outputs are needed, an unchanged run
+    // would print nothing with --stdout, nor with post hooks which must run
+    // every time
+    let stamp_path = args
+        .stamp_file
+        .as_ref()
+        .filter(|_| {
+            !args.no_stamp
+                && !args.update_references
+                && !args.stdout
+                && args.emit_combined_header.is_none()
+                && args.emit_rustflags.is_none()
+                && args.emit_provenance_spdx.is_none()
+                && args.post_hook.is_empty()
+        });
+    let mut stamps = stamp_path.map(|p| StampFile::load(p));
+    let options = options_fingerprint(args);
+    let backend = trace_source(args);
+
+    let progress = Progress::new(args.quiet || args.print_plan);
+    let mut logs = RunLogs::new();
+    let mut report = BatchReport { schema_version: REPORT_SCHEMA_VERSION, success: true, runs: Vec::new() };
+    // Parameters of the default matrix cell of every device, for the combined header
+    let mut device_params = Vec::new();
+    // SDK API level and path of the first extraction, for the GitHub outputs
+    let mut first_sdk = None;
+    // SDK and outputs of the default cell of every device, for the provenance
+    let mut device_runs = BTreeMap::new();
+    for app_path in &app_paths {
+        let app = dir_name(app_path);
+        let resolved = resolve_app_path(app_path, args.makefile.as_deref())
+            .and_then(|path| Ok((app_output_dir(args, &output_dir, &app, &path, batch)?, path)))
+            .and_then(|(dir, path)| {
+                check_output_dir(args, &dir, &path)?;
+                Ok((progress.suspend(|| select_devices(args, &path, &requested))?, dir, path))
+            });
+        let (selection, app_output_dir, app_path) = match resolved {
+            Ok(resolved) => resolved,
+            Err(e) => {
+                progress.suspend(|| eprintln!("{}: {}", app, e));
+                report.success = false;
+                report.runs.push(RunReport::new(&app, app_path, "-", RunStatus::Error, vec![e]));
+                continue;
+            }
+        };
+        for (device, reason) in selection.skipped {
+            progress.suspend(|| eprintln!("Warning: {} is {}, skipping", device.name, reason));
+            report.runs.push(RunReport::new(&app, &app_path, device.name, RunStatus::Skipped, vec![reason]));
+        }
+        progress.add_runs(selection.devices.len() * cells.len());
+        // Outputs installed into the app, by device
+        let mut installed = BTreeMap::new();
+        for device in selection.devices {
+            let device_output_dir =
+                if batch && args.install_into_app.is_none() { app_output_dir.join(device.name) } else { app_output_dir.clone() };
+            let mut cell_params = Vec::new();
+            for cell in &cells {
+                let plan = Plan::new(&app_path, device, cell.clone(), &device_output_dir, &plan_options, |name| {
+                    env::var(name).ok()
+                });
+                if args.print_plan {
+                    plan.print();
+                    println!();
+                    report.success &= plan.is_executable();
+                    continue;
+                }
+                let digest = stamps.as_ref().and_then(|_| inputs_digest(&plan, &options, backend.recorded_trace()));
+                let stamped = stamps.as_ref().and_then(|s| s.entries.get(&stamp_key(&plan)));
+                if digest.is_some() && digest.as_ref() == stamped {
+                    progress.suspend(|| {
+                        println!("{} {}: {} (stamp)", app, device.name, color::status(&RunStatus::Unchanged))
+                    });
+                    progress.skip();
+                    let message = "unchanged (stamp)".to_string();
+                    let run = RunReport::new(&app, &app_path, device.name, RunStatus::Unchanged, vec![message]);
+                    report.runs.push(RunReport { cell: cell.label(), ..run });
+                    continue;
+                }
+                let spinner = progress.start(format!("{} {} {}", app, device.name, cell.label()).trim_end().to_string());
+                let mut log = RunLog::default();
+                let run = format!("cbpx {}, run of {} for {} {}", env!("CARGO_PKG_VERSION"), app, device.name, cell.label());
+                log.debug(run.trim_end());
+                log.debug(format!("Options: {}", options));
+                let extracted = extract(args, &name_template, &filters, &config, &plan, &spinner, &mut log);
+                progress.finish(spinner);
+                let (mut status, mut messages, outcome) = match extracted {
+                    Ok(outcome) if outcome.mismatches.is_empty() => (RunStatus::Match, Vec::new(), outcome),
+                    Ok(mut outcome) => (RunStatus::Mismatch, std::mem::take(&mut outcome.mismatches), outcome),
+                    Err(e) => (RunStatus::Error, vec![e], ExtractOutcome::default()),
+                };
+                // Post hooks run once the outputs are written, a failing one failing the run
+                if outcome.params.is_some() && !args.post_hook.is_empty() {
+                    let hook_env = [
+                        ("CSDK_OUTPUT_DIR", device_output_dir.display().to_string()),
+                        ("CSDK_DEVICE", device.name.to_string()),
+                        ("CSDK_MATCH", matches!(status, RunStatus::Match).to_string()),
+                    ];
+                    log.debug(format!("Running {} post hook(s)", args.post_hook.len()));
+                    if let Err(e) = progress.suspend(|| run_hooks(&args.post_hook, &hook_env)) {
+                        status = RunStatus::Error;
+                        messages.push(e);
+                    }
+                }
+                let context = match (batch, cell.vars.is_empty()) {
+                    (true, true) => format!("{} ({}): ", app, device.name),
+                    (true, false) => format!("{} ({}, {}): ", app, device.name, cell.label()),
+                    (false, true) => String::new(),
+                    (false, false) => format!("{}: ", cell.label()),
+                };
+                progress.suspend(|| {
+                    for message in &messages {
+                        let message =
+                            if message.starts_with("  ") { color::diff_line(message) } else { color::error(message) };
+                        eprintln!("{}{}", context, message);
+                    }
+                });
+                for comparison in &outcome.comparisons {
+                    let line = format!("Comparison of {}: {:?} {}", comparison.artifact, comparison.status, comparison.reference);
+                    log.debug(line.trim_end());
+                }
+                for message in &messages {
+                    log.error(message);
+                }
+                log.debug(format!("Status: {}", status.label()));
+                if let Some(path) = log_path(args, &device_output_dir, device) {
+                    logs.entry(path).or_default().append(log, !matches!(status, RunStatus::Match));
+                }
+                match (&status, &mut stamps, digest) {
+                    (RunStatus::Match, Some(stamps), Some(digest)) => {
+                        stamps.entries.insert(stamp_key(&plan), digest);
+                    }
+                    (RunStatus::Match, _, _) => {}
+                    (_, stamps, _) => {
+                        report.success = false;
+                        if let Some(stamps) = stamps {
+                            stamps.entries.remove(&stamp_key(&plan));
                         }
-                        writeln!(define_file).unwrap();
                     }
                 }
-                else if word.starts_with("-I") {}
-                else if word.starts_with("-") {
-                    //let bool = FILTERED_CFLAGS.iter().any(|&x| x == word);
-                    //if !bool {
-                        // Write the word to the cflags file
-                        writeln!(cflags_file, "{}", word).unwrap();
-                    //}
+                let sdk_hash = plan.child_env.as_ref().and_then(|e| head_hash(Path::new(&e.bolos_sdk)));
+                if let (Some(_), Some(params)) = (&args.install_into_app, &outcome.params) {
+                    let entry: &mut InstalledDevice = installed.entry(device.name.to_string()).or_default();
+                    let names = outcome.outputs.iter().filter_map(|p| p.file_name()).map(|n| n.to_string_lossy().into_owned());
+                    entry.files.extend(names);
+                    entry.api_level = params.metadata.api_level.as_ref().and_then(|c| c.sdk.clone());
+                    entry.sdk_hash = sdk_hash.clone();
                 }
-            });
-            //writeln!(cflags_file, "-Wno-unused-command-line-argument").unwrap();
-            break;
+                report.runs.push(RunReport {
+                    cell: cell.label(),
+                    outputs: outcome.outputs.iter().map(|p| p.display().to_string()).collect(),
+                    comparisons: outcome.comparisons,
+                    diff: outcome.diff,
+                    sdk_hash,
+                    metrics: outcome.params.as_ref().map(|p| p.metrics.clone()),
+                    toggled_features: outcome.toggled_features,
+                    unchanged_outputs: outcome.unchanged,
+                    parse_issues: outcome.params.as_ref().map(|p| p.metadata.parse_issues.clone()).unwrap_or_default(),
+                    applied_overrides: outcome.params.as_ref().map(|p| p.metadata.applied_overrides.clone()).unwrap_or_default(),
+                    ..RunReport::new(&app, &app_path, device.name, status, messages)
+                });
+                if let Some(params) = outcome.params {
+                    if first_sdk.is_none() {
+                        let api_level = params.metadata.api_level.as_ref().and_then(|c| c.sdk.clone());
+                        first_sdk = plan.child_env.as_ref().map(|e| (api_level, PathBuf::from(&e.bolos_sdk)));
+                    }
+                    if cell.is_default {
+                        let sdk = plan.child_env.as_ref().map(|e| PathBuf::from(&e.bolos_sdk));
+                        device_runs.insert(device.name.to_string(), (app.clone(), sdk, outcome.outputs.clone()));
+                        device_params.push(params.clone());
+                    }
+                    cell_params.push((cell, params));
+                }
+            }
+            progress.suspend(|| print_matrix_summary(device, &cell_params));
+        }
+        if !installed.is_empty() {
+            if let Err(e) = progress.suspend(|| install(args, &app_path, &app_output_dir, installed)) {
+                progress.suspend(|| eprintln!("{}: {}", app, e));
+                report.success = false;
+            }
         }
     }
 
-    // Compare output files with reference files
-    let ref_define_file = format!("references/c_sdk_build_{}.defines", args.device.as_str());
-    let ref_cflags_file = format!("references/c_sdk_build_{}.cflags", args.device.as_str());
-    let curr_define_file = format!("c_sdk_build_{}.defines", args.device.as_str());
-    let curr_cflags_file = format!("c_sdk_build_{}.cflags", args.device.as_str());
+    progress.clear();
 
-    let curr_define_contents = std::fs::read_to_string(&curr_define_file).expect("Failed to read current defines file");
-    let curr_cflags_contents = std::fs::read_to_string(&curr_cflags_file).expect("Failed to read current cflags file");
-    let ref_define_contents = std::fs::read_to_string(&ref_define_file).expect("Failed to read reference defines file");
-    let ref_cflags_contents = std::fs::read_to_string(&ref_cflags_file).expect("Failed to read reference cflags file");
+    if let (Some(stamps), Some(path)) = (&stamps, stamp_path) {
+        if let Err(e) = stamps.save(path) {
+            eprintln!("{}", e);
+            report.success = false;
+        }
+    }
 
-    if curr_define_contents != ref_define_contents {
-        eprintln!("Current defines file does not match reference for target {}", args.device.as_str());
-        // terminate with error
-        std::process::exit(1);
+    if let Some(header_path) = args.emit_combined_header.as_ref().filter(|_| !args.print_plan) {
+        let merged = device_params.iter().map(|p| (p.device.clone(), p.clone())).collect();
+        let written = combined_header(&merged)
+            .and_then(|header| write_if_changed(header_path, header.as_bytes()));
+        if let Err(e) = written {
+            eprintln!("Failed to write combined header {}: {}", header_path.display(), e);
+            report.success = false;
+        }
     }
 
-    if curr_cflags_contents != ref_cflags_contents {
-        eprintln!("Current cflags file does not match reference for target {}", args.device.as_str());
-        // terminate with error
-        std::process::exit(1);
+    if let Some(template) = args.emit_rustflags.as_deref().filter(|_| !args.print_plan) {
+        if let Err(e) = write_rustflags(template, &device_params) {
+            eprintln!("{}", e);
+            report.success = false;
+        }
+    }
+
+    if let Some(template) = args.emit_provenance_spdx.as_deref().filter(|_| !args.print_plan) {
+        if let Err(e) = write_provenance(template, &device_params, &device_runs) {
+            eprintln!("{}", e);
+            report.success = false;
+        }
+    }
+
+    if report.runs.len() > 1 {
+        print_summary(&report);
+    }
+    print_overrides(&report);
+
+    for (path, log) in &logs {
+        match log.write(path) {
+            Ok(()) if log.failed() => eprintln!("Diagnostics of the failed run(s) written to {}", path.display()),
+            Ok(()) => {}
+            Err(e) => {
+                eprintln!("{}", e);
+                report.success = false;
+            }
+        }
+    }
+
+    if let Some(junit_path) = args.junit.as_ref().filter(|_| !args.print_plan) {
+        if let Err(e) = fs::write(junit_path, junit_xml(&report)) {
+            eprintln!("Failed to write JUnit report {}: {}", junit_path.display(), e);
+            report.success = false;
+        }
     }
 
+    if args.github_output && !args.print_plan {
+        let (api_level, sdk) = first_sdk.unzip();
+        let sdk = sdk.as_deref();
+        let changed = |changed: fn(&ParamsDiff) -> bool| report.runs.iter().any(|r| r.diff.as_ref().is_some_and(changed));
+        let mut outputs = vec![
+            ("api_level".to_string(), api_level.flatten().unwrap_or_default()),
+            ("sdk_hash".to_string(), sdk.and_then(head_hash).unwrap_or_default()),
+            ("sdk_version".to_string(), sdk.and_then(describe).unwrap_or_default()),
+            ("defines_changed".to_string(), changed(ParamsDiff::defines_changed).to_string()),
+            ("cflags_changed".to_string(), changed(ParamsDiff::cflags_changed).to_string()),
+        ];
+        for run in &report.runs {
+            let label = if batch { format!("{} {} {}", run.app, run.device, run.cell) } else { format!("{} {}", run.device, run.cell) };
+            outputs.push((format!("status_{}", output_name(label.trim_end())), run.status.label().to_string()));
+        }
+        if let Err(e) = write_outputs(&outputs, env::var_os("GITHUB_OUTPUT").as_deref().map(Path::new)) {
+            eprintln!("{}", e);
+            report.success = false;
+        }
+    }
+
+    if batch && !args.print_plan {
+        let report_path = output_dir.join("report.json");
+        report.write(&report_path).unwrap_or_else(|e| {
+            eprintln!("Failed to write report {}: {}", report_path.display(), e);
+            std::process::exit(1);
+        });
+        let failed =
+            report.runs.iter().filter(|r| !matches!(r.status, RunStatus::Match | RunStatus::Unchanged | RunStatus::Skipped)).count();
+        println!("{} run(s), {} failed or mismatched, report written to {}", report.runs.len(), failed, report_path.display());
+    }
+
+    if let Some(bundle_path) = &args.bundle {
+        let generator = format!("cbpx {}", env!("CARGO_PKG_VERSION"));
+        let written = bundle_files(args, &output_dir, &report, &logs, &device_params)
+            .and_then(|files| write_bundle(bundle_path, &files, &generator).map(|_| files.len()));
+        match written {
+            Ok(count) => println!("{} file(s) bundled into {}", count, bundle_path.display()),
+            Err(e) => {
+                eprintln!("{}", e);
+                report.success = false;
+            }
+        }
+    }
+
+    if let Some(dir) = builtin_dir {
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    if report.success { 0 } else { 1 }
+}
+
+// Result of a completed extraction: the written files, a message for every
+// output differing from its reference and the detail of each comparison
+#[derive(Default)]
+struct ExtractOutcome {
+    outputs: Vec<PathBuf>,
+    mismatches: Vec<String>,
+    comparisons: Vec<Comparison>,
+    params: Option<BuildParameters>,
+    diff: Option<ParamsDiff>,
+    toggled_features: Vec<ToggledFeature>,
+    // Outputs whose contents didn't change, which were not rewritten
+    unchanged: Vec<String>,
+}
+
+// SDK defines the app enables or disables, from the SDK compile units of its
+// extraction and the compile lines of the baseline of --baseline: the
+// extraction of the minimal app with the same options, or a JSON output of
+// one. Nothing without --baseline.
+fn baseline_toggles(
+    args: &Args,
+    options: &ExtractOptions,
+    plan: &Plan,
+    params: &BuildParameters,
+    spinner: &ProgressBar,
+    log: &mut RunLog,
+) -> Result<Vec<ToggledFeature>, String> {
+    let baseline = match args.baseline.as_deref() {
+        None => return Ok(Vec::new()),
+        Some("run") => {
+            let dir = env::temp_dir().join(format!("cbpx-baseline-{}-{}", plan.device.name, std::process::id()));
+            write_baseline_app(&dir)?;
+            let baseline_options = ExtractOptions {
+                makefile: None,
+                make_args: Vec::new(),
+                strategy: Strategy::Union,
+                scope: Scope::All,
+                capture_make_vars: Vec::new(),
+                builtin_defines: false,
+                ..options.clone()
+            };
+            let extracted = extract_with_progress(plan.device, &dir, &baseline_options, || spinner.inc(1));
+            let _ = fs::remove_dir_all(&dir);
+            let baseline = extracted.map_err(|e| format!("Extraction of the baseline app failed: {}", e))?;
+            let path = plan.output_dir.join(format!("baseline_{}.json", plan.device.name));
+            fs::create_dir_all(&plan.output_dir)
+                .and_then(|_| fs::write(&path, baseline.to_json()))
+                .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+            log.debug(format!("Wrote {}", path.display()));
+            baseline
+        }
+        Some(template) => {
+            let path = PathBuf::from(template.replace("{device}", plan.device.name));
+            let contents = fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            serde_json::from_str::<BuildParameters>(&contents).map_err(|e| format!("{}: {}", path.display(), e))?
+        }
+    };
+    let units = params.raw_commands.iter().map(|line| parse_compile_unit(line)).collect::<Vec<_>>();
+    let roots = PathRoots::new(&options.bolos_sdk, &plan.app_path);
+    let sdk_defines = sdk_unit_defines(&units, plan.make_dir(), &plan.app_path, &roots);
+    if sdk_defines.is_empty() {
+        return Err("The trace has no compile line of an SDK source, the SDK features toggled by the app can't be told".to_string());
+    }
+    Ok(toggled_features(&sdk_defines, &raw_defines(&baseline.raw_commands)))
+}
+
+// Trace source selected by the arguments
+fn trace_source(args: &Args) -> Arc<dyn TraceSource> {
+    let source: Arc<dyn TraceSource> = match (&args.docker_image, &args.trace_file) {
+        (Some(image), _) => Arc::new(DockerMake::new(image)),
+        (None, Some(path)) => Arc::new(SavedTrace { path: path.clone() }),
+        (None, None) if args.build_kind == BuildKind::HostTests => Arc::new(HostCmake::default()),
+        (None, None) => Arc::new(LocalMake {
+            hermetic_env: args.hermetic.then(|| hermetic_env(&args.keep_env, |name| env::var(name).ok())),
+        }),
+    };
+    if args.copy_app_to_temp {
+        return Arc::new(TempAppCopy { inner: source });
+    }
+    source
+}
+
+// Log of the runs of a device, None without --log-file
+fn log_path(args: &Args, device_output_dir: &Path, device: &DeviceInfo) -> Option<PathBuf> {
+    match args.log_file.as_deref() {
+        Some(path) if path == Path::new("auto") => Some(device_output_dir.join(format!("extractor_{}.log", device.name))),
+        path => path.map(|p| std::path::absolute(p).unwrap_or_else(|_| p.to_path_buf())),
+    }
+}
+
+// Filters applied to the defines and cflags
+struct Filters {
+    defines: FilterSet,
+    cflags: FilterSet,
+}
+
+fn filters(args: &Args) -> Result<Filters, String> {
+    let load = |path: &Option<PathBuf>| path.as_deref().map(FilterSet::from_file).transpose();
+    Ok(Filters {
+        defines: FilterSet::default_defines().merge(load(&args.filter_defines)?.unwrap_or_default()),
+        cflags: load(&args.filter_cflags)?.unwrap_or_default(),
+    })
+}
+
+// Extract the parameters of a plan, `spinner` ticking on every trace line and
+// the diagnostics being recorded in `log`
+fn extract(
+    args: &Args,
+    name_template: &NameTemplate,
+    filters: &Filters,
+    config: &Config,
+    plan: &Plan,
+    spinner: &ProgressBar,
+    log: &mut RunLog,
+) -> Result<ExtractOutcome, String> {
+    let (path, device, output_dir) = (plan.app_path.as_path(), plan.device, plan.output_dir.as_path());
+    let child_env = match (&plan.child_env, plan.problems.first()) {
+        (Some(child_env), None) => child_env,
+        (_, Some(problem)) => return Err(problem.clone()),
+        (None, None) => unreachable!("a plan without environment has problems"),
+    };
+    spinner.suspend(|| {
+        for notice in &child_env.notices {
+            eprintln!("Notice: {}", notice);
+        }
+    });
+    // Concurrent runs sharing an output directory would interleave their writes
+    let _lock = if args.stdout {
+        None
+    } else {
+        let waiting = || spinner.suspend(|| eprintln!("Waiting for another extraction of {} in {}", device.name, output_dir.display()));
+        Some(OutputLock::acquire(output_dir, device.name, Duration::from_secs(args.lock_timeout), waiting)?)
+    };
+    log.debug(format!("App path: {}", path.display()));
+    log.debug(format!("Makefile: {}", plan.makefile.display()));
+    log.debug(format!("Device: {} (TARGET={}), BOLOS_SDK={}", device.name, child_env.target, child_env.bolos_sdk));
+    log.debug(format!("Output directory: {}, reference directory: {}", output_dir.display(), plan.reference_dir.display()));
+    log.debug(format!("Command: {}", plan.command_line(child_env)));
+    for notice in &child_env.notices {
+        log.debug(format!("Notice: {}", notice));
+    }
+
+    let modified = modified_files(Path::new(&child_env.bolos_sdk));
+    if let Some(files) = modified.as_ref().filter(|f| !f.is_empty()) {
+        let relevant = files.iter().filter(|f| affects_build(f)).collect::<Vec<_>>();
+        let mut message = format!("the SDK {} has local modifications", child_env.bolos_sdk);
+        for file in &relevant {
+            message.push_str(&format!("\n  {}", file));
+        }
+        if relevant.len() < files.len() {
+            message.push_str(&format!("\n  and {} other file(s)", files.len() - relevant.len()));
+        }
+        if args.require_clean_sdk {
+            return Err(format!("Refusing to extract, {}", message));
+        }
+        spinner.suspend(|| eprintln!("{}", color::error(&format!("Warning: {}", message))));
+        log.warn(&message);
+    }
+
+    let (makefile, default_pattern) = match args.build_kind {
+        BuildKind::Firmware => (args.makefile.clone(), default_compiler_pattern()),
+        BuildKind::HostTests => (
+            Some(host_cmake_lists(path, args.host_tests_dir.as_deref())?),
+            Regex::new(HOST_COMPILER_PATTERN).expect("valid pattern"),
+        ),
+    };
+    let options = ExtractOptions {
+        bolos_sdk: PathBuf::from(&child_env.bolos_sdk),
+        makefile,
+        make_args: plan.make_variables(),
+        backend: trace_source(args),
+        strategy: args.strategy,
+        occurrence_units: args.occurrence_units,
+        raw_values: args.raw_values,
+        portable_paths: args.portable_paths,
+        scope: args.scope,
+        compiler_pattern: args.compiler_pattern.clone().unwrap_or(default_pattern),
+        define_filters: filters.defines.clone(),
+        cflag_filters: filters.cflags.clone(),
+        max_define_length: (!args.keep_large_defines).then_some(args.max_define_length),
+        split_flags: args.split_flags,
+        drop_host_paths: args.drop_host_paths,
+        normalize_warnings: args.normalize_warnings,
+        check_consistency: args.check_consistency,
+        capture_make_vars: args.capture_make_vars.clone(),
+        builtin_defines: args.include_builtin_defines,
+        lossy: args.lossy,
+        on_invalid: args.on_invalid,
+    };
+    log.debug(format!("Trace source: {:?}", options.backend));
+    let mut params = match extract_with_progress(device, path, &options, || spinner.inc(1)) {
+        Ok(params) => params,
+        Err(ExtractError::NoCompileLine { mut message, trace }) => {
+            // Keep the full trace around for bug reports
+            let trace_file = output_dir.join(format!("failed_trace_{}.log", device.name));
+            match fs::create_dir_all(output_dir).and_then(|_| fs::write(&trace_file, trace)) {
+                Ok(()) => message.push_str(&format!("\nFull trace written to {}", trace_file.display())),
+                Err(e) => message.push_str(&format!("\nFailed to write the trace to {}: {}", trace_file.display(), e)),
+            }
+            return Err(message);
+        }
+        Err(e) => return Err(e.to_string()),
+    };
+    // Corrections of the app, applied before anything is written or compared
+    if let Some(overrides) = read_overrides(&plan.app_path)? {
+        let applied = apply_overrides(&overrides, &mut params.defines, &mut params.cflags);
+        for conflict in applied.conflicts {
+            let warning = format!("override of {} for {} not applied, {}", OVERRIDES_FILE, device.name, conflict);
+            spinner.suspend(|| eprintln!("Warning: {}", warning));
+            log.warn(warning);
+        }
+        for override_line in &applied.applied {
+            log.debug(format!("Override: {}", override_line));
+        }
+        params.metadata.applied_overrides = applied.applied;
+    }
+    // The outputs meant for other builds leave -Werror out, the canonical ones
+    // and the references keep the flags as the SDK gives them
+    let consumer_cflags = if args.strip_werror {
+        let (kept, stripped) = strip_werror(&params.cflags);
+        params.metadata.werror_stripped = stripped;
+        kept
+    } else {
+        params.cflags.clone()
+    };
+    let metadata = &params.metadata;
+    // Only make run on this host with the caller's environment can pick up its variables
+    if !metadata.hermetic && args.docker_image.is_none() && args.trace_file.is_none() {
+        for (name, value) in leaked_variables(&params.raw_commands, |name| env::var(name).ok()) {
+            let warning = format!(
+                "{}=\"{}\" of the environment appears verbatim on the compile lines, use --hermetic to leave it out",
+                name, value
+            );
+            spinner.suspend(|| eprintln!("Warning: {}", warning));
+            log.warn(warning);
+        }
+    }
+    if args.build_kind == BuildKind::Firmware {
+        for name in missing_display_defines(device.name, &raw_defines(&params.raw_commands)) {
+            let warning = format!(
+                "display define {} expected on {} is missing from the trace, was the wrong UI library selected?",
+                name, device.name
+            );
+            spinner.suspend(|| eprintln!("Warning: {}", warning));
+            log.warn(warning);
+        }
+    }
+    for issue in &metadata.parse_issues {
+        let warning = if metadata.invalid_defines.contains(&issue.token) { format!("skipped {}", issue) } else { issue.to_string() };
+        spinner.suspend(|| eprintln!("Warning: {}", warning));
+        log.warn(warning);
+    }
+    if args.verbose {
+        spinner.suspend(|| eprintln!("Metrics: {}", params.metrics.describe()));
+    }
+    log.debug(format!("Metrics: {}", params.metrics.describe()));
+    if !params.make_stderr.trim().is_empty() {
+        log.debug(format!("Standard error of make:\n{}", params.make_stderr.trim_end()));
+    }
+    log.debug(format!("{} compile line(s) found, combined with the {:?} strategy", params.raw_commands.len(), args.strategy));
+    for line in &params.raw_commands {
+        log.debug(format!("Compile line: {}", line));
+    }
+    log.debug(format!(
+        "{} define(s), {} cflag(s), {} include path(s) and {} source(s) kept",
+        params.defines.len(),
+        params.cflags.len(),
+        params.includes.len(),
+        params.sources.len()
+    ));
+    for define in &metadata.moved_defines {
+        log.debug(format!("Define {} moved to the metadata for its length", define.name));
+    }
+    for dropped in &metadata.dropped_host_paths {
+        log.debug(format!("Dropped host path {}", dropped));
+    }
+    if let Some(app_only) = &params.app_only {
+        log.debug(format!(
+            "The app adds {} define(s), {} cflag(s) and {} include path(s) to the SDK",
+            app_only.defines.len(),
+            app_only.cflags.len(),
+            app_only.includes.len()
+        ));
+    }
+    for overridden in &metadata.overridden_warnings {
+        log.debug(format!("Warning flag {} overridden by {}", overridden.flag, overridden.by));
+    }
+    // Audit of the define filters against the defines before filtering
+    let unfiltered = raw_defines(&params.raw_commands);
+    let mut audit = stale_entries(&filters.defines, &unfiltered)
+        .into_iter()
+        .map(|entry| format!("stale filter entry: {}", entry.pattern))
+        .collect::<Vec<_>>();
+    audit.extend(
+        suspicious_defines(&unfiltered, &filters.defines)
+            .into_iter()
+            .map(|(define, reason)| format!("unfiltered define {} looks app-specific ({})", define.name, reason)),
+    );
+    spinner.suspend(|| {
+        for note in &audit {
+            eprintln!("Note: {}", note);
+        }
+    });
+    for note in audit {
+        log.warn(note);
+    }
+    let toggled_features = baseline_toggles(args, &options, plan, &params, spinner, log)?;
+    if !toggled_features.is_empty() {
+        spinner.suspend(|| {
+            println!("SDK features toggled by the app:");
+            for feature in &toggled_features {
+                println!("  {} {}", if feature.enabled { "+" } else { "-" }, feature.name);
+            }
+        });
+    }
+    for feature in &toggled_features {
+        log.debug(format!("SDK feature {} {} by the app", feature.name, if feature.enabled { "enabled" } else { "disabled" }));
+    }
+    let asymmetry_warnings = asymmetries(&metadata.invocation_defines);
+    for asymmetry in &asymmetry_warnings {
+        log.warn(asymmetry);
+    }
+    log.debug(format!("Build directory: {}", metadata.build_dir.as_deref().unwrap_or("unknown")));
+    let build_dir_warnings = metadata
+        .build_dir_conflicts
+        .iter()
+        .map(|(source, dir)| {
+            format!(
+                "{} is built in {} instead of {}",
+                source,
+                dir,
+                metadata.build_dir.as_deref().unwrap_or_default()
+            )
+        })
+        .collect::<Vec<_>>();
+    spinner.suspend(|| {
+        for warning in &build_dir_warnings {
+            eprintln!("Warning: {}", warning);
+        }
+    });
+    for warning in build_dir_warnings {
+        log.warn(warning);
+    }
+
+    spinner.suspend(|| {
+        for dropped in &metadata.dropped_host_paths {
+            eprintln!("Dropped host path {}", dropped);
+        }
+        for asymmetry in &asymmetry_warnings {
+            eprintln!("Warning: {}", asymmetry);
+        }
+    });
+    let target_warnings = check_target(
+        device,
+        config.targets.get(device.name),
+        metadata.target_id.as_deref(),
+        metadata.target_name.as_deref(),
+    );
+    spinner.suspend(|| {
+        for warning in &target_warnings {
+            eprintln!("Warning: {}", warning);
+        }
+    });
+    for warning in target_warnings {
+        log.warn(warning);
+    }
+    let api_level_check = metadata.api_level.clone().unwrap_or_default();
+    log.debug(format!("API level: {}", api_level_check.describe()));
+    if api_level_check.is_mismatch() && !args.require_api_level_match {
+        spinner.suspend(|| eprintln!("Warning: API level mismatch, {}", api_level_check.describe()));
+    }
+    let define_value = |name: &str| params.defines.iter().find(|d| d.name == name).and_then(|d| d.value.clone());
+    let app = metadata.app_name.clone().unwrap_or_else(|| dir_name(path));
+    let api_level = define_value("API_LEVEL").or(api_level_check.sdk.clone()).unwrap_or_default();
+    let reference_name = name_template.render(&NameContext {
+        app: &app,
+        device: args.filename_style.device_name(device),
+        variant: &args.variant,
+        api_level: &api_level,
+        toolchain: metadata.toolchain.map_or("unknown", |t| t.name()),
+    })? + args.build_kind.suffix();
+    let base_name = format!("{}{}", reference_name, plan.cell.suffix());
+
+    // Host builds target the machine running them
+    if metadata.rust_target != device.rust_target
+        && device.rust_target != UNKNOWN_RUST_TARGET
+        && args.build_kind == BuildKind::Firmware
+    {
+        let warning = format!(
+            "the arch flags of {} map to Rust target {} instead of {}, has the SDK toolchain changed?",
+            device.name, metadata.rust_target, device.rust_target
+        );
+        spinner.suspend(|| eprintln!("Warning: {}", warning));
+        log.warn(warning);
+    }
+
+    let escaped_defines = escape_defines(&params.defines, args.escape_for);
+    let escaped_cflags = escape_cflags(&params.cflags, args.escape_for);
+    let moved_notes = metadata
+        .moved_defines
+        .iter()
+        .map(|d| {
+            let length = d.value.as_ref().map_or(0, String::len);
+            format!("// {} ({} bytes) moved to {}.json, see metadata.moved_defines\n", d.name, length, base_name)
+        })
+        .collect::<String>();
+    let override_notes = metadata
+        .define_overrides
+        .iter()
+        .map(|o| format!("// {}: {}, {} in the end\n", o.name, o.sequence.join(" "), if o.defined { "defined" } else { "undefined" }))
+        .collect::<String>();
+    let defines_text = |version: FormatVersion| {
+        version.header("//")
+            + &header(args.escape_for, "//")
+            + &render_defines(&version.order_defines(&escaped_defines))
+            + &moved_notes
+            + &override_notes
+    };
+    let cflags_text = |version: FormatVersion| version.header("#") + &header(args.escape_for, "#") + &render_cflags(&escaped_cflags);
+    let mut rendered = vec![
+        ("defines", defines_text(args.format_version)),
+        ("cflags", cflags_text(args.format_version)),
+        ("includes", render_cflags(&params.includes)),
+        ("sources", render_cflags(&params.sources)),
+        ("warnings", render_cflags(&params.warnings)),
+        ("codegenflags", render_cflags(&params.codegen)),
+        ("cmd", render_cflags(&params.raw_commands)),
+    ];
+    if args.emit_both_versions {
+        rendered.push(("v1.defines", defines_text(FormatVersion::V1)));
+        rendered.push(("v2.defines", defines_text(FormatVersion::V2)));
+        rendered.push(("v1.cflags", cflags_text(FormatVersion::V1)));
+        rendered.push(("v2.cflags", cflags_text(FormatVersion::V2)));
+    }
+    if !metadata.make_vars.is_empty() {
+        rendered.push(("makevars", render_make_vars(&metadata.make_vars)));
+    }
+    if args.include_builtin_defines {
+        rendered.push(("builtin.defines", render_defines(&params.builtin_defines)));
+    }
+    if args.format.contains(&Format::Properties) || args.emit.iter().any(|e| matches!(e, Emit::Properties)) {
+        rendered.push(("properties", render_properties(&params.defines, &consumer_cflags)));
+    }
+    if args.format.contains(&Format::Meson) || args.emit.iter().any(|e| matches!(e, Emit::Meson)) {
+        let compiler = metadata.compiler.as_ref().map(|c| c.resolved_path.as_deref().unwrap_or(&c.command));
+        let machine = MachineFile {
+            defines: &params.defines,
+            cflags: &consumer_cflags,
+            codegen: &params.codegen,
+            includes: &params.includes,
+            compiler: compiler.unwrap_or("cc"),
+            sdk: Path::new(&child_env.bolos_sdk),
+            app: &plan.app_path,
+            make_dir: plan.make_dir(),
+        };
+        if host_machine(&consumer_cflags).is_none() {
+            let warning = format!("no host_machine in the Meson machine file of {}, the CPU of the cflags is unknown", device.name);
+            spinner.suspend(|| eprintln!("Warning: {}", warning));
+            log.warn(warning);
+        }
+        rendered.push(("ini", render_meson(&machine)));
+    }
+    if let Some(app_only) = &params.app_only {
+        let defines = escape_defines(&app_only.defines, args.escape_for);
+        rendered.push(("app-only.defines", header(args.escape_for, "//") + &render_defines(&defines)));
+        rendered.push(("app-only.cflags", header(args.escape_for, "#") + &render_cflags(&escape_cflags(&app_only.cflags, args.escape_for))));
+        rendered.push(("app-only.includes", render_cflags(&app_only.includes)));
+    }
+    rendered.push(("json", params.to_json()));
+    let rendered =
+        rendered.into_iter().map(|(extension, text)| (extension, normalize_output(extension, &text))).collect::<Vec<_>>();
+    let rendered_text = |artifact: &str| rendered.iter().find(|(e, _)| *e == artifact).map_or("", |(_, text)| text.as_str());
+
+    // With --stdout, nothing touches the disk and there is no comparison
+    if let Some(emit) = args.emit.first().filter(|_| args.stdout) {
+        let (_, contents) = rendered.iter().find(|(extension, _)| *extension == emit.extension()).expect("rendered");
+        print!("{}", contents);
+        return Ok(ExtractOutcome { params: Some(params), ..Default::default() });
+    }
+
+    fs::create_dir_all(output_dir)
+        .map_err(|e| format!("Failed to create output directory {}: {}", output_dir.display(), e))?;
+    let mut outputs = Vec::new();
+    let mut unchanged = Vec::new();
+    for (extension, contents) in &rendered {
+        let file = output_dir.join(format!("{}.{}", base_name, extension));
+        if write_if_changed(&file, contents.as_bytes())? {
+            log.debug(format!("Wrote {}", file.display()));
+        } else {
+            log.debug(format!("Unchanged {}", file.display()));
+            unchanged.push(file.display().to_string());
+        }
+        outputs.push(file);
+    }
+
+    // Compare output files with reference files. Defines, cflags and make
+    // variables are compared as parameters, the other artifacts as normalized
+    // text. With --byte-exact, any byte difference is a mismatch.
+    let read = |path: &Path| fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e));
+    let existing_reference =
+        |artifact| plan.reference(&reference_name, artifact).filter(|r| r.exists() && !args.update_references);
+    // The references hold the normalized text outputs, escaping included
+    let current_params = BuildParameters {
+        defines: parse_defines(rendered_text("defines"))?,
+        cflags: parse_cflags(rendered_text("cflags")),
+        includes: parse_cflags(rendered_text("includes")),
+        metadata: Metadata { make_vars: parse_make_vars(rendered_text("makevars")), ..Default::default() },
+        occurrences: params.occurrences.clone(),
+        ..Default::default()
+    };
+    let mut reference_params = BuildParameters {
+        defines: existing_reference("defines")
+            .map(|r| read_defines(&r))
+            .transpose()?
+            .unwrap_or(current_params.defines.clone()),
+        cflags: existing_reference("cflags")
+            .map(|r| read_cflags(&r))
+            .transpose()?
+            .unwrap_or(current_params.cflags.clone()),
+        includes: existing_reference("includes")
+            .map(|r| read_cflags(&r))
+            .transpose()?
+            .unwrap_or(current_params.includes.clone()),
+        metadata: Metadata {
+            make_vars: existing_reference("makevars").map(|r| read_make_vars(&r)).transpose()?.unwrap_or_default(),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    // References written before the values were normalized compare with the
+    // values they stand for
+    if !args.raw_values {
+        normalize_define_values(&mut reference_params.defines, normalize_stored_value);
+    }
+    // and the ones written before the names were unescaped with the names
+    for define in &mut reference_params.defines {
+        define.name = unescape_define_name(&define.name);
+    }
+    let compare_options = CompareOptions {
+        renames: args.renames.as_deref().map(read_renames).transpose()?.unwrap_or_default(),
+        allow_renames: args.allow_renames,
+        strict_include_order: args.strict_include_order,
+        tolerate_partial: args.tolerate_partial,
+        ..Default::default()
+    };
+    let diff = compare(&reference_params, &current_params, &compare_options);
+    if let Some((first, second)) = diff.reordered_includes.as_ref().filter(|_| !args.strict_include_order) {
+        let warning = format!(
+            "the include paths of {} are the reference ones in another order, {} is now searched before {}",
+            device.name, first, second
+        );
+        spinner.suspend(|| eprintln!("Warning: {}", warning));
+        log.warn(warning);
+    }
+
+    // A truncated extraction fails before it can replace the references
+    let counts = [("defines", current_params.defines.len()), ("cflags", current_params.cflags.len())];
+    for (artifact, count) in counts {
+        let reference = plan.reference(&reference_name, artifact).filter(|r| r.exists());
+        let reference_count = match artifact {
+            "defines" => reference.map(|r| read_defines(&r).map(|d| d.len())).transpose()?,
+            _ => reference.map(|r| read_cflags(&r).map(|f| f.len())).transpose()?,
+        };
+        let bytes = rendered_text(artifact).len();
+        let reference_text = reference_count.map_or("no reference".to_string(), |c| format!("{} in the reference", c));
+        log.debug(format!("{} {} ({} bytes), {}", count, artifact, bytes, reference_text));
+        if let Some(problem) = config.shrink.check(artifact, count, reference_count) {
+            if !args.allow_shrink {
+                return Err(format!("Suspiciously small extraction for {}: {}, use --allow-shrink if expected", device.name, problem));
+            }
+            let warning = format!("small extraction for {} accepted with --allow-shrink: {}", device.name, problem);
+            spinner.suspend(|| eprintln!("Warning: {}", warning));
+            log.warn(warning);
+        }
+    }
+
+    let mut mismatches = Vec::new();
+    if api_level_check.is_mismatch() && args.require_api_level_match {
+        mismatches.push(format!("API level mismatch, {}", api_level_check.describe()));
+    }
+    if let Some(api_level) = &args.reference {
+        write_embedded_references(api_level, device.name, &plan.reference_dir, &reference_name)?;
+    }
+    let mut comparisons = Vec::new();
+    for (artifact, required) in COMPARED_ARTIFACTS {
+        if artifact == "makevars" && params.metadata.make_vars.is_empty() {
+            comparisons.push(Comparison {
+                artifact: artifact.to_string(),
+                reference: String::new(),
+                status: ComparisonStatus::Skipped,
+            });
+            continue;
+        }
+        let current = output_dir.join(format!("{}.{}", base_name, artifact));
+        let reference = if args.update_references {
+            plan.reference_dir.join(format!("{}.{}", base_name, artifact))
+        } else {
+            match plan.reference(&reference_name, artifact) {
+                Some(reference) => reference,
+                None => {
+                    comparisons.push(Comparison {
+                        artifact: artifact.to_string(),
+                        reference: String::new(),
+                        status: ComparisonStatus::Skipped,
+                    });
+                    continue;
+                }
+            }
+        };
+        let status = if args.update_references {
+            fs::create_dir_all(&plan.reference_dir).map_err(|e| format!("Failed to create references: {}", e))?;
+            fs::copy(&current, &reference)
+                .map_err(|e| format!("Failed to update {}: {}", reference.display(), e))?;
+            ComparisonStatus::Updated
+        } else if !reference.exists() {
+            if required && args.custom_target.is_none() {
+                mismatches.push(format!("Reference {} is missing for target {}", reference.display(), device.name));
+                ComparisonStatus::Missing
+            } else {
+                ComparisonStatus::Skipped
+            }
+        } else if match artifact {
+            // A reference still in the other format is compared with the
+            // output rendered in its format
+            "defines" | "cflags" if args.byte_exact => {
+                let expected = read(&reference)?;
+                let version = file_format_version(&String::from_utf8_lossy(&expected))?;
+                let current = match (artifact, version) {
+                    _ if version == args.format_version => read(&current)?,
+                    ("defines", version) => normalize_output("defines", &defines_text(version)).into_bytes(),
+                    (_, version) => normalize_output("cflags", &cflags_text(version)).into_bytes(),
+                };
+                current != expected
+            }
+            _ if args.byte_exact => read(&current)? != read(&reference)?,
+            "defines" => diff.defines_changed(),
+            "cflags" => diff.cflags_changed(),
+            "includes" => diff.includes_changed(),
+            "makevars" => diff.make_vars_changed(),
+            _ => rendered_text(artifact) != read_output(&reference)?,
+        } {
+            mismatches.push(format!("Current {} file does not match reference for target {}", artifact, device.name));
+            ComparisonStatus::Mismatch
+        } else {
+            ComparisonStatus::Match
+        };
+        comparisons.push(Comparison { artifact: artifact.to_string(), reference: reference.display().to_string(), status });
+    }
+    if args.update_references {
+        write_tool_version(&plan.reference_dir)?;
+    } else {
+        // A mismatch with references of an older cbpx may come from its
+        // parser rather than from the SDK
+        let mismatched_dirs = comparisons
+            .iter()
+            .filter(|c| matches!(c.status, ComparisonStatus::Mismatch))
+            .filter_map(|c| Path::new(&c.reference).parent())
+            .collect::<BTreeSet<_>>();
+        for dir in mismatched_dirs {
+            let Some(recorded) = read_tool_version(dir).filter(|v| predates(v, env!("CARGO_PKG_VERSION"))) else {
+                continue;
+            };
+            let note = format!(
+                "the references in {} were written by cbpx {}, before parser changes of cbpx {}; if the SDK didn't change, re-baseline them with --update-references",
+                dir.display(),
+                recorded,
+                env!("CARGO_PKG_VERSION")
+            );
+            spinner.suspend(|| eprintln!("Note: {}", note));
+            log.warn(note);
+        }
+    }
+
+    if !diff.is_empty() {
+        mismatches.extend(diff.lines().into_iter().map(|line| format!("  {}", line)));
+    } else if !diff.partial_defines.is_empty() || !diff.partial_cflags.is_empty() {
+        let partial = |kind: &str, entries: &BTreeMap<String, (usize, usize)>| {
+            entries.iter().map(|(name, (n, of))| format!("{} {} on {}/{} units", kind, name, n, of)).collect::<Vec<_>>()
+        };
+        let entries = [partial("define", &diff.partial_defines), partial("cflag", &diff.partial_cflags)].concat();
+        let warning = format!(
+            "differences of {} on only some compile units, tolerated with --tolerate-partial:\n  {}",
+            device.name,
+            entries.join("\n  ")
+        );
+        spinner.suspend(|| eprintln!("Warning: {}", warning));
+        log.warn(warning);
+    }
+    let diff = Some(diff).filter(|d| !d.is_empty());
+    Ok(ExtractOutcome { outputs, mismatches, comparisons, params: Some(params), diff, toggled_features, unchanged })
 }
@@ -0,0 +1,19 @@
+use std::{env, process::Command};
+
+// Check that GNU make can be run, returning the first line of its version.
+// The error says what was searched so that it can't be mistaken for a
+// problem with the app.
+pub fn make_version() -> Result<String, String> {
+    let output = Command::new("make").arg("--version").output().map_err(|e| {
+        let path = env::var("PATH").unwrap_or_default();
+        format!("GNU make is required but could not be run ({}), it was searched in PATH={}", e, path)
+    })?;
+    let version = String::from_utf8_lossy(&output.stdout).lines().next().unwrap_or("").trim().to_string();
+    if !version.starts_with("GNU Make") {
+        return Err(format!(
+            "GNU make is required (for --trace), `make --version` gave `{}`; BSD make is not supported, install GNU make (gmake) as `make`",
+            version
+        ));
+    }
+    Ok(version)
+}
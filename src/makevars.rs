@@ -0,0 +1,38 @@
+use std::collections::BTreeMap;
+
+// Goal injected with --eval to print variables once all makefiles are read
+const QUERY_GOAL: &str = "__cbpx_print_vars";
+const QUERY_MARKER: &str = "__cbpx_var";
+
+// Make arguments printing the final (expanded) value of make variables. A
+// recipe printing them through $(info) is injected with --eval: recipes are
+// expanded after every makefile has been read, even with -n.
+pub fn query_arguments(names: &[String]) -> Vec<String> {
+    let prints = names
+        .iter()
+        .map(|name| format!("$(info {}|{}|$(origin {})|$({}))", QUERY_MARKER, name, name, name))
+        .collect::<String>();
+    ["-n", "--no-print-directory", "--eval"]
+        .iter()
+        .map(|a| a.to_string())
+        .chain([format!("{}: ; @:{}", QUERY_GOAL, prints), QUERY_GOAL.to_string()])
+        .collect()
+}
+
+// Values printed by make run with `query_arguments`, undefined variables
+// being reported as None rather than empty
+pub fn parse_query_output(stdout: &[u8]) -> BTreeMap<String, Option<String>> {
+    let mut values = BTreeMap::new();
+    for line in String::from_utf8_lossy(stdout).lines() {
+        let mut fields = line.splitn(4, '|');
+        if fields.next() != Some(QUERY_MARKER) {
+            continue;
+        }
+        let (Some(name), Some(origin), Some(value)) = (fields.next(), fields.next(), fields.next()) else {
+            continue;
+        };
+        let value = (origin != "undefined").then(|| value.to_string());
+        values.insert(name.to_string(), value);
+    }
+    values
+}
@@ -0,0 +1,56 @@
+// A make variable and the values it takes in a `--matrix NAME=v1,v2` run
+#[derive(Debug, Clone)]
+pub struct MatrixAxis {
+    pub name: String,
+    pub values: Vec<String>,
+}
+
+pub fn parse_axis(s: &str) -> Result<MatrixAxis, String> {
+    let (name, values) = s.split_once('=').ok_or_else(|| format!("expected NAME=v1,v2,..., got `{}`", s))?;
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(format!("invalid make variable name `{}`", name));
+    }
+    let values = values.split(',').map(str::to_string).collect::<Vec<_>>();
+    if values.iter().any(|v| v.is_empty()) {
+        return Err(format!("empty value in `{}`", s));
+    }
+    Ok(MatrixAxis { name: name.to_string(), values })
+}
+
+// One combination of the matrix, i.e. the make variable assignments of a run
+#[derive(Debug, Clone, Default)]
+pub struct MatrixCell {
+    pub vars: Vec<(String, String)>,
+    // The default cell combines the first value of every axis
+    pub is_default: bool,
+}
+
+impl MatrixCell {
+    // Suffix appended to the base name of the output files, empty without matrix
+    pub fn suffix(&self) -> String {
+        self.vars.iter().map(|(name, value)| format!("_{}-{}", name, value)).collect()
+    }
+
+    pub fn label(&self) -> String {
+        self.vars.iter().map(|(name, value)| format!("{}={}", name, value)).collect::<Vec<_>>().join(",")
+    }
+}
+
+// Every combination of the axes, the default one first. Without axes there is
+// a single default cell with no variables.
+pub fn cells(axes: &[MatrixAxis]) -> Vec<MatrixCell> {
+    let mut cells = vec![MatrixCell { vars: Vec::new(), is_default: true }];
+    for axis in axes {
+        cells = cells
+            .iter()
+            .flat_map(|cell| {
+                axis.values.iter().enumerate().map(move |(index, value)| {
+                    let mut vars = cell.vars.clone();
+                    vars.push((axis.name.clone(), value.clone()));
+                    MatrixCell { vars, is_default: cell.is_default && index == 0 }
+                })
+            })
+            .collect();
+    }
+    cells
+}
@@ -0,0 +1,77 @@
+use std::path::Path;
+
+use crate::{build_dir::lexical, files::Define, rust_target::host_machine};
+
+// Inputs of a Meson machine file, gathered from the extraction
+pub struct MachineFile<'a> {
+    pub defines: &'a [Define],
+    pub cflags: &'a [String],
+    pub codegen: &'a [String],
+    // Include paths as extracted, `${BOLOS_SDK}/...`, `${APP_DIR}/...` or
+    // relative to `make_dir`
+    pub includes: &'a [String],
+    // Compiler binary, resolved on this host when possible
+    pub compiler: &'a str,
+    pub sdk: &'a Path,
+    pub app: &'a Path,
+    pub make_dir: &'a Path,
+}
+
+// Meson string literal, backslashes and quotes escaped
+pub fn meson_string(text: &str) -> String {
+    format!("'{}'", text.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+// Arch flags of the cflags: the target triple and the -m machine options
+pub fn is_target_flag(flag: &str) -> bool {
+    flag.starts_with("--target=") || (flag.len() > 2 && flag.starts_with("-m"))
+}
+
+// Meson expression of an include path, the SDK and the app directory being
+// the `bolos_sdk` and `app_dir` constants of the file
+fn include_arg(include: &str, app: &Path, make_dir: &Path) -> String {
+    let relative_to = |constant: &str, rest: &str| format!("'-I' + {} + {}", constant, meson_string(rest));
+    if let Some(rest) = include.strip_prefix("${BOLOS_SDK}") {
+        return relative_to("bolos_sdk", rest);
+    }
+    if let Some(rest) = include.strip_prefix("${APP_DIR}") {
+        return relative_to("app_dir", rest);
+    }
+    let path = lexical(&make_dir.join(include));
+    match path.strip_prefix(lexical(app)) {
+        Ok(rest) if rest.as_os_str().is_empty() => "'-I' + app_dir".to_string(),
+        Ok(rest) => relative_to("app_dir", &format!("/{}", rest.display())),
+        Err(_) => meson_string(&format!("-I{}", path.display())),
+    }
+}
+
+// Render a Meson machine file for cross builds against the SDK: the compiler
+// in `[binaries]`, the defines, arch flags, codegen flags and include paths as
+// `c_args`, and the `[host_machine]` of the arch flags, left out when the CPU
+// is unknown
+pub fn render_meson(machine: &MachineFile) -> String {
+    let defines = machine.defines.iter().map(|d| match &d.value {
+        Some(value) => meson_string(&format!("-D{}={}", d.name, value)),
+        None => meson_string(&format!("-D{}", d.name)),
+    });
+    let flags = machine.cflags.iter().filter(|f| is_target_flag(f)).chain(machine.codegen).map(|f| meson_string(f));
+    let includes = machine.includes.iter().map(|i| include_arg(i, machine.app, machine.make_dir));
+    let c_args = defines.chain(flags).chain(includes).map(|arg| format!("    {},\n", arg)).collect::<String>();
+
+    let mut out = format!(
+        "[constants]\nbolos_sdk = {}\napp_dir = {}\n\n[binaries]\nc = {}\n\n[built-in options]\nc_args = [\n{}    ]\n",
+        meson_string(&machine.sdk.display().to_string()),
+        meson_string(&machine.app.display().to_string()),
+        meson_string(machine.compiler),
+        c_args
+    );
+    if let Some(host) = host_machine(machine.cflags) {
+        out.push_str(&format!(
+            "\n[host_machine]\nsystem = 'none'\ncpu_family = {}\ncpu = {}\nendian = {}\n",
+            meson_string(host.cpu_family),
+            meson_string(host.cpu),
+            meson_string(host.endian)
+        ));
+    }
+    out
+}
@@ -0,0 +1,131 @@
+use crate::{
+    files::{normalize_output, parse_cflags, parse_defines, render_cflags, render_defines},
+    identifier::unescape_define_name,
+    portable::PathRoots,
+    quoting::{normalize_define_values, normalize_stored_value},
+};
+
+// Normalizations applied to legacy reference files
+#[derive(Default)]
+pub struct Normalizations {
+    // Sort the defines by name, the cflags keep their order which matters
+    pub sort: bool,
+    // Rewrite the absolute SDK and app paths as with a fresh extraction
+    pub roots: Option<PathRoots>,
+    // Write the define values as the compiler receives them, string literals
+    // in canonical syntax, as extractions do unless --raw-values
+    pub values: bool,
+}
+
+// Contents of a migrated file, and what changed in it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migrated {
+    pub contents: String,
+    pub changes: Vec<String>,
+}
+
+// Comment lines before the first entry, and the other ones. Files are only
+// migrated when rendering their entries between both gives them back, so that
+// nothing is lost.
+fn split_comments<'a>(contents: &'a str, comment: &str) -> (Vec<&'a str>, Vec<&'a str>) {
+    let lines = contents.lines().collect::<Vec<_>>();
+    let header = lines.iter().take_while(|l| l.starts_with(comment)).count();
+    let notes = lines[header..].iter().filter(|l| l.starts_with(comment)).copied().collect();
+    (lines[..header].to_vec(), notes)
+}
+
+fn with_comments(header: &[&str], body: &str, notes: &[&str]) -> String {
+    let comments = |lines: &[&str]| lines.iter().map(|l| format!("{}\n", l)).collect::<String>();
+    comments(header) + body + &comments(notes)
+}
+
+// Path rewritten as a whole word, or after the `=` of an option or define value
+fn portable_word(roots: &PathRoots, word: &str) -> String {
+    let whole = roots.portable(word);
+    if whole != word {
+        return whole;
+    }
+    match word.split_once('=') {
+        Some((option, value)) => format!("{}={}", option, roots.portable(value)),
+        None => word.to_string(),
+    }
+}
+
+fn check_round_trip(rendered: &str, contents: &str, extension: &str) -> Result<(), String> {
+    if rendered != contents {
+        let line = rendered.lines().zip(contents.lines()).position(|(a, b)| a != b).unwrap_or(rendered.lines().count());
+        return Err(format!("the {} file does not read back identically from line {}, it can't be migrated safely", extension, line + 1));
+    }
+    Ok(())
+}
+
+pub fn migrate_defines(contents: &str, normalizations: &Normalizations) -> Result<Migrated, String> {
+    let contents = normalize_output("defines", contents);
+    let mut defines = parse_defines(&contents)?;
+    let (header, notes) = split_comments(&contents, "//");
+    check_round_trip(&with_comments(&header, &render_defines(&defines), &notes), &contents, "defines")?;
+
+    let mut changes = Vec::new();
+    if let Some(roots) = &normalizations.roots {
+        let mut rewritten = 0;
+        for define in &mut defines {
+            if let Some(value) = define.value.as_mut() {
+                let portable = portable_word(roots, value);
+                if portable != *value {
+                    *value = portable;
+                    rewritten += 1;
+                }
+            }
+        }
+        if rewritten > 0 {
+            changes.push(format!("made {} define value(s) portable", rewritten));
+        }
+    }
+    if normalizations.values {
+        let mut normalized = defines.clone();
+        normalize_define_values(&mut normalized, normalize_stored_value);
+        let rewritten = normalized.iter().zip(&defines).filter(|(a, b)| a != b).count();
+        if rewritten > 0 {
+            changes.push(format!("normalized the quoting of {} define value(s)", rewritten));
+        }
+        let mut unescaped = 0;
+        for define in &mut normalized {
+            let name = unescape_define_name(&define.name);
+            if name != define.name {
+                define.name = name;
+                unescaped += 1;
+            }
+        }
+        if unescaped > 0 {
+            changes.push(format!("unescaped {} define name(s)", unescaped));
+        }
+        defines = normalized;
+    }
+    if normalizations.sort {
+        let mut sorted = defines.clone();
+        sorted.sort_by(|a, b| a.name.cmp(&b.name));
+        if sorted != defines {
+            changes.push(format!("sorted {} define(s)", sorted.len()));
+            defines = sorted;
+        }
+    }
+    Ok(Migrated { contents: with_comments(&header, &render_defines(&defines), &notes), changes })
+}
+
+pub fn migrate_cflags(contents: &str, normalizations: &Normalizations) -> Result<Migrated, String> {
+    let contents = normalize_output("cflags", contents);
+    let mut cflags = parse_cflags(&contents);
+    let (header, notes) = split_comments(&contents, "#");
+    check_round_trip(&with_comments(&header, &render_cflags(&cflags), &notes), &contents, "cflags")?;
+
+    let mut changes = Vec::new();
+    if let Some(roots) = &normalizations.roots {
+        let portable = cflags.iter().map(|f| portable_word(roots, f)).collect::<Vec<_>>();
+        let rewritten = portable.iter().zip(&cflags).filter(|(a, b)| a != b).count();
+        if rewritten > 0 {
+            changes.push(format!("made {} cflag(s) portable", rewritten));
+            cflags = portable;
+        }
+    }
+    Ok(Migrated { contents: with_comments(&header, &render_cflags(&cflags), &notes), changes })
+}
@@ -0,0 +1,118 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anstream::{eprintln, println};
+
+use cbpx::{
+    migrate::{migrate_cflags, migrate_defines, Migrated, Normalizations},
+    portable::PathRoots,
+};
+
+#[derive(clap::Args, Debug)]
+#[command(group = clap::ArgGroup::new("normalization").required(true).multiple(true))]
+pub struct MigrateReferencesArgs {
+    /// Directory of the reference .defines and .cflags files
+    #[arg(default_value = "references")]
+    reference_dir: PathBuf,
+
+    /// Sort the defines by name
+    #[arg(long, group = "normalization")]
+    sort: bool,
+
+    /// SDK path the references were generated with, rewritten as
+    /// ${BOLOS_SDK} in the define values and cflags
+    #[arg(long, value_name = "PATH", group = "normalization")]
+    portable_sdk: Option<PathBuf>,
+
+    /// App path the references were generated with, rewritten as ${APP_DIR}
+    #[arg(long, value_name = "PATH", requires = "portable_sdk")]
+    portable_app: Option<PathBuf>,
+
+    /// Write the define values as the compiler receives them, e.g.
+    /// `"1.2.3"` for `\"1.2.3\"`, as extractions do without --raw-values
+    #[arg(long, group = "normalization")]
+    normalize_values: bool,
+
+    /// Print the summary without writing anything
+    #[arg(long)]
+    dry_run: bool,
+}
+
+// Reference files of the directory, by name
+fn reference_files(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+    let mut files = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && p.extension().is_some_and(|e| e == "defines" || e == "cflags"))
+        .collect::<Vec<_>>();
+    files.sort();
+    Ok(files)
+}
+
+fn migrate(args: &MigrateReferencesArgs) -> Result<(), String> {
+    let normalizations = Normalizations {
+        sort: args.sort,
+        roots: args.portable_sdk.as_deref().map(|sdk| {
+            // Without an app path, only the SDK prefix is rewritten
+            PathRoots::new(sdk, args.portable_app.as_deref().unwrap_or(Path::new("")))
+        }),
+        values: args.normalize_values,
+    };
+    let files = reference_files(&args.reference_dir)?;
+    if files.is_empty() {
+        return Err(format!("No .defines or .cflags file in {}", args.reference_dir.display()));
+    }
+    // Every file is parsed before any is written, so that a file which can't
+    // be migrated leaves the directory untouched
+    let mut migrated: Vec<(&PathBuf, Migrated)> = Vec::new();
+    let mut errors = Vec::new();
+    for file in &files {
+        let result = fs::read_to_string(file).map_err(|e| e.to_string()).and_then(|contents| {
+            if file.extension().is_some_and(|e| e == "defines") {
+                migrate_defines(&contents, &normalizations)
+            } else {
+                migrate_cflags(&contents, &normalizations)
+            }
+        });
+        match result {
+            Ok(result) => migrated.push((file, result)),
+            Err(e) => errors.push(format!("{}: {}", file.display(), e)),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(format!("Refusing to migrate, some files can't be fully parsed:\n  {}", errors.join("\n  ")));
+    }
+
+    for (file, result) in migrated {
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        if result.changes.is_empty() {
+            println!("{}: unchanged", name);
+            continue;
+        }
+        println!("{}: {}", name, result.changes.join(", "));
+        if args.dry_run {
+            continue;
+        }
+        // The first backup is kept, it holds the legacy contents
+        let backup = PathBuf::from(format!("{}.bak", file.display()));
+        if !backup.exists() {
+            fs::copy(file, &backup).map_err(|e| format!("Failed to back up {}: {}", file.display(), e))?;
+        }
+        fs::write(file, result.contents).map_err(|e| format!("Failed to write {}: {}", file.display(), e))?;
+    }
+    Ok(())
+}
+
+// Run the `migrate-references` subcommand, returning 0 once the files are
+// migrated and 1 on error
+pub fn run(args: &MigrateReferencesArgs) -> i32 {
+    match migrate(args) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+
+use crate::{files::Define, params::BuildParameters};
+
+// Defines of one device, in compile line order
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DeviceDefines {
+    pub device: String,
+    pub defines: Vec<Define>,
+}
+
+// A device defining a name, and the value it gives it
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct DeviceValue {
+    pub device: String,
+    pub value: Option<String>,
+}
+
+// Parameters of several devices merged, keeping which device contributed
+// which define. Shared by the combined header and the capability matrix.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct MultiDeviceParameters {
+    pub devices: Vec<DeviceDefines>,
+}
+
+impl FromIterator<(String, BuildParameters)> for MultiDeviceParameters {
+    fn from_iter<I: IntoIterator<Item = (String, BuildParameters)>>(iter: I) -> Self {
+        let devices = iter.into_iter().map(|(device, params)| DeviceDefines { device, defines: params.defines });
+        MultiDeviceParameters { devices: devices.collect() }
+    }
+}
+
+impl MultiDeviceParameters {
+    pub fn device_names(&self) -> Vec<String> {
+        self.devices.iter().map(|d| d.device.clone()).collect()
+    }
+
+    fn is_common(&self, define: &Define) -> bool {
+        self.devices.iter().all(|d| d.defines.contains(define))
+    }
+
+    // Defines with the same value on every device, in the order of the first
+    // device
+    pub fn common_defines(&self) -> Vec<Define> {
+        let first = self.devices.first().map_or(&[][..], |d| d.defines.as_slice());
+        first.iter().filter(|d| self.is_common(d)).cloned().collect()
+    }
+
+    // Defines of a device missing on another device or valued differently
+    // there, none for an unknown device
+    pub fn device_specific(&self, device: &str) -> Vec<Define> {
+        let defines = self.devices.iter().find(|d| d.device == device).map_or(&[][..], |d| d.defines.as_slice());
+        defines.iter().filter(|d| !self.is_common(d)).cloned().collect()
+    }
+
+    // Devices defining each name, with their values
+    pub fn matrix(&self) -> BTreeMap<String, BTreeSet<DeviceValue>> {
+        let mut matrix = BTreeMap::<String, BTreeSet<DeviceValue>>::new();
+        for device in &self.devices {
+            for define in &device.defines {
+                let value = DeviceValue { device: device.device.clone(), value: define.value.clone() };
+                matrix.entry(define.name.clone()).or_default().insert(value);
+            }
+        }
+        matrix
+    }
+}
@@ -0,0 +1,146 @@
+// Output file naming. The base name of every generated and reference file
+// comes from a template such as `c_sdk_build_{device}`, the extension
+// (`.defines`, `.cflags`...) being appended by the caller.
+
+use regex::Regex;
+
+use crate::device::DeviceInfo;
+
+pub const DEFAULT_NAME_TEMPLATE: &str = "c_sdk_build_{device}";
+
+// Name of the device substituted into {device}
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum FilenameStyle {
+    /// Marketing name, e.g. nanosplus
+    #[default]
+    Marketing,
+    /// SDK TARGET, e.g. nanos2
+    Target,
+}
+
+impl FilenameStyle {
+    pub fn device_name(self, device: &DeviceInfo) -> &'static str {
+        match self {
+            FilenameStyle::Marketing => device.name,
+            FilenameStyle::Target => device.target,
+        }
+    }
+}
+
+const PLACEHOLDERS: [&str; 5] = ["app", "device", "variant", "api_level", "toolchain"];
+
+// Values substituted into the template placeholders
+pub struct NameContext<'a> {
+    pub app: &'a str,
+    pub device: &'a str,
+    pub variant: &'a str,
+    pub api_level: &'a str,
+    // `clang` or `gcc`, so that snapshots of both toolchains can coexist
+    pub toolchain: &'a str,
+}
+
+// Part of a parsed template
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Placeholder(&'static str),
+}
+
+#[derive(Debug, Clone)]
+pub struct NameTemplate {
+    template: String,
+    segments: Vec<Segment>,
+}
+
+impl NameContext<'_> {
+    fn value(&self, placeholder: &str) -> &str {
+        match placeholder {
+            "app" => self.app,
+            "device" => self.device,
+            "variant" => self.variant,
+            "api_level" => self.api_level,
+            _ => self.toolchain,
+        }
+    }
+}
+
+impl NameTemplate {
+    // Validate a template: placeholders must be known and properly closed, and
+    // the literal parts cannot contain path separators
+    pub fn parse(template: &str) -> Result<Self, String> {
+        if template.contains('/') || template.contains('\\') {
+            return Err(format!("Name template `{}` cannot contain path separators", template));
+        }
+        let mut segments = Vec::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| format!("Unclosed placeholder in name template `{}`", template))?;
+            let name = &rest[start + 1..start + end];
+            let Some(placeholder) = PLACEHOLDERS.iter().find(|p| **p == name) else {
+                return Err(format!(
+                    "Unknown placeholder {{{}}} in name template `{}`, expected one of {}",
+                    name,
+                    template,
+                    PLACEHOLDERS.map(|p| format!("{{{}}}", p)).join(", ")
+                ));
+            };
+            if start > 0 {
+                segments.push(Segment::Literal(rest[..start].to_string()));
+            }
+            segments.push(Segment::Placeholder(placeholder));
+            rest = &rest[start + end + 1..];
+        }
+        if !rest.is_empty() {
+            segments.push(Segment::Literal(rest.to_string()));
+        }
+        if segments.is_empty() {
+            return Err("Name template is empty".to_string());
+        }
+        Ok(NameTemplate { template: template.to_string(), segments })
+    }
+
+    // Render the base name in a single pass, so that a value containing a
+    // placeholder is taken literally, rejecting results that are empty or
+    // contain path separators coming from substituted values
+    pub fn render(&self, context: &NameContext) -> Result<String, String> {
+        let name = self
+            .segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Literal(text) => text.as_str(),
+                Segment::Placeholder(placeholder) => context.value(placeholder),
+            })
+            .collect::<String>();
+        if name.is_empty() {
+            return Err(format!("Name template `{}` produced an empty name", self.template));
+        }
+        if name.contains('/') || name.contains('\\') {
+            return Err(format!("Name template `{}` produced `{}` which contains a path separator", self.template, name));
+        }
+        Ok(name)
+    }
+
+    // Regular expression matching the base names the template renders to,
+    // `known` giving the pattern of a placeholder, the others matching any
+    // non-empty value
+    pub fn pattern(&self, known: impl Fn(&str) -> Option<String>) -> Regex {
+        let pattern = self
+            .segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Literal(text) => regex::escape(text),
+                Segment::Placeholder(placeholder) => known(placeholder).unwrap_or_else(|| ".+".to_string()),
+            })
+            .collect::<String>();
+        Regex::new(&format!("^(?:{})$", pattern)).expect("escaped template pattern")
+    }
+}
+
+// Derive the `{app}` value from the APPNAME define value, removing the shell
+// and C quoting around it (`\""Boilerplate"\"` gives `Boilerplate`)
+pub fn app_name_from_define(value: &str) -> Option<String> {
+    let name: String = value.chars().filter(|c| *c != '"' && *c != '\\').collect();
+    Some(name).filter(|n| !n.is_empty())
+}
@@ -0,0 +1,59 @@
+use std::{
+    fs::{self, File, TryLockError},
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+
+// Suffix of the lock files, excluded from `git add` of an install directory
+pub const LOCK_SUFFIX: &str = ".cbpx.lock";
+
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+// Advisory lock of the outputs of a device in an output directory, held
+// while the value lives. The operating system drops it with the file
+// descriptor, so that a panicking or interrupted run (Ctrl-C) releases it
+// as well. The lock file itself is left in place: removing it would let a
+// waiting run lock a file nobody else sees.
+#[derive(Debug)]
+pub struct OutputLock {
+    _file: File,
+    pub path: PathBuf,
+}
+
+pub fn lock_path(output_dir: &Path, device: &str) -> PathBuf {
+    output_dir.join(format!(".{}{}", device, LOCK_SUFFIX))
+}
+
+impl OutputLock {
+    // Lock the outputs of `device`, waiting up to `timeout` for another
+    // extraction to finish. `waiting` is called once when the lock is busy.
+    pub fn acquire(output_dir: &Path, device: &str, timeout: Duration, waiting: impl FnOnce()) -> Result<Self, String> {
+        let path = lock_path(output_dir, device);
+        fs::create_dir_all(output_dir).map_err(|e| format!("Failed to create {}: {}", output_dir.display(), e))?;
+        let file = File::create(&path).map_err(|e| format!("Failed to create lock file {}: {}", path.display(), e))?;
+        let started = Instant::now();
+        let mut waiting = Some(waiting);
+        loop {
+            match file.try_lock() {
+                Ok(()) => return Ok(OutputLock { _file: file, path }),
+                Err(TryLockError::WouldBlock) if started.elapsed() < timeout => {
+                    if let Some(waiting) = waiting.take() {
+                        waiting();
+                    }
+                    thread::sleep(POLL_INTERVAL);
+                }
+                Err(TryLockError::WouldBlock) => {
+                    return Err(format!(
+                        "Another extraction is in progress for {} in {}, still locked after {}s (lock file {})",
+                        device,
+                        output_dir.display(),
+                        timeout.as_secs(),
+                        path.display()
+                    ))
+                }
+                Err(TryLockError::Error(e)) => return Err(format!("Failed to lock {}: {}", path.display(), e)),
+            }
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use std::{fs, io, path::Path};
+
+use serde::Deserialize;
+
+use crate::{files::Define, identifier::is_valid_define_name};
+
+// Corrections an app applies to its extraction, e.g. a define the dry run
+// can't see as a phony target injects it, or a host-specific flag
+pub const OVERRIDES_FILE: &str = "csdk_overrides.toml";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Overrides {
+    pub add_defines: Vec<Define>,
+    pub remove_defines: Vec<String>,
+    pub add_cflags: Vec<String>,
+    pub remove_cflags: Vec<String>,
+}
+
+// Contents of an overrides file, the defines as they are written
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OverridesFile {
+    #[serde(default)]
+    add_defines: Vec<String>,
+    #[serde(default)]
+    remove_defines: Vec<String>,
+    #[serde(default)]
+    add_cflags: Vec<String>,
+    #[serde(default)]
+    remove_cflags: Vec<String>,
+}
+
+// What applying overrides did: a line per override applied, and the
+// overrides which had nothing to act on
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AppliedOverrides {
+    pub applied: Vec<String>,
+    pub conflicts: Vec<String>,
+}
+
+// `NAME` or `NAME=VALUE`, the value as the compiler receives it
+fn define(entry: &str) -> Result<Define, String> {
+    let (name, value) = match entry.split_once('=') {
+        Some((name, value)) => (name, Some(value.to_string())),
+        None => (entry, None),
+    };
+    if !is_valid_define_name(name) {
+        return Err(format!("`{}` is not a valid macro name", name));
+    }
+    Ok(Define { name: name.to_string(), value })
+}
+
+// Parse an overrides file: a TOML document of `add_defines`,
+// `remove_defines`, `add_cflags` and `remove_cflags` arrays of strings
+pub fn parse_overrides(contents: &str) -> Result<Overrides, String> {
+    let file: OverridesFile = toml::from_str(contents).map_err(|e| e.to_string())?;
+    let add_defines = file.add_defines.iter().map(|e| define(e)).collect::<Result<Vec<_>, _>>();
+    let add_defines = add_defines.map_err(|e| format!("add_defines: {}", e))?;
+    Ok(Overrides {
+        add_defines,
+        remove_defines: file.remove_defines,
+        add_cflags: file.add_cflags,
+        remove_cflags: file.remove_cflags,
+    })
+}
+
+// Overrides of the app directory, None without overrides file
+pub fn read_overrides(app_path: &Path) -> Result<Option<Overrides>, String> {
+    let path = app_path.join(OVERRIDES_FILE);
+    match fs::read_to_string(&path) {
+        Ok(contents) => parse_overrides(&contents).map(Some).map_err(|e| format!("{}: {}", path.display(), e)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+    }
+}
+
+fn describe(define: &Define) -> String {
+    match &define.value {
+        Some(value) => format!("{}={}", define.name, value),
+        None => define.name.clone(),
+    }
+}
+
+// Apply the overrides to extracted defines and cflags, the removals first so
+// that a define can be removed and added back with another value. An added
+// define replaces the value of the extracted one.
+pub fn apply_overrides(overrides: &Overrides, defines: &mut Vec<Define>, cflags: &mut Vec<String>) -> AppliedOverrides {
+    let mut result = AppliedOverrides::default();
+    for name in &overrides.remove_defines {
+        let count = defines.len();
+        defines.retain(|d| d.name != *name);
+        if defines.len() == count {
+            result.conflicts.push(format!("remove_defines: {} is not defined", name));
+        } else {
+            result.applied.push(format!("removed define {}", name));
+        }
+    }
+    for flag in &overrides.remove_cflags {
+        let count = cflags.len();
+        cflags.retain(|f| f != flag);
+        if cflags.len() == count {
+            result.conflicts.push(format!("remove_cflags: {} is not in the cflags", flag));
+        } else {
+            result.applied.push(format!("removed cflag {}", flag));
+        }
+    }
+    for added in &overrides.add_defines {
+        match defines.iter_mut().find(|d| d.name == added.name) {
+            Some(existing) if existing == added => {
+                result.conflicts.push(format!("add_defines: {} is already defined", describe(added)));
+            }
+            Some(existing) => {
+                result.applied.push(format!("replaced define {} with {}", describe(existing), describe(added)));
+                *existing = added.clone();
+            }
+            None => {
+                result.applied.push(format!("added define {}", describe(added)));
+                defines.push(added.clone());
+            }
+        }
+    }
+    for flag in &overrides.add_cflags {
+        if cflags.contains(flag) {
+            result.conflicts.push(format!("add_cflags: {} is already in the cflags", flag));
+        } else {
+            result.applied.push(format!("added cflag {}", flag));
+            cflags.push(flag.clone());
+        }
+    }
+    result
+}
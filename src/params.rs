@@ -0,0 +1,185 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::{
+    api_level::ApiLevelCheck, app_flags::AppFlags, compiler::CompilerInfo, consistency::KindDefines, display::Display, files::Define,
+    flags::OverriddenWarning, linker::MemoryLayout, parse_issue::ParseIssue, scope::AppOnly,
+    unit::Occurrences,
+    trace::{DefineOverride, Toolchain},
+};
+
+// Version of the JSON schema below, to be bumped on every change to it,
+// including changes of the serialized order, along with the golden files of
+// tests/golden.rs. tests/schema.rs fails on a change of the fields without a
+// bump.
+pub const SCHEMA_VERSION: u32 = 16;
+
+// Defines sorted by name, whatever their order on the compile line
+fn sorted_defines<S: Serializer>(defines: &[Define], serializer: S) -> Result<S::Ok, S::Error> {
+    let mut sorted = defines.iter().collect::<Vec<_>>();
+    sorted.sort_by(|a, b| a.name.cmp(&b.name));
+    serializer.collect_seq(sorted)
+}
+
+// Values in order of first appearance, duplicates removed
+fn first_seen<S: Serializer>(values: &[String], serializer: S) -> Result<S::Ok, S::Error> {
+    let mut seen = BTreeSet::new();
+    serializer.collect_seq(values.iter().filter(|v| seen.insert(v.as_str())))
+}
+
+// Structured form of the parameters extracted for one device, written next to
+// the text outputs as `<name>.json`. The serialized form is deterministic, as
+// it is diffed in git: fields in declaration order, maps sorted by key,
+// defines sorted by name, and cflags and include paths in order of first
+// appearance.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BuildParameters {
+    pub schema_version: u32,
+    pub device: String,
+    pub target: String,
+    #[serde(serialize_with = "sorted_defines")]
+    pub defines: Vec<Define>,
+    #[serde(serialize_with = "first_seen")]
+    pub cflags: Vec<String>,
+    #[serde(serialize_with = "first_seen")]
+    pub includes: Vec<String>,
+    pub sources: Vec<String>,
+    pub warnings: Vec<String>,
+    pub codegen: Vec<String>,
+    // Matched compile lines, verbatim
+    #[serde(default)]
+    pub raw_commands: Vec<String>,
+    // Predefined macros of the compiler, with --include-builtin-defines
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub builtin_defines: Vec<Define>,
+    // Compile units each define and cflag appears on, with --strategy union
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub occurrences: Option<Occurrences>,
+    // Parameters the app adds to the SDK, with --scope app
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub app_only: Option<AppOnly>,
+    // Standard error of make, for diagnostics only
+    #[serde(skip)]
+    pub make_stderr: String,
+    // Measurements of the extraction, which would make the output
+    // nondeterministic
+    #[serde(skip)]
+    pub metrics: Metrics,
+    pub metadata: Metadata,
+}
+
+// Measurements of an extraction, reported in the run summary and the batch
+// report
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Metrics {
+    // Wall-clock time of the trace source, e.g. of make
+    pub make_ms: u64,
+    pub trace_lines: usize,
+    pub trace_bytes: usize,
+    pub compile_lines: usize,
+    // Time from the trace to the parameters
+    pub parse_ms: u64,
+}
+
+impl Metrics {
+    pub fn describe(&self) -> String {
+        format!(
+            "make {} ms, trace of {} lines ({} bytes), {} compile line(s), parsed in {} ms",
+            self.make_ms, self.trace_lines, self.trace_bytes, self.compile_lines, self.parse_ms
+        )
+    }
+}
+
+// Information about how the parameters were produced
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Metadata {
+    // App name from the APPNAME define, before filtering
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub app_name: Option<String>,
+    // TARGET_ID and TARGET_NAME defines, before filtering
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_name: Option<String>,
+    // Makefile the parameters were extracted with
+    #[serde(default)]
+    pub makefile: Option<String>,
+    // Root of the build directory layout found in the -MF, -MT and -o
+    // arguments, relative to the app directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build_dir: Option<String>,
+    // Compile lines using another build directory, as (source, build
+    // directory) pairs. Only the first line is checked with the first
+    // strategy.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub build_dir_conflicts: Vec<(String, String)>,
+    pub compiler: Option<CompilerInfo>,
+    #[serde(default)]
+    pub toolchain: Option<Toolchain>,
+    pub memory: Option<MemoryLayout>,
+    // Screen and font parameters from the display defines, before filtering
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display: Option<Display>,
+    // Application flags of the load parameters, before filtering
+    #[serde(default)]
+    pub app_flags: AppFlags,
+    // Final values of the make variables requested with --capture-make-vars,
+    // None for undefined ones
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub make_vars: BTreeMap<String, Option<String>>,
+    // Whether the SDK checkout had local modifications, None when it is not
+    // a git checkout
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sdk_dirty: Option<bool>,
+    // Whether make ran with the explicit environment of --hermetic
+    #[serde(default)]
+    pub hermetic: bool,
+    // Rust target matching the -mcpu flag, `unknown` when there is no match
+    #[serde(default)]
+    pub rust_target: String,
+    // API level of the app against the one of the SDK
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_level: Option<ApiLevelCheck>,
+    // Defines moved out of the defines output for being too long
+    #[serde(default, skip_serializing_if = "Vec::is_empty", serialize_with = "sorted_defines")]
+    pub moved_defines: Vec<Define>,
+    // Defines whose path value was rewritten with --portable-paths, with
+    // their values as extracted
+    #[serde(default, skip_serializing_if = "Vec::is_empty", serialize_with = "sorted_defines")]
+    pub path_defines: Vec<Define>,
+    // Flags removed with --drop-host-paths for pointing outside of the SDK and
+    // the app
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dropped_host_paths: Vec<String>,
+    // Warning flags removed with --normalize-warnings, in command-line order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overridden_warnings: Vec<OverriddenWarning>,
+    // Flags left out of the consumer outputs with --strip-werror
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub werror_stripped: Vec<String>,
+    // Defines with a -U on a compile line, with their -D and -U sequence.
+    // The defines above are the ones in effect at the end of the lines.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub define_overrides: Vec<DefineOverride>,
+    // `-D` tokens left out with --on-invalid skip, their name not being a
+    // valid macro name
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub invalid_defines: Vec<String>,
+    // Words of the compile lines the parser could not make sense of, which
+    // didn't fail the extraction
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parse_issues: Vec<ParseIssue>,
+    // Overrides of the app csdk_overrides.toml applied to the extraction
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub applied_overrides: Vec<String>,
+    // Defines passed to each kind of invocation, with --check-consistency
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub invocation_defines: KindDefines,
+}
+
+impl BuildParameters {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Failed to serialize parameters") + "\n"
+    }
+}
@@ -0,0 +1,65 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{identifier::is_valid_define_name, trace::shell_words};
+
+// Characters of the compile line quoted in an issue
+pub const COMPILE_LINE_EXCERPT: usize = 200;
+
+// Word of a compile line the parser can't make sense of, with where it was
+// found, so that a surprising trace is reported rather than misparsed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParseIssue {
+    pub device: String,
+    // 1-based line of the make trace the compile line starts on
+    pub trace_line: usize,
+    pub token: String,
+    pub message: String,
+    // Compile line, truncated to COMPILE_LINE_EXCERPT characters
+    pub compile_line: String,
+    // Whether the issue fails the extraction
+    pub fatal: bool,
+}
+
+impl fmt::Display for ParseIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "`{}` {} ({} trace line {}: {})", self.token, self.message, self.device, self.trace_line, self.compile_line)
+    }
+}
+
+fn excerpt(line: &str) -> String {
+    match line.char_indices().nth(COMPILE_LINE_EXCERPT) {
+        Some((end, _)) => format!("{}...", &line[..end]),
+        None => line.to_string(),
+    }
+}
+
+// Issues of the `-D` and `-U` words of a compile line: names missing, the
+// `-D NAME` spelling not being supported, or not valid macro names. They are
+// not fatal until the caller decides so.
+pub fn compile_line_issues(device: &str, trace_line: usize, line: &str) -> Vec<ParseIssue> {
+    let mut issues = Vec::new();
+    for word in shell_words(line) {
+        let message = if let Some(define) = word.strip_prefix("-D") {
+            match define.split_once('=').map_or(define, |(name, _)| name) {
+                "" => "has no macro name, `-D NAME` with a space is not supported",
+                name if !is_valid_define_name(name) => "does not define a valid macro name",
+                _ => continue,
+            }
+        } else if word == "-U" {
+            "has no macro name, `-U NAME` with a space is not supported"
+        } else {
+            continue;
+        };
+        issues.push(ParseIssue {
+            device: device.to_string(),
+            trace_line,
+            token: word.to_string(),
+            message: message.to_string(),
+            compile_line: excerpt(line),
+            fatal: false,
+        });
+    }
+    issues
+}
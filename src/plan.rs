@@ -0,0 +1,221 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    app_makefile::includes_sdk_makefile,
+    device::{child_env, ChildEnv, DeviceInfo},
+    matrix::MatrixCell,
+    naming::{FilenameStyle, NameContext, NameTemplate},
+    sdk_layout::missing_sdk_files,
+};
+
+// Arguments given to make to obtain the trace. `-j1` overrides any `-j` of
+// MAKEFLAGS, as the output of parallel jobs interleaves and can split a
+// compile line.
+pub const MAKE_ARGS: [&str; 3] = ["--trace", "--dry-run", "-j1"];
+
+// Extensions of the files written for every run
+pub const OUTPUT_EXTENSIONS: [&str; 8] =
+    ["defines", "cflags", "includes", "sources", "warnings", "codegenflags", "cmd", "json"];
+
+// Outputs compared with their references. Optional ones are skipped when the
+// reference file doesn't exist, so that setups predating them keep working.
+// The raw commands are never compared, they hold absolute paths.
+pub const COMPARED_ARTIFACTS: [(&str, bool); 5] =
+    [("defines", true), ("cflags", true), ("includes", false), ("sources", false), ("makevars", false)];
+
+// Makefile of an app: `<app-path>/Makefile` unless another one is given,
+// relative to the app path
+pub fn makefile_path(app_path: &Path, makefile: Option<&Path>) -> PathBuf {
+    app_path.join(makefile.unwrap_or(Path::new("Makefile")))
+}
+
+// Directory make runs in, the one of the Makefile
+pub fn make_dir(makefile: &Path) -> &Path {
+    makefile.parent().unwrap_or(Path::new("."))
+}
+
+// Arguments selecting a Makefile, relative to its `make_dir`
+pub fn makefile_args(makefile: &Path) -> Vec<String> {
+    let name = makefile.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    vec!["-f".to_string(), name]
+}
+
+// Options shared by the plans of every app, device and matrix cell
+pub struct PlanOptions<'a> {
+    pub inherit_env: bool,
+    pub name_template: &'a NameTemplate,
+    pub filename_style: FilenameStyle,
+    pub variant: &'a str,
+    pub reference_dir: &'a Path,
+    pub makefile: Option<&'a Path>,
+    // Check that the SDK has what make needs for the device, pointless when
+    // make doesn't run
+    pub check_sdk: bool,
+    // Check that the Makefile is the one of a Ledger app
+    pub check_makefile: bool,
+}
+
+// Everything resolved from the arguments and the environment before running
+// make. Building a plan has no side effects, problems preventing the
+// extraction are collected in `problems` rather than reported.
+pub struct Plan<'a> {
+    pub app_path: PathBuf,
+    pub makefile: PathBuf,
+    pub device: &'a DeviceInfo,
+    pub cell: MatrixCell,
+    pub child_env: Option<ChildEnv>,
+    pub output_dir: PathBuf,
+    pub reference_dir: PathBuf,
+    // Base name of the files, with {app}, {api_level} and {toolchain} left
+    // unresolved as they can only be known from the trace
+    pub provisional_name: String,
+    pub problems: Vec<String>,
+}
+
+impl<'a> Plan<'a> {
+    pub fn new(
+        app_path: &Path,
+        device: &'a DeviceInfo,
+        cell: MatrixCell,
+        output_dir: &Path,
+        options: &PlanOptions,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Self {
+        let mut problems = Vec::new();
+
+        let makefile = makefile_path(app_path, options.makefile);
+        if !makefile.is_file() {
+            problems.push(format!("Makefile {} not found", makefile.display()));
+        } else if options.check_makefile {
+            match includes_sdk_makefile(&makefile) {
+                Ok(true) => {}
+                Ok(false) => problems.push(format!(
+                    "{} does not include a Makefile of $(BOLOS_SDK), this does not look like a Ledger app \
+                     Makefile (use --skip-makefile-check if it is)",
+                    makefile.display()
+                )),
+                Err(e) => problems.push(e),
+            }
+        }
+
+        let child_env = child_env(device, options.inherit_env, lookup).map_err(|e| problems.push(e)).ok();
+        if let Some(env) = &child_env {
+            if !Path::new(&env.bolos_sdk).is_dir() {
+                problems.push(format!("BOLOS_SDK {} is not a directory", env.bolos_sdk));
+            } else if options.check_sdk {
+                let missing = missing_sdk_files(device, Path::new(&env.bolos_sdk));
+                if !missing.is_empty() {
+                    problems.push(format!(
+                        "BOLOS_SDK {} is incomplete for {}, is it a partial checkout? Missing {}",
+                        env.bolos_sdk,
+                        device.name,
+                        missing.join(", ")
+                    ));
+                }
+            }
+        }
+
+        let provisional_name = options
+            .name_template
+            .render(&NameContext {
+                app: "{app}",
+                device: options.filename_style.device_name(device),
+                variant: options.variant,
+                api_level: "{api_level}",
+                toolchain: "{toolchain}",
+            })
+            .unwrap_or_else(|e| {
+                problems.push(e);
+                String::new()
+            });
+
+        Plan {
+            app_path: app_path.to_path_buf(),
+            makefile,
+            device,
+            cell,
+            child_env,
+            output_dir: output_dir.to_path_buf(),
+            reference_dir: options.reference_dir.to_path_buf(),
+            provisional_name,
+            problems,
+        }
+    }
+
+    pub fn make_dir(&self) -> &Path {
+        make_dir(&self.makefile)
+    }
+
+    pub fn makefile_args(&self) -> Vec<String> {
+        makefile_args(&self.makefile)
+    }
+
+    // Make variable assignments of the matrix cell, as make arguments
+    pub fn make_variables(&self) -> Vec<String> {
+        self.cell.vars.iter().map(|(name, value)| format!("{}={}", name, value)).collect()
+    }
+
+    // Reference of an artifact: the per-combination reference when it exists,
+    // falling back to the plain reference for the default combination only
+    pub fn reference(&self, reference_name: &str, artifact: &str) -> Option<PathBuf> {
+        let own = self.reference_dir.join(format!("{}{}.{}", reference_name, self.cell.suffix(), artifact));
+        if own.exists() || self.cell.vars.is_empty() {
+            Some(own)
+        } else if self.cell.is_default {
+            Some(self.reference_dir.join(format!("{}.{}", reference_name, artifact)))
+        } else {
+            None
+        }
+    }
+
+    // Make command of the plan, as it would be typed in a shell
+    pub fn command_line(&self, env: &ChildEnv) -> String {
+        let make_dir = self.make_dir().canonicalize().unwrap_or_else(|_| self.make_dir().to_path_buf());
+        format!(
+            "TARGET={} BOLOS_SDK={} make -C {} {}",
+            env.target,
+            env.bolos_sdk,
+            make_dir.display(),
+            self.makefile_args().into_iter().chain(MAKE_ARGS.iter().map(|a| a.to_string())).chain(self.make_variables()).collect::<Vec<_>>().join(" ")
+        )
+    }
+
+    pub fn is_executable(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    pub fn print(&self) {
+        let resolved = self.app_path.canonicalize().unwrap_or_else(|_| self.app_path.clone());
+        println!("App path:    {}", resolved.display());
+        println!("Makefile:    {}", self.makefile.display());
+        println!("Device:      {}", self.device.name);
+        match &self.child_env {
+            Some(env) => {
+                let exists = if Path::new(&env.bolos_sdk).is_dir() { "exists" } else { "missing" };
+                println!("TARGET:      {}", env.target);
+                println!("BOLOS_SDK:   {} ({})", env.bolos_sdk, exists);
+                println!("Command:     {}", self.command_line(env));
+                for notice in &env.notices {
+                    println!("Notice:      {}", notice);
+                }
+            }
+            None => println!("TARGET:      {}", self.device.target),
+        }
+        println!("Outputs:");
+        for extension in OUTPUT_EXTENSIONS {
+            let name = format!("{}{}.{}", self.provisional_name, self.cell.suffix(), extension);
+            println!("  {}", self.output_dir.join(name).display());
+        }
+        println!("References:");
+        for (artifact, required) in COMPARED_ARTIFACTS {
+            let note = if required { "" } else { " (optional)" };
+            match self.reference(&self.provisional_name, artifact) {
+                Some(reference) => println!("  {}{}", reference.display(), note),
+                None => println!("  none for {}, skipped", artifact),
+            }
+        }
+        for problem in &self.problems {
+            println!("Problem:     {}", problem);
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use std::path::Path;
+
+use crate::quoting::{c_string_literal, parse_c_string};
+
+// Rewriting of absolute paths below the SDK or the app directory into
+// `${BOLOS_SDK}/...` and `${APP_DIR}/...`, so that outputs containing paths
+// compare equal across machines
+pub struct PathRoots {
+    // (prefix, placeholder) pairs, longest prefix first
+    roots: Vec<(String, &'static str)>,
+}
+
+impl PathRoots {
+    pub fn new(sdk: &Path, app: &Path) -> Self {
+        let mut roots = Vec::new();
+        for (path, placeholder) in [(sdk, "${BOLOS_SDK}"), (app, "${APP_DIR}")] {
+            // Both the path as given and its canonical form may appear in the trace
+            let mut forms = vec![path.to_path_buf()];
+            forms.extend(path.canonicalize().ok());
+            for form in forms {
+                let prefix = form.display().to_string().trim_end_matches('/').to_string();
+                if !prefix.is_empty() && !roots.iter().any(|(p, _)| *p == prefix) {
+                    roots.push((prefix, placeholder));
+                }
+            }
+        }
+        roots.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+        PathRoots { roots }
+    }
+
+    pub fn portable(&self, path: &str) -> String {
+        for (prefix, placeholder) in &self.roots {
+            if let Some(rest) = path.strip_prefix(prefix.as_str()) {
+                if rest.is_empty() || rest.starts_with('/') {
+                    return format!("{}{}", placeholder, rest);
+                }
+            }
+        }
+        path.to_string()
+    }
+
+    // Define value rewritten when it is a path below the SDK or the app, bare
+    // or in a string literal, None otherwise. Only absolute paths under one
+    // of the roots and relative paths which exist from `make_dir` are taken
+    // as paths, so that values such as `1.2/beta` are left alone.
+    pub fn portable_define_value(&self, value: &str, make_dir: &Path) -> Option<String> {
+        let literal = parse_c_string(value);
+        let text = literal.as_deref().unwrap_or(value);
+        if text.is_empty() || text.contains(char::is_whitespace) {
+            return None;
+        }
+        let path = if text.starts_with('/') {
+            text.to_string()
+        } else if make_dir.join(text).exists() {
+            make_dir.join(text.trim_start_matches("./")).display().to_string()
+        } else {
+            return None;
+        };
+        let portable = self.portable(&path);
+        if portable == path {
+            return None;
+        }
+        Some(if literal.is_some() { c_string_literal(&portable) } else { portable })
+    }
+}
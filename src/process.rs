@@ -0,0 +1,32 @@
+use std::{
+    io::{self, BufRead, BufReader, Read},
+    process::{Command, Output, Stdio},
+    thread,
+};
+
+// Run `command` to completion like `Command::output`, calling `on_line` with
+// every line of its standard output as soon as it is received. Standard error
+// is collected by a thread so that neither pipe can fill up and block the
+// child.
+pub fn output_streaming(command: &mut Command, mut on_line: impl FnMut(&[u8])) -> io::Result<Output> {
+    let mut child = command.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    let mut stderr_pipe = child.stderr.take().expect("stderr is piped");
+    let stderr_reader = thread::spawn(move || {
+        let mut stderr = Vec::new();
+        stderr_pipe.read_to_end(&mut stderr).map(|_| stderr)
+    });
+
+    let mut stdout = Vec::new();
+    let mut reader = BufReader::new(child.stdout.take().expect("stdout is piped"));
+    loop {
+        let start = stdout.len();
+        if reader.read_until(b'\n', &mut stdout)? == 0 {
+            break;
+        }
+        on_line(&stdout[start..]);
+    }
+
+    let stderr = stderr_reader.join().expect("stderr reader panicked")?;
+    let status = child.wait()?;
+    Ok(Output { status, stdout, stderr })
+}
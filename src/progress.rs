@@ -0,0 +1,65 @@
+use std::{io::IsTerminal, time::Duration};
+
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+
+// Progress of a run: an overall bar counting the completed extractions, and a
+// spinner for the make invocation in progress. Everything is hidden when
+// disabled, so that callers don't need to check.
+pub struct Progress {
+    multi: MultiProgress,
+    overall: ProgressBar,
+}
+
+impl Progress {
+    // Enabled unless `quiet` or when stdout is not a terminal
+    pub fn new(quiet: bool) -> Self {
+        let target = if quiet || !std::io::stdout().is_terminal() {
+            ProgressDrawTarget::hidden()
+        } else {
+            ProgressDrawTarget::stderr()
+        };
+        let multi = MultiProgress::with_draw_target(target);
+        let overall = multi.add(ProgressBar::new(0));
+        overall.set_style(ProgressStyle::with_template("[{pos}/{len}] {wide_bar} {elapsed}").expect("valid template"));
+        Progress { multi, overall }
+    }
+
+    // Account for runs discovered along the way, e.g. once the devices of an
+    // app are known
+    pub fn add_runs(&self, count: usize) {
+        self.overall.inc_length(count as u64);
+    }
+
+    // Spinner of an extraction, ticking on every trace line and showing how
+    // long make has been running
+    pub fn start(&self, label: String) -> ProgressBar {
+        let spinner = self.multi.insert_before(&self.overall, ProgressBar::new_spinner());
+        spinner.set_style(
+            ProgressStyle::with_template("{spinner} {msg}: {pos} trace lines, make running for {elapsed}")
+                .expect("valid template"),
+        );
+        spinner.set_message(label);
+        spinner.enable_steady_tick(Duration::from_millis(100));
+        spinner
+    }
+
+    pub fn finish(&self, spinner: ProgressBar) {
+        spinner.finish_and_clear();
+        self.multi.remove(&spinner);
+        self.overall.inc(1);
+    }
+
+    // Account for an extraction skipped without running make
+    pub fn skip(&self) {
+        self.overall.inc(1);
+    }
+
+    // Run `f` with the bars hidden, for printing without garbling them
+    pub fn suspend<R>(&self, f: impl FnOnce() -> R) -> R {
+        self.multi.suspend(f)
+    }
+
+    pub fn clear(&self) {
+        self.overall.finish_and_clear();
+    }
+}
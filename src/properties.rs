@@ -0,0 +1,55 @@
+use std::collections::BTreeMap;
+
+use crate::files::Define;
+
+// Escape a key or value of a Java .properties file, which is read as
+// ISO-8859-1: backslashes, separators, comment characters and control
+// characters are escaped, and anything outside of printable ASCII is written
+// as `\uXXXX` UTF-16 escapes. Spaces are escaped in keys, and at both ends of
+// values where they would be trimmed, the trailing one as `\u0020` so that
+// trimming the output lines can't leave a line continuation.
+fn escape_property(text: &str, key: bool) -> String {
+    let last = text.chars().count().saturating_sub(1);
+    let mut out = String::new();
+    for (index, c) in text.chars().enumerate() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\x0c' => out.push_str("\\f"),
+            '=' | ':' | '#' | '!' => {
+                out.push('\\');
+                out.push(c);
+            }
+            ' ' if !key && index == last => out.push_str("\\u0020"),
+            ' ' if key || index == 0 => out.push_str("\\ "),
+            ' '..='~' => out.push(c),
+            _ => {
+                let mut units = [0; 2];
+                for unit in c.encode_utf16(&mut units) {
+                    out.push_str(&format!("\\u{:04X}", unit));
+                }
+            }
+        }
+    }
+    out
+}
+
+// Render defines and cflags as a .properties file: `define.NAME=VALUE` sorted
+// by name, a valueless define being worth 1 as for the compiler and the last
+// value of a define winning, then `cflag.N=FLAG` in command-line order
+pub fn render_properties(defines: &[Define], cflags: &[String]) -> String {
+    let values = defines
+        .iter()
+        .map(|d| (d.name.as_str(), d.value.as_deref().unwrap_or("1")))
+        .collect::<BTreeMap<_, _>>();
+    let mut out = String::new();
+    for (name, value) in values {
+        out.push_str(&format!("{}={}\n", escape_property(&format!("define.{}", name), true), escape_property(value, false)));
+    }
+    for (index, cflag) in cflags.iter().enumerate() {
+        out.push_str(&format!("cflag.{}={}\n", index, escape_property(cflag, false)));
+    }
+    out
+}
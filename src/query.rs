@@ -0,0 +1,111 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anstream::{eprintln, println};
+use regex::Regex;
+
+use cbpx::diff::{compare, CompareOptions, ParamsDiff};
+
+use crate::compare::load;
+
+#[derive(clap::Args, Debug)]
+#[command(group = clap::ArgGroup::new("question").required(true))]
+pub struct QueryArgs {
+    /// Old parameters, as a .json output, a .defines/.cflags file or an
+    /// output directory
+    #[arg(long)]
+    old: PathBuf,
+
+    /// New parameters, in the same form as the old ones
+    #[arg(long)]
+    new: PathBuf,
+
+    /// Whether a define was added, removed or changed, printing `yes` with
+    /// its old and new values, or `no`
+    #[arg(long, value_name = "NAME", group = "question")]
+    changed_define: Option<String>,
+
+    /// Whether defines whose whole name matches a regular expression were
+    /// removed, printing `yes` with their names, or `no`
+    #[arg(long, value_name = "REGEX", group = "question")]
+    removed_matching: Option<String>,
+}
+
+// Outputs present in both directories, by file name. The JSON outputs are
+// preferred, the .defines files being used when a directory has none.
+fn paired_outputs(old: &Path, new: &Path) -> Result<Vec<(String, PathBuf, PathBuf)>, String> {
+    let names = |dir: &Path, extension: &str| -> Result<Vec<String>, String> {
+        let entries = fs::read_dir(dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+        let mut names = entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.is_file() && p.extension().is_some_and(|e| e == extension))
+            .filter_map(|p| p.file_name().map(|n| n.to_string_lossy().into_owned()))
+            .collect::<Vec<_>>();
+        names.sort();
+        Ok(names)
+    };
+    for extension in ["json", "defines"] {
+        let (old_names, new_names) = (names(old, extension)?, names(new, extension)?);
+        let pairs = old_names
+            .into_iter()
+            .filter(|n| new_names.contains(n))
+            .map(|n| (n.clone(), old.join(&n), new.join(&n)))
+            .collect::<Vec<_>>();
+        if !pairs.is_empty() {
+            return Ok(pairs);
+        }
+    }
+    Err(format!("{} and {} have no output file in common", old.display(), new.display()))
+}
+
+fn answer(diff: &ParamsDiff, args: &QueryArgs, pattern: Option<&Regex>) -> String {
+    if let Some(name) = &args.changed_define {
+        return match diff.define_change(name) {
+            Some((old, new)) => format!("yes {} -> {}", old, new),
+            None => "no".to_string(),
+        };
+    }
+    match diff.removed_matching(pattern.expect("A question is required")).as_slice() {
+        [] => "no".to_string(),
+        names => format!("yes {}", names.join(" ")),
+    }
+}
+
+fn query(args: &QueryArgs) -> Result<Vec<String>, String> {
+    let pattern = args
+        .removed_matching
+        .as_deref()
+        .map(|p| Regex::new(p).map_err(|e| format!("Invalid --removed-matching expression: {}", e)))
+        .transpose()?;
+    let diff = |old: &Path, new: &Path| -> Result<ParamsDiff, String> {
+        let old = load(&old.to_string_lossy())?;
+        Ok(compare(&old, &load(&new.to_string_lossy())?, &CompareOptions::default()))
+    };
+    match (args.old.is_dir(), args.new.is_dir()) {
+        (false, false) => Ok(vec![answer(&diff(&args.old, &args.new)?, args, pattern.as_ref())]),
+        (true, true) => paired_outputs(&args.old, &args.new)?
+            .into_iter()
+            .map(|(name, old, new)| Ok(format!("{}: {}", name, answer(&diff(&old, &new)?, args, pattern.as_ref()))))
+            .collect(),
+        _ => Err("--old and --new must both be files or both be directories".to_string()),
+    }
+}
+
+// Run the `query` subcommand, returning 0 whenever the query could be
+// answered, whatever the answer, and 2 on error
+pub fn run(args: &QueryArgs) -> i32 {
+    match query(args) {
+        Ok(lines) => {
+            for line in lines {
+                println!("{}", line);
+            }
+            0
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            2
+        }
+    }
+}
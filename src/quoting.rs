@@ -0,0 +1,118 @@
+use crate::files::Define;
+
+// Define values arrive with the shell quoting of the Makefile that wrote them:
+// `\"1.2.3\"`, `'"1.2.3"'` and `"\"1.2.3\""` are the same string to the
+// compiler. Values are stored as the compiler receives them, string literals
+// in a single canonical syntax, so that a change of quoting in the SDK doesn't
+// show up as a difference.
+
+// Word with one level of shell quoting removed: backslash escapes, and
+// single-quoted and double-quoted segments
+pub fn shell_unquote(word: &str) -> String {
+    let mut out = String::new();
+    let mut chars = word.chars().peekable();
+    let mut quote = None;
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some('\''), '\'') | (Some('"'), '"') => quote = None,
+            (Some('\''), _) => out.push(c),
+            // Within double quotes, a backslash only escapes these
+            (Some(_), '\\') => match chars.peek() {
+                Some(&next) if "$`\"\\".contains(next) => out.extend(chars.next()),
+                _ => out.push(c),
+            },
+            (None, '\\') => out.push(chars.next().unwrap_or(c)),
+            (None, '\'' | '"') => quote = Some(c),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+// Text of a C string literal, None when `literal` is not a single string
+// literal or uses escapes which are not recognized
+pub fn parse_c_string(literal: &str) -> Option<String> {
+    let body = literal.strip_prefix('"')?.strip_suffix('"')?;
+    let mut out = String::new();
+    let mut chars = body.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => return None,
+            '\\' => {
+                let escaped = match chars.next()? {
+                    'n' => '\n',
+                    't' => '\t',
+                    'r' => '\r',
+                    'a' => '\x07',
+                    'b' => '\x08',
+                    'f' => '\x0c',
+                    'v' => '\x0b',
+                    c @ ('\\' | '"' | '\'' | '?') => c,
+                    c @ '0'..='7' => {
+                        let mut value = c.to_digit(8)?;
+                        for _ in 0..2 {
+                            match chars.peek().and_then(|c| c.to_digit(8)) {
+                                Some(digit) => {
+                                    value = value * 8 + digit;
+                                    chars.next();
+                                }
+                                None => break,
+                            }
+                        }
+                        char::from_u32(value)?
+                    }
+                    _ => return None,
+                };
+                out.push(escaped);
+            }
+            _ => out.push(c),
+        }
+    }
+    Some(out)
+}
+
+// C string literal of a text, escaping only the backslashes, the quotes and
+// the control characters
+pub fn c_string_literal(text: &str) -> String {
+    let mut out = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c if c.is_control() && (c as u32) < 0o400 => out.push_str(&format!("\\{:03o}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// Value as the compiler receives it, string literals in canonical syntax
+pub fn normalize_define_value(value: &str) -> String {
+    let unquoted = shell_unquote(value);
+    match parse_c_string(&unquoted) {
+        Some(text) => c_string_literal(&text),
+        None => unquoted,
+    }
+}
+
+// Value of a defines file, which may predate the normalization. A canonical
+// string literal is taken as already normalized, as the shell would have
+// removed its quotes.
+pub fn normalize_stored_value(value: &str) -> String {
+    match parse_c_string(value).map(|text| c_string_literal(&text)) {
+        Some(canonical) if canonical == value => canonical,
+        _ => normalize_define_value(value),
+    }
+}
+
+pub fn normalize_define_values(defines: &mut [Define], normalize: fn(&str) -> String) {
+    for define in defines {
+        if let Some(value) = define.value.as_mut() {
+            *value = normalize(value);
+        }
+    }
+}
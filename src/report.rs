@@ -0,0 +1,125 @@
+use std::{fs, io, path::Path};
+
+use serde::Serialize;
+
+use crate::{baseline::ToggledFeature, diff::ParamsDiff, params::Metrics, parse_issue::ParseIssue};
+
+// Version of the batch report schema, to be bumped on every change to it
+pub const REPORT_SCHEMA_VERSION: u32 = 7;
+
+// Outcome of the extraction of one app for one device
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunStatus {
+    Match,
+    Mismatch,
+    Error,
+    // Skipped thanks to an up-to-date stamp of a previous matching run
+    Unchanged,
+    // Device not extracted for the app, e.g. not in its SUPPORTED_DEVICES
+    Skipped,
+}
+
+impl RunStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            RunStatus::Match => "match",
+            RunStatus::Mismatch => "mismatch",
+            RunStatus::Error => "error",
+            RunStatus::Unchanged => "unchanged",
+            RunStatus::Skipped => "skipped",
+        }
+    }
+}
+
+// Outcome of the comparison of one output with its reference
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ComparisonStatus {
+    Match,
+    Mismatch,
+    Missing,
+    Skipped,
+    Updated,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Comparison {
+    pub artifact: String,
+    pub reference: String,
+    pub status: ComparisonStatus,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RunReport {
+    pub app: String,
+    pub app_path: String,
+    pub device: String,
+    // Make variable assignments of the matrix cell, empty without matrix
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub cell: String,
+    pub status: RunStatus,
+    pub messages: Vec<String>,
+    pub outputs: Vec<String>,
+    pub comparisons: Vec<Comparison>,
+    // Differences of the defines and cflags with their references
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff: Option<ParamsDiff>,
+    // HEAD of the SDK checkout, when it is a git repository
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sdk_hash: Option<String>,
+    // Measurements of the extraction, absent when it didn't complete
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<Metrics>,
+    // SDK defines the app enables or disables, with --baseline
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub toggled_features: Vec<ToggledFeature>,
+    // Outputs left untouched as their contents didn't change
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unchanged_outputs: Vec<String>,
+    // Words of the compile lines the parser skipped, with their context
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub parse_issues: Vec<ParseIssue>,
+    // Overrides of the app csdk_overrides.toml applied to the extraction
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub applied_overrides: Vec<String>,
+}
+
+impl RunReport {
+    // Report of a run with a status and messages, before anything was
+    // extracted
+    pub fn new(app: &str, app_path: &Path, device: &str, status: RunStatus, messages: Vec<String>) -> Self {
+        RunReport {
+            app: app.to_string(),
+            app_path: app_path.display().to_string(),
+            device: device.to_string(),
+            cell: String::new(),
+            status,
+            messages,
+            outputs: Vec::new(),
+            comparisons: Vec::new(),
+            diff: None,
+            sdk_hash: None,
+            metrics: None,
+            toggled_features: Vec::new(),
+            unchanged_outputs: Vec::new(),
+            parse_issues: Vec::new(),
+            applied_overrides: Vec::new(),
+        }
+    }
+}
+
+// Aggregated report of a batch run, written at the top of the output directory
+#[derive(Debug, Default, Serialize)]
+pub struct BatchReport {
+    pub schema_version: u32,
+    pub success: bool,
+    pub runs: Vec<RunReport>,
+}
+
+impl BatchReport {
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, json + "\n")
+    }
+}
@@ -0,0 +1,58 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+// Diagnostics written to --log-file, at debug level whatever the console
+// verbosity: the resolved configuration, the make command and its standard
+// error, what was found in the trace and the comparison results
+#[derive(Default)]
+pub struct RunLog {
+    lines: Vec<String>,
+    // Whether a run of the log failed or mismatched its references
+    failed: bool,
+}
+
+impl RunLog {
+    pub fn debug(&mut self, message: impl AsRef<str>) {
+        self.record("DEBUG", message.as_ref());
+    }
+
+    pub fn warn(&mut self, message: impl AsRef<str>) {
+        self.record("WARN", message.as_ref());
+    }
+
+    pub fn error(&mut self, message: impl AsRef<str>) {
+        self.record("ERROR", message.as_ref());
+    }
+
+    // Add the lines of another log, `failed` telling whether its run failed
+    pub fn append(&mut self, mut other: RunLog, failed: bool) {
+        self.lines.append(&mut other.lines);
+        self.failed |= failed;
+    }
+
+    // Multi-line messages are indented under their level
+    fn record(&mut self, level: &str, message: &str) {
+        let mut lines = message.lines();
+        self.lines.push(format!("{:<5} {}", level, lines.next().unwrap_or_default()));
+        self.lines.extend(lines.map(|line| format!("      {}", line)));
+    }
+
+    pub fn failed(&self) -> bool {
+        self.failed
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        let contents = self.lines.iter().map(|line| format!("{}\n", line)).collect::<String>();
+        fs::write(path, contents).map_err(|e| format!("Failed to write log {}: {}", path.display(), e))
+    }
+}
+
+// Logs of an invocation by path, the runs sharing a path being appended to
+// the same log
+pub type RunLogs = BTreeMap<PathBuf, RunLog>;
@@ -0,0 +1,48 @@
+// CPUs given with -mcpu, extensions (`+nodsp`) ignored, with their Rust target
+// and their Meson CPU family
+struct Cpu {
+    name: &'static str,
+    rust_target: &'static str,
+    cpu_family: &'static str,
+}
+
+const CPUS: [Cpu; 6] = [
+    Cpu { name: "cortex-m0", rust_target: "thumbv6m-none-eabi", cpu_family: "arm" },
+    Cpu { name: "cortex-m0plus", rust_target: "thumbv6m-none-eabi", cpu_family: "arm" },
+    Cpu { name: "cortex-m3", rust_target: "thumbv7m-none-eabi", cpu_family: "arm" },
+    Cpu { name: "cortex-m4", rust_target: "thumbv7em-none-eabi", cpu_family: "arm" },
+    Cpu { name: "cortex-m33", rust_target: "thumbv8m.main-none-eabi", cpu_family: "arm" },
+    Cpu { name: "cortex-m35p", rust_target: "thumbv8m.main-none-eabi", cpu_family: "arm" },
+];
+
+pub const UNKNOWN_RUST_TARGET: &str = "unknown";
+
+// CPU of the last -mcpu flag, as for the compiler, when it is in the table
+fn cpu(cflags: &[String]) -> Option<&'static Cpu> {
+    let cpu = cflags.iter().rev().find_map(|f| f.strip_prefix("-mcpu="))?;
+    let cpu = cpu.split('+').next().unwrap_or(cpu);
+    CPUS.iter().find(|c| c.name == cpu)
+}
+
+// Rust target matching the arch flags of the cflags, `unknown` when there is
+// no -mcpu flag or its CPU is not in the table
+pub fn rust_target(cflags: &[String]) -> &'static str {
+    cpu(cflags).map_or(UNKNOWN_RUST_TARGET, |c| c.rust_target)
+}
+
+// Meson `host_machine` of the arch flags of the cflags
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostMachine {
+    pub cpu_family: &'static str,
+    pub cpu: &'static str,
+    pub endian: &'static str,
+}
+
+// Host machine matching the arch flags of the cflags, None when there is no
+// -mcpu flag or its CPU is not in the table. Little endian unless the last
+// endianness flag says otherwise.
+pub fn host_machine(cflags: &[String]) -> Option<HostMachine> {
+    let cpu = cpu(cflags)?;
+    let big_endian = cflags.iter().rev().find(|f| *f == "-mbig-endian" || *f == "-mlittle-endian").is_some_and(|f| f == "-mbig-endian");
+    Some(HostMachine { cpu_family: cpu.cpu_family, cpu: cpu.name, endian: if big_endian { "big" } else { "little" } })
+}
@@ -0,0 +1,37 @@
+use std::collections::BTreeSet;
+
+use crate::files::Define;
+
+// Prefix of the cfg names, keeping them apart from the cfgs of other crates
+pub const CFG_PREFIX: &str = "csdk_";
+
+// cfg name of a define: prefixed and lower-cased, every character which is
+// not an ASCII letter, digit or underscore being replaced by an underscore
+// (`HAVE_AES-GCM` gives `csdk_have_aes_gcm`)
+pub fn cfg_name(define: &str) -> String {
+    let mangled = define
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c.to_ascii_lowercase() } else { '_' })
+        .collect::<String>();
+    format!("{}{}", CFG_PREFIX, mangled)
+}
+
+// Boolean capability defines are the `HAVE_*` ones without a value, or set
+// to 1
+pub fn is_capability(define: &Define) -> bool {
+    define.name.starts_with("HAVE_") && matches!(define.value.as_deref(), None | Some("1"))
+}
+
+// cfg names of the capability defines, sorted
+pub fn capability_cfgs(defines: &[Define]) -> BTreeSet<String> {
+    defines.iter().filter(|d| is_capability(d)).map(|d| cfg_name(&d.name)).collect()
+}
+
+// `--cfg` flags of the capabilities of a device, and a `--check-cfg` line
+// declaring every known cfg name. Each line can be appended to RUSTFLAGS or
+// split into the `rustflags` array of `.cargo/config.toml`.
+pub fn render_rustflags(cfgs: &BTreeSet<String>, known: &BTreeSet<String>) -> String {
+    let flags = cfgs.iter().map(|cfg| format!("--cfg {}", cfg)).collect::<Vec<_>>().join(" ");
+    let known = known.iter().map(String::as_str).collect::<Vec<_>>().join(",");
+    format!("{}\n--check-cfg cfg({})\n", flags, known)
+}
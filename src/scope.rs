@@ -0,0 +1,99 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{build_dir::lexical, files::Define, portable::PathRoots, unit::{merge_units, CompileUnit, Strategy}};
+
+// Compile units the parameters are extracted from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Scope {
+    /// Every compile unit, combined with the strategy
+    #[default]
+    All,
+    /// Also extract what the compile units of the app sources add on top of
+    /// the ones of the SDK sources
+    App,
+}
+
+// Defines, cflags and include paths of the compile units of app sources that
+// no compile unit of an SDK source has, in order of first appearance
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppOnly {
+    pub defines: Vec<Define>,
+    pub cflags: Vec<String>,
+    pub includes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Origin {
+    Sdk,
+    App,
+    Other,
+}
+
+// Whether a source is below the SDK, below the app directory or elsewhere.
+// `make_dir` is the directory make runs in, relative sources being relative
+// to it. Sources are resolved both as written and canonicalized, so that a
+// build directory or an SDK reached through a symbolic link doesn't hide where
+// they come from. The SDK wins when it is inside the app directory.
+fn origin(source: &str, make_dir: &Path, app_path: &Path, roots: &PathRoots) -> Origin {
+    let source = make_dir.join(source);
+    let forms = [lexical(&source)].into_iter().chain(source.canonicalize().ok()).collect::<Vec<_>>();
+    let app_dirs = [lexical(app_path)].into_iter().chain(app_path.canonicalize().ok()).collect::<Vec<_>>();
+    if forms.iter().any(|form| roots.portable(&form.display().to_string()).starts_with("${BOLOS_SDK}")) {
+        Origin::Sdk
+    } else if forms.iter().any(|form| app_dirs.iter().any(|app| form.starts_with(app))) {
+        Origin::App
+    } else {
+        Origin::Other
+    }
+}
+
+// Defines of the compile units of SDK sources, the first value of a define
+// winning
+pub fn sdk_unit_defines(units: &[CompileUnit], make_dir: &Path, app_path: &Path, roots: &PathRoots) -> Vec<Define> {
+    let sdk = units
+        .iter()
+        .filter(|u| u.source.as_deref().map(|s| origin(s, make_dir, app_path, roots)) == Some(Origin::Sdk))
+        .cloned()
+        .collect::<Vec<_>>();
+    merge_units(&sdk, Strategy::Union).0
+}
+
+// Parameters the app adds to the SDK: the set difference between the
+// parameters of the app compile units and the ones of the SDK compile units
+pub fn app_only(units: &[CompileUnit], make_dir: &Path, app_path: &Path, roots: &PathRoots) -> Result<AppOnly, String> {
+    let units_of = |wanted: Origin| {
+        units
+            .iter()
+            .filter(|u| u.source.as_deref().map(|s| origin(s, make_dir, app_path, roots)) == Some(wanted))
+            .collect::<Vec<_>>()
+    };
+    let (app, sdk) = (units_of(Origin::App), units_of(Origin::Sdk));
+    if app.is_empty() || sdk.is_empty() {
+        return Err(format!(
+            "The trace has {} compile line(s) of app sources and {} of SDK sources, both are needed to tell the app parameters apart",
+            app.len(),
+            sdk.len()
+        ));
+    }
+    let mut only = AppOnly::default();
+    for unit in app {
+        for define in &unit.defines {
+            if !only.defines.contains(define) && !sdk.iter().any(|u| u.defines.contains(define)) {
+                only.defines.push(define.clone());
+            }
+        }
+        for cflag in &unit.cflags {
+            if !only.cflags.contains(cflag) && !sdk.iter().any(|u| u.cflags.contains(cflag)) {
+                only.cflags.push(cflag.clone());
+            }
+        }
+        for include in &unit.includes {
+            if !only.includes.contains(include) && !sdk.iter().any(|u| u.includes.contains(include)) {
+                only.includes.push(include.clone());
+            }
+        }
+    }
+    Ok(only)
+}
@@ -0,0 +1,68 @@
+use std::{path::Path, thread};
+
+use serde::Serialize;
+
+use crate::{
+    device::DeviceInfo,
+    diff::{compare, CompareOptions, ParamsDiff},
+    extract::{extract, ExtractOptions},
+    git::head_hash,
+};
+
+// SDK checkout one side of the comparison was extracted with
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SdkSide {
+    pub sdk: String,
+    // None when the checkout is not a git one
+    pub hash: Option<String>,
+}
+
+impl SdkSide {
+    fn new(sdk: &Path) -> Self {
+        SdkSide { sdk: sdk.display().to_string(), hash: head_hash(sdk) }
+    }
+
+    fn label(&self) -> String {
+        format!("{} ({})", self.sdk, self.hash.as_deref().unwrap_or("not a git checkout"))
+    }
+}
+
+// Differences of the parameters of an app between two SDK checkouts
+#[derive(Debug, Clone, Serialize)]
+pub struct SdkDelta {
+    pub device: String,
+    pub old: SdkSide,
+    pub new: SdkSide,
+    pub diff: ParamsDiff,
+}
+
+impl SdkDelta {
+    pub fn header(&self) -> String {
+        format!("{}: old SDK {} → new SDK {}", self.device, self.old.label(), self.new.label())
+    }
+}
+
+// Extract the parameters of the app with both SDKs, the SDK of each side
+// being the `bolos_sdk` of its options. The extractions run concurrently,
+// each make getting its own environment.
+pub fn sdk_delta(
+    device: &DeviceInfo,
+    app_path: &Path,
+    old: &ExtractOptions,
+    new: &ExtractOptions,
+    options: &CompareOptions,
+) -> Result<SdkDelta, String> {
+    let (old_params, new_params) = thread::scope(|scope| {
+        let old_run = scope.spawn(|| extract(device, app_path, old));
+        let new_params = extract(device, app_path, new);
+        (old_run.join().expect("extraction thread panicked"), new_params)
+    });
+    let old_params = old_params.map_err(|e| format!("With the old SDK: {}", e))?;
+    let new_params = new_params.map_err(|e| format!("With the new SDK: {}", e))?;
+    Ok(SdkDelta {
+        device: device.name.to_string(),
+        old: SdkSide::new(&old.bolos_sdk),
+        new: SdkSide::new(&new.bolos_sdk),
+        diff: compare(&old_params, &new_params, options),
+    })
+}
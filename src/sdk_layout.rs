@@ -0,0 +1,37 @@
+use std::path::Path;
+
+use crate::device::DeviceInfo;
+
+// Something a build for some devices needs in the SDK checkout: one of the
+// `any_of` paths, relative to the SDK, `{target}` standing for the TARGET of
+// the device. No devices means every device.
+pub struct SdkRequirement {
+    pub devices: &'static [&'static str],
+    pub any_of: &'static [&'static str],
+    pub what: &'static str,
+}
+
+pub const SDK_REQUIREMENTS: [SdkRequirement; 5] = [
+    SdkRequirement { devices: &[], any_of: &["Makefile.defines"], what: "the build definitions" },
+    SdkRequirement { devices: &[], any_of: &["Makefile.rules"], what: "the build rules" },
+    SdkRequirement { devices: &[], any_of: &["target/{target}/"], what: "the target directory of the device" },
+    SdkRequirement { devices: &["stax", "flex", "apex_p"], any_of: &["lib_nbgl/"], what: "the NBGL UI library" },
+    SdkRequirement { devices: &["nanox", "nanosplus"], any_of: &["lib_nbgl/", "lib_bagl/"], what: "a UI library" },
+];
+
+// Requirements of the device missing from the SDK, as `<paths> (<what>)`.
+// A shallow or partial checkout fails deep in the trace otherwise.
+pub fn missing_sdk_files(device: &DeviceInfo, sdk: &Path) -> Vec<String> {
+    let applies = |r: &&SdkRequirement| r.devices.is_empty() || r.devices.contains(&device.name);
+    let path = |p: &str| p.replace("{target}", device.target);
+    let present = |p: &str| match p.strip_suffix('/') {
+        Some(dir) => sdk.join(dir).is_dir(),
+        None => sdk.join(p).is_file(),
+    };
+    SDK_REQUIREMENTS
+        .iter()
+        .filter(applies)
+        .filter(|r| !r.any_of.iter().any(|p| present(&path(p))))
+        .map(|r| format!("{} ({})", r.any_of.iter().map(|p| path(p)).collect::<Vec<_>>().join(" or "), r.what))
+        .collect()
+}
@@ -0,0 +1,184 @@
+use std::{
+    collections::BTreeMap,
+    env,
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use cbpx::{
+    device::{child_env, parse_device},
+    extract::{extract, ExtractError, ExtractOptions},
+    params::BuildParameters,
+    parse_issue::ParseIssue,
+};
+
+// Version of the protocol, announced by both sides in the handshake
+const PROTOCOL_VERSION: u32 = 1;
+
+#[derive(clap::Args, Debug)]
+pub struct ServeArgs {
+    /// Serve newline-delimited JSON requests on the standard input, writing
+    /// one JSON response per line on the standard output
+    #[arg(long, required = true)]
+    stdio: bool,
+}
+
+// Request line, `id` being echoed in its response so that the responses of
+// concurrent requests can be matched
+#[derive(Debug, Deserialize)]
+struct Envelope {
+    #[serde(default)]
+    id: Value,
+    #[serde(flatten)]
+    request: Request,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "cmd", rename_all = "lowercase", deny_unknown_fields)]
+enum Request {
+    // Handshake, which has to come first
+    Hello { protocol: u32 },
+    // Extraction of an app, with the SDK of the device environment variable
+    // unless `sdk` is given
+    Extract { device: String, app_path: PathBuf, sdk: Option<PathBuf> },
+}
+
+#[derive(Debug, Serialize)]
+struct RpcError {
+    // `request`, `protocol` or the kind of extraction error
+    kind: &'static str,
+    message: String,
+    // Words of the compile lines which failed the extraction, for `parse`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    issues: Vec<ParseIssue>,
+}
+
+#[derive(Debug, Serialize)]
+struct Response {
+    #[serde(skip_serializing_if = "Value::is_null")]
+    id: Value,
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    protocol: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    params: Option<BuildParameters>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+}
+
+impl Response {
+    fn success(id: Value) -> Self {
+        Response { id, ok: true, protocol: None, version: None, params: None, error: None }
+    }
+
+    fn failure(id: Value, kind: &'static str, message: String) -> Self {
+        Response { ok: false, error: Some(RpcError { kind, message, issues: Vec::new() }), ..Response::success(id) }
+    }
+}
+
+fn error_kind(error: &ExtractError) -> &'static str {
+    match error {
+        ExtractError::Make(_) => "make",
+        ExtractError::NoCompileLine { .. } => "no_compile_line",
+        ExtractError::Trace(_) => "trace",
+        ExtractError::MakeVariables(_) => "make_variables",
+        ExtractError::BuiltinDefines(_) => "builtin_defines",
+        ExtractError::Scope(_) => "scope",
+        ExtractError::Parse(_) => "parse",
+    }
+}
+
+// Responses are written whole, concurrent requests completing in any order
+fn respond(stdout: &Mutex<io::Stdout>, response: &Response) {
+    let line = serde_json::to_string(response).expect("Failed to serialize response");
+    let mut stdout = stdout.lock().expect("stdout lock");
+    // The client going away ends the session at the next read
+    let _ = writeln!(stdout, "{}", line).and_then(|_| stdout.flush());
+}
+
+fn run_extract(device: &str, app_path: &Path, sdk: Option<&Path>) -> Result<BuildParameters, RpcError> {
+    let request_error = |message: String| RpcError { kind: "request", message, issues: Vec::new() };
+    let device = parse_device(device).map_err(request_error)?;
+    let sdk = match sdk {
+        Some(sdk) => sdk.display().to_string(),
+        None => child_env(device, false, |name| env::var(name).ok()).map_err(request_error)?.bolos_sdk,
+    };
+    // make runs in the app directory, relative paths would resolve there
+    let (sdk, app_path) = match (std::path::absolute(sdk), std::path::absolute(app_path)) {
+        (Ok(sdk), Ok(app_path)) => (sdk, app_path),
+        _ => return Err(request_error("Failed to resolve the SDK and app paths".to_string())),
+    };
+    extract(device, &app_path, &ExtractOptions::new(sdk)).map_err(|e| {
+        let issues = match &e {
+            ExtractError::Parse(issues) => issues.clone(),
+            _ => Vec::new(),
+        };
+        RpcError { kind: error_kind(&e), message: e.to_string(), issues }
+    })
+}
+
+// Run the `serve` subcommand until the end of the standard input, returning
+// 0 once the requests in flight have been answered. Extractions run
+// concurrently, one at a time per device.
+pub fn run(_args: &ServeArgs) -> i32 {
+    let stdout = Mutex::new(io::stdout());
+    let device_locks: Mutex<BTreeMap<&'static str, Arc<Mutex<()>>>> = Mutex::default();
+    let mut greeted = false;
+    thread::scope(|scope| {
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let Envelope { id, request } = match serde_json::from_str::<Envelope>(&line) {
+                Ok(envelope) => envelope,
+                Err(e) => {
+                    let id = serde_json::from_str::<Value>(&line).ok().and_then(|v| v.get("id").cloned()).unwrap_or_default();
+                    respond(&stdout, &Response::failure(id, "request", format!("Invalid request: {}", e)));
+                    continue;
+                }
+            };
+            match request {
+                Request::Hello { protocol } if protocol == PROTOCOL_VERSION => {
+                    greeted = true;
+                    let response =
+                        Response { protocol: Some(PROTOCOL_VERSION), version: Some(env!("CARGO_PKG_VERSION")), ..Response::success(id) };
+                    respond(&stdout, &response);
+                }
+                Request::Hello { protocol } => {
+                    let message = format!("Protocol {} is not supported, this server speaks {}", protocol, PROTOCOL_VERSION);
+                    respond(&stdout, &Response::failure(id, "protocol", message));
+                }
+                Request::Extract { .. } if !greeted => {
+                    let message = format!("Handshake first with {{\"cmd\":\"hello\",\"protocol\":{}}}", PROTOCOL_VERSION);
+                    respond(&stdout, &Response::failure(id, "protocol", message));
+                }
+                Request::Extract { device, app_path, sdk } => {
+                    // Unknown devices are answered by the extraction itself
+                    let lock = parse_device(&device)
+                        .ok()
+                        .map(|d| device_locks.lock().expect("device locks").entry(d.name).or_default().clone());
+                    let stdout = &stdout;
+                    scope.spawn(move || {
+                        let _guard = lock.as_ref().map(|l| l.lock().expect("device lock"));
+                        let response = match run_extract(&device, &app_path, sdk.as_deref()) {
+                            Ok(params) => Response { params: Some(params), ..Response::success(id) },
+                            Err(error) => Response { ok: false, error: Some(error), ..Response::success(id) },
+                        };
+                        respond(stdout, &response);
+                    });
+                }
+            }
+        }
+    });
+    0
+}
@@ -0,0 +1,42 @@
+use serde::Deserialize;
+
+// Sanity thresholds of the entry counts, catching extractions silently
+// truncated by a partially failed make
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShrinkThresholds {
+    // Smallest accepted count, as a fraction of the reference one
+    pub min_ratio: f64,
+    // Smallest accepted counts, whether there is a reference or not
+    pub min_defines: Option<usize>,
+    pub min_cflags: Option<usize>,
+}
+
+impl Default for ShrinkThresholds {
+    fn default() -> Self {
+        ShrinkThresholds { min_ratio: 0.5, min_defines: None, min_cflags: None }
+    }
+}
+
+impl ShrinkThresholds {
+    // Why the `count` entries of an artifact ("defines" or "cflags") look
+    // truncated, None when they don't
+    pub fn check(&self, artifact: &str, count: usize, reference_count: Option<usize>) -> Option<String> {
+        let minimum = match artifact {
+            "defines" => self.min_defines,
+            "cflags" => self.min_cflags,
+            _ => None,
+        };
+        if let Some(minimum) = minimum.filter(|m| count < *m) {
+            return Some(format!("{} {} instead of at least {}", count, artifact, minimum));
+        }
+        let reference_count = reference_count.filter(|r| (count as f64) < *r as f64 * self.min_ratio)?;
+        Some(format!(
+            "{} {} against {} in the reference, less than {}%",
+            count,
+            artifact,
+            reference_count,
+            (self.min_ratio * 100.0).round()
+        ))
+    }
+}
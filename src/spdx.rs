@@ -0,0 +1,194 @@
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+// Minimal SPDX 2.3 document recording what produced the parameters of a run:
+// the SDK and the toolchain as packages, the generated files as files
+// generated from both.
+const NOASSERTION: &str = "NOASSERTION";
+
+// Inputs of the document, gathered from the run
+pub struct Provenance<'a> {
+    // Name of the document, e.g. `<app>-<device>`
+    pub name: &'a str,
+    pub generator: &'a str,
+    // Seconds since the epoch
+    pub created: u64,
+    pub sdk_remote: Option<&'a str>,
+    pub sdk_hash: Option<&'a str>,
+    pub api_level: Option<&'a str>,
+    // `clang` or `gcc`, and the first line of `<compiler> --version`
+    pub toolchain: Option<&'a str>,
+    pub toolchain_version: Option<&'a str>,
+    // Generated files by name, with their contents
+    pub files: &'a [(String, Vec<u8>)],
+    // Overrides of the app applied to the extraction
+    pub overrides: &'a [String],
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpdxDocument {
+    pub spdx_version: String,
+    pub data_license: String,
+    #[serde(rename = "SPDXID")]
+    pub spdx_id: String,
+    pub name: String,
+    pub document_namespace: String,
+    pub creation_info: CreationInfo,
+    pub packages: Vec<Package>,
+    pub files: Vec<File>,
+    pub relationships: Vec<Relationship>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreationInfo {
+    pub created: String,
+    pub creators: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Package {
+    pub name: String,
+    #[serde(rename = "SPDXID")]
+    pub spdx_id: String,
+    pub version_info: String,
+    pub download_location: String,
+    pub files_analyzed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct File {
+    pub file_name: String,
+    #[serde(rename = "SPDXID")]
+    pub spdx_id: String,
+    pub checksums: Vec<Checksum>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Checksum {
+    pub algorithm: String,
+    pub checksum_value: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Relationship {
+    pub spdx_element_id: String,
+    pub relationship_type: String,
+    pub related_spdx_element: String,
+}
+
+// `YYYY-MM-DDThh:mm:ssZ` of a time since the epoch
+pub fn utc_timestamp(seconds: u64) -> String {
+    let (days, time) = (seconds / 86400, seconds % 86400);
+    // Civil date of a day count, from Howard Hinnant's days_from_civil inverse
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, time / 3600, time / 60 % 60, time % 60)
+}
+
+// SPDX download location of the SDK checkout, credentials being removed from
+// the remote URL
+pub fn download_location(remote: Option<&str>, hash: Option<&str>) -> String {
+    let Some(remote) = remote else {
+        return NOASSERTION.to_string();
+    };
+    let remote = match remote.split_once("://") {
+        Some((scheme, rest)) => match rest.split_once('/') {
+            Some((host, path)) => format!("{}://{}/{}", scheme, host.rsplit('@').next().unwrap_or(host), path),
+            None => remote.to_string(),
+        },
+        None => remote.to_string(),
+    };
+    match hash {
+        Some(hash) => format!("git+{}@{}", remote, hash),
+        None => format!("git+{}", remote),
+    }
+}
+
+// SPDX identifiers only allow letters, digits, `.` and `-`
+fn spdx_id(kind: &str, name: &str) -> String {
+    let name = name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '.' { c } else { '-' }).collect::<String>();
+    format!("SPDXRef-{}-{}", kind, name)
+}
+
+pub fn provenance_document(provenance: &Provenance) -> SpdxDocument {
+    let sha256 = |contents: &[u8]| format!("{:x}", Sha256::digest(contents));
+    let mut packages = vec![Package {
+        name: "ledger-secure-sdk".to_string(),
+        spdx_id: "SPDXRef-Package-sdk".to_string(),
+        version_info: provenance.sdk_hash.unwrap_or(NOASSERTION).to_string(),
+        download_location: download_location(provenance.sdk_remote, provenance.sdk_hash),
+        files_analyzed: false,
+        comment: provenance.api_level.map(|level| format!("API_LEVEL {}", level)),
+    }];
+    if let Some(toolchain) = provenance.toolchain {
+        packages.push(Package {
+            name: toolchain.to_string(),
+            spdx_id: "SPDXRef-Package-toolchain".to_string(),
+            version_info: provenance.toolchain_version.unwrap_or(NOASSERTION).to_string(),
+            download_location: NOASSERTION.to_string(),
+            files_analyzed: false,
+            comment: None,
+        });
+    }
+
+    let files = provenance
+        .files
+        .iter()
+        .map(|(name, contents)| File {
+            file_name: format!("./{}", name),
+            spdx_id: spdx_id("File", name),
+            checksums: vec![Checksum { algorithm: "SHA256".to_string(), checksum_value: sha256(contents) }],
+        })
+        .collect::<Vec<_>>();
+
+    let relationship = |element: &str, kind: &str, related: &str| Relationship {
+        spdx_element_id: element.to_string(),
+        relationship_type: kind.to_string(),
+        related_spdx_element: related.to_string(),
+    };
+    let mut relationships = files.iter().map(|f| relationship("SPDXRef-DOCUMENT", "DESCRIBES", &f.spdx_id)).collect::<Vec<_>>();
+    for file in &files {
+        for package in &packages {
+            relationships.push(relationship(&file.spdx_id, "GENERATED_FROM", &package.spdx_id));
+        }
+    }
+
+    // The namespace has to be unique to the document, the checksums of the
+    // files make it so while keeping the document reproducible
+    let mut digest = Sha256::new();
+    for file in &files {
+        digest.update(file.checksums[0].checksum_value.as_bytes());
+    }
+    SpdxDocument {
+        spdx_version: "SPDX-2.3".to_string(),
+        data_license: "CC0-1.0".to_string(),
+        spdx_id: "SPDXRef-DOCUMENT".to_string(),
+        name: provenance.name.to_string(),
+        document_namespace: format!("https://spdx.org/spdxdocs/{}-{:x}", provenance.name, digest.finalize()),
+        creation_info: CreationInfo {
+            created: utc_timestamp(provenance.created),
+            creators: vec![format!("Tool: {}", provenance.generator)],
+            comment: (!provenance.overrides.is_empty())
+                .then(|| format!("App overrides applied: {}", provenance.overrides.join("; "))),
+        },
+        packages,
+        files,
+        relationships,
+    }
+}
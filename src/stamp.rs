@@ -0,0 +1,66 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{app_makefile::makefile_includes, git, overrides::OVERRIDES_FILE, plan::Plan};
+
+// Stamps of successful matching runs, keyed by app, device and matrix cell.
+// A run whose inputs digest equals its stamp can skip make entirely.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct StampFile {
+    pub entries: BTreeMap<String, String>,
+}
+
+impl StampFile {
+    // A missing or unreadable stamp file is an empty one
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path).ok().and_then(|s| serde_json::from_str(&s).ok()).unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).expect("Failed to serialize stamps");
+        fs::write(path, json + "\n").map_err(|e| format!("Failed to write stamp file {}: {}", path.display(), e))
+    }
+}
+
+pub fn stamp_key(plan: &Plan) -> String {
+    format!("{}|{}|{}", plan.app_path.display(), plan.device.name, plan.cell.label())
+}
+
+// Digest of everything the result of a run depends on: the SDK commit and
+// working tree state, the app Makefile and the fragments it includes, the
+// device, the options, the recorded trace read instead of running make and
+// the reference files. None when the SDK is not a git checkout, as its state
+// can't be captured cheaply.
+pub fn inputs_digest(plan: &Plan, options: &str, recorded_trace: Option<&Path>) -> Option<String> {
+    let sdk = Path::new(&plan.child_env.as_ref()?.bolos_sdk);
+    let mut hasher = Sha256::new();
+    hasher.update(git::head_hash(sdk)?);
+    hasher.update(git::status_porcelain(sdk)?);
+    hasher.update(fs::read(&plan.makefile).ok()?);
+    for fragment in makefile_includes(&plan.makefile, sdk, plan.device.target).iter().skip(1) {
+        hasher.update(fragment.display().to_string());
+        hasher.update(fs::read(fragment).unwrap_or_default());
+    }
+    // Absent overrides hash as empty ones, which change nothing either
+    hasher.update(fs::read(plan.app_path.join(OVERRIDES_FILE)).unwrap_or_default());
+    hasher.update(plan.device.name);
+    hasher.update(plan.cell.label());
+    hasher.update(options);
+    if let Some(trace) = recorded_trace {
+        hasher.update(fs::read(trace).ok()?);
+    }
+    if let Ok(entries) = fs::read_dir(&plan.reference_dir) {
+        let mut references = entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.file_name().is_some_and(|n| n.to_string_lossy().starts_with(&plan.provisional_name)))
+            .collect::<Vec<_>>();
+        references.sort();
+        for reference in references {
+            hasher.update(reference.display().to_string());
+            hasher.update(fs::read(&reference).unwrap_or_default());
+        }
+    }
+    Some(format!("{:x}", hasher.finalize()))
+}
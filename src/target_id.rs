@@ -0,0 +1,46 @@
+use serde::Deserialize;
+
+use crate::device::DeviceInfo;
+
+// Expected TARGET_ID and TARGET_NAME of a device, overriding the ones of the
+// device table, e.g. during the bring-up of new hardware
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TargetExpectation {
+    pub target_id: Option<String>,
+    pub target_name: Option<String>,
+}
+
+// Value of a define as the SDK writes it, without the quoting of the trace
+fn unquoted(value: &str) -> String {
+    value.chars().filter(|c| *c != '"' && *c != '\\').collect()
+}
+
+// Compare the TARGET_ID and TARGET_NAME defines with the expected ones,
+// returning a warning for every deviation. Hexadecimal identifiers are
+// compared regardless of their case, and unknown expectations or values are
+// not checked.
+pub fn check_target(
+    device: &DeviceInfo,
+    expectation: Option<&TargetExpectation>,
+    target_id: Option<&str>,
+    target_name: Option<&str>,
+) -> Vec<String> {
+    let expected_id = expectation.and_then(|e| e.target_id.as_deref()).unwrap_or(device.target_id);
+    let expected_name = expectation.and_then(|e| e.target_name.as_deref()).unwrap_or(device.target_name);
+    let mut warnings = Vec::new();
+    let checks = [("TARGET_ID", target_id, expected_id, true), ("TARGET_NAME", target_name, expected_name, false)];
+    for (define, value, expected, any_case) in checks {
+        let Some(value) = value.map(unquoted) else {
+            continue;
+        };
+        let matches = if any_case { value.eq_ignore_ascii_case(expected) } else { value == expected };
+        if !expected.is_empty() && !matches {
+            warnings.push(format!(
+                "{} of {} is {} instead of {}, is the right SDK branch checked out?",
+                define, device.name, value, expected
+            ));
+        }
+    }
+    warnings
+}
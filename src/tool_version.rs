@@ -0,0 +1,37 @@
+use std::{fs, path::Path};
+
+use crate::files::write_if_changed;
+
+// Version of cbpx which wrote the references of a directory, alone in a
+// sidecar file, so that a mismatch with references written before parser
+// changes can be told apart from an SDK change
+pub const TOOL_VERSION_FILE: &str = ".toolversion";
+
+pub fn write_tool_version(reference_dir: &Path) -> Result<(), String> {
+    write_if_changed(&reference_dir.join(TOOL_VERSION_FILE), format!("{}\n", env!("CARGO_PKG_VERSION")).as_bytes())?;
+    Ok(())
+}
+
+// Recorded version, None when the references predate the sidecar file
+pub fn read_tool_version(reference_dir: &Path) -> Option<String> {
+    let contents = fs::read_to_string(reference_dir.join(TOOL_VERSION_FILE)).ok()?;
+    Some(contents.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+// `major.minor.patch`, pre-release and build suffixes ignored
+fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let core = version.split(['-', '+']).next()?;
+    let mut parts = core.split('.').map(|p| p.parse::<u64>().ok());
+    let version = (parts.next()??, parts.next()??, parts.next()??);
+    parts.next().is_none().then_some(version)
+}
+
+// Whether references recorded with `recorded` predate the `current` version.
+// A version which doesn't parse is taken as older.
+pub fn predates(recorded: &str, current: &str) -> bool {
+    match (parse_version(recorded), parse_version(current)) {
+        (Some(recorded), Some(current)) => recorded < current,
+        (None, _) => recorded != current,
+        (Some(_), None) => false,
+    }
+}
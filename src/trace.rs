@@ -0,0 +1,360 @@
+use std::collections::HashMap;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{files::Define, identifier::unescape_define_name};
+
+// Source file extensions recognized on compile lines
+const SOURCE_EXTENSIONS: [&str; 4] = [".c", ".s", ".S", ".cpp"];
+
+// Flags whose argument is given as the next word
+const FLAGS_WITH_ARGUMENT: [&str; 5] = ["-o", "-MT", "-MF", "-MQ", "-I"];
+
+// Compiler family of a compile line. Older SDKs build with
+// arm-none-eabi-gcc, current ones with clang.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Toolchain {
+    Clang,
+    Gcc,
+}
+
+impl Toolchain {
+    pub fn name(self) -> &'static str {
+        match self {
+            Toolchain::Clang => "clang",
+            Toolchain::Gcc => "gcc",
+        }
+    }
+}
+
+// Toolchain of a command word from its file name, e.g. `clang`, `clang-15`,
+// `/opt/gcc/bin/arm-none-eabi-gcc`
+fn toolchain_of(word: &[u8]) -> Option<Toolchain> {
+    let name = word.rsplit(|b| *b == b'/').next().unwrap_or(word);
+    let contains = |needle: &[u8]| name.windows(needle.len()).any(|w| w == needle);
+    if contains(b"clang") {
+        Some(Toolchain::Clang)
+    } else if contains(b"gcc") {
+        Some(Toolchain::Gcc)
+    } else {
+        None
+    }
+}
+
+// Default pattern of the compiler command of compile lines: any clang or gcc,
+// possibly with a path, a target prefix or a version suffix
+pub const DEFAULT_COMPILER_PATTERN: &str = r"(^|/)[^/]*(clang|gcc)[^/]*$";
+
+pub fn default_compiler_pattern() -> Regex {
+    Regex::new(DEFAULT_COMPILER_PATTERN).expect("valid pattern")
+}
+
+// Commands running the command given as their arguments
+const WRAPPERS: [&str; 4] = ["ccache", "sccache", "distcc", "env"];
+
+// Command run by a command line: its first word once the wrappers and the
+// variable assignments (`env VAR=value ccache clang ...`) are skipped
+pub fn command_word(line: &str) -> Option<&str> {
+    shell_words(line).into_iter().find(|word| {
+        let name = word.rsplit('/').next().unwrap_or(word);
+        let assignment = word.split_once('=').is_some_and(|(variable, _)| {
+            !variable.is_empty() && variable.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        });
+        !WRAPPERS.contains(&name) && !assignment
+    })
+}
+
+// A compile line runs a command matching `compiler_pattern` with `-c`
+fn is_compile_line(line: &[u8], compiler_pattern: &Regex) -> bool {
+    let line = String::from_utf8_lossy(line);
+    command_word(&line).is_some_and(|command| compiler_pattern.is_match(command))
+        && shell_words(&line).contains(&"-c")
+}
+
+// A link line invokes clang, gcc or ld to produce an output without `-c`
+pub fn is_link_line(line: &str) -> bool {
+    let words = line.split_whitespace().collect::<Vec<_>>();
+    let invokes_linker = words.iter().any(|w| {
+        let name = w.rsplit('/').next().unwrap_or(w);
+        toolchain_of(w.as_bytes()).is_some() || name == "ld" || name.ends_with("-ld")
+    });
+    invokes_linker && words.contains(&"-o") && !words.contains(&"-c")
+}
+
+// Toolchain of the compiler invoked by a compile line
+pub fn line_toolchain(line: &str) -> Option<Toolchain> {
+    line.split_whitespace().find_map(|w| toolchain_of(w.as_bytes()))
+}
+
+// Lines of the trace with their 0-based index, a line ending with a
+// backslash being joined with the next one as the shell would do. The CR of
+// CRLF line endings is dropped.
+fn joined_lines(trace: &[u8]) -> Vec<(usize, Vec<u8>)> {
+    let mut lines: Vec<(usize, Vec<u8>)> = Vec::new();
+    let mut continued = false;
+    for (index, line) in trace.split(|b| *b == b'\n').enumerate() {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        match lines.last_mut() {
+            Some((_, last)) if continued => last.extend_from_slice(line),
+            _ => lines.push((index, line.to_vec())),
+        }
+        let last = &mut lines.last_mut().expect("pushed").1;
+        continued = last.ends_with(b"\\");
+        if continued {
+            last.pop();
+        }
+    }
+    lines
+}
+
+// Return the compile lines of the make trace, continuation lines joined,
+// operating on raw bytes so that invalid UTF-8 in a matched line is reported
+// instead of silently replaced by U+FFFD (which would corrupt define values).
+// With `lossy`, the old replacing behavior is used. Lines without a source or
+// an output, or with several `-c`, are fragments of commands split by the
+// output of parallel jobs (traces recorded with -j) and are skipped.
+pub fn find_compile_lines(trace: &[u8], lossy: bool) -> Result<Vec<String>, String> {
+    find_compile_lines_matching(trace, lossy, &default_compiler_pattern())
+}
+
+// `find_compile_lines` with another pattern of the compiler command
+pub fn find_compile_lines_matching(trace: &[u8], lossy: bool, compiler_pattern: &Regex) -> Result<Vec<String>, String> {
+    Ok(find_numbered_compile_lines(trace, lossy, compiler_pattern)?.into_iter().map(|(_, line)| line).collect())
+}
+
+// `find_compile_lines_matching` with the 0-based index of the trace line each
+// compile line starts on
+pub fn find_numbered_compile_lines(trace: &[u8], lossy: bool, compiler_pattern: &Regex) -> Result<Vec<(usize, String)>, String> {
+    let mut lines = Vec::new();
+    for (index, line) in joined_lines(trace) {
+        if !is_compile_line(&line, compiler_pattern) {
+            continue;
+        }
+        let line = match std::str::from_utf8(&line) {
+            Ok(line) => line.to_string(),
+            Err(_) if lossy => String::from_utf8_lossy(&line).into_owned(),
+            Err(e) => {
+                return Err(format!(
+                    "Invalid UTF-8 in compile line {} of the make trace at byte offset {} (use --lossy to replace invalid bytes)",
+                    index + 1,
+                    e.valid_up_to()
+                ))
+            }
+        };
+        let compiles = line.split_whitespace().filter(|w| *w == "-c").count();
+        if compiles > 1 || parse_source(&line).is_none() || parse_object(&line).is_none() {
+            continue;
+        }
+        lines.push((index, line));
+    }
+    Ok(lines)
+}
+
+// Number of trace lines quoted in the error reported when no compile line is found
+const EXCERPT_LINES: usize = 10;
+
+// Number of command lines quoted in the same error
+const COMMAND_LINES: usize = 5;
+
+// Describe a trace in which no compile line was found: how many lines were
+// processed, the lines most likely related to the problem, and hints about
+// the usual causes
+pub fn no_compile_line_error(trace: &[u8]) -> String {
+    let text = String::from_utf8_lossy(trace);
+    let excerpt = text
+        .lines()
+        .filter(|l| ["clang", "gcc", "error"].iter().any(|k| l.to_lowercase().contains(k)))
+        .take(EXCERPT_LINES)
+        .collect::<Vec<_>>();
+
+    let mut message = format!("No compile line found in the make trace ({} lines processed)", text.lines().count());
+    if excerpt.is_empty() {
+        message.push_str("\nNo line of the trace mentions clang, gcc or an error");
+    } else {
+        message.push_str("\nTrace lines mentioning clang, gcc or an error:");
+        for line in excerpt {
+            message.push_str("\n  ");
+            message.push_str(line);
+        }
+    }
+    // The lines of the trace which are not make's own (`Makefile:12: ...`,
+    // `make[1]: ...`) are the commands
+    let make_message = Regex::new(r"^(\S+:\d+|make(\[\d+\])?): ").expect("valid pattern");
+    let commands = text
+        .lines()
+        .filter(|l| !l.trim().is_empty() && !make_message.is_match(l))
+        .take(COMMAND_LINES)
+        .collect::<Vec<_>>();
+    if !commands.is_empty() {
+        message.push_str("\nFirst command lines of the trace:");
+        for line in commands {
+            message.push_str("\n  ");
+            message.push_str(line);
+        }
+    }
+    message.push_str(
+        "\nHints: stale objects in the build directory make the dry run skip compilation (clean the app or build with -B), \
+         GNU make is required (BSD make does not support --trace), \
+         and a compiler run through a wrapper variable can be matched with --compiler-pattern <regex>",
+    );
+    message
+}
+
+// Words of a command line as the shell splits them, the quotes and
+// backslashes being kept verbatim: `-DNAME="a b"` and `-DNAME=a\ b` are
+// single words. An unterminated quote runs to the end of the line. A CR
+// always ends a word, even quoted or escaped, as it can't be kept in the
+// line-based outputs.
+pub fn shell_words(line: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut start = None;
+    let mut quote = None;
+    let mut escaped = false;
+    for (index, c) in line.char_indices() {
+        if c == '\r' {
+            words.extend(start.take().map(|start| &line[start..index]));
+            escaped = false;
+            continue;
+        }
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        if quote.is_none() && c.is_whitespace() {
+            words.extend(start.take().map(|start| &line[start..index]));
+            continue;
+        }
+        start.get_or_insert(index);
+        match (quote, c) {
+            (Some('\''), '\'') => quote = None,
+            (Some('\''), _) => {}
+            (_, '\\') => escaped = true,
+            (Some(_), '"') => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            _ => {}
+        }
+    }
+    words.extend(start.map(|start| &line[start..]));
+    words
+}
+
+// Include paths of a compile line, in command-line order, accepting both the
+// `-Ipath` and `-I path` spellings
+pub fn parse_includes(line: &str) -> Vec<String> {
+    let mut includes = Vec::new();
+    let mut words = shell_words(line).into_iter();
+    while let Some(word) = words.next() {
+        if word == "-I" {
+            includes.extend(words.next().map(str::to_string));
+        } else if let Some(path) = word.strip_prefix("-I") {
+            includes.push(path.to_string());
+        }
+    }
+    includes
+}
+
+// Source file compiled by a compile line, i.e. the operand with a source
+// extension which is not the argument of a flag
+pub fn parse_source(line: &str) -> Option<String> {
+    let mut previous = "";
+    let mut source = None;
+    for word in shell_words(line) {
+        if !word.starts_with('-')
+            && !FLAGS_WITH_ARGUMENT.contains(&previous)
+            && SOURCE_EXTENSIONS.iter().any(|e| word.ends_with(e))
+        {
+            source = Some(word.to_string());
+        }
+        previous = word;
+    }
+    source
+}
+
+// Object file produced by a compile line, i.e. the argument of `-o`
+pub fn parse_object(line: &str) -> Option<String> {
+    flag_argument(line, "-o")
+}
+
+// Argument of the first occurrence of a flag given as a separate word, such
+// as `-MF dep/main.d`
+pub fn flag_argument(line: &str, flag: &str) -> Option<String> {
+    let mut words = shell_words(line).into_iter();
+    while let Some(word) = words.next() {
+        if word == flag {
+            return words.next().map(str::to_string);
+        }
+    }
+    None
+}
+
+// Split a compile line into its defines and cflags, include paths are ignored.
+// Defines and undefs are applied in command-line order, so that the defines
+// are the ones in effect at the end of the line: `-DX -UX` leaves X
+// undefined, `-UX -DX` defines it.
+pub fn parse_compile_line(line: &str) -> (Vec<Define>, Vec<String>) {
+    // Undefined defines are left as holes, so that a -U costs the number of
+    // defines of its name rather than of the line
+    let mut defines: Vec<Option<Define>> = Vec::new();
+    let mut positions: HashMap<String, Vec<usize>> = HashMap::new();
+    let mut cflags = Vec::new();
+
+    for word in shell_words(line) {
+        if let Some(define) = word.strip_prefix("-D") {
+            // The value runs from the first `=`, and may contain others
+            let (name, value) = match define.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (define, None),
+            };
+            let name = unescape_define_name(name);
+            positions.entry(name.clone()).or_default().push(defines.len());
+            defines.push(Some(Define { name, value }));
+        } else if let Some(name) = word.strip_prefix("-U") {
+            for position in positions.remove(&unescape_define_name(name)).unwrap_or_default() {
+                defines[position] = None;
+            }
+        } else if word.starts_with("-I") {
+        } else if word.starts_with('-') {
+            cflags.push(word.to_string());
+        }
+    }
+
+    (defines.into_iter().flatten().collect(), cflags)
+}
+
+// Defines undefined with `-U` on a compile line: the `-D` and `-U` words of
+// the define in command-line order, and whether it is defined in the end
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DefineOverride {
+    pub name: String,
+    pub sequence: Vec<String>,
+    pub defined: bool,
+}
+
+// Overrides of the defines of a compile line having a `-U`, in order of first
+// appearance
+pub fn define_overrides(line: &str) -> Vec<DefineOverride> {
+    let words = shell_words(line);
+    let mut overrides: Vec<DefineOverride> = Vec::new();
+    let mut indices: HashMap<String, usize> = HashMap::new();
+    for name in words.iter().filter_map(|w| w.strip_prefix("-U")).map(unescape_define_name) {
+        if !indices.contains_key(&name) {
+            indices.insert(name.clone(), overrides.len());
+            overrides.push(DefineOverride { name, sequence: Vec::new(), defined: false });
+        }
+    }
+    for word in &words {
+        let name = match (word.strip_prefix("-U"), word.strip_prefix("-D")) {
+            (Some(name), _) => name,
+            (None, Some(define)) => define.split_once('=').map_or(define, |(name, _)| name),
+            (None, None) => continue,
+        };
+        if let Some(index) = indices.get(&unescape_define_name(name)) {
+            let define_override = &mut overrides[*index];
+            define_override.sequence.push(word.to_string());
+            define_override.defined = word.starts_with("-D");
+        }
+    }
+    overrides
+}
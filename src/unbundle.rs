@@ -0,0 +1,45 @@
+use std::{fs, path::PathBuf};
+
+use anstream::{eprintln, println};
+
+use cbpx::bundle::{entry_path, read_bundle, verify_bundle};
+
+#[derive(clap::Args, Debug)]
+pub struct UnbundleArgs {
+    /// Bundle written with --bundle
+    bundle: PathBuf,
+
+    /// Directory the files are extracted into, existing files being replaced
+    #[arg(short, long, default_value = ".")]
+    output_dir: PathBuf,
+}
+
+// Extract the bundle once its files are checked against the manifest
+fn unbundle(args: &UnbundleArgs) -> Result<usize, String> {
+    let entries = read_bundle(&args.bundle)?;
+    let problems = verify_bundle(&entries);
+    if !problems.is_empty() {
+        return Err(format!("{} does not match its manifest:\n  {}", args.bundle.display(), problems.join("\n  ")));
+    }
+    let paths = entries.iter().map(|(name, _)| entry_path(&args.output_dir, name)).collect::<Result<Vec<_>, _>>()?;
+    for (path, (_, contents)) in paths.iter().zip(&entries) {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        fs::write(path, contents).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    }
+    Ok(entries.len())
+}
+
+pub fn run(args: &UnbundleArgs) -> i32 {
+    match unbundle(args) {
+        Ok(count) => {
+            println!("{} file(s) extracted into {}", count, args.output_dir.display());
+            0
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    files::Define,
+    trace::{parse_compile_line, parse_includes, parse_object, parse_source},
+};
+
+// What a single compile line of the trace builds, and with which flags
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompileUnit {
+    pub source: Option<String>,
+    pub object: Option<String>,
+    pub defines: Vec<Define>,
+    pub cflags: Vec<String>,
+    pub includes: Vec<String>,
+}
+
+pub fn parse_compile_unit(line: &str) -> CompileUnit {
+    let (defines, cflags) = parse_compile_line(line);
+    CompileUnit { source: parse_source(line), object: parse_object(line), defines, cflags, includes: parse_includes(line) }
+}
+
+// How the parameters of the compile units are combined into a single set
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Strategy {
+    /// Parameters of the first compile line only
+    #[default]
+    First,
+    /// Parameters common to every compile line
+    Intersection,
+    /// Parameters of any compile line, the first value of a define winning
+    Union,
+}
+
+// Defines, cflags and include paths of the units combined with `strategy`,
+// in order of first appearance
+pub fn merge_units(units: &[CompileUnit], strategy: Strategy) -> (Vec<Define>, Vec<String>, Vec<String>) {
+    let Some(first) = units.first() else {
+        return Default::default();
+    };
+    match strategy {
+        Strategy::First => (first.defines.clone(), first.cflags.clone(), first.includes.clone()),
+        Strategy::Intersection => (
+            first.defines.iter().filter(|d| units.iter().all(|u| u.defines.contains(d))).cloned().collect(),
+            first.cflags.iter().filter(|f| units.iter().all(|u| u.cflags.contains(f))).cloned().collect(),
+            first.includes.iter().filter(|i| units.iter().all(|u| u.includes.contains(i))).cloned().collect(),
+        ),
+        Strategy::Union => {
+            let mut defines: Vec<Define> = Vec::new();
+            let mut cflags: Vec<String> = Vec::new();
+            let mut includes: Vec<String> = Vec::new();
+            for unit in units {
+                for define in &unit.defines {
+                    if !defines.iter().any(|d| d.name == define.name) {
+                        defines.push(define.clone());
+                    }
+                }
+                cflags.extend(unit.cflags.iter().filter(|f| !cflags.contains(f)).cloned().collect::<Vec<_>>());
+                includes.extend(unit.includes.iter().filter(|i| !includes.contains(i)).cloned().collect::<Vec<_>>());
+            }
+            (defines, cflags, includes)
+        }
+    }
+}
+
+// Number of compile units a define or cflag appears on, and the units
+// themselves when they are listed
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Occurrence {
+    pub count: usize,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub units: Vec<String>,
+}
+
+// Occurrences of the defines, by name, and of the cflags over the compile
+// units, telling a flag applied everywhere from one of a few units
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Occurrences {
+    pub units: usize,
+    pub defines: BTreeMap<String, Occurrence>,
+    pub cflags: BTreeMap<String, Occurrence>,
+}
+
+impl Occurrences {
+    fn partial(&self, occurrence: Option<&Occurrence>) -> Option<(usize, usize)> {
+        occurrence.filter(|o| o.count < self.units).map(|o| (o.count, self.units))
+    }
+
+    // Units defining `name` out of all the units, when only some of them do
+    pub fn partial_define(&self, name: &str) -> Option<(usize, usize)> {
+        self.partial(self.defines.get(name))
+    }
+
+    pub fn partial_cflag(&self, flag: &str) -> Option<(usize, usize)> {
+        self.partial(self.cflags.get(flag))
+    }
+}
+
+// Occurrences over the units, each unit counting once per define name or
+// cflag. With `names`, the name of each unit, the units are listed as well.
+pub fn occurrences(units: &[CompileUnit], names: Option<&[String]>) -> Occurrences {
+    let mut occurrences = Occurrences { units: units.len(), ..Default::default() };
+    for (index, unit) in units.iter().enumerate() {
+        let count = |map: &mut BTreeMap<String, Occurrence>, key: &str| {
+            let occurrence = map.entry(key.to_string()).or_default();
+            occurrence.count += 1;
+            occurrence.units.extend(names.and_then(|n| n.get(index)).cloned());
+        };
+        let mut define_names = unit.defines.iter().map(|d| d.name.as_str()).collect::<Vec<_>>();
+        define_names.sort_unstable();
+        define_names.dedup();
+        for name in define_names {
+            count(&mut occurrences.defines, name);
+        }
+        let mut cflags = unit.cflags.iter().map(String::as_str).collect::<Vec<_>>();
+        cflags.sort_unstable();
+        cflags.dedup();
+        for flag in cflags {
+            count(&mut occurrences.cflags, flag);
+        }
+    }
+    occurrences
+}
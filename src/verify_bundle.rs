@@ -0,0 +1,30 @@
+use std::path::PathBuf;
+
+use anstream::{eprintln, println};
+
+use cbpx::bundle::{read_bundle, verify_bundle};
+
+#[derive(clap::Args, Debug)]
+pub struct VerifyBundleArgs {
+    /// Bundle written with --bundle
+    bundle: PathBuf,
+}
+
+pub fn run(args: &VerifyBundleArgs) -> i32 {
+    let entries = match read_bundle(&args.bundle) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+    let problems = verify_bundle(&entries);
+    for problem in &problems {
+        eprintln!("{}", problem);
+    }
+    if !problems.is_empty() {
+        return 1;
+    }
+    println!("{}: {} file(s) matching the manifest", args.bundle.display(), entries.len() - 1);
+    0
+}
@@ -0,0 +1,41 @@
+use std::{fs, path::PathBuf};
+
+use cbpx::app_copy::{copy_app_skeleton, copy_missing, missing_files, replace_bytes};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("cbpx-app-copy-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn skeleton_keeps_the_makefiles_and_icons_only() {
+    let (app, copy) = (temp_dir("app"), temp_dir("copy"));
+    for (file, contents) in [("Makefile", "all:\n"), ("src/main.c", "int main;"), ("icons/app.gif", "GIF"), ("build/main.d", "dep")] {
+        fs::create_dir_all(app.join(file).parent().unwrap()).unwrap();
+        fs::write(app.join(file), contents).unwrap();
+    }
+    copy_app_skeleton(&app, &copy).unwrap();
+    assert_eq!(fs::read_to_string(copy.join("Makefile")).unwrap(), "all:\n");
+    assert_eq!(fs::read_to_string(copy.join("icons/app.gif")).unwrap(), "GIF");
+    assert_eq!(fs::read_to_string(copy.join("src/main.c")).unwrap(), "");
+    assert!(!copy.join("build").exists());
+
+    // Files reported missing are copied from the app, when they are in it
+    assert!(copy_missing(&app, &copy, &copy, "build/main.d").unwrap());
+    assert_eq!(fs::read_to_string(copy.join("build/main.d")).unwrap(), "dep");
+    assert!(!copy_missing(&app, &copy, &copy, "build/other.d").unwrap());
+    assert!(!copy_missing(&app, &copy, &copy, "../escape").unwrap());
+    fs::remove_dir_all(&app).unwrap();
+    fs::remove_dir_all(&copy).unwrap();
+}
+
+#[test]
+fn missing_files_are_read_from_make_errors() {
+    let stderr = "make: *** No rule to make target 'glyphs/icon.gif', needed by 'all'.  Stop.\n\
+                  Makefile:3: build/app.mk: No such file or directory\n\
+                  make: *** No rule to make target 'glyphs/icon.gif', needed by 'x'.  Stop.\n";
+    assert_eq!(missing_files(stderr), ["glyphs/icon.gif", "build/app.mk"]);
+    assert_eq!(replace_bytes(b"-DDIR=/tmp/copy/x \xff /tmp/copy", b"/tmp/copy", b"/app"), b"-DDIR=/app/x \xff /app");
+}
@@ -0,0 +1,38 @@
+use cbpx::{
+    app_flags::{decode_app_flags, find_app_flags},
+    files::Define,
+};
+
+fn define(name: &str, value: &str) -> Define {
+    Define { name: name.to_string(), value: Some(value.to_string()) }
+}
+
+#[test]
+fn flags_are_decoded_into_named_booleans() {
+    let flags = decode_app_flags(0x10240, "--appFlags");
+    assert_eq!(flags.value.as_deref(), Some("0x10240"));
+    assert!(flags.flags["global_pin"] && flags.flags["bolos_settings"]);
+    assert!(!flags.flags["derive_master"] && !flags.flags["library"]);
+    assert_eq!(flags.unknown_bits.as_deref(), Some("0x10000"));
+}
+
+#[test]
+fn trace_takes_precedence_over_the_defines() {
+    let defines = [define("APP_FLAGS_APP_LOAD_PARAMS", "0x800")];
+    let trace = "python3 -m ledgerblue.loadApp --targetId 0x33200004 --appFlags 0x210 --appName \"Boil\"\n";
+    let flags = find_app_flags(trace, &defines);
+    assert_eq!((flags.value.as_deref(), flags.source.as_deref()), (Some("0x210"), Some("--appFlags")));
+    assert!(flags.flags["derive_master"] && flags.flags["bolos_settings"]);
+    assert_eq!(find_app_flags("install_params.py --appFlags=576\n", &[]).value.as_deref(), Some("0x240"));
+
+    let flags = find_app_flags("clang -c src/main.c\n", &defines);
+    assert_eq!((flags.value.as_deref(), flags.source.as_deref()), (Some("0x800"), Some("APP_FLAGS_APP_LOAD_PARAMS")));
+    assert!(flags.flags["library"]);
+}
+
+#[test]
+fn flags_not_found_are_unknown() {
+    let flags = find_app_flags("clang -c src/main.c\n", &[define("APP_FLAGS", "$(FLAGS)")]);
+    assert!(!flags.is_known());
+    assert!(flags.flags.is_empty());
+}
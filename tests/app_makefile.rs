@@ -0,0 +1,93 @@
+use std::{fs, process::Command};
+
+use cbpx::app_makefile::includes_sdk_makefile;
+
+#[test]
+fn the_sdk_makefile_is_found_one_include_deep() {
+    let app = std::env::temp_dir().join(format!("cbpx-app-makefile-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&app);
+    fs::create_dir_all(&app).unwrap();
+    let makefile = app.join("Makefile");
+
+    fs::write(&makefile, "APPNAME = App\ninclude ${BOLOS_SDK}/Makefile.standard_app\n").unwrap();
+    assert_eq!(includes_sdk_makefile(&makefile), Ok(true));
+
+    // Not a Ledger app
+    fs::write(&makefile, "all:\n\tcc -o main main.c\n").unwrap();
+    assert_eq!(includes_sdk_makefile(&makefile), Ok(false));
+
+    // Through a file of the app
+    fs::write(&makefile, "-include $(CONFIG)\ninclude common.mk\n").unwrap();
+    fs::write(app.join("common.mk"), "include $(BOLOS_SDK)/Makefile.defines\n").unwrap();
+    assert_eq!(includes_sdk_makefile(&makefile), Ok(true));
+
+    // Only one level deep
+    fs::write(app.join("common.mk"), "include deeper.mk\n").unwrap();
+    fs::write(app.join("deeper.mk"), "include $(BOLOS_SDK)/Makefile.defines\n").unwrap();
+    assert_eq!(includes_sdk_makefile(&makefile), Ok(false));
+    fs::remove_dir_all(&app).unwrap();
+}
+
+// An app declaring stax and flex, built against an SDK with just the layout
+// checked before running make
+#[test]
+fn devices_are_read_from_supported_devices() {
+    let root = std::env::temp_dir().join(format!("cbpx-supported-devices-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    for dir in ["app", "sdk/target/stax", "sdk/target/flex", "sdk/target/nanox", "sdk/lib_nbgl"] {
+        fs::create_dir_all(root.join(dir)).unwrap();
+    }
+    for file in ["sdk/Makefile.defines", "sdk/Makefile.rules"] {
+        fs::write(root.join(file), "").unwrap();
+    }
+    fs::write(
+        root.join("app/Makefile"),
+        "include $(BOLOS_SDK)/Makefile.defines\n\
+         SUPPORTED_DEVICES = stax flex nanos\n\
+         all:\n\tclang -c -DHAVE_X -Wall -o build/main.o src/main.c\n",
+    )
+    .unwrap();
+
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_cbpx"))
+            .args(["--app-path", "app", "--devices", "from-makefile", "--reference-dir", "refs", "--update-references"])
+            .args(args)
+            .current_dir(&root)
+            .env("STAX_SDK", root.join("sdk"))
+            .env("NANOX_SDK", root.join("sdk"))
+            .env_remove("FLEX_SDK")
+            .env_remove("BOLOS_SDK")
+            .output()
+            .unwrap()
+    };
+    let output = run(&["--output-dir", "declared"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Warning: unknown device nanos in SUPPORTED_DEVICES, skipping"), "{}", stderr);
+    assert!(stderr.contains("Warning: flex is declared in SUPPORTED_DEVICES but FLEX_SDK is not set, skipping"), "{}", stderr);
+    let outputs = |dir: &str| {
+        let mut names = fs::read_dir(root.join(dir))
+            .map(|entries| entries.filter_map(|e| e.unwrap().file_name().into_string().ok()).collect::<Vec<_>>())
+            .unwrap_or_default();
+        names.retain(|name| name.ends_with(".defines"));
+        names.sort();
+        names
+    };
+    assert_eq!(outputs("declared"), ["c_sdk_build_stax.defines"]);
+
+    // Requested devices which are not declared are skipped unless forced
+    let output = run(&["--output-dir", "requested", "--device", "nanox", "--device", "stax"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Warning: nanox is not declared in SUPPORTED_DEVICES (use --force to extract it), skipping"), "{}", stderr);
+    assert_eq!(outputs("requested"), ["c_sdk_build_stax.defines"]);
+    let output = run(&["--output-dir", "forced", "--device", "nanox", "--force"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(outputs("forced"), ["c_sdk_build_nanox.defines"]);
+
+    fs::write(root.join("app/Makefile"), "include $(BOLOS_SDK)/Makefile.defines\nall:\n").unwrap();
+    let output = run(&["--output-dir", "undeclared"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("SUPPORTED_DEVICES is not defined by the app Makefile"));
+    fs::remove_dir_all(&root).unwrap();
+}
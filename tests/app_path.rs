@@ -0,0 +1,102 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+// Copy of the fixture app under a directory whose name has a space and a
+// non-ASCII character, next to an SDK with just the layout checked before
+// running make
+fn setup(name: &str) -> (PathBuf, PathBuf) {
+    let root = std::env::temp_dir().join(format!("cbpx-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    let app = root.join("My Apps").join("app-bôilerplate");
+    fs::create_dir_all(&app).unwrap();
+    for dir in ["sdk/target/stax", "sdk/lib_nbgl"] {
+        fs::create_dir_all(root.join(dir)).unwrap();
+    }
+    for file in ["sdk/Makefile.defines", "sdk/Makefile.rules"] {
+        fs::write(root.join(file), "").unwrap();
+    }
+    fs::copy(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/app/Makefile"), app.join("Makefile")).unwrap();
+    (root, app)
+}
+
+fn cbpx(root: &Path, args: &[&std::ffi::OsStr]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_cbpx"))
+        .args(args)
+        .current_dir(root)
+        .env("STAX_SDK", root.join("sdk"))
+        .env_remove("BOLOS_SDK")
+        .env_remove("TARGET")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn app_path_with_space_and_unicode() {
+    let (root, app) = setup("unicode");
+    let args = [app.as_os_str(), "--device".as_ref(), "stax".as_ref(), "--reference-dir".as_ref(), "refs".as_ref()];
+    let mut update = vec!["--app-path".as_ref()];
+    update.extend(args);
+    update.push("--update-references".as_ref());
+    let output = cbpx(&root, &update);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let defines = fs::read_to_string(root.join("c_sdk_build_stax.defines")).unwrap();
+    assert_eq!(defines, "#define HAVE_X\n#define APPNAME \"App\"\n#define API_LEVEL 22\n");
+    let sources = fs::read_to_string(root.join("c_sdk_build_stax.sources")).unwrap();
+    assert_eq!(sources, "src/main.c\n");
+
+    let mut compare = vec!["--app-path".as_ref()];
+    compare.extend(args);
+    let output = cbpx(&root, &compare);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn missing_app_path_is_reported() {
+    let (root, app) = setup("missing");
+    let missing = app.join("nowhere");
+    let output = cbpx(&root, &["--app-path".as_ref(), missing.as_os_str(), "--device".as_ref(), "stax".as_ref()]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(&format!("App path {} does not exist", missing.display())), "{}", stderr);
+    fs::remove_dir_all(&root).unwrap();
+}
+
+// Entries of a directory, a stray output directory being one of them
+fn entries(dir: &Path) -> Vec<String> {
+    let mut entries = fs::read_dir(dir).unwrap().map(|e| e.unwrap().file_name().to_string_lossy().into_owned()).collect::<Vec<_>>();
+    entries.sort();
+    entries
+}
+
+#[test]
+fn failed_runs_leave_no_file_in_the_app() {
+    let (root, app) = setup("stray");
+    // No compile line: the run fails after make, with a trace to keep
+    fs::write(app.join("Makefile"), "include $(BOLOS_SDK)/Makefile.defines\nall:\n\t@echo nothing to build\n").unwrap();
+
+    // Run from the app directory, the default output directory
+    let output = Command::new(env!("CARGO_BIN_EXE_cbpx"))
+        .args(["--app-path", ".", "--device", "stax"])
+        .current_dir(&app)
+        .env("STAX_SDK", root.join("sdk"))
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Refusing to write the outputs into the app directory"), "{}", stderr);
+    assert_eq!(entries(&app), ["Makefile"]);
+
+    // With an explicit output directory, the failed trace is written there only
+    let out = root.join("out");
+    let args = ["--app-path".as_ref(), app.as_os_str(), "--device".as_ref(), "stax".as_ref(), "--output-dir".as_ref(), out.as_os_str()];
+    let output = cbpx(&root, &args);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(out.join("failed_trace_stax.log").is_file());
+    assert_eq!(entries(&app), ["Makefile"]);
+    fs::remove_dir_all(&root).unwrap();
+}
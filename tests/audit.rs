@@ -0,0 +1,31 @@
+use cbpx::{
+    audit::{raw_defines, stale_entries, suspicious_defines},
+    filter::FilterSet,
+};
+
+const LINES: [&str; 2] = [
+    "clang -c -DAPPNAME=\\\"Boilerplate\\\" -DAPPVERSION=\\\"1.2.3\\\" -DMAJOR_VERSION=1 -DHAVE_NBGL -o a.o a.c",
+    "clang -c -DAPP_TITLE=\\\"Boilerplate\\\" -DKEYS_DIR=\\\"/home/ci/keys\\\" -DVERSION_TAG=\\\"v2.0.1-rc1\\\" -DAPI_LEVEL=22 -o b.o b.c",
+];
+
+#[test]
+fn filter_entries_matching_nothing_are_stale() {
+    let defines = raw_defines(&LINES.map(str::to_string));
+    assert_eq!(defines.len(), 8);
+    let filters = FilterSet::default_defines();
+    let stale = stale_entries(&filters, &defines).into_iter().map(|e| e.pattern.as_str()).collect::<Vec<_>>();
+    assert_eq!(stale, ["MINOR_VERSION", "PATCH_VERSION", "SDK_VERSION", "SDK_HASH"]);
+}
+
+#[test]
+fn unfiltered_app_specific_defines_are_reported() {
+    let defines = raw_defines(&LINES.map(str::to_string));
+    let suspicious = suspicious_defines(&defines, &FilterSet::default_defines())
+        .into_iter()
+        .map(|(d, reason)| (d.name.as_str(), reason))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        suspicious,
+        [("APP_TITLE", "same value as APPNAME"), ("KEYS_DIR", "absolute path"), ("VERSION_TAG", "version string")]
+    );
+}
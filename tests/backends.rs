@@ -0,0 +1,189 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Barrier, Mutex},
+};
+
+use cbpx::{
+    backend::{DockerMake, HostCmake, LocalMake, SavedTrace, TempAppCopy, Trace, TraceRequest, TraceSource},
+    device::find_device,
+};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("cbpx-backend-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn request<'a>(app: &'a Path, makefile: &'a Path, args: &'a [String]) -> TraceRequest<'a> {
+    TraceRequest { device: find_device("stax").unwrap(), app_path: app, makefile, bolos_sdk: Path::new("/sdk"), make_args: args }
+}
+
+#[test]
+fn local_make_traces_and_queries_the_makefile() {
+    let app = temp_dir("local");
+    let makefile = app.join("Makefile");
+    fs::write(&makefile, "FLAVOR ?= plain\nall:\n\tclang -c -DTARGET_$(TARGET) -DSDK=$(BOLOS_SDK) -o a.o a.c\n").unwrap();
+    let args = ["FLAVOR=debug".to_string()];
+    let request = request(&app, &makefile, &args);
+
+    let mut lines = 0;
+    let trace = LocalMake::default().trace(&request, &mut || lines += 1).unwrap();
+    let output = String::from_utf8(trace.output).unwrap();
+    assert!(output.contains("clang -c -DTARGET_stax -DSDK=/sdk -o a.o a.c"), "{}", output);
+    assert!(trace.failure.is_none());
+    assert!(lines > 0);
+
+    let vars = LocalMake::default().query_make_variables(&request, &["FLAVOR".to_string(), "NOPE".to_string()]).unwrap();
+    assert_eq!(vars["FLAVOR"].as_deref(), Some("debug"));
+    assert_eq!(vars["NOPE"], None);
+    fs::remove_dir_all(&app).unwrap();
+}
+
+#[test]
+fn hermetic_local_make_only_passes_the_given_environment() {
+    let app = temp_dir("hermetic");
+    let makefile = app.join("Makefile");
+    fs::write(&makefile, "all:\n\tclang -c -DPKG=$(CARGO_PKG_NAME) -DTARGET_$(TARGET) -o a.o a.c\n").unwrap();
+    let request = request(&app, &makefile, &[]);
+
+    let trace = |source: LocalMake| String::from_utf8(source.trace(&request, &mut || {}).unwrap().output).unwrap();
+    assert!(trace(LocalMake::default()).contains("-DPKG=cbpx -DTARGET_stax"));
+    let env = [("PATH".to_string(), std::env::var("PATH").unwrap())].into_iter().collect();
+    let source = LocalMake { hermetic_env: Some(env) };
+    assert!(source.hermetic());
+    assert!(trace(source).contains("-DPKG= -DTARGET_stax"));
+    fs::remove_dir_all(&app).unwrap();
+}
+
+#[test]
+fn temp_app_copy_runs_make_out_of_the_app_directory() {
+    let app = temp_dir("copy");
+    fs::create_dir_all(app.join("bin")).unwrap();
+    fs::write(app.join("bin/prerequisite"), "").unwrap();
+    let makefile = app.join("Makefile");
+    fs::write(&makefile, "all: bin/prerequisite\n\tclang -c -DDIR=$(CURDIR) -o a.o a.c\n").unwrap();
+    let request = request(&app, &makefile, &[]);
+
+    let source = TempAppCopy { inner: Arc::new(LocalMake::default()) };
+    let trace = source.trace(&request, &mut || {}).unwrap();
+    assert!(trace.failure.is_none(), "{:?}", trace.failure);
+    let output = String::from_utf8(trace.output).unwrap();
+    assert!(output.contains(&format!("clang -c -DDIR={} -o a.o a.c", app.canonicalize().unwrap().display())), "{}", output);
+    fs::remove_dir_all(&app).unwrap();
+}
+
+// Trace source recording the app directory it is run in, once every thread
+// of the test runs one
+#[derive(Debug)]
+struct AppDirRecorder {
+    barrier: Barrier,
+    app_dirs: Mutex<Vec<PathBuf>>,
+}
+
+impl TraceSource for AppDirRecorder {
+    fn trace(&self, request: &TraceRequest, _on_line: &mut dyn FnMut()) -> Result<Trace, String> {
+        self.barrier.wait();
+        assert!(request.makefile.is_file(), "{} was removed", request.makefile.display());
+        self.app_dirs.lock().unwrap().push(request.app_path.to_path_buf());
+        Ok(Trace { output: Vec::new(), failure: None, stderr: String::new() })
+    }
+}
+
+#[test]
+fn concurrent_temp_app_copies_are_distinct() {
+    let app = temp_dir("concurrent-copies");
+    let makefile = app.join("Makefile");
+    fs::write(&makefile, "all:\n").unwrap();
+    let recorder = Arc::new(AppDirRecorder { barrier: Barrier::new(2), app_dirs: Mutex::new(Vec::new()) });
+    let source = TempAppCopy { inner: recorder.clone() };
+    std::thread::scope(|scope| {
+        for _ in 0..2 {
+            scope.spawn(|| source.trace(&request(&app, &makefile, &[]), &mut || {}).unwrap());
+        }
+    });
+    let app_dirs = recorder.app_dirs.lock().unwrap();
+    assert_ne!(app_dirs[0], app_dirs[1]);
+    assert!(app_dirs.iter().all(|dir| !dir.exists()));
+    fs::remove_dir_all(&app).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn docker_make_mounts_the_sdk_and_the_app() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = temp_dir("docker");
+    let app = dir.join("app");
+    fs::create_dir_all(&app).unwrap();
+    // Fake docker client recording its arguments and printing a compile line
+    let docker = dir.join("docker");
+    let log = dir.join("args.txt");
+    fs::write(&docker, format!("#!/bin/sh\necho \"$@\" > {}\necho 'clang -c -DIN_DOCKER -o a.o a.c'\n", log.display())).unwrap();
+    fs::set_permissions(&docker, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let source = DockerMake { image: "ledger-app-builder".to_string(), docker };
+    let makefile = app.join("Makefile");
+    let trace = source.trace(&request(&app, &makefile, &[]), &mut || {}).unwrap();
+    assert_eq!(trace.output, b"clang -c -DIN_DOCKER -o a.o a.c\n");
+    let args = fs::read_to_string(&log).unwrap();
+    let app = app.display();
+    assert_eq!(
+        args.trim(),
+        format!(
+            "run --rm -v /sdk:/sdk -v {app}:{app} -w {app} -e TARGET=stax -e BOLOS_SDK=/sdk ledger-app-builder \
+             make -f Makefile --trace --dry-run -j1"
+        )
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn host_cmake_traces_the_compilation_database() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = temp_dir("cmake");
+    let tests = dir.join("unit-tests");
+    fs::create_dir_all(&tests).unwrap();
+    // Fake cmake writing a compilation database in the build directory
+    let cmake = dir.join("cmake");
+    let log = dir.join("args.txt");
+    let script = format!(
+        "#!/bin/sh\necho \"$@\" >> {}\nmkdir -p \"$4\"\n\
+         echo '[{{\"arguments\": [\"cc\", \"-DNAME=a b\", \"-c\", \"-o\", \"t.o\", \"t.c\"]}}]' > \"$4/compile_commands.json\"\n",
+        log.display()
+    );
+    fs::write(&cmake, script).unwrap();
+    fs::set_permissions(&cmake, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let source = HostCmake { cmake };
+    let lists = tests.join("CMakeLists.txt");
+    let args = ["FUZZ=1".to_string()];
+    let trace = source.trace(&request(&dir, &lists, &args), &mut || {}).unwrap();
+    assert_eq!(String::from_utf8(trace.output).unwrap(), "cc -DNAME='a b' -c -o t.o t.c\n");
+    let args = fs::read_to_string(&log).unwrap();
+    assert!(args.starts_with(&format!("-S {} -B ", tests.display())), "{}", args);
+    assert!(args.trim().ends_with("-DCMAKE_EXPORT_COMPILE_COMMANDS=ON -DTARGET=stax -DBOLOS_SDK=/sdk -DFUZZ=1"), "{}", args);
+
+    // Every call has its own build directory, removed afterwards
+    source.trace(&request(&dir, &lists, &[]), &mut || {}).unwrap();
+    let calls = fs::read_to_string(&log).unwrap();
+    let build_dirs = calls.lines().map(|line| line.split(' ').nth(3).unwrap()).collect::<Vec<_>>();
+    assert_ne!(build_dirs[0], build_dirs[1]);
+    assert!(!Path::new(&build_dirs[0]).exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn saved_trace_is_read_and_cannot_be_queried() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/gcc_trace.txt");
+    let source = SavedTrace { path: path.clone() };
+    let makefile = Path::new("/app/Makefile");
+    let request = request(Path::new("/app"), makefile, &[]);
+    assert_eq!(source.trace(&request, &mut || {}).unwrap().output, fs::read(&path).unwrap());
+    assert!(source.query_make_variables(&request, &["X".to_string()]).is_err());
+    let missing = SavedTrace { path: path.with_extension("missing") };
+    assert!(missing.trace(&request, &mut || {}).unwrap_err().starts_with("Failed to read the trace"));
+}
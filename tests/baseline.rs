@@ -0,0 +1,63 @@
+use std::{fs, process::Command};
+
+use cbpx::{baseline::toggled_features, files::Define};
+
+#[test]
+fn toggles_are_sorted_by_name() {
+    let defines = |names: &[&str]| names.iter().map(|n| Define { name: n.to_string(), value: None }).collect::<Vec<_>>();
+    let toggled = toggled_features(&defines(&["HAVE_SWAP", "HAVE_NBGL", "APPNAME"]), &defines(&["HAVE_NBGL", "APPNAME", "HAVE_BAGL"]));
+    let toggled = toggled.iter().map(|t| (t.name.as_str(), t.enabled)).collect::<Vec<_>>();
+    assert_eq!(toggled, [("HAVE_BAGL", false), ("HAVE_SWAP", true)]);
+}
+
+// The minimal app is built against a fake SDK whose standard app rules
+// compile an SDK source with the DEFINES of the app
+#[test]
+fn baseline_run_reports_the_features_of_the_app() {
+    let root = std::env::temp_dir().join(format!("cbpx-baseline-test-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    let (sdk, app) = (root.join("sdk"), root.join("app"));
+    fs::create_dir_all(sdk.join("target/stax")).unwrap();
+    fs::create_dir_all(sdk.join("lib_nbgl")).unwrap();
+    fs::create_dir_all(&app).unwrap();
+    fs::write(sdk.join("Makefile.rules"), "").unwrap();
+    fs::write(sdk.join("Makefile.defines"), "DEFINES += HAVE_NBGL HAVE_BAGL_FONTS\n").unwrap();
+    let rules = "all:\n\
+                 \tclang -c $(addprefix -D,$(DEFINES)) -o build/main.o src/main.c\n\
+                 \tclang -c $(addprefix -D,$(DEFINES)) -o build/os.o $(BOLOS_SDK)/src/os.c\n";
+    fs::write(sdk.join("Makefile.standard_app"), rules).unwrap();
+    let makefile = "include $(BOLOS_SDK)/Makefile.defines\n\
+                    ENABLE_SWAP = 1\n\
+                    ifeq ($(ENABLE_SWAP),1)\nDEFINES += HAVE_SWAP\nendif\n\
+                    DEFINES := $(filter-out HAVE_BAGL_FONTS,$(DEFINES))\n\
+                    include $(BOLOS_SDK)/Makefile.standard_app\n";
+    fs::write(app.join("Makefile"), makefile).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_cbpx"))
+        .args(["--app-path", "app", "--device", "stax", "--output-dir", "out", "--baseline", "run", "--no-stamp"])
+        .current_dir(&root)
+        .env("STAX_SDK", &sdk)
+        .env_remove("BOLOS_SDK")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("SDK features toggled by the app:\n  - HAVE_BAGL_FONTS\n  + HAVE_SWAP\n"),
+        "{}{}",
+        stdout,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(root.join("out/baseline_stax.json").is_file());
+
+    // The written baseline is loaded back without running it again
+    let output = Command::new(env!("CARGO_BIN_EXE_cbpx"))
+        .args(["--app-path", "app", "--device", "stax", "--output-dir", "out", "--no-stamp"])
+        .args(["--baseline", "out/baseline_{device}.json"])
+        .current_dir(&root)
+        .env("STAX_SDK", &sdk)
+        .env_remove("BOLOS_SDK")
+        .output()
+        .unwrap();
+    assert!(String::from_utf8_lossy(&output.stdout).contains("  - HAVE_BAGL_FONTS\n  + HAVE_SWAP\n"));
+    fs::remove_dir_all(&root).unwrap();
+}
@@ -0,0 +1,58 @@
+use std::{fs, path::Path, process::Command};
+
+// Two apps built against an SDK with just the layout checked before running
+// make, the second one without Makefile
+#[test]
+fn failed_apps_are_reported_without_stopping_the_batch() {
+    let root = std::env::temp_dir().join(format!("cbpx-batch-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    for dir in ["sdk/target/stax", "sdk/lib_nbgl", "apps/good", "apps/broken"] {
+        fs::create_dir_all(root.join(dir)).unwrap();
+    }
+    for file in ["sdk/Makefile.defines", "sdk/Makefile.rules"] {
+        fs::write(root.join(file), "").unwrap();
+    }
+    fs::copy(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/app/Makefile"), root.join("apps/good/Makefile")).unwrap();
+    fs::write(root.join("apps.txt"), "# Apps of the monorepo\napps/broken\n\napps/good\n").unwrap();
+
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_cbpx"))
+            .args(["--app-list", "apps.txt", "--device", "stax", "--output-dir", "out", "--reference-dir", "refs"])
+            .args(args)
+            .current_dir(&root)
+            .env("STAX_SDK", root.join("sdk"))
+            .env_remove("BOLOS_SDK")
+            .output()
+            .unwrap()
+    };
+    let output = run(&["--update-references"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("broken: Makefile"), "{}", stderr);
+    assert!(root.join("out/good/stax/c_sdk_build_stax.defines").is_file());
+    assert!(!root.join("out/broken").exists());
+
+    let output = run(&[]);
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let report_path = root.join("out/report.json");
+    assert!(stdout.contains(&format!("2 run(s), 1 failed or mismatched, report written to {}", report_path.display())), "{}", stdout);
+    let report: serde_json::Value = serde_json::from_str(&fs::read_to_string(&report_path).unwrap()).unwrap();
+    assert_eq!(report["success"], false);
+    let runs = report["runs"].as_array().unwrap();
+    let status = runs.iter().map(|r| (r["app"].as_str().unwrap(), r["device"].as_str().unwrap(), r["status"].as_str().unwrap()));
+    assert_eq!(status.collect::<Vec<_>>(), [("broken", "-", "error"), ("good", "stax", "match")]);
+    assert!(runs[0]["messages"][0].as_str().unwrap().contains("not found"), "{}", runs[0]);
+    assert_eq!(runs[1]["comparisons"][0]["status"], "match");
+
+    // Without failing app, the batch succeeds
+    fs::create_dir_all(root.join("apps/other")).unwrap();
+    fs::copy(root.join("apps/good/Makefile"), root.join("apps/other/Makefile")).unwrap();
+    fs::write(root.join("apps.txt"), "apps/good\napps/other\n").unwrap();
+    let output = run(&[]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(root.join("out/other/stax/c_sdk_build_stax.defines").is_file());
+    let report: serde_json::Value = serde_json::from_str(&fs::read_to_string(&report_path).unwrap()).unwrap();
+    assert_eq!(report["success"], true);
+    fs::remove_dir_all(&root).unwrap();
+}
@@ -0,0 +1,77 @@
+use std::{fs, path::Path, sync::Arc};
+
+use cbpx::{
+    backend::SavedTrace,
+    build_dir::{line_build_dir, objects_build_dir, relative_build_dir},
+    device::find_device,
+    extract::{extract, ExtractOptions},
+    portable::PathRoots,
+    scope::Scope,
+    unit::Strategy,
+};
+
+const LINE: &str = "clang -c -Oz -MMD -MT build/stax/obj/app/src/main.o -MF build/stax/dep/main.d \
+                    -o build/stax/obj/app/src/main.o src/main.c";
+
+#[test]
+fn build_dir_is_the_root_shared_by_the_dependency_paths() {
+    assert_eq!(line_build_dir(LINE).unwrap(), Path::new("build/stax"));
+    assert_eq!(line_build_dir("gcc -c -MF dep/main.d -o obj/main.o src/main.c").unwrap(), Path::new(""));
+    assert_eq!(line_build_dir("clang -c -o build/main.o src/main.c"), None);
+}
+
+#[test]
+fn build_dir_is_relative_to_the_app() {
+    let roots = PathRoots::new(Path::new("/sdk"), Path::new("/app"));
+    let relative = |line, make_dir: &str| relative_build_dir(line, Path::new(make_dir), Path::new("/app"), &roots);
+    assert_eq!(relative(LINE, "/app").as_deref(), Some("build/stax"));
+    assert_eq!(relative(LINE, "/app/ledger").as_deref(), Some("ledger/build/stax"));
+    assert_eq!(relative("clang -c -MF ../dep/a.d -o ../obj/a.o a.c", "/app/ledger").as_deref(), Some("."));
+    assert_eq!(relative("clang -c -MF /sdk/build/a.d -o /sdk/build/a.o a.c", "/app").as_deref(), Some("${BOLOS_SDK}/build"));
+}
+
+#[test]
+fn build_dir_without_dependency_files_is_the_root_of_the_objects() {
+    let lines = ["clang -c -o out/stax/obj/src/main.o src/main.c", "clang -c -o out/stax/obj/sdk/os_io.o /sdk/src/os_io.c"];
+    assert_eq!(objects_build_dir(lines).unwrap(), Path::new("out/stax/obj"));
+    assert_eq!(objects_build_dir(["clang -c src/main.c"]), None);
+}
+
+fn options(trace: &Path) -> ExtractOptions {
+    let mut options = ExtractOptions::new("/sdk");
+    options.backend = Arc::new(SavedTrace { path: trace.to_path_buf() });
+    options.strategy = Strategy::Union;
+    options.scope = Scope::App;
+    options
+}
+
+#[test]
+fn custom_build_dir_is_found_and_sources_classified() {
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let params =
+        extract(find_device("stax").unwrap(), &fixtures.join("app"), &options(&fixtures.join("custom_build_dir_trace.txt"))).unwrap();
+    assert_eq!(params.metadata.build_dir.as_deref(), Some("out/stax"));
+    assert!(params.metadata.build_dir_conflicts.is_empty());
+    let app_only = params.app_only.unwrap();
+    assert_eq!(app_only.defines.iter().map(|d| d.name.as_str()).collect::<Vec<_>>(), ["HAVE_APP_FEATURE"]);
+}
+
+#[test]
+fn app_sources_are_classified_through_symbolic_links() {
+    let root = std::env::temp_dir().join(format!("cbpx-build-dir-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("app")).unwrap();
+    let root = root.canonicalize().unwrap();
+    std::os::unix::fs::symlink(root.join("app"), root.join("link")).unwrap();
+    // make reports the app sources by their canonical path
+    let trace = format!(
+        "clang -c -DHAVE_APP_FEATURE -MF {0}/app/out/dep/main.d -o {0}/app/out/obj/main.o {0}/app/src/main.c\n\
+         clang -c -MF {0}/app/out/dep/os_io.d -o {0}/app/out/obj/os_io.o /sdk/src/os_io.c\n",
+        root.display()
+    );
+    fs::write(root.join("trace.txt"), trace).unwrap();
+    let params = extract(find_device("stax").unwrap(), &root.join("link"), &options(&root.join("trace.txt"))).unwrap();
+    assert_eq!(params.metadata.build_dir.as_deref(), Some("out"));
+    assert_eq!(params.app_only.unwrap().defines.iter().map(|d| d.name.as_str()).collect::<Vec<_>>(), ["HAVE_APP_FEATURE"]);
+    fs::remove_dir_all(&root).unwrap();
+}
@@ -0,0 +1,30 @@
+use cbpx::{
+    builtin::{is_builtin_flag, parse_define_dump},
+    files::Define,
+};
+
+fn define(name: &str, value: Option<&str>) -> Define {
+    Define { name: name.to_string(), value: value.map(|v| v.to_string()) }
+}
+
+#[test]
+fn define_dumps_are_parsed_and_sorted() {
+    let dump = "#define __thumb__ 1\n#define __ARM_ARCH 8\n#define __EMPTY \n#define __NOVALUE\n#define F(a, b) a\n";
+    assert_eq!(
+        parse_define_dump(dump),
+        vec![
+            define("F(a, b)", Some("a")),
+            define("__ARM_ARCH", Some("8")),
+            define("__EMPTY", Some("")),
+            define("__NOVALUE", None),
+            define("__thumb__", Some("1")),
+        ]
+    );
+}
+
+#[test]
+fn only_target_flags_are_passed_to_the_compiler() {
+    let flags = ["--target=armv8m-none-eabi", "-mcpu=cortex-m35p", "-Os", "-fropi", "-Wall", "-MMD", "-c"];
+    let kept = flags.iter().filter(|f| is_builtin_flag(f)).collect::<Vec<_>>();
+    assert_eq!(kept, [&"--target=armv8m-none-eabi", &"-mcpu=cortex-m35p", &"-Os", &"-fropi"]);
+}
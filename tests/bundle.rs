@@ -0,0 +1,65 @@
+use std::fs;
+
+use cbpx::bundle::{archive_entries, bundle_archive, entry_path, read_bundle, verify_bundle, write_bundle, MANIFEST_NAME};
+
+fn files() -> Vec<(String, Vec<u8>)> {
+    let long = format!("{}/c_sdk_build_stax.defines", "app-with-a-long-name/".repeat(6).trim_end_matches('/'));
+    vec![
+        ("report.json".to_string(), b"{}\n".to_vec()),
+        ("c_sdk_build_stax.defines".to_string(), b"#define HAVE_NBGL\n".to_vec()),
+        (long, vec![b'x'; 1000]),
+    ]
+}
+
+#[test]
+fn archives_are_deterministic_and_read_back() {
+    let archive = bundle_archive(&files(), "cbpx test").unwrap();
+    let mut reversed = files();
+    reversed.reverse();
+    assert_eq!(bundle_archive(&reversed, "cbpx test").unwrap(), archive);
+
+    let entries = archive_entries(&archive).unwrap();
+    let names = entries.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names[..3], [MANIFEST_NAME, "app-with-a-long-name/app-with-a-long-name/app-with-a-long-name/app-with-a-long-name/app-with-a-long-name/app-with-a-long-name/c_sdk_build_stax.defines", "c_sdk_build_stax.defines"]);
+    assert_eq!(entries[2].1, b"#define HAVE_NBGL\n");
+    assert!(verify_bundle(&entries).is_empty());
+}
+
+#[test]
+fn tampered_bundles_are_reported() {
+    let mut entries = archive_entries(&bundle_archive(&files(), "cbpx test").unwrap()).unwrap();
+    entries[3].1.push(b'\n');
+    entries.remove(1);
+    entries.push(("extra.txt".to_string(), Vec::new()));
+    assert_eq!(
+        verify_bundle(&entries),
+        [
+            "app-with-a-long-name/app-with-a-long-name/app-with-a-long-name/app-with-a-long-name/app-with-a-long-name/app-with-a-long-name/c_sdk_build_stax.defines is missing",
+            "report.json does not match its hash",
+            "extra.txt is not in the manifest",
+        ]
+    );
+    assert_eq!(verify_bundle(&entries[1..]), ["No MANIFEST.json in the bundle"]);
+}
+
+#[test]
+fn bundles_are_gzipped_reproducibly() {
+    let dir = std::env::temp_dir().join(format!("cbpx-bundle-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let (first, second) = (dir.join("first.tar.gz"), dir.join("second.tar.gz"));
+    write_bundle(&first, &files(), "cbpx test").unwrap();
+    write_bundle(&second, &files(), "cbpx test").unwrap();
+    assert_eq!(fs::read(&first).unwrap(), fs::read(&second).unwrap());
+    assert_eq!(read_bundle(&first).unwrap().len(), 4);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn entries_stay_in_the_target_directory() {
+    let dir = std::path::Path::new("out");
+    assert_eq!(entry_path(dir, "app/report.json").unwrap(), dir.join("app/report.json"));
+    for entry in ["../evil", "/etc/passwd", "a/../../b", ""] {
+        assert!(entry_path(dir, entry).is_err(), "{}", entry);
+    }
+}
@@ -0,0 +1,48 @@
+use cbpx::{
+    capability::{capability_changes, capability_matrix, is_crypto_capability, render_csv, CapabilityChange},
+    files::Define,
+    multi_device::MultiDeviceParameters,
+    params::BuildParameters,
+};
+
+fn device(name: &str, defines: &[&str]) -> (String, BuildParameters) {
+    let defines = defines.iter().map(|n| Define { name: n.to_string(), value: None }).collect();
+    (name.to_string(), BuildParameters { defines, ..Default::default() })
+}
+
+fn devices<const N: usize>(devices: [(String, BuildParameters); N]) -> MultiDeviceParameters {
+    devices.into_iter().collect()
+}
+
+#[test]
+fn capabilities_are_the_curve_and_crypto_defines() {
+    assert!(["HAVE_SECP256K1_CURVE", "HAVE_SHA256", "HAVE_ECDSA"].iter().all(|name| is_crypto_capability(name)));
+    assert!(!is_crypto_capability("HAVE_NBGL") && !is_crypto_capability("API_LEVEL"));
+
+    let matrix = capability_matrix(&devices([
+        device("stax", &["HAVE_NBGL", "HAVE_SHA256", "HAVE_ED25519_CURVE"]),
+        device("nanox", &["HAVE_SHA256"]),
+    ]));
+    assert_eq!(matrix.devices, ["stax", "nanox"]);
+    assert_eq!(matrix.capabilities.keys().collect::<Vec<_>>(), ["HAVE_ED25519_CURVE", "HAVE_SHA256"]);
+    assert!(matrix.is_enabled("HAVE_SHA256", "nanox") && !matrix.is_enabled("HAVE_ED25519_CURVE", "nanox"));
+}
+
+#[test]
+fn changes_are_reported_for_the_devices_of_both_snapshots() {
+    let old = capability_matrix(&devices([
+        device("stax", &["HAVE_AES", "HAVE_SHA256"]),
+        device("nanox", &["HAVE_SHA256"]),
+    ]));
+    let new = capability_matrix(&devices([
+        device("stax", &["HAVE_SHA256", "HAVE_BLS12381_CURVE"]),
+        device("flex", &["HAVE_SHA256"]),
+    ]));
+    let changes = capability_changes(&old, &new);
+    let change = |capability: &str, gained| CapabilityChange { capability: capability.to_string(), device: "stax".to_string(), gained };
+    assert_eq!(changes, [change("HAVE_AES", false), change("HAVE_BLS12381_CURVE", true)]);
+    assert_eq!(
+        render_csv(&new, &changes),
+        "capability,stax,flex\nHAVE_AES,lost,0\nHAVE_BLS12381_CURVE,gained,0\nHAVE_SHA256,1,1\n"
+    );
+}
@@ -0,0 +1,27 @@
+use regex::Regex;
+
+use cbpx::trace::{command_word, find_compile_lines, find_compile_lines_matching, no_compile_line_error};
+
+const TRACE: &str = "Makefile:12: update target 'a.o' due to: a.c\n\
+                     env CCACHE_DIR=/tmp/ccache ccache /opt/llvm/bin/clang-15 -c -o a.o a.c\n\
+                     Makefile:12: update target 'b.o' due to: b.c\n\
+                     LANG=C ledger-cc --target=armv8m-none-eabi -c -o b.o b.c\n";
+
+#[test]
+fn wrappers_and_assignments_are_skipped() {
+    assert_eq!(command_word("env CCACHE_DIR=/tmp/ccache ccache /opt/llvm/bin/clang-15 -c a.c"), Some("/opt/llvm/bin/clang-15"));
+    assert_eq!(command_word("LANG=C ledger-cc -c b.c"), Some("ledger-cc"));
+    let lines = find_compile_lines(TRACE.as_bytes(), false).unwrap();
+    assert_eq!(lines.len(), 1, "ledger-cc is not matched by default");
+}
+
+#[test]
+fn compiler_pattern_selects_other_commands() {
+    let pattern = Regex::new(r"(^|/)ledger-cc$").unwrap();
+    let lines = find_compile_lines_matching(TRACE.as_bytes(), false, &pattern).unwrap();
+    assert_eq!(lines, ["LANG=C ledger-cc --target=armv8m-none-eabi -c -o b.o b.c"]);
+
+    let message = no_compile_line_error(b"Makefile:12: update target 'b.o' due to: b.c\nledger-cc -c -o b.o b.c\n");
+    assert!(message.contains("First command lines of the trace:\n  ledger-cc -c -o b.o b.c"), "{}", message);
+    assert!(message.contains("--compiler-pattern"), "{}", message);
+}
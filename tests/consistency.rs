@@ -0,0 +1,35 @@
+use cbpx::{
+    consistency::{asymmetries, kind_defines, InvocationKind},
+    trace::find_compile_lines,
+    unit::parse_compile_unit,
+};
+
+const TRACE: &str = "\
+clang -c -Oz -DHAVE_NBGL -DHAVE_SWAP -o build/stax/obj/app/src/main.o src/main.c
+clang -c -Oz -DHAVE_NBGL -DHAVE_BOLOS_APP_STACK_CANARY -o build/stax/obj/sdk/app_entry.o sdk/app_entry.S
+clang -Oz -o bin/app.elf -Tscript.ld build/stax/obj/app/src/main.o build/stax/obj/sdk/app_entry.o
+";
+
+fn sets() -> cbpx::consistency::KindDefines {
+    let units = find_compile_lines(TRACE.as_bytes(), false).unwrap().iter().map(|l| parse_compile_unit(l)).collect::<Vec<_>>();
+    kind_defines(&units, TRACE)
+}
+
+#[test]
+fn defines_are_grouped_by_invocation_kind() {
+    let sets = sets();
+    assert_eq!(sets[&InvocationKind::C].iter().collect::<Vec<_>>(), ["HAVE_NBGL", "HAVE_SWAP"]);
+    assert_eq!(sets[&InvocationKind::Assembly].iter().collect::<Vec<_>>(), ["HAVE_BOLOS_APP_STACK_CANARY", "HAVE_NBGL"]);
+    assert!(sets[&InvocationKind::Link].is_empty());
+}
+
+#[test]
+fn asymmetries_ignore_kinds_without_defines() {
+    assert_eq!(
+        asymmetries(&sets()),
+        [
+            "HAVE_SWAP passed to C compiles but not to assembly",
+            "HAVE_BOLOS_APP_STACK_CANARY passed to assembly but not to C compiles",
+        ]
+    );
+}
@@ -0,0 +1,64 @@
+use std::collections::BTreeMap;
+
+use cbpx::{
+    device::{child_env, custom_device, parse_device},
+    naming::FilenameStyle,
+};
+
+#[test]
+fn devices_are_accepted_by_marketing_name_and_target() {
+    assert_eq!(parse_device("nanosplus").unwrap().name, "nanosplus");
+    assert_eq!(parse_device("nanos2").unwrap().name, "nanosplus");
+    assert_eq!(parse_device("apex_p").unwrap().target, "apex_p");
+    assert!(parse_device("nanos").is_err_and(|e| e.contains("Unsupported device nanos")));
+    assert!(custom_device("nanos2", "NANOS2_SDK").is_err());
+}
+
+#[test]
+fn filename_style_picks_the_device_name() {
+    let device = parse_device("nanos2").unwrap();
+    assert_eq!(FilenameStyle::Marketing.device_name(device), "nanosplus");
+    assert_eq!(FilenameStyle::Target.device_name(device), "nanos2");
+}
+
+fn lookup(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+    let vars = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<BTreeMap<_, _>>();
+    move |name| vars.get(name).cloned()
+}
+
+#[test]
+fn inherited_target_and_sdk_are_overridden_with_a_notice() {
+    let device = parse_device("stax").unwrap();
+    let env = child_env(device, false, lookup(&[("TARGET", "nanox"), ("BOLOS_SDK", "/old"), ("STAX_SDK", "/sdk")])).unwrap();
+    assert_eq!((env.target.as_str(), env.bolos_sdk.as_str()), ("stax", "/sdk"));
+    assert_eq!(
+        env.notices,
+        [
+            "inherited TARGET=nanox overridden with TARGET=stax",
+            "inherited BOLOS_SDK=/old overridden with BOLOS_SDK=/sdk (from STAX_SDK), use --inherit-env to keep it",
+        ]
+    );
+
+    // Values matching the chosen ones are not worth a notice
+    let env = child_env(device, false, lookup(&[("TARGET", "stax"), ("BOLOS_SDK", "/sdk"), ("STAX_SDK", "/sdk")])).unwrap();
+    assert!(env.notices.is_empty());
+}
+
+#[test]
+fn inherit_env_keeps_the_inherited_sdk() {
+    let device = parse_device("flex").unwrap();
+    let env = child_env(device, true, lookup(&[("BOLOS_SDK", "/inherited"), ("FLEX_SDK", "/sdk")])).unwrap();
+    assert_eq!((env.target.as_str(), env.bolos_sdk.as_str()), ("flex", "/inherited"));
+    assert!(env.notices.is_empty());
+
+    // The device variable is no substitute for the inherited one
+    let error = child_env(device, true, lookup(&[("FLEX_SDK", "/sdk")])).err().unwrap();
+    assert_eq!(error, "--inherit-env requires BOLOS_SDK to be set in the environment");
+}
+
+#[test]
+fn a_missing_device_variable_is_an_error() {
+    let device = parse_device("nanox").unwrap();
+    let error = child_env(device, false, lookup(&[("BOLOS_SDK", "/sdk")])).err().unwrap();
+    assert_eq!(error, "NANOX_SDK is not set (required for device nanox)");
+}
@@ -0,0 +1,168 @@
+use cbpx::{
+    diff::{compare, parse_renames, ChangedDefine, CompareOptions, RenamedDefine},
+    files::Define,
+    params::BuildParameters,
+    trace::find_compile_lines,
+    unit::{occurrences, parse_compile_unit},
+};
+
+fn define(name: &str, value: Option<&str>) -> Define {
+    Define { name: name.to_string(), value: value.map(str::to_string) }
+}
+
+fn params(defines: &[Define], cflags: &[&str]) -> BuildParameters {
+    BuildParameters {
+        defines: defines.to_vec(),
+        cflags: cflags.iter().map(|f| f.to_string()).collect(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn identical_parameters_have_an_empty_diff() {
+    let p = params(&[define("HAVE_NBGL", None), define("API_LEVEL", Some("22"))], &["-Oz", "-fropi"]);
+    assert!(compare(&p, &p.clone(), &CompareOptions::default()).is_empty());
+}
+
+#[test]
+fn added_define() {
+    let diff = compare(&params(&[], &[]), &params(&[define("HAVE_NFC", None)], &[]), &CompareOptions::default());
+    assert_eq!(diff.added_defines, vec![define("HAVE_NFC", None)]);
+    assert!(diff.defines_changed() && !diff.cflags_changed());
+}
+
+#[test]
+fn removed_define() {
+    let diff = compare(&params(&[define("HAVE_NFC", None)], &[]), &params(&[], &[]), &CompareOptions::default());
+    assert_eq!(diff.removed_defines, vec![define("HAVE_NFC", None)]);
+}
+
+#[test]
+fn changed_define() {
+    let old = params(&[define("API_LEVEL", Some("21"))], &[]);
+    let new = params(&[define("API_LEVEL", Some("22"))], &[]);
+    let diff = compare(&old, &new, &CompareOptions::default());
+    assert_eq!(
+        diff.changed_defines,
+        vec![ChangedDefine { name: "API_LEVEL".to_string(), old: Some("21".to_string()), new: Some("22".to_string()) }]
+    );
+    assert_eq!(diff.lines(), vec!["~ define API_LEVEL: 21 -> 22"]);
+}
+
+#[test]
+fn empty_value_differs_from_no_value() {
+    let diff = compare(&params(&[define("X", None)], &[]), &params(&[define("X", Some(""))], &[]), &CompareOptions::default());
+    assert_eq!(diff.changed_defines.len(), 1);
+}
+
+#[test]
+fn added_and_removed_cflags() {
+    let diff = compare(&params(&[], &["-Oz", "-fropi"]), &params(&[], &["-fropi", "-Os"]), &CompareOptions::default());
+    assert_eq!(diff.added_cflags, vec!["-Os"]);
+    assert_eq!(diff.removed_cflags, vec!["-Oz"]);
+    assert!(diff.cflags_changed() && !diff.defines_changed());
+}
+
+#[test]
+fn ignored_patterns_are_left_out() {
+    let old = params(&[define("APPVERSION", Some("\"1.0.0\""))], &["-Oz"]);
+    let new = params(&[define("APPVERSION", Some("\"1.0.1\""))], &["-Os"]);
+    let options = CompareOptions {
+        ignored_defines: vec!["APP*".to_string()],
+        ignored_cflags: vec!["-O*".to_string()],
+        ..Default::default()
+    };
+    assert!(compare(&old, &new, &options).is_empty());
+}
+
+#[test]
+fn renames_are_parsed_from_toml_pairs() {
+    let renames = parse_renames("# SDK 1.2\nHAVE_FOO = \"HAVE_FOO_V2\"  # since 1.2\n\n\"HAVE_BAR\" = \"HAVE_BAZ\"\n").unwrap();
+    assert_eq!(renames.get("HAVE_FOO").map(String::as_str), Some("HAVE_FOO_V2"));
+    assert_eq!(renames.get("HAVE_BAR").map(String::as_str), Some("HAVE_BAZ"));
+    assert!(parse_renames("HAVE_FOO = HAVE_FOO_V2\n").unwrap_err().contains("line 1, column 12"));
+    assert!(parse_renames("HAVE_FOO = \"A\"\nHAVE_FOO = \"B\"\n").unwrap_err().contains("duplicate key"));
+    assert!(parse_renames("HAVE_FOO = \"\"\n").unwrap_err().starts_with("empty define name"));
+}
+
+#[test]
+fn known_renames_with_equal_values_are_reported_as_renames() {
+    let old = params(&[define("HAVE_FOO", None), define("LEVEL", Some("1"))], &[]);
+    let new = params(&[define("HAVE_FOO_V2", None), define("LEVEL_V2", Some("2"))], &[]);
+    let mut options = CompareOptions {
+        renames: parse_renames("HAVE_FOO = \"HAVE_FOO_V2\"\nLEVEL = \"LEVEL_V2\"\n").unwrap(),
+        ..Default::default()
+    };
+    let diff = compare(&old, &new, &options);
+    assert_eq!(diff.renamed_defines, [RenamedDefine { old: "HAVE_FOO".to_string(), new: "HAVE_FOO_V2".to_string() }]);
+    assert_eq!(diff.lines(), ["+ define LEVEL_V2", "~ define HAVE_FOO renamed to HAVE_FOO_V2", "- define LEVEL"]);
+    assert!(diff.defines_changed());
+
+    options.allow_renames = true;
+    let renamed_only = compare(&params(&[define("HAVE_FOO", None)], &[]), &params(&[define("HAVE_FOO_V2", None)], &[]), &options);
+    assert!(renamed_only.is_empty());
+}
+
+#[test]
+fn define_changes_and_removals_are_queried() {
+    let old = params(&[define("API_LEVEL", Some("21")), define("HAVE_SECP256K1_CURVE", None), define("HAVE_AES", None)], &[]);
+    let new = params(&[define("API_LEVEL", Some("22")), define("HAVE_NFC", None)], &[]);
+    let diff = compare(&old, &new, &CompareOptions::default());
+    assert_eq!(diff.define_change("API_LEVEL"), Some(("21".to_string(), "22".to_string())));
+    assert_eq!(diff.define_change("HAVE_NFC"), Some(("(undefined)".to_string(), "(defined)".to_string())));
+    assert_eq!(diff.define_change("HAVE_BAGL"), None);
+    let pattern = regex::Regex::new("HAVE_.*_CURVE").unwrap();
+    assert_eq!(diff.removed_matching(&pattern), ["HAVE_SECP256K1_CURVE"]);
+    assert!(diff.removed_matching(&regex::Regex::new("AES_.*").unwrap()).is_empty());
+}
+
+fn includes(fixture: &str) -> BuildParameters {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/includes").join(fixture);
+    BuildParameters { includes: cbpx::files::read_cflags(&path).unwrap(), ..Default::default() }
+}
+
+#[test]
+fn added_and_removed_includes() {
+    let reference = includes("reference.includes");
+    let added = compare(&reference, &includes("added.includes"), &CompareOptions::default());
+    assert_eq!(added.added_includes, ["${BOLOS_SDK}/lib_ux_nbgl"]);
+    assert!(added.removed_includes.is_empty() && added.reordered_includes.is_none() && added.includes_changed());
+
+    let removed = compare(&reference, &includes("removed.includes"), &CompareOptions::default());
+    assert_eq!(removed.removed_includes, ["${BOLOS_SDK}/lib_cxng/include"]);
+    assert_eq!(removed.lines(), ["- include ${BOLOS_SDK}/lib_cxng/include"]);
+    assert!(removed.reordered_includes.is_none());
+}
+
+#[test]
+fn reordered_includes_are_a_difference_only_when_strict() {
+    let (reference, reordered) = (includes("reference.includes"), includes("reordered.includes"));
+    let diff = compare(&reference, &reordered, &CompareOptions::default());
+    assert_eq!(diff.reordered_includes, Some(("${BOLOS_SDK}/include".to_string(), "include".to_string())));
+    assert!(diff.added_includes.is_empty() && diff.removed_includes.is_empty());
+    assert!(!diff.includes_changed() && diff.is_empty());
+    assert_eq!(diff.lines(), ["~ include ${BOLOS_SDK}/include now searched before include"]);
+
+    let strict = CompareOptions { strict_include_order: true, ..Default::default() };
+    assert!(compare(&reference, &reordered, &strict).includes_changed());
+}
+
+#[test]
+fn differences_on_some_units_only_can_be_tolerated() {
+    let trace = "clang -c -Oz -DHAVE_NBGL -o a.o a.c\nclang -c -Oz -Wno-shadow -DDEBUG=1 -DHAVE_NBGL -o b.o b.c\n";
+    let units = find_compile_lines(trace.as_bytes(), false).unwrap().iter().map(|l| parse_compile_unit(l)).collect::<Vec<_>>();
+    let old = params(&[define("HAVE_NBGL", None)], &["-c", "-Oz", "-o"]);
+    let mut new = params(&[define("HAVE_NBGL", None), define("DEBUG", Some("1"))], &["-c", "-Oz", "-o", "-Wno-shadow"]);
+    new.occurrences = Some(occurrences(&units, None));
+
+    let diff = compare(&old, &new, &CompareOptions::default());
+    assert_eq!(diff.lines(), ["+ define DEBUG (on 1/2 units)", "+ cflag -Wno-shadow (on 1/2 units)"]);
+    assert!(diff.defines_changed() && diff.cflags_changed());
+
+    let tolerant = compare(&old, &new, &CompareOptions { tolerate_partial: true, ..Default::default() });
+    assert!(tolerant.is_empty());
+    assert_eq!(tolerant.lines().len(), 2);
+    // A flag of every unit is not tolerated
+    let everywhere = params(&[define("HAVE_NBGL", None)], &["-c", "-o"]);
+    assert!(compare(&everywhere, &new, &CompareOptions { tolerate_partial: true, ..Default::default() }).cflags_changed());
+}
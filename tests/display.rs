@@ -0,0 +1,40 @@
+use std::path::Path;
+
+use cbpx::{
+    display::{display_parameters, missing_display_defines, Display},
+    files::{read_defines, Define},
+};
+
+fn reference(device: &str) -> Vec<Define> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(format!("references/c_sdk_build_{}.defines", device));
+    read_defines(&path).unwrap()
+}
+
+#[test]
+fn display_parameters_are_typed() {
+    let nanox = display_parameters(&reference("nanox")).unwrap();
+    assert_eq!((nanox.width, nanox.height), (Some(128), Some(64)));
+    assert_eq!(nanox.screen_size.as_deref(), Some("nano"));
+    assert_eq!(nanox.get("width").as_deref(), Some("128"));
+    assert_eq!(nanox.get("eink").as_deref(), Some("false"));
+    assert_eq!(nanox.get("fonts").as_deref(), Some("OPEN_SANS_REGULAR_11PX OPEN_SANS_EXTRABOLD_11PX OPEN_SANS_LIGHT_16PX"));
+
+    let stax = display_parameters(&reference("stax")).unwrap();
+    assert_eq!(stax.width, None);
+    assert!(stax.eink && stax.fonts.contains(&"INTER_REGULAR_24PX".to_string()));
+    assert_eq!(stax.get("width"), None);
+
+    let define = |name: &str, value: &str| Define { name: name.to_string(), value: Some(value.to_string()) };
+    let parenthesized = display_parameters(&[define("SCREEN_WIDTH", "(400)"), define("BAGL_WIDTH", "128")]).unwrap();
+    assert_eq!(parenthesized, Display { width: Some(400), ..Default::default() });
+    assert_eq!(display_parameters(&[define("API_LEVEL", "22")]), None);
+}
+
+#[test]
+fn missing_display_defines_depend_on_the_device_family() {
+    for device in ["nanox", "nanosplus", "stax", "flex", "apex_p"] {
+        assert!(missing_display_defines(device, &reference(device)).is_empty(), "{}", device);
+    }
+    assert_eq!(missing_display_defines("stax", &reference("nanox")), ["SCREEN_SIZE_WALLET"]);
+    assert_eq!(missing_display_defines("nanox", &[]), ["SCREEN_SIZE_NANO", "BAGL_WIDTH", "BAGL_HEIGHT"]);
+}
@@ -0,0 +1,23 @@
+use std::{env, fs};
+
+use cbpx::embedded::{check_embedded_api_level, embedded_api_levels, write_embedded_references};
+
+#[test]
+fn embedded_references_are_written_under_the_rendered_name() {
+    let dir = env::temp_dir().join(format!("cbpx-embedded-test-{}", std::process::id()));
+    let result = write_embedded_references("26", "stax", &dir, "Boilerplate_stax_26");
+    if cfg!(feature = "embedded-references") {
+        result.unwrap();
+        let defines = fs::read_to_string(dir.join("Boilerplate_stax_26.defines")).unwrap();
+        assert_eq!(defines, include_str!("../embedded_references/26/c_sdk_build_stax.defines"));
+        assert!(dir.join("Boilerplate_stax_26.cflags").exists());
+        assert!(!dir.join("c_sdk_build_stax.defines").exists());
+        let error = check_embedded_api_level("1").unwrap_err();
+        assert!(error.contains("available: 26"), "{}", error);
+        fs::remove_dir_all(&dir).unwrap();
+    } else {
+        assert!(embedded_api_levels().is_empty());
+        assert_eq!(result.unwrap_err(), "cbpx was built without the embedded-references feature");
+        assert!(!dir.exists());
+    }
+}
@@ -0,0 +1,37 @@
+use cbpx::{
+    escape::{escape, escape_defines, header, Escaping},
+    files::{parse_defines, render_defines, Define},
+};
+
+// A value with a make variable reference, quotes and a space
+const VALUE: &str = "\"$(HOME) dir\"";
+
+#[test]
+fn none_keeps_the_value() {
+    assert_eq!(escape(VALUE, Escaping::None), VALUE);
+    assert_eq!(header(Escaping::None, "//"), "");
+}
+
+#[test]
+fn shell_single_quotes_the_value() {
+    assert_eq!(escape(VALUE, Escaping::Shell), "'\"$(HOME) dir\"'");
+    assert_eq!(escape("it's", Escaping::Shell), "'it'\\''s'");
+    assert_eq!(escape("-DAPI_LEVEL=22", Escaping::Shell), "-DAPI_LEVEL=22");
+    assert_eq!(escape("", Escaping::Shell), "''");
+}
+
+#[test]
+fn make_escapes_dollars_hashes_and_spaces() {
+    assert_eq!(escape(VALUE, Escaping::Make), "\"$$(HOME)\\ dir\"");
+    assert_eq!(escape("a#b", Escaping::Make), "a\\#b");
+}
+
+#[test]
+fn escaped_defines_read_back_with_their_header() {
+    let defines = vec![Define { name: "PATH_VALUE".to_string(), value: Some(VALUE.to_string()) }];
+    for escaping in [Escaping::Shell, Escaping::Make, Escaping::None] {
+        let escaped = escape_defines(&defines, escaping);
+        let contents = header(escaping, "//") + &render_defines(&escaped);
+        assert_eq!(parse_defines(&contents).unwrap(), escaped);
+    }
+}
@@ -0,0 +1,77 @@
+use std::{path::Path, sync::Arc, thread};
+
+use cbpx::{
+    backend::SavedTrace,
+    device::find_device,
+    extract::{extract, ExtractError, ExtractOptions},
+    identifier::OnInvalid,
+};
+
+fn options(trace: &str) -> ExtractOptions {
+    let mut options = ExtractOptions::new("/sdk");
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(trace);
+    options.backend = Arc::new(SavedTrace { path });
+    options
+}
+
+#[test]
+fn extractions_run_concurrently() {
+    let options = options("gcc_trace.txt");
+    let app = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/app");
+    // Every thread is spawned before any is joined
+    let params = thread::scope(|scope| {
+        ["nanox", "stax", "flex"]
+            .map(|name| {
+                let (options, app) = (&options, &app);
+                scope.spawn(move || extract(find_device(name).unwrap(), app, options))
+            })
+            .map(|handle| handle.join().unwrap().unwrap())
+    });
+    assert_eq!(params.iter().map(|p| p.device.as_str()).collect::<Vec<_>>(), ["nanox", "stax", "flex"]);
+    for p in &params {
+        assert_eq!(p.sources, ["src/main.c", "${BOLOS_SDK}/src/os_io.c"]);
+        assert_eq!(p.metadata.app_name.as_deref(), Some("Boilerplate"));
+        assert_eq!(p.raw_commands.len(), 2);
+    }
+}
+
+#[test]
+fn missing_compile_lines_keep_the_trace() {
+    let options = options("crlf.sources");
+    match extract(find_device("stax").unwrap(), Path::new("."), &options) {
+        Err(ExtractError::NoCompileLine { trace, .. }) => assert!(!trace.is_empty()),
+        other => panic!("unexpected result {:?}", other.map(|p| p.device)),
+    }
+}
+
+#[test]
+fn warning_overrides_are_resolved_on_request() {
+    let app = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/app");
+    let mut options = options("warnings_trace.txt");
+    let exact = extract(find_device("stax").unwrap(), &app, &options).unwrap();
+    assert_eq!(exact.warnings, ["-Wall", "-Wshadow", "-Werror=format", "-Wno-shadow", "-Wno-error=format"]);
+    assert!(exact.metadata.overridden_warnings.is_empty());
+
+    options.normalize_warnings = true;
+    let normalized = extract(find_device("stax").unwrap(), &app, &options).unwrap();
+    assert_eq!(normalized.warnings, ["-Wall", "-Wno-shadow", "-Wno-error=format"]);
+    assert_eq!(normalized.cflags, ["-c", "-Oz", "-Wall", "-Wno-shadow", "-Wno-error=format", "-MMD", "-MT", "-MF", "-o"]);
+    let overridden = normalized.metadata.overridden_warnings.iter().map(|o| (o.flag.as_str(), o.by.as_str())).collect::<Vec<_>>();
+    assert_eq!(overridden, [("-Wshadow", "-Wno-shadow"), ("-Werror=format", "-Wno-error=format")]);
+}
+
+#[test]
+fn invalid_define_names_fail_or_are_skipped() {
+    let app = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/app");
+    let mut options = options("invalid_defines_trace.txt");
+    match extract(find_device("stax").unwrap(), &app, &options) {
+        Err(e @ ExtractError::Parse(_)) => assert!(e.to_string().starts_with("`-D1ST_BOOT` does not define"), "{}", e),
+        other => panic!("unexpected result {:?}", other.map(|p| p.defines)),
+    }
+
+    options.on_invalid = OnInvalid::Skip;
+    let params = extract(find_device("stax").unwrap(), &app, &options).unwrap();
+    let names = params.defines.iter().map(|d| d.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["HAVE_NBGL", "PRINTF(...)", "MAX(a,b)"]);
+    assert_eq!(params.metadata.invalid_defines, ["-D1ST_BOOT", "-D\"APP\"NAME"]);
+}
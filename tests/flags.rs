@@ -0,0 +1,63 @@
+use std::path::Path;
+
+use cbpx::{
+    files::read_cflags,
+    flags::{normalize_warnings, warning_flags, OverriddenWarning},
+};
+
+fn flags(flags: &str) -> Vec<String> {
+    flags.split_whitespace().map(str::to_string).collect()
+}
+
+fn overridden(flag: &str, by: &str) -> OverriddenWarning {
+    OverriddenWarning { flag: flag.to_string(), by: by.to_string() }
+}
+
+#[test]
+fn the_last_flag_of_each_warning_is_kept() {
+    let (effective, overrides) = normalize_warnings(&flags("-Wall -Wshadow -Oz -Wno-shadow -Wextra"));
+    assert_eq!(effective, flags("-Wall -Oz -Wno-shadow -Wextra"));
+    assert_eq!(overrides, [overridden("-Wshadow", "-Wno-shadow")]);
+
+    let (effective, overrides) = normalize_warnings(&flags("-Wno-shadow -Wshadow"));
+    assert_eq!(effective, flags("-Wshadow"));
+    assert_eq!(overrides, [overridden("-Wno-shadow", "-Wshadow")]);
+
+    let (effective, overrides) = normalize_warnings(&flags("-Wshadow -Wno-shadow -Wshadow -Wshadow"));
+    assert_eq!(effective, flags("-Wshadow"));
+    assert_eq!(
+        overrides,
+        [overridden("-Wshadow", "-Wno-shadow"), overridden("-Wno-shadow", "-Wshadow"), overridden("-Wshadow", "-Wshadow")]
+    );
+}
+
+#[test]
+fn error_levels_and_valued_warnings_are_told_apart() {
+    let line = "-Werror -Werror=format -Wformat=2 -Wno-error -Wno-format -Wno-error=format -Wl,--gc-sections -Wl,-Map -w -w";
+    let (effective, overrides) = normalize_warnings(&flags(line));
+    assert_eq!(effective, flags("-Wno-error -Wno-format -Wno-error=format -Wl,--gc-sections -Wl,-Map -w -w"));
+    assert_eq!(
+        overrides,
+        [
+            overridden("-Werror", "-Wno-error"),
+            overridden("-Wformat=2", "-Wno-format"),
+            overridden("-Werror=format", "-Wno-error=format")
+        ]
+    );
+    // Without overrides, the flags are left untouched
+    let (effective, overrides) = normalize_warnings(&flags("-Wall -Wextra -Wno-unused"));
+    assert_eq!(effective, flags("-Wall -Wextra -Wno-unused"));
+    assert!(overrides.is_empty());
+}
+
+#[test]
+fn the_warnings_of_each_reference_are_pinned() {
+    let sdk_warnings = flags(
+        "-Wall -Wextra -Wno-main -Werror=int-to-pointer-cast -Wno-error=int-conversion -Wimplicit-fallthrough -Wvla \
+         -Wundef -Wshadow -Wformat=2 -Wformat-security -Wwrite-strings",
+    );
+    for device in ["nanox", "nanosplus", "stax", "flex", "apex_p"] {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(format!("references/c_sdk_build_{}.cflags", device));
+        assert_eq!(warning_flags(&read_cflags(&path).unwrap()), sdk_warnings, "{}", device);
+    }
+}
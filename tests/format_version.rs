@@ -0,0 +1,47 @@
+use cbpx::{
+    diff::{compare, CompareOptions},
+    files::{parse_cflags, parse_defines, render_defines, Define},
+    format_version::{file_format_version, FormatVersion},
+    params::BuildParameters,
+};
+
+fn define(name: &str, value: Option<&str>) -> Define {
+    Define { name: name.to_string(), value: value.map(str::to_string) }
+}
+
+fn v2_defines(defines: &[Define]) -> String {
+    FormatVersion::V2.header("//") + &render_defines(&FormatVersion::V2.order_defines(defines))
+}
+
+#[test]
+fn version_is_read_from_the_header() {
+    assert_eq!(file_format_version("#define HAVE_BAGL\n"), Ok(FormatVersion::V1));
+    assert_eq!(file_format_version("// format: 2\n#define HAVE_BAGL\n"), Ok(FormatVersion::V2));
+    assert_eq!(file_format_version("# format: 2\n-Oz\n"), Ok(FormatVersion::V2));
+    assert_eq!(file_format_version("// format: 1\n"), Ok(FormatVersion::V1));
+    assert!(file_format_version("// format: 3\n").unwrap_err().contains("Unsupported format version 3"));
+    assert_eq!(FormatVersion::V1.header("#"), "");
+}
+
+#[test]
+fn version_two_sorts_the_defines() {
+    let defines = [define("TARGET_STAX", None), define("API_LEVEL", Some("22")), define("HAVE_NBGL", None)];
+    assert_eq!(v2_defines(&defines), "// format: 2\n#define API_LEVEL 22\n#define HAVE_NBGL\n#define TARGET_STAX\n");
+    assert_eq!(FormatVersion::V1.order_defines(&defines), defines);
+}
+
+#[test]
+fn version_one_references_match_version_two_outputs() {
+    let defines = vec![define("TARGET_STAX", None), define("API_LEVEL", Some("22"))];
+    let reference = BuildParameters {
+        defines: parse_defines(&render_defines(&defines)).unwrap(),
+        cflags: parse_cflags("-Oz\n-mthumb\n"),
+        ..Default::default()
+    };
+    let current = BuildParameters {
+        defines: parse_defines(&v2_defines(&defines)).unwrap(),
+        cflags: parse_cflags(&(FormatVersion::V2.header("#") + "-Oz\n-mthumb\n")),
+        ..Default::default()
+    };
+    assert!(compare(&reference, &current, &CompareOptions::default()).is_empty());
+}
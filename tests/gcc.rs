@@ -0,0 +1,36 @@
+use cbpx::{
+    rust_target::rust_target,
+    trace::{find_compile_lines, line_toolchain, Toolchain},
+    unit::parse_compile_unit,
+};
+
+const TRACE: &str = include_str!("fixtures/gcc_trace.txt");
+
+#[test]
+fn gcc_compile_lines_are_found() {
+    let lines = find_compile_lines(TRACE.as_bytes(), false).unwrap();
+    assert_eq!(lines.len(), 2, "the link line is not a compile line");
+    assert!(lines.iter().all(|l| line_toolchain(l) == Some(Toolchain::Gcc)));
+}
+
+#[test]
+fn gcc_compile_line_is_parsed() {
+    let lines = find_compile_lines(TRACE.as_bytes(), false).unwrap();
+    let unit = parse_compile_unit(&lines[1]);
+    assert_eq!(unit.source.as_deref(), Some("/sdk/src/os_io.c"));
+    assert_eq!(unit.object.as_deref(), Some("obj/os_io.o"));
+    assert_eq!(
+        unit.defines.iter().map(|d| d.name.as_str()).collect::<Vec<_>>(),
+        vec!["HAVE_BOLOS_APP_STACK_CANARY", "TARGET_NANOS", "APPNAME"]
+    );
+    assert!(unit.cflags.contains(&"-mno-unaligned-access".to_string()));
+    assert_eq!(rust_target(&unit.cflags), "thumbv6m-none-eabi");
+}
+
+#[test]
+fn clang_lines_keep_their_toolchain() {
+    let trace = b"clang -c -Oz -o a.o a.c\nccache clang-15 -c -o b.o b.c\n";
+    let lines = find_compile_lines(trace, false).unwrap();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(line_toolchain(&lines[1]), Some(Toolchain::Clang));
+}
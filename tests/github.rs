@@ -0,0 +1,13 @@
+use cbpx::github::{format_output, output_name};
+
+#[test]
+fn single_line_values_are_plain_pairs() {
+    assert_eq!(format_output("api_level", "22"), "api_level=22\n");
+    assert_eq!(output_name("app-boilerplate stax DEBUG=1"), "app-boilerplate_stax_DEBUG_1");
+}
+
+#[test]
+fn multi_line_values_use_a_heredoc_not_found_in_the_value() {
+    assert_eq!(format_output("notes", "a\nb\n"), "notes<<CBPX_EOF\na\nb\nCBPX_EOF\n");
+    assert_eq!(format_output("notes", "CBPX_EOF\nb"), "notes<<CBPX_EOF_\nCBPX_EOF\nb\nCBPX_EOF_\n");
+}
@@ -0,0 +1,41 @@
+use std::{env, fs, path::Path, sync::Arc};
+
+use cbpx::{
+    backend::SavedTrace,
+    device::find_device,
+    extract::{extract, ExtractOptions},
+};
+
+// Serialized parameters of a fixture trace, compared with the committed
+// `<trace>.expected.json`. Set UPDATE_GOLDEN=1 to rewrite the expected files
+// after an intentional schema change, bumping SCHEMA_VERSION.
+fn check_golden(trace: &str) {
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let mut options = ExtractOptions::new("/sdk");
+    options.backend = Arc::new(SavedTrace { path: fixtures.join(trace) });
+    let mut params = extract(find_device("stax").unwrap(), &fixtures.join("app"), &options).unwrap();
+    // The compiler is looked up on this host
+    params.metadata.compiler = params.metadata.compiler.map(|mut c| {
+        c.resolved_path = None;
+        c.version = None;
+        c
+    });
+    let json = params.to_json();
+
+    let expected_path = fixtures.join(trace).with_extension("expected.json");
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&expected_path, &json).unwrap();
+    }
+    let expected = fs::read_to_string(&expected_path).unwrap();
+    assert_eq!(json, expected, "{} differs from the serialized parameters", expected_path.display());
+}
+
+#[test]
+fn gcc_trace_serializes_as_expected() {
+    check_golden("gcc_trace.txt");
+}
+
+#[test]
+fn interleaved_trace_serializes_as_expected() {
+    check_golden("interleaved_trace.txt");
+}
@@ -0,0 +1,24 @@
+use std::collections::BTreeMap;
+
+use cbpx::hermetic::{hermetic_env, leaked_variables};
+
+fn lookup<'a>(vars: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<String> + 'a {
+    |name| vars.iter().find(|(n, _)| *n == name).map(|(_, v)| v.to_string())
+}
+
+#[test]
+fn hermetic_environment_keeps_the_allowed_variables() {
+    let vars = [("PATH", "/usr/bin"), ("CFLAGS", "-O0"), ("CCACHE_DIR", "/cache"), ("TARGET", "nanox")];
+    let env = hermetic_env(&["CCACHE_DIR".to_string(), "UNSET".to_string()], lookup(&vars));
+    let expected = [("CCACHE_DIR", "/cache"), ("PATH", "/usr/bin")].map(|(n, v)| (n.to_string(), v.to_string()));
+    assert_eq!(env, BTreeMap::from(expected));
+}
+
+#[test]
+fn inherited_flags_found_verbatim_are_reported() {
+    let lines = ["clang -c -DLEAK -O0 -Wall -o a.o a.c".to_string()];
+    let vars = [("CFLAGS", "-DLEAK -O0"), ("CPPFLAGS", "-Wall -O0")];
+    assert_eq!(leaked_variables(&lines, lookup(&vars)), [("CFLAGS".to_string(), "-DLEAK -O0".to_string())]);
+    // Empty values and partial matches are not reported
+    assert!(leaked_variables(&lines, lookup(&[("CFLAGS", " "), ("CPPFLAGS", "-DLEAK=1")])).is_empty());
+}
@@ -0,0 +1,34 @@
+use std::{fs, path::Path, process::Command};
+
+#[test]
+fn post_hooks_run_in_order_and_fail_the_run() {
+    let root = std::env::temp_dir().join(format!("cbpx-hooks-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    let app = root.join("app");
+    fs::create_dir_all(&app).unwrap();
+    // SDK with just the layout checked before running make
+    for dir in ["sdk/target/stax", "sdk/lib_nbgl"] {
+        fs::create_dir_all(root.join(dir)).unwrap();
+    }
+    for file in ["sdk/Makefile.defines", "sdk/Makefile.rules"] {
+        fs::write(root.join(file), "").unwrap();
+    }
+    fs::copy(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/app/Makefile"), app.join("Makefile")).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_cbpx"))
+        .args(["--app-path", "app", "--device", "stax", "--reference-dir", "refs", "--update-references", "--no-stamp"])
+        .args(["--post-hook", "echo $CSDK_DEVICE $CSDK_MATCH $CSDK_OUTPUT_DIR; echo done >&2"])
+        .args(["--post-hook", "exit 4", "--post-hook", "echo never"])
+        .current_dir(&root)
+        .env("STAX_SDK", root.join("sdk"))
+        .env_remove("BOLOS_SDK")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let (stdout, stderr) = (String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+    assert!(stdout.contains(&format!("[hook 1] stax true {}\n", root.display())), "{}", stdout);
+    assert!(stderr.contains("[hook 1] done\n"), "{}", stderr);
+    assert!(stderr.contains("Post hook `exit 4` failed"), "{}", stderr);
+    assert!(!stdout.contains("never"), "{}", stdout);
+    fs::remove_dir_all(&root).unwrap();
+}
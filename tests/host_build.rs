@@ -0,0 +1,44 @@
+use std::{fs, path::Path};
+
+use cbpx::{
+    files::Define,
+    host_build::{compile_commands_trace, host_cmake_lists, BuildKind},
+    trace::parse_compile_line,
+};
+
+#[test]
+fn host_build_is_found_in_the_documented_directories() {
+    let app = std::env::temp_dir().join(format!("cbpx-host-build-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&app);
+    fs::create_dir_all(app.join("fuzzing")).unwrap();
+    fs::create_dir_all(app.join("unit-tests")).unwrap();
+    assert!(host_cmake_lists(&app, None).unwrap_err().contains("none of fuzzing/, unit-tests/ has a CMakeLists.txt"));
+    fs::write(app.join("unit-tests/CMakeLists.txt"), "").unwrap();
+    assert_eq!(host_cmake_lists(&app, None).unwrap(), Path::new("unit-tests/CMakeLists.txt"));
+    assert!(host_cmake_lists(&app, Some(Path::new("fuzzing"))).is_err());
+    fs::write(app.join("fuzzing/CMakeLists.txt"), "").unwrap();
+    assert_eq!(host_cmake_lists(&app, None).unwrap(), Path::new("fuzzing/CMakeLists.txt"));
+    fs::remove_dir_all(&app).unwrap();
+    assert_eq!(BuildKind::HostTests.suffix(), "_host");
+}
+
+#[test]
+fn compilation_database_entries_are_read_back_by_the_tokenizer() {
+    let database = r#"[
+        {"directory": "/b", "arguments": ["/usr/bin/cc", "-DUNIT_TEST", "-DNAME=\"a b\"", "-O0", "-c", "-o", "t.o", "/app/t.c"]},
+        {"directory": "/b", "command": "/usr/bin/cc -DFUZZ=1 -c -o f.o /app/f.c"}
+    ]"#;
+    let trace = compile_commands_trace(database).unwrap();
+    let lines = trace.lines().collect::<Vec<_>>();
+    assert_eq!(lines[1], "/usr/bin/cc -DFUZZ=1 -c -o f.o /app/f.c");
+    let (defines, cflags) = parse_compile_line(lines[0]);
+    assert_eq!(
+        defines,
+        [
+            Define { name: "UNIT_TEST".to_string(), value: None },
+            Define { name: "NAME".to_string(), value: Some("'\"a b\"'".to_string()) },
+        ]
+    );
+    assert_eq!(cflags, ["-O0", "-c", "-o"]);
+    assert!(compile_commands_trace(r#"[{"file": "t.c"}]"#).unwrap_err().contains("has no command"));
+}
@@ -0,0 +1,32 @@
+use std::path::Path;
+
+use cbpx::{host_paths::drop_host_paths, portable::PathRoots, unit::parse_compile_unit};
+
+const LINE: &str = "clang -c -Oz --sysroot=\"/usr/lib/arm-none-eabi\" -I/opt/sdk/include -isystem /usr/lib/gcc/arm-none-eabi/include \
+                    -I /usr/lib/arm-none-eabi/include -Isrc -I/home/ci/app/inc -DHAVE_NBGL -o build/main.o src/main.c";
+
+fn roots() -> PathRoots {
+    PathRoots::new(Path::new("/opt/sdk"), Path::new("/home/ci/app"))
+}
+
+#[test]
+fn host_include_injections_are_dropped() {
+    let (line, dropped) = drop_host_paths(LINE, &roots());
+    assert_eq!(
+        dropped,
+        [
+            "--sysroot=\"/usr/lib/arm-none-eabi\"",
+            "-isystem /usr/lib/gcc/arm-none-eabi/include",
+            "-I /usr/lib/arm-none-eabi/include",
+        ]
+    );
+    let unit = parse_compile_unit(&line);
+    assert_eq!(unit.includes, ["/opt/sdk/include", "src", "/home/ci/app/inc"]);
+    assert_eq!(unit.cflags, ["-c", "-Oz", "-o"]);
+}
+
+#[test]
+fn lines_without_host_paths_are_kept() {
+    let line = "clang -c -I/opt/sdk/include -isystem /opt/sdk/lib -o main.o main.c";
+    assert_eq!(drop_host_paths(line, &roots()), (line.to_string(), Vec::new()));
+}
@@ -0,0 +1,23 @@
+use cbpx::{
+    identifier::{is_valid_define_name, unescape_define_name},
+    trace::parse_compile_line,
+};
+
+#[test]
+fn define_names_follow_the_macro_name_grammar() {
+    for name in ["HAVE_NBGL", "_private", "x1", "PRINTF(...)", "PRINTF\\(...\\)", "MAX(a, b)", "LOG(fmt, ...)", "F()"] {
+        assert!(is_valid_define_name(name), "{}", name);
+    }
+    for name in ["", "1ST", "\"APPNAME", "APP-NAME", "A B", "F(", "F(a,)", "F(..., a)", "F(1)", "(a)", "F(a)b"] {
+        assert!(!is_valid_define_name(name), "{}", name);
+    }
+}
+
+#[test]
+fn escaped_define_names_are_unescaped() {
+    assert_eq!(unescape_define_name("PRINTF\\(...\\)"), "PRINTF(...)");
+    assert_eq!(unescape_define_name("HAVE_NBGL"), "HAVE_NBGL");
+    let (defines, _) = parse_compile_line("clang -c -DPRINTF\\(...\\)= -DX -UPRINTF\\(...\\) -DPRINTF\\(...\\)=printf -o a.o a.c");
+    let names = defines.iter().map(|d| (d.name.as_str(), d.value.as_deref())).collect::<Vec<_>>();
+    assert_eq!(names, [("X", None), ("PRINTF(...)", Some("printf"))]);
+}
@@ -0,0 +1,56 @@
+use std::{fs, path::Path, process::Command, thread, time::Duration};
+
+use cbpx::files::write_if_changed;
+
+#[test]
+fn identical_contents_are_not_rewritten() {
+    let path = std::env::temp_dir().join(format!("cbpx-write-if-changed-{}", std::process::id()));
+    let _ = fs::remove_file(&path);
+    assert!(write_if_changed(&path, b"HAVE_X\n").unwrap());
+    assert!(!write_if_changed(&path, b"HAVE_X\n").unwrap());
+    assert!(write_if_changed(&path, b"HAVE_Y\n").unwrap());
+    assert_eq!(fs::read(&path).unwrap(), b"HAVE_Y\n");
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn unchanged_rerun_preserves_the_mtimes() {
+    let root = std::env::temp_dir().join(format!("cbpx-incremental-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    let app = root.join("app");
+    fs::create_dir_all(&app).unwrap();
+    for dir in ["sdk/target/stax", "sdk/lib_nbgl"] {
+        fs::create_dir_all(root.join(dir)).unwrap();
+    }
+    for file in ["sdk/Makefile.defines", "sdk/Makefile.rules"] {
+        fs::write(root.join(file), "").unwrap();
+    }
+    fs::write(
+        app.join("Makefile"),
+        "include $(BOLOS_SDK)/Makefile.defines\n\
+         all:\n\tclang -c -DHAVE_X -Wall -Oz -o build/main.o src/main.c\n",
+    )
+    .unwrap();
+
+    let run = || {
+        let output = Command::new(env!("CARGO_BIN_EXE_cbpx"))
+            .args(["--app-path", "app", "--device", "stax", "--reference-dir", "refs", "--update-references"])
+            .current_dir(&root)
+            .env("STAX_SDK", root.join("sdk"))
+            .env_remove("BOLOS_SDK")
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    };
+    let mtime = |name: &str| fs::metadata(Path::new(&root).join(name)).unwrap().modified().unwrap();
+
+    run();
+    let before = (mtime("c_sdk_build_stax.defines"), mtime("c_sdk_build_stax.cflags"));
+    // Past the mtime granularity of the file system
+    thread::sleep(Duration::from_millis(1100));
+    run();
+    assert_eq!((mtime("c_sdk_build_stax.defines"), mtime("c_sdk_build_stax.cflags")), before);
+    let leftovers = fs::read_dir(&root).unwrap().filter_map(|e| e.ok()).filter(|e| e.file_name().to_string_lossy().ends_with(".tmp"));
+    assert_eq!(leftovers.count(), 0);
+    fs::remove_dir_all(&root).unwrap();
+}
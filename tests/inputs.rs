@@ -0,0 +1,51 @@
+use std::{fs, sync::Arc};
+
+use cbpx::{
+    backend::SavedTrace,
+    device::parse_device,
+    extract::ExtractOptions,
+    inputs::{extract_inputs, rerun_if_changed_lines},
+};
+
+#[test]
+fn the_inputs_follow_the_makefile_includes() {
+    let root = std::env::temp_dir().join(format!("cbpx-inputs-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    let (app, sdk) = (root.join("app"), root.join("sdk"));
+    fs::create_dir_all(&app).unwrap();
+    fs::create_dir_all(sdk.join("target/stax")).unwrap();
+    fs::write(
+        app.join("Makefile"),
+        "include $(BOLOS_SDK)/Makefile.defines\n-include local.mk\n-include missing.mk\ninclude $(OTHER)/x.mk\n",
+    )
+    .unwrap();
+    fs::write(app.join("local.mk"), "include ${BOLOS_SDK}/Makefile.defines\n").unwrap();
+    fs::write(sdk.join("Makefile.defines"), "include $(BOLOS_SDK)/target/$(TARGET)/include.mk\n").unwrap();
+    fs::write(sdk.join("target/stax/include.mk"), "").unwrap();
+    fs::write(sdk.join("Makefile.rules"), "").unwrap();
+
+    let device = parse_device("stax").unwrap();
+    let inputs = extract_inputs(device, &app, &ExtractOptions::new(&sdk));
+    assert_eq!(
+        inputs.makefiles,
+        [
+            app.join("Makefile"),
+            sdk.join("Makefile.defines"),
+            app.join("local.mk"),
+            sdk.join("target/stax/include.mk"),
+            sdk.join("Makefile.rules"),
+        ]
+    );
+    assert_eq!(inputs.trace, None);
+
+    // Offline, the recorded trace is an input too
+    let trace = root.join("stax.trace");
+    let options = ExtractOptions { backend: Arc::new(SavedTrace { path: trace.clone() }), ..ExtractOptions::new(&sdk) };
+    let inputs = extract_inputs(device, &app, &options);
+    assert_eq!(inputs.trace, Some(trace.clone()));
+    let lines = rerun_if_changed_lines(&inputs);
+    assert!(lines.contains(&format!("cargo:rerun-if-changed={}", trace.display())));
+    assert!(lines.contains(&format!("cargo:rerun-if-changed={}", app.join("local.mk").display())));
+    assert!(lines.contains(&"cargo:rerun-if-env-changed=STAX_SDK".to_string()));
+    fs::remove_dir_all(&root).unwrap();
+}
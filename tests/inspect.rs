@@ -0,0 +1,69 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+// Generated files of stax in a directory of their own, as an extraction
+// writes them
+fn setup(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("cbpx-inspect-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let defines = "#define APPNAME \"Boilerplate\"\n#define API_LEVEL 26\n#define HAVE_AES_GCM\n#define HAVE_NBGL\n";
+    fs::write(dir.join("c_sdk_build_stax.defines"), defines).unwrap();
+    fs::write(dir.join("c_sdk_build_stax.cflags"), "-Oz\n-mcpu=cortex-m35p\n-Wall\n").unwrap();
+    dir
+}
+
+fn inspect(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_cbpx")).arg("inspect").args(args).current_dir(dir).output().unwrap()
+}
+
+#[test]
+fn get_prints_a_single_value() {
+    let dir = setup("get");
+    let output = inspect(&dir, &["--device", "stax", "--get", "API_LEVEL"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "26\n");
+
+    // The device is also given by its TARGET, or the file directly. Failing
+    // to load the files exits with 2, unlike an absent define.
+    let output = inspect(&dir, &["stax", "--get", "API_LEVEL"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "26\n");
+    let output = inspect(&dir, &["nanos2", "--get", "API_LEVEL"]);
+    assert_eq!(output.status.code(), Some(2));
+    let output = inspect(&dir, &["c_sdk_build_stax.defines", "--get", "APPNAME"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "\"Boilerplate\"\n");
+
+    // A define without value prints an empty line, an absent one nothing
+    let output = inspect(&dir, &["--device", "stax", "--get", "HAVE_NBGL"]);
+    assert_eq!((output.status.code(), String::from_utf8_lossy(&output.stdout).as_ref()), (Some(0), "\n"));
+    let output = inspect(&dir, &["--device", "stax", "--get", "HAVE_PRINTF"]);
+    assert_eq!((output.status.code(), String::from_utf8_lossy(&output.stdout).as_ref()), (Some(1), ""));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn grep_matches_the_defines_and_cflags() {
+    let dir = setup("grep");
+    let output = inspect(&dir, &["--device", "stax", "--grep", "^HAVE_|^-m"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "HAVE_AES_GCM\nHAVE_NBGL\n-mcpu=cortex-m35p\n");
+
+    let output = inspect(&dir, &["--device", "stax", "--grep", "("]);
+    assert_eq!(output.status.code(), Some(2));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn defines_are_printed_by_category() {
+    let dir = setup("table");
+    let output = inspect(&dir, &["--device", "stax"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("Application:\n  APPNAME"), "{}", stdout);
+    assert!(stdout.contains("\n\nTarget and SDK:\n  API_LEVEL"), "{}", stdout);
+    assert!(stdout.contains("\n\nCflags:\n  -Oz\n  -mcpu=cortex-m35p\n  -Wall\n"), "{}", stdout);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,50 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use cbpx::install::{install_dir, update_index, InstallIndex, InstalledDevice, INDEX_NAME};
+
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("cbpx-install-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn device(files: &[&str]) -> BTreeMap<String, InstalledDevice> {
+    let installed = InstalledDevice { files: files.iter().map(|f| f.to_string()).collect(), ..Default::default() };
+    BTreeMap::from([("stax".to_string(), installed)])
+}
+
+#[test]
+fn install_directories_stay_inside_the_app() {
+    let app = temp_dir("dirs");
+    assert_eq!(install_dir(&app, Path::new("params/sdk")).unwrap(), app.join("params/sdk"));
+    for relative in ["../params", "/tmp/params", ".", ""] {
+        assert!(install_dir(&app, Path::new(relative)).is_err(), "{}", relative);
+    }
+    fs::remove_dir_all(&app).unwrap();
+}
+
+#[test]
+fn stale_outputs_are_removed_from_the_install_directory_only() {
+    let dir = temp_dir("index");
+    for file in ["a.defines", "a.cflags", "b.defines"] {
+        fs::write(dir.join(file), "").unwrap();
+    }
+    update_index(&dir, "cbpx test", 1, device(&["a.defines", "a.cflags"])).unwrap();
+    let removed = update_index(&dir, "cbpx test", 1, device(&["a.defines", "b.defines"])).unwrap();
+    assert_eq!(removed, [dir.join("a.cflags")]);
+    assert!(dir.join("a.defines").is_file() && dir.join("b.defines").is_file());
+    let index: InstallIndex = serde_json::from_str(&fs::read_to_string(dir.join(INDEX_NAME)).unwrap()).unwrap();
+    assert_eq!(index.devices["stax"].files, ["a.defines", "b.defines"]);
+
+    // An index edited to name a file outside of the directory is refused
+    let outside = dir.parent().unwrap().join(format!("cbpx-install-outside-{}", std::process::id()));
+    fs::write(&outside, "").unwrap();
+    let name = format!("../{}", outside.file_name().unwrap().to_string_lossy());
+    update_index(&dir, "cbpx test", 1, device(&[&name])).unwrap();
+    let error = update_index(&dir, "cbpx test", 1, device(&["a.defines"])).unwrap_err();
+    assert!(error.starts_with("Refusing to delete"), "{}", error);
+    assert!(outside.is_file());
+    fs::remove_file(&outside).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
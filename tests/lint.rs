@@ -0,0 +1,54 @@
+use cbpx::{
+    files::Define,
+    lint::{lint, parse_policy, Violation, ViolationKind},
+    params::BuildParameters,
+};
+
+const POLICY: &str = r#"
+# Features the app code assumes
+required = ["HAVE_AES_GCM", "HAVE_SECP256K1_CURVE"]
+forbidden = ["HAVE_PRINTF"]
+
+[devices.nanos2]
+required = [
+    "HAVE_BAGL", # the Nano UI
+]
+"#;
+
+fn params(device: &str, defines: &[&str]) -> BuildParameters {
+    let defines = defines.iter().map(|name| Define { name: name.to_string(), value: None }).collect();
+    BuildParameters { device: device.to_string(), defines, ..Default::default() }
+}
+
+#[test]
+fn policies_have_common_and_device_tables() {
+    let policy = parse_policy(POLICY).unwrap();
+    assert_eq!(policy.required, ["HAVE_AES_GCM", "HAVE_SECP256K1_CURVE"]);
+    // Device tables are keyed by marketing name
+    assert_eq!(policy.devices["nanosplus"].required, ["HAVE_BAGL"]);
+    assert_eq!(policy.for_device("nanosplus").required.len(), 3);
+    assert_eq!(policy.for_device("stax").required.len(), 2);
+
+    assert!(parse_policy("[devices.nanos]\n").is_err_and(|e| e.starts_with("Unsupported device nanos")));
+    assert!(parse_policy("optional = [\"X\"]\n").is_err_and(|e| e.contains("unknown field `optional`")));
+}
+
+#[test]
+fn violations_are_reported_per_device() {
+    let policy = parse_policy(POLICY).unwrap();
+    let violation = |device: &str, define: &str, kind| Violation { device: device.to_string(), define: define.to_string(), kind };
+
+    let stax = params("stax", &["HAVE_AES_GCM", "HAVE_SECP256K1_CURVE", "HAVE_BAGL"]);
+    assert!(lint(&policy, &stax).is_empty());
+
+    let nanosplus = params("nanosplus", &["HAVE_AES_GCM", "HAVE_PRINTF"]);
+    assert_eq!(
+        lint(&policy, &nanosplus),
+        [
+            violation("nanosplus", "HAVE_SECP256K1_CURVE", ViolationKind::Missing),
+            violation("nanosplus", "HAVE_BAGL", ViolationKind::Missing),
+            violation("nanosplus", "HAVE_PRINTF", ViolationKind::Forbidden),
+        ]
+    );
+    assert_eq!(lint(&policy, &nanosplus)[2].to_string(), "nanosplus: forbidden define HAVE_PRINTF is defined");
+}
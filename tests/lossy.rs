@@ -0,0 +1,49 @@
+use std::{fs, path::Path, sync::Arc};
+
+use cbpx::{
+    backend::SavedTrace,
+    device::find_device,
+    extract::{extract, ExtractError, ExtractOptions},
+    trace::find_compile_lines,
+    unit::parse_compile_unit,
+};
+
+fn trace() -> Vec<u8> {
+    fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/invalid_utf8.trace")).unwrap()
+}
+
+#[test]
+fn invalid_utf8_in_a_define_is_an_error() {
+    let error = find_compile_lines(&trace(), false).unwrap_err();
+    assert_eq!(
+        error,
+        "Invalid UTF-8 in compile line 2 of the make trace at byte offset 28 (use --lossy to replace invalid bytes)"
+    );
+}
+
+#[test]
+fn lossy_replaces_the_invalid_bytes() {
+    let lines = find_compile_lines(&trace(), true).unwrap();
+    assert_eq!(lines.len(), 2);
+    let unit = parse_compile_unit(&lines[0]);
+    let appname = unit.defines.iter().find(|d| d.name == "APPNAME").unwrap();
+    assert_eq!(appname.value.as_deref(), Some("\\\"Caf\u{fffd}\\\""));
+    assert!(unit.defines.iter().any(|d| d.name == "HAVE_NBGL"));
+}
+
+#[test]
+fn extraction_fails_unless_lossy() {
+    let mut options = ExtractOptions::new("/sdk");
+    options.backend = Arc::new(SavedTrace { path: Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/invalid_utf8.trace") });
+    let app = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/app");
+    let device = find_device("stax").unwrap();
+    match extract(device, &app, &options) {
+        Err(ExtractError::Trace(message)) => assert!(message.contains("compile line 2"), "{}", message),
+        other => panic!("unexpected result {:?}", other.map(|p| p.defines)),
+    }
+
+    options.lossy = true;
+    let params = extract(device, &app, &options).unwrap();
+    let appname = params.defines.iter().find(|d| d.name == "APPNAME").unwrap();
+    assert_eq!(appname.value.as_deref(), Some("\"Caf\u{fffd}\""));
+}
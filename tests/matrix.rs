@@ -0,0 +1,74 @@
+use std::{fs, process::Command};
+
+use cbpx::matrix::{cells, parse_axis};
+
+#[test]
+fn cells_combine_every_axis_the_default_first() {
+    let axes = [parse_axis("DEBUG=0,1").unwrap(), parse_axis("SCREEN=a,b").unwrap()];
+    let cells = cells(&axes);
+    let labels = cells.iter().map(|c| (c.label(), c.suffix(), c.is_default)).collect::<Vec<_>>();
+    assert_eq!(labels[0], ("DEBUG=0,SCREEN=a".to_string(), "_DEBUG-0_SCREEN-a".to_string(), true));
+    assert_eq!(labels.len(), 4);
+    assert!(labels[1..].iter().all(|(_, _, is_default)| !is_default));
+
+    assert!(parse_axis("DEBUG").is_err());
+    assert!(parse_axis("DEBUG=0,").is_err());
+}
+
+// The app enables PRINTF in debug builds, and is built against an SDK with
+// just the layout checked before running make
+#[test]
+fn debug_matrix_suffixes_the_outputs_and_diffs_the_cells() {
+    let root = std::env::temp_dir().join(format!("cbpx-matrix-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    for dir in ["app", "sdk/target/stax", "sdk/lib_nbgl"] {
+        fs::create_dir_all(root.join(dir)).unwrap();
+    }
+    for file in ["sdk/Makefile.defines", "sdk/Makefile.rules"] {
+        fs::write(root.join(file), "").unwrap();
+    }
+    fs::write(
+        root.join("app/Makefile"),
+        "include $(BOLOS_SDK)/Makefile.defines\n\
+         DEBUG ?= 0\n\
+         ifneq ($(DEBUG),0)\nDEBUG_DEFINES = -DHAVE_PRINTF\nendif\n\
+         all:\n\tclang -c -DHAVE_X $(DEBUG_DEFINES) -Wall -o build/main.o src/main.c\n",
+    )
+    .unwrap();
+
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_cbpx"))
+            .args(["--app-path", "app", "--device", "stax", "--reference-dir", "refs"])
+            .args(args)
+            .current_dir(&root)
+            .env("STAX_SDK", root.join("sdk"))
+            .env_remove("BOLOS_SDK")
+            .output()
+            .unwrap()
+    };
+    // References of the default build only, which the debug cell is not
+    // compared with
+    let output = run(&["--output-dir", "plain", "--update-references"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let output = run(&["--output-dir", "out", "--matrix", "DEBUG=0,1"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("stax DEBUG=1 vs DEBUG=0: 1 difference(s)\n  + define HAVE_PRINTF\n"), "{}", stdout);
+    let defines = |name: &str| fs::read_to_string(root.join("out").join(name)).unwrap();
+    assert_eq!(defines("c_sdk_build_stax_DEBUG-0.defines"), "#define HAVE_X\n");
+    assert_eq!(defines("c_sdk_build_stax_DEBUG-1.defines"), "#define HAVE_X\n#define HAVE_PRINTF\n");
+
+    // The default cell is compared with the plain references
+    fs::write(root.join("refs/c_sdk_build_stax.defines"), "#define HAVE_Y\n").unwrap();
+    let output = run(&["--output-dir", "out", "--matrix", "DEBUG=0,1"]);
+    assert_eq!(output.status.code(), Some(1));
+
+    // Once every cell has its references, each is compared with its own
+    let output = run(&["--output-dir", "out", "--matrix", "DEBUG=0,1", "--update-references"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(root.join("refs/c_sdk_build_stax_DEBUG-1.defines").is_file());
+    let output = run(&["--output-dir", "out", "--matrix", "DEBUG=1,0"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    fs::remove_dir_all(&root).unwrap();
+}
@@ -0,0 +1,50 @@
+use std::path::Path;
+
+use cbpx::{
+    files::Define,
+    meson::{meson_string, render_meson, MachineFile},
+    rust_target::{host_machine, HostMachine},
+};
+
+fn flags(flags: &[&str]) -> Vec<String> {
+    flags.iter().map(|f| f.to_string()).collect()
+}
+
+#[test]
+fn host_machine_follows_the_arch_flags() {
+    let host = host_machine(&flags(&["--target=arm-none-eabi", "-mcpu=cortex-m35p+nodsp", "-mthumb"]));
+    assert_eq!(host, Some(HostMachine { cpu_family: "arm", cpu: "cortex-m35p", endian: "little" }));
+    let host = host_machine(&flags(&["-mcpu=cortex-m3", "-mlittle-endian", "-mbig-endian"]));
+    assert_eq!(host.unwrap().endian, "big");
+    assert_eq!(host_machine(&flags(&["-mcpu=cortex-a53"])), None);
+}
+
+#[test]
+fn machine_file_quotes_the_c_args() {
+    assert_eq!(meson_string(r"it's a \ path"), r"'it\'s a \\ path'");
+    let defines = [
+        Define { name: "HAVE_NBGL".to_string(), value: None },
+        Define { name: "APPNAME".to_string(), value: Some("\"Boil'\"".to_string()) },
+    ];
+    let cflags = flags(&["-c", "-Oz", "--target=arm-none-eabi", "-mcpu=cortex-m35p+nodsp", "-mthumb", "-Wall", "-fropi"]);
+    let machine = MachineFile {
+        defines: &defines,
+        cflags: &cflags,
+        codegen: &flags(&["-fropi"]),
+        includes: &flags(&["${BOLOS_SDK}/include", "src", "/usr/include/extra"]),
+        compiler: "/opt/clang/bin/clang",
+        sdk: Path::new("/sdk"),
+        app: Path::new("/app"),
+        make_dir: Path::new("/app"),
+    };
+    assert_eq!(
+        render_meson(&machine),
+        "[constants]\nbolos_sdk = '/sdk'\napp_dir = '/app'\n\n\
+         [binaries]\nc = '/opt/clang/bin/clang'\n\n\
+         [built-in options]\nc_args = [\n    \
+         '-DHAVE_NBGL',\n    '-DAPPNAME=\"Boil\\'\"',\n    \
+         '--target=arm-none-eabi',\n    '-mcpu=cortex-m35p+nodsp',\n    '-mthumb',\n    '-fropi',\n    \
+         '-I' + bolos_sdk + '/include',\n    '-I' + app_dir + '/src',\n    '-I/usr/include/extra',\n    ]\n\n\
+         [host_machine]\nsystem = 'none'\ncpu_family = 'arm'\ncpu = 'cortex-m35p'\nendian = 'little'\n"
+    );
+}
@@ -0,0 +1,49 @@
+use std::path::Path;
+
+use cbpx::{
+    migrate::{migrate_cflags, migrate_defines, Normalizations},
+    portable::PathRoots,
+};
+
+#[test]
+fn defines_are_sorted_keeping_the_comments() {
+    let sort = Normalizations { sort: true, ..Default::default() };
+    let legacy = "// Escaped for shell by cbpx\n#define TARGET_STAX\n#define API_LEVEL 22\n#define EMPTY \n// X: -DX -UX, undefined in the end\n";
+    let migrated = migrate_defines(legacy, &sort).unwrap();
+    assert_eq!(
+        migrated.contents,
+        "// Escaped for shell by cbpx\n#define API_LEVEL 22\n#define EMPTY \n#define TARGET_STAX\n// X: -DX -UX, undefined in the end\n"
+    );
+    assert_eq!(migrated.changes, ["sorted 3 define(s)"]);
+    // Migrating again changes nothing
+    let again = migrate_defines(&migrated.contents, &sort).unwrap();
+    assert_eq!(again.contents, migrated.contents);
+    assert!(again.changes.is_empty());
+}
+
+#[test]
+fn absolute_paths_are_made_portable() {
+    let portable = Normalizations { roots: Some(PathRoots::new(Path::new("/opt/sdk"), Path::new(""))), ..Default::default() };
+    let defines = migrate_defines("#define ICON /opt/sdk/icons/app.gif\n#define NAME app\n", &portable).unwrap();
+    assert_eq!(defines.contents, "#define ICON ${BOLOS_SDK}/icons/app.gif\n#define NAME app\n");
+    let cflags = migrate_cflags("-Oz\n--sysroot=/opt/sdk/sysroot\n/opt/sdk/lib.a\n", &portable).unwrap();
+    assert_eq!(cflags.contents, "-Oz\n--sysroot=${BOLOS_SDK}/sysroot\n${BOLOS_SDK}/lib.a\n");
+    assert_eq!(cflags.changes, ["made 2 cflag(s) portable"]);
+}
+
+#[test]
+fn files_not_read_back_identically_are_refused() {
+    let sort = Normalizations { sort: true, ..Default::default() };
+    assert!(migrate_defines("#define A 1\n// note\n#define B\n", &sort).unwrap_err().contains("from line 2"));
+    assert!(migrate_defines("#define A 1\n\n#define B\n", &sort).is_err());
+    assert!(migrate_defines("#ifndef A\n", &sort).unwrap_err().contains("expected `#define NAME [VALUE]`"));
+    assert!(migrate_cflags("-Oz\n# comment\n-g\n", &sort).is_err());
+}
+
+#[test]
+fn escaped_define_names_are_unescaped() {
+    let values = Normalizations { values: true, ..Default::default() };
+    let migrated = migrate_defines("#define APPNAME \\\"\"Boilerplate\"\\\"\n#define PRINTF\\(...\\) \n", &values).unwrap();
+    assert_eq!(migrated.contents, "#define APPNAME \"Boilerplate\"\n#define PRINTF(...) \n");
+    assert_eq!(migrated.changes, ["normalized the quoting of 1 define value(s)", "unescaped 1 define name(s)"]);
+}
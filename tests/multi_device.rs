@@ -0,0 +1,108 @@
+use std::{fs, process::Command};
+
+use cbpx::{
+    combined::combined_header,
+    files::Define,
+    multi_device::{DeviceValue, MultiDeviceParameters},
+    params::BuildParameters,
+};
+
+fn define(name: &str, value: Option<&str>) -> Define {
+    Define { name: name.to_string(), value: value.map(str::to_string) }
+}
+
+fn device(name: &str, target_name: &str, defines: &[Define]) -> (String, BuildParameters) {
+    let mut defines = defines.to_vec();
+    defines.push(define("TARGET_NAME", Some(&format!("\\\"{}\\\"", target_name))));
+    (name.to_string(), BuildParameters { device: name.to_string(), defines, ..Default::default() })
+}
+
+fn merged() -> MultiDeviceParameters {
+    [
+        device("stax", "TARGET_STAX", &[define("HAVE_NBGL", None), define("SCREEN_HEIGHT", Some("672")), define("HAVE_SE_TOUCH", None)]),
+        device("flex", "TARGET_FLEX", &[define("HAVE_NBGL", None), define("SCREEN_HEIGHT", Some("600"))]),
+    ]
+    .into_iter()
+    .collect()
+}
+
+#[test]
+fn defines_valued_differently_are_device_specific() {
+    let merged = merged();
+    assert_eq!(merged.device_names(), ["stax", "flex"]);
+    assert_eq!(merged.common_defines(), [define("HAVE_NBGL", None)]);
+    assert_eq!(
+        merged.device_specific("stax")[..2],
+        [define("SCREEN_HEIGHT", Some("672")), define("HAVE_SE_TOUCH", None)]
+    );
+    assert_eq!(merged.device_specific("flex")[0], define("SCREEN_HEIGHT", Some("600")));
+    assert!(merged.device_specific("nanos").is_empty());
+
+    let matrix = merged.matrix();
+    let value = |device: &str, value: Option<&str>| DeviceValue { device: device.to_string(), value: value.map(str::to_string) };
+    assert_eq!(matrix["SCREEN_HEIGHT"].iter().collect::<Vec<_>>(), [&value("flex", Some("600")), &value("stax", Some("672"))]);
+    assert_eq!(matrix["HAVE_SE_TOUCH"].iter().collect::<Vec<_>>(), [&value("stax", None)]);
+    assert_eq!(matrix["HAVE_NBGL"].len(), 2);
+
+    let json = serde_json::to_value(&matrix).unwrap();
+    assert_eq!(json["SCREEN_HEIGHT"][1], serde_json::json!({"device": "stax", "value": "672"}));
+}
+
+#[test]
+fn combined_header_guards_the_device_specific_defines() {
+    let header = combined_header(&merged()).unwrap();
+    assert!(header.contains("// Common to stax, flex\n#define HAVE_NBGL\n\n#if defined(TARGET_STAX)\n#define SCREEN_HEIGHT 672\n"), "{}", header);
+    assert!(header.contains("#if defined(TARGET_FLEX)\n#define SCREEN_HEIGHT 600\n"), "{}", header);
+}
+
+// Every device built against one SDK with just the layout checked before
+// running make, the app giving stax a taller screen
+#[test]
+fn all_devices_run_writes_the_combined_header() {
+    let root = std::env::temp_dir().join(format!("cbpx-combined-header-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    for dir in ["app", "sdk/lib_nbgl"] {
+        fs::create_dir_all(root.join(dir)).unwrap();
+    }
+    for target in ["nanox", "nanos2", "stax", "flex", "apex_p"] {
+        fs::create_dir_all(root.join("sdk/target").join(target)).unwrap();
+    }
+    for file in ["sdk/Makefile.defines", "sdk/Makefile.rules"] {
+        fs::write(root.join(file), "").unwrap();
+    }
+    let makefile = "include $(BOLOS_SDK)/Makefile.defines\n\
+                    TARGET_NAME = TARGET_$(shell echo $(TARGET) | tr a-z A-Z)\n\
+                    ifeq ($(TARGET),stax)\nHEIGHT = 672\nelse\nHEIGHT = 600\nendif\n\
+                    all:\n\tclang -c -DHAVE_NBGL -DTARGET_NAME=\\\"$(TARGET_NAME)\\\" -DSCREEN_HEIGHT=$(HEIGHT) -o main.o main.c\n";
+    fs::write(root.join("app/Makefile"), makefile).unwrap();
+
+    let run = || {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_cbpx"));
+        command.args(["--app-path", "app", "--all-devices", "--output-dir", "out", "--reference-dir", "refs"]);
+        command.args(["--update-references", "--emit-combined-header", "combined.h"]).current_dir(&root);
+        for var in ["NANOX_SDK", "NANOSP_SDK", "STAX_SDK", "FLEX_SDK", "APEX_P_SDK"] {
+            command.env(var, root.join("sdk"));
+        }
+        command.env_remove("BOLOS_SDK").output().unwrap()
+    };
+    let output = run();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let header = fs::read_to_string(root.join("combined.h")).unwrap();
+    assert!(header.contains("// Common to nanox, nanosplus, stax, flex, apex_p\n#define HAVE_NBGL\n\n"), "{}", header);
+    assert!(
+        header.contains("#if defined(TARGET_STAX)\n#define TARGET_NAME \"TARGET_STAX\"\n#define SCREEN_HEIGHT 672\n#endif // TARGET_STAX\n"),
+        "{}",
+        header
+    );
+    assert!(header.contains("#if defined(TARGET_NANOS2)\n#define TARGET_NAME \"TARGET_NANOS2\"\n#define SCREEN_HEIGHT 600\n"), "{}", header);
+    assert_eq!(header.matches("#if defined(").count(), 5);
+
+    // A device without TARGET_NAME has no guard
+    fs::write(root.join("app/Makefile"), makefile.replace("-DTARGET_NAME=\\\"$(TARGET_NAME)\\\" ", "")).unwrap();
+    let output = run();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("TARGET_NAME not found for device nanox, cannot build its guard"), "{}", stderr);
+    assert_eq!(fs::read_to_string(root.join("combined.h")).unwrap(), header);
+    fs::remove_dir_all(&root).unwrap();
+}
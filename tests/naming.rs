@@ -0,0 +1,67 @@
+use std::{fs, process::Command};
+
+use cbpx::naming::{NameContext, NameTemplate};
+
+fn context<'a>(app: &'a str) -> NameContext<'a> {
+    NameContext { app, device: "stax", variant: "debug", api_level: "26", toolchain: "clang" }
+}
+
+#[test]
+fn templates_are_validated() {
+    assert!(NameTemplate::parse("c_sdk_build_{device}").is_ok());
+    assert!(NameTemplate::parse("{app}_{device}_{variant}_{api_level}_{toolchain}").is_ok());
+    let error = |template: &str| NameTemplate::parse(template).unwrap_err();
+    assert_eq!(error("out/{device}"), "Name template `out/{device}` cannot contain path separators");
+    assert_eq!(error("out\\{device}"), "Name template `out\\{device}` cannot contain path separators");
+    assert_eq!(error("c_sdk_{device"), "Unclosed placeholder in name template `c_sdk_{device`");
+    assert!(error("{target}").starts_with("Unknown placeholder {target} in name template `{target}`, expected one of {app}"));
+    assert_eq!(error(""), "Name template is empty");
+}
+
+#[test]
+fn values_are_substituted_in_a_single_pass() {
+    let template = NameTemplate::parse("{app}_{device}").unwrap();
+    assert_eq!(template.render(&context("Boilerplate")).unwrap(), "Boilerplate_stax");
+    // A value containing a placeholder is taken literally
+    assert_eq!(template.render(&context("my{device}app")).unwrap(), "my{device}app_stax");
+    assert_eq!(template.render(&context("{variant}")).unwrap(), "{variant}_stax");
+}
+
+#[test]
+fn rendered_names_are_checked() {
+    let template = NameTemplate::parse("{app}").unwrap();
+    assert_eq!(template.render(&context("")).unwrap_err(), "Name template `{app}` produced an empty name");
+    assert_eq!(
+        template.render(&context("a/b")).unwrap_err(),
+        "Name template `{app}` produced `a/b` which contains a path separator"
+    );
+}
+
+#[test]
+fn inspect_finds_the_files_named_after_the_template() {
+    let dir = std::env::temp_dir().join(format!("cbpx-naming-inspect-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("Boilerplate_stax.defines"), "#define API_LEVEL 26\n").unwrap();
+    fs::write(dir.join("Boilerplate_stax.cflags"), "-Oz\n").unwrap();
+    fs::write(dir.join("Boilerplate_flex.defines"), "#define API_LEVEL 25\n").unwrap();
+    let inspect = |template: &str| {
+        Command::new(env!("CARGO_BIN_EXE_cbpx"))
+            .args(["inspect", "--device", "stax", "--get", "API_LEVEL", "--name-template", template])
+            .current_dir(&dir)
+            .output()
+            .unwrap()
+    };
+    let output = inspect("{app}_{device}");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "26\n");
+
+    let output = inspect("c_sdk_build_{device}");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("No defines file of stax named after `c_sdk_build_{device}`"));
+
+    fs::write(dir.join("Other_stax.defines"), "#define API_LEVEL 24\n").unwrap();
+    let output = inspect("{app}_{device}");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Boilerplate_stax, Other_stax"));
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,34 @@
+use std::path::Path;
+
+use cbpx::files::{normalize, read_defines, read_output, Define};
+
+fn fixture(name: &str) -> std::path::PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)
+}
+
+#[test]
+fn line_endings_trailing_spaces_and_newlines_are_normalized() {
+    assert_eq!(normalize("a  \r\nb\rc\t\n\n\n", false), "a\nb\nc\n");
+    assert_eq!(normalize("a", false), "a\n");
+    assert_eq!(normalize("\r\n\n", false), "");
+    assert_eq!(normalize("", false), "");
+}
+
+#[test]
+fn defines_keep_the_space_of_empty_values() {
+    assert_eq!(normalize("#define EMPTY \r\n#define SET\r\n", true), "#define EMPTY \n#define SET\n");
+    let defines = read_defines(&fixture("crlf.defines")).unwrap();
+    assert_eq!(
+        defines,
+        [
+            Define { name: "HAVE_NBGL".to_string(), value: None },
+            Define { name: "API_LEVEL".to_string(), value: Some("22".to_string()) },
+            Define { name: "EMPTY".to_string(), value: Some(String::new()) },
+        ]
+    );
+}
+
+#[test]
+fn crlf_reference_matches_the_written_output() {
+    assert_eq!(read_output(&fixture("crlf.sources")).unwrap(), "src/main.c\nsdk/lib_ux/ux.c\n");
+}
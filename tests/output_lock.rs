@@ -0,0 +1,22 @@
+use std::{cell::Cell, fs, time::Duration};
+
+use cbpx::output_lock::{lock_path, OutputLock};
+
+#[test]
+fn a_second_run_waits_for_the_first_one() {
+    let dir = std::env::temp_dir().join(format!("cbpx-lock-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let first = OutputLock::acquire(&dir, "stax", Duration::ZERO, || panic!("not locked yet")).unwrap();
+    assert_eq!(first.path, lock_path(&dir, "stax"));
+
+    let waited = Cell::new(false);
+    let error = OutputLock::acquire(&dir, "stax", Duration::from_millis(300), || waited.set(true)).unwrap_err();
+    assert!(waited.get());
+    assert!(error.starts_with("Another extraction is in progress for stax in "), "{}", error);
+    // Other devices have their own lock
+    OutputLock::acquire(&dir, "flex", Duration::ZERO, || panic!("flex is not locked")).unwrap();
+
+    drop(first);
+    OutputLock::acquire(&dir, "stax", Duration::ZERO, || panic!("released")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,55 @@
+use cbpx::{
+    files::Define,
+    overrides::{apply_overrides, parse_overrides, Overrides},
+};
+
+fn define(name: &str, value: Option<&str>) -> Define {
+    Define { name: name.to_string(), value: value.map(str::to_string) }
+}
+
+#[test]
+fn overrides_are_parsed_from_toml_arrays() {
+    let overrides = parse_overrides(
+        "# Corrections of the dry run\n\
+         add_defines = [\"HAVE_PHONY\", \"APPNAME=\\\"a, # b\\\"\"]  # from a phony target\n\
+         remove_cflags = [\n    '-I/home/ci/include',\n    \"-g\",\n]\n",
+    )
+    .unwrap();
+    assert_eq!(
+        overrides,
+        Overrides {
+            add_defines: vec![define("HAVE_PHONY", None), define("APPNAME", Some("\"a, # b\""))],
+            remove_cflags: vec!["-I/home/ci/include".to_string(), "-g".to_string()],
+            ..Default::default()
+        }
+    );
+    assert!(parse_overrides("add_flags = [\"-g\"]").unwrap_err().contains("unknown field `add_flags`"));
+    assert!(parse_overrides("add_defines = [\"1ST\"]").unwrap_err() == "add_defines: `1ST` is not a valid macro name");
+}
+
+#[test]
+fn conflicting_overrides_are_reported_not_applied() {
+    let overrides = Overrides {
+        add_defines: vec![define("HAVE_PHONY", None), define("API_LEVEL", Some("23")), define("HAVE_NBGL", None)],
+        remove_defines: vec!["HAVE_DEBUG".to_string(), "HAVE_MISSING".to_string()],
+        add_cflags: vec!["-Oz".to_string()],
+        remove_cflags: vec!["-g".to_string()],
+    };
+    let mut defines = vec![define("HAVE_NBGL", None), define("API_LEVEL", Some("22")), define("HAVE_DEBUG", None)];
+    let mut cflags = vec!["-Oz".to_string(), "-g".to_string()];
+    let applied = apply_overrides(&overrides, &mut defines, &mut cflags);
+    assert_eq!(defines, [define("HAVE_NBGL", None), define("API_LEVEL", Some("23")), define("HAVE_PHONY", None)]);
+    assert_eq!(cflags, ["-Oz"]);
+    assert_eq!(
+        applied.applied,
+        ["removed define HAVE_DEBUG", "removed cflag -g", "added define HAVE_PHONY", "replaced define API_LEVEL=22 with API_LEVEL=23"]
+    );
+    assert_eq!(
+        applied.conflicts,
+        [
+            "remove_defines: HAVE_MISSING is not defined",
+            "add_defines: HAVE_NBGL is already defined",
+            "add_cflags: -Oz is already in the cflags"
+        ]
+    );
+}
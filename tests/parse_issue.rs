@@ -0,0 +1,70 @@
+use std::{fs, path::Path, process::Command, sync::Arc};
+
+use cbpx::{
+    backend::SavedTrace,
+    device::find_device,
+    extract::{extract, ExtractError, ExtractOptions},
+    identifier::OnInvalid,
+    parse_issue::{compile_line_issues, COMPILE_LINE_EXCERPT},
+};
+
+fn fixture(name: &str) -> std::path::PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)
+}
+
+#[test]
+fn issues_quote_the_token_and_a_truncated_line() {
+    let line = format!("clang -c -D -DOK -U -o main.o main.c {}", "-Wall ".repeat(100));
+    let issues = compile_line_issues("stax", 7, &line);
+    assert_eq!(issues.iter().map(|i| i.token.as_str()).collect::<Vec<_>>(), ["-D", "-U"]);
+    assert_eq!((issues[0].device.as_str(), issues[0].trace_line), ("stax", 7));
+    assert_eq!(issues[0].compile_line.chars().count(), COMPILE_LINE_EXCERPT + 3);
+    assert!(issues[0].compile_line.ends_with("..."));
+    assert!(!issues[0].fatal);
+}
+
+#[test]
+fn malformed_defines_are_structured_issues() {
+    let app = fixture("app");
+    let mut options = ExtractOptions::new("/sdk");
+    options.backend = Arc::new(SavedTrace { path: fixture("malformed_defines_trace.txt") });
+    let issues = match extract(find_device("stax").unwrap(), &app, &options) {
+        Err(ExtractError::Parse(issues)) => issues,
+        other => panic!("unexpected result {:?}", other.map(|p| p.defines)),
+    };
+    assert_eq!(issues.len(), 1);
+    let issue = &issues[0];
+    assert_eq!((issue.device.as_str(), issue.trace_line, issue.token.as_str()), ("stax", 2, "-D"));
+    assert!(issue.fatal);
+    assert!(issue.compile_line.starts_with("clang -c -Oz -DHAVE_NBGL -D APPNAME"), "{}", issue.compile_line);
+
+    // Skipped, the issues are kept with the parameters
+    options.on_invalid = OnInvalid::Skip;
+    let params = extract(find_device("stax").unwrap(), &app, &options).unwrap();
+    let tokens = params.metadata.parse_issues.iter().map(|i| (i.token.as_str(), i.fatal)).collect::<Vec<_>>();
+    assert_eq!(tokens, [("-D", false), ("-U", false)]);
+}
+
+#[test]
+fn failed_parse_writes_no_output() {
+    let root = std::env::temp_dir().join(format!("cbpx-parse-issue-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("sdk")).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_cbpx"))
+        .args(["--app-path", fixture("app").to_str().unwrap(), "--device", "stax", "--output-dir", "out"])
+        .args(["--trace-file", fixture("malformed_defines_trace.txt").to_str().unwrap()])
+        .current_dir(&root)
+        .env("STAX_SDK", root.join("sdk"))
+        .env_remove("BOLOS_SDK")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("`-D` has no macro name, `-D NAME` with a space is not supported (stax trace line 2: clang -c"), "{}", stderr);
+    // The output lock aside, nothing is left in the output directory
+    let written = fs::read_dir(root.join("out"))
+        .map(|entries| entries.filter_map(|e| e.ok()).filter(|e| e.file_name().to_string_lossy().starts_with("c_sdk_build")).count())
+        .unwrap_or(0);
+    assert_eq!(written, 0);
+    fs::remove_dir_all(&root).unwrap();
+}
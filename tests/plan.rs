@@ -0,0 +1,63 @@
+use std::{fs, path::Path, process::Command};
+
+// The plan is printed without running make nor writing anything, and its exit
+// code tells whether it is executable
+#[test]
+fn print_plan_runs_nothing() {
+    let root = std::env::temp_dir().join(format!("cbpx-plan-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    let app = root.join("app");
+    fs::create_dir_all(&app).unwrap();
+    for dir in ["sdk/target/stax", "sdk/lib_nbgl"] {
+        fs::create_dir_all(root.join(dir)).unwrap();
+    }
+    for file in ["sdk/Makefile.defines", "sdk/Makefile.rules"] {
+        fs::write(root.join(file), "").unwrap();
+    }
+    fs::copy(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/app/Makefile"), app.join("Makefile")).unwrap();
+    let (root, app) = (root.canonicalize().unwrap(), app.canonicalize().unwrap());
+
+    let plan = |devices: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_cbpx"))
+            .args(["--app-path", "app", "--output-dir", "out", "--reference-dir", "refs", "--print-plan"])
+            .args(devices.iter().flat_map(|d| ["--device", d]))
+            .current_dir(&root)
+            .env("STAX_SDK", root.join("sdk"))
+            .env_remove("NANOX_SDK")
+            .env_remove("BOLOS_SDK")
+            // No make to run
+            .env("PATH", "")
+            .output()
+            .unwrap()
+    };
+    let output = plan(&["stax"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let (sdk, out, refs) = (root.join("sdk"), root.join("out"), root.join("refs"));
+    let expected = [
+        format!("App path:    {}\n", app.display()),
+        "Device:      stax\nTARGET:      stax\n".to_string(),
+        format!("BOLOS_SDK:   {} (exists)\n", sdk.display()),
+        format!(
+            "Command:     TARGET=stax BOLOS_SDK={} make -C {} -f Makefile --trace --dry-run -j1\n",
+            sdk.display(),
+            app.display()
+        ),
+        format!("Outputs:\n  {}\n", out.join("c_sdk_build_stax.defines").display()),
+        format!("References:\n  {}\n", refs.join("c_sdk_build_stax.defines").display()),
+    ];
+    for line in &expected {
+        assert!(stdout.contains(line.as_str()), "{}\nnot in\n{}", line, stdout);
+    }
+    assert!(!out.exists());
+
+    // A device without SDK makes the plan invalid, the other devices still
+    // being planned
+    let output = plan(&["stax", "nanox"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Device:      stax\n"), "{}", stdout);
+    assert!(stdout.contains("Problem:     NANOX_SDK is not set (required for device nanox)\n"), "{}", stdout);
+    assert!(!out.exists());
+    fs::remove_dir_all(&root).unwrap();
+}
@@ -0,0 +1,75 @@
+use std::{fs, path::Path, sync::Arc};
+
+use cbpx::{
+    backend::SavedTrace,
+    device::find_device,
+    extract::{extract, ExtractOptions},
+    files::Define,
+    portable::PathRoots,
+};
+
+fn roots() -> PathRoots {
+    PathRoots::new(Path::new("/opt/sdk"), Path::new("/home/ci/app"))
+}
+
+#[test]
+fn path_values_below_the_roots_are_rewritten() {
+    let roots = roots();
+    let make_dir = Path::new("/home/ci/app");
+    let portable = |value: &str| roots.portable_define_value(value, make_dir);
+    assert_eq!(portable("\"/opt/sdk/lib_nbgl/glyphs.h\"").as_deref(), Some("\"${BOLOS_SDK}/lib_nbgl/glyphs.h\""));
+    assert_eq!(portable("/home/ci/app/install_params.bin").as_deref(), Some("${APP_DIR}/install_params.bin"));
+    assert_eq!(portable("/opt/sdk").as_deref(), Some("${BOLOS_SDK}"));
+}
+
+#[test]
+fn values_which_only_look_like_paths_are_kept() {
+    let roots = roots();
+    let make_dir = Path::new("/home/ci/app");
+    for value in ["\"1.2/beta\"", "1.2/3", "\"arm/v7\"", "/usr/include/stdint.h", "/opt/sdkfoo/x.h", "\"/opt/sdk x\"", "1", ""] {
+        assert_eq!(roots.portable_define_value(value, make_dir), None, "{}", value);
+    }
+}
+
+#[test]
+fn relative_values_are_paths_when_they_exist() {
+    let app = std::env::temp_dir().join(format!("cbpx-portable-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&app);
+    fs::create_dir_all(app.join("glyphs")).unwrap();
+    fs::write(app.join("glyphs/icon.gif"), "").unwrap();
+    let roots = PathRoots::new(Path::new("/opt/sdk"), &app);
+    assert_eq!(roots.portable_define_value("\"glyphs/icon.gif\"", &app).as_deref(), Some("\"${APP_DIR}/glyphs/icon.gif\""));
+    assert_eq!(roots.portable_define_value("./glyphs", &app).as_deref(), Some("${APP_DIR}/glyphs"));
+    assert_eq!(roots.portable_define_value("glyphs/missing.gif", &app), None);
+    fs::remove_dir_all(&app).unwrap();
+}
+
+#[test]
+fn path_defines_are_portable_on_request() {
+    let dir = std::env::temp_dir().join(format!("cbpx-portable-trace-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let trace = dir.join("trace.txt");
+    fs::write(
+        &trace,
+        "clang -c -DGLYPHS='\"/sdk/lib_nbgl/glyphs.h\"' -DVERSION='\"1.2/beta\"' -DHAVE_NBGL -o build/main.o src/main.c\n",
+    )
+    .unwrap();
+    let mut options = ExtractOptions::new("/sdk");
+    options.backend = Arc::new(SavedTrace { path: trace });
+    let app = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/app");
+    let device = find_device("stax").unwrap();
+    let value = |defines: &[Define], name: &str| defines.iter().find(|d| d.name == name).and_then(|d| d.value.clone());
+
+    let params = extract(device, &app, &options).unwrap();
+    assert_eq!(value(&params.defines, "GLYPHS").as_deref(), Some("\"/sdk/lib_nbgl/glyphs.h\""));
+    assert!(params.metadata.path_defines.is_empty());
+
+    options.portable_paths = true;
+    let params = extract(device, &app, &options).unwrap();
+    assert_eq!(value(&params.defines, "GLYPHS").as_deref(), Some("\"${BOLOS_SDK}/lib_nbgl/glyphs.h\""));
+    assert_eq!(value(&params.defines, "VERSION").as_deref(), Some("\"1.2/beta\""));
+    assert_eq!(value(&params.metadata.path_defines, "GLYPHS").as_deref(), Some("\"/sdk/lib_nbgl/glyphs.h\""));
+    assert_eq!(params.metadata.path_defines.len(), 1);
+    fs::remove_dir_all(&dir).unwrap();
+}
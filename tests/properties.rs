@@ -0,0 +1,77 @@
+use cbpx::{
+    files::Define,
+    trace::{flag_argument, parse_compile_line, parse_includes, parse_object, parse_source},
+};
+use proptest::prelude::*;
+
+// Round-trip properties of the compile line parser over generated inputs
+const CASES: u32 = 512;
+
+// C identifier
+fn name() -> impl Strategy<Value = String> {
+    "[ABCXYZabc_][ABCXYZabc_0129]{0,12}"
+}
+
+// Shell word as make prints it: unquoted text, escaped characters and quoted
+// segments, which may contain spaces, parentheses and `=`
+fn shell_text(max_segments: usize) -> impl Strategy<Value = String> {
+    let segment = prop_oneof![
+        "[abcXYZ019_=(),.+/-]{0,6}",
+        r#"\\["' ()=]"#,
+        r#""[ab01 ()=,'-]{0,8}""#,
+        r#"'[ab01 ()=,"\\-]{0,8}'"#,
+    ];
+    prop::collection::vec(segment, 0..=max_segments).prop_map(|segments| segments.concat())
+}
+
+fn define() -> impl Strategy<Value = Define> {
+    (name(), prop::option::weighted(2.0 / 3.0, shell_text(4))).prop_map(|(name, value)| Define { name, value })
+}
+
+// Path argument of a flag, not starting with a dash
+fn path() -> impl Strategy<Value = String> {
+    ("[abc/._]", shell_text(3)).prop_map(|(first, rest)| first + &rest)
+}
+
+fn render_define(define: &Define) -> String {
+    match &define.value {
+        Some(value) => format!("-D{}={}", define.name, value),
+        None => format!("-D{}", define.name),
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(CASES))]
+
+    #[test]
+    fn defines_are_recovered_from_compile_lines(defines in prop::collection::vec(define(), 1..=5)) {
+        let rendered = defines.iter().map(render_define).collect::<Vec<_>>().join(" ");
+        let line = format!("clang -c -Oz {} -Iinclude -o build/main.o src/main.c", rendered);
+        let (parsed, cflags) = parse_compile_line(&line);
+        prop_assert_eq!(parsed, defines, "{}", line);
+        prop_assert_eq!(cflags, ["-c", "-Oz", "-o"], "{}", line);
+    }
+
+    #[test]
+    fn separate_flag_arguments_are_recovered_from_compile_lines(
+        (target, dep_file, include, object) in (path(), path(), path(), path()),
+        define in define(),
+    ) {
+        let line = format!(
+            "clang -c -MMD -MT {} -MF {} {} -I {} -o {} src/main.c",
+            target,
+            dep_file,
+            render_define(&define),
+            include,
+            object
+        );
+        prop_assert_eq!(flag_argument(&line, "-MT"), Some(target), "{}", line);
+        prop_assert_eq!(flag_argument(&line, "-MF"), Some(dep_file), "{}", line);
+        prop_assert_eq!(parse_object(&line), Some(object), "{}", line);
+        prop_assert_eq!(parse_includes(&line), [include], "{}", line);
+        prop_assert_eq!(parse_source(&line), Some("src/main.c".to_string()), "{}", line);
+        let (defines, cflags) = parse_compile_line(&line);
+        prop_assert_eq!(defines, [define], "{}", line);
+        prop_assert_eq!(cflags, ["-c", "-MMD", "-MT", "-MF", "-o"], "{}", line);
+    }
+}
@@ -0,0 +1,30 @@
+use cbpx::{files::Define, properties::render_properties};
+
+fn define(name: &str, value: Option<&str>) -> Define {
+    Define { name: name.to_string(), value: value.map(str::to_string) }
+}
+
+#[test]
+fn defines_are_sorted_and_cflags_indexed() {
+    let defines = [define("HAVE_SHA3", None), define("API_LEVEL", Some("23")), define("EMPTY", Some("")), define("API_LEVEL", Some("24"))];
+    let cflags = ["-fomit-frame-pointer".to_string(), "-mcpu=cortex-m35p+nodsp".to_string()];
+    assert_eq!(
+        render_properties(&defines, &cflags),
+        "define.API_LEVEL=24\ndefine.EMPTY=\ndefine.HAVE_SHA3=1\ncflag.0=-fomit-frame-pointer\ncflag.1=-mcpu\\=cortex-m35p+nodsp\n"
+    );
+}
+
+#[test]
+fn special_characters_are_escaped() {
+    let defines = [
+        define("APPNAME", Some("\\\"Bôilerplate\\\"")),
+        define("TICKER", Some(" ETH #1: 𝄞 ")),
+        define("PRINTF(...)", Some("mcu_usb_printf(__VA_ARGS__)")),
+    ];
+    assert_eq!(
+        render_properties(&defines, &[]),
+        "define.APPNAME=\\\\\"B\\u00F4ilerplate\\\\\"\n\
+         define.PRINTF(...)=mcu_usb_printf(__VA_ARGS__)\n\
+         define.TICKER=\\ ETH \\#1\\: \\uD834\\uDD1E\\u0020\n"
+    );
+}
@@ -0,0 +1,61 @@
+use std::{fs, path::Path, sync::Arc};
+
+use cbpx::{
+    backend::SavedTrace,
+    device::find_device,
+    extract::{extract, ExtractOptions},
+    migrate::{migrate_defines, Normalizations},
+    quoting::{normalize_define_value, normalize_stored_value, shell_unquote},
+};
+
+#[test]
+fn the_quoting_styles_of_sdk_makefiles_give_the_same_value() {
+    // APPVERSION=\"$(APPVERSION)\", '"$(APPVERSION)"' and "\"$(APPVERSION)\""
+    for raw in [r#"\"1.2.3\""#, r#"'"1.2.3"'"#, r#""\"1.2.3\"""#] {
+        assert_eq!(normalize_define_value(raw), r#""1.2.3""#, "{}", raw);
+    }
+    // APPNAME=\"$(APPNAME)\" with APPNAME = "Boilerplate"
+    assert_eq!(normalize_define_value(r#"\""Boilerplate"\""#), r#""Boilerplate""#);
+    // Escapes within the string are rewritten canonically, a literal with
+    // unrecognized ones is kept as is
+    assert_eq!(normalize_define_value(r#"'"tab\011"'"#), r#""tab\t""#);
+    assert_eq!(normalize_define_value(r#"'"a\x5c"'"#), r#""a\x5c""#);
+    // Other values only lose their shell quoting
+    assert_eq!(normalize_define_value("22"), "22");
+    assert_eq!(normalize_define_value(r"a\ b"), "a b");
+    assert_eq!(normalize_define_value(r#""(1 << 2)""#), "(1 << 2)");
+    assert_eq!(shell_unquote(r#""a\$b\n""#), r"a$b\n");
+}
+
+#[test]
+fn stored_values_are_normalized_once() {
+    assert_eq!(normalize_stored_value(r#"\""Boilerplate"\""#), r#""Boilerplate""#);
+    assert_eq!(normalize_stored_value(r#""Boilerplate""#), r#""Boilerplate""#);
+    assert_eq!(normalize_stored_value(r#""a\"b""#), r#""a\"b""#);
+
+    let values = Normalizations { values: true, ..Default::default() };
+    let migrated = migrate_defines("#define APPNAME \\\"App\\\"\n#define API_LEVEL 22\n", &values).unwrap();
+    assert_eq!(migrated.contents, "#define APPNAME \"App\"\n#define API_LEVEL 22\n");
+    assert_eq!(migrated.changes, ["normalized the quoting of 1 define value(s)"]);
+    assert!(migrate_defines(&migrated.contents, &values).unwrap().changes.is_empty());
+}
+
+#[test]
+fn raw_values_keep_the_quoting_of_the_trace() {
+    let root = std::env::temp_dir().join(format!("cbpx-quoting-{}", std::process::id()));
+    fs::create_dir_all(&root).unwrap();
+    let trace = root.join("trace.txt");
+    fs::write(&trace, "clang -c -DA=\\\"1.2.3\\\" -DB='\"1.2.3\"' -DC=\"\\\"1.2.3\\\"\" -o main.o main.c\n").unwrap();
+    let mut options = ExtractOptions::new("/sdk");
+    options.backend = Arc::new(SavedTrace { path: trace });
+    let app = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/app");
+    let stax = find_device("stax").unwrap();
+
+    let values = |options: &ExtractOptions| {
+        extract(stax, &app, options).unwrap().defines.into_iter().map(|d| d.value.unwrap()).collect::<Vec<_>>()
+    };
+    assert_eq!(values(&options), [r#""1.2.3""#; 3]);
+    options.raw_values = true;
+    assert_eq!(values(&options), [r#"\"1.2.3\""#, r#"'"1.2.3"'"#, r#""\"1.2.3\"""#]);
+    fs::remove_dir_all(&root).unwrap();
+}
@@ -0,0 +1,67 @@
+use std::{fs, path::Path, process::Command};
+
+use cbpx::{audit::raw_defines, device::find_device, params::BuildParameters};
+
+// Defines of the traces left out by the default filter, and moved to the
+// metadata for their length
+const FILTERED: [&str; 6] = ["APPVERSION", "MAJOR_VERSION", "MINOR_VERSION", "PATCH_VERSION", "SDK_HASH", "SDK_VERSION"];
+const MOVED: [&str; 1] = ["APP_INSTALL_PARAMS_DATA"];
+
+// Extraction of the recorded trace of every device by the binary, whose
+// outputs have to be the checked-in references byte for byte. A change of
+// the output format updates the references along with it.
+#[test]
+fn recorded_traces_give_the_references() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    for name in ["nanox", "nanosplus", "stax", "flex", "apex_p"] {
+        let device = find_device(name).unwrap();
+        let root = std::env::temp_dir().join(format!("cbpx-references-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let (app, sdk) = (root.join("app"), root.join("sdk"));
+        fs::create_dir_all(&app).unwrap();
+        for dir in [format!("target/{}", device.target), "lib_nbgl".to_string()] {
+            fs::create_dir_all(sdk.join(dir)).unwrap();
+        }
+        for file in ["Makefile.defines", "Makefile.rules"] {
+            fs::write(sdk.join(file), "").unwrap();
+        }
+        fs::write(app.join("Makefile"), "include $(BOLOS_SDK)/Makefile.standard_app\n").unwrap();
+        // The traces are recorded with the SDK and app paths made portable
+        let trace = fs::read_to_string(manifest_dir.join("tests/traces").join(format!("{}.trace", name)))
+            .unwrap()
+            .replace("${BOLOS_SDK}", &sdk.display().to_string())
+            .replace("${APP_DIR}", &app.display().to_string());
+        fs::write(root.join("make.trace"), trace).unwrap();
+
+        let output = Command::new(env!("CARGO_BIN_EXE_cbpx"))
+            .args(["--app-path", "app", "--device", name, "--trace-file", "make.trace", "--output-dir", "out"])
+            .current_dir(&root)
+            .env(device.sdk_env, &sdk)
+            .env_remove("BOLOS_SDK")
+            .output()
+            .unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(output.status.success(), "{}: {}", name, stderr);
+        // Every entry of the default filter matches a define of the trace
+        assert!(!stderr.contains("stale filter entry"), "{}: {}", name, stderr);
+        for artifact in ["defines", "cflags"] {
+            let file = format!("c_sdk_build_{}.{}", name, artifact);
+            let expected = fs::read_to_string(manifest_dir.join("references").join(&file)).unwrap();
+            let actual = fs::read_to_string(root.join("out").join(&file)).unwrap();
+            assert!(actual == expected, "{} differs from the reference:\n{}", file, actual);
+        }
+
+        let json = fs::read_to_string(root.join("out").join(format!("c_sdk_build_{}.json", name))).unwrap();
+        let params: BuildParameters = serde_json::from_str(&json).unwrap();
+        let moved = params.metadata.moved_defines.iter().map(|d| d.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(moved, MOVED, "{}", name);
+        let mut filtered = raw_defines(&params.raw_commands)
+            .into_iter()
+            .map(|d| d.name)
+            .filter(|n| !params.defines.iter().any(|d| d.name == *n) && !moved.contains(&n.as_str()))
+            .collect::<Vec<_>>();
+        filtered.sort();
+        assert_eq!(filtered, FILTERED, "{}", name);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
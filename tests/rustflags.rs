@@ -0,0 +1,35 @@
+use cbpx::{
+    files::Define,
+    rustflags::{capability_cfgs, cfg_name, render_rustflags},
+};
+
+fn define(name: &str, value: Option<&str>) -> Define {
+    Define { name: name.to_string(), value: value.map(|v| v.to_string()) }
+}
+
+#[test]
+fn cfg_names_are_prefixed_lowercase_identifiers() {
+    assert_eq!(cfg_name("HAVE_SHA3"), "csdk_have_sha3");
+    assert_eq!(cfg_name("HAVE_AES-GCM"), "csdk_have_aes_gcm");
+    assert_eq!(cfg_name("HAVE_NBGL.v2"), "csdk_have_nbgl_v2");
+}
+
+#[test]
+fn only_boolean_capabilities_become_cfgs() {
+    let defines = [
+        define("HAVE_SHA3", None),
+        define("HAVE_AES_GCM", Some("1")),
+        define("HAVE_BAGL_FONT", Some("2")),
+        define("API_LEVEL", Some("22")),
+        define("DEBUG", None),
+    ];
+    let cfgs = capability_cfgs(&defines);
+    assert_eq!(cfgs.iter().collect::<Vec<_>>(), ["csdk_have_aes_gcm", "csdk_have_sha3"]);
+
+    let mut known = cfgs.clone();
+    known.insert("csdk_have_nbgl".to_string());
+    assert_eq!(
+        render_rustflags(&cfgs, &known),
+        "--cfg csdk_have_aes_gcm --cfg csdk_have_sha3\n--check-cfg cfg(csdk_have_aes_gcm,csdk_have_nbgl,csdk_have_sha3)\n"
+    );
+}
@@ -0,0 +1,107 @@
+use serde::{
+    de::{self, Deserialize, Deserializer, Visitor},
+    forward_to_deserialize_any,
+};
+use sha2::{Digest, Sha256};
+
+use cbpx::{
+    api_level::ApiLevelCheck,
+    app_flags::AppFlags,
+    compiler::CompilerInfo,
+    display::Display,
+    files::Define,
+    flags::OverriddenWarning,
+    linker::MemoryLayout,
+    params::{BuildParameters, Metadata, SCHEMA_VERSION},
+    parse_issue::ParseIssue,
+    scope::AppOnly,
+    trace::{DefineOverride, Toolchain},
+    unit::Occurrences,
+};
+
+// Every schema version with the digest of its fields. A change of the fields,
+// of their order or of their serialized names appends an entry with the next
+// version, SCHEMA_VERSION being bumped to it.
+const SCHEMA_HISTORY: &[(u32, &str)] = &[(16, "7452936cb3a6dc53064d749e232dd514fce1ea0f217d0eb6dcaa70fb8e696070")];
+
+// Deserializer recording the name and the fields of the type asking for a
+// struct or an enum, and failing there
+#[derive(Default)]
+struct FieldRecorder {
+    recorded: Option<String>,
+}
+
+impl<'de> Deserializer<'de> for &mut FieldRecorder {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(de::Error::custom("not a struct"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.recorded = Some(format!("{}: {}", name, fields.join(", ")));
+        Err(de::Error::custom("recorded"))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_struct(name, variants, visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf option unit
+        unit_struct newtype_struct seq tuple tuple_struct map identifier ignored_any
+    }
+}
+
+fn fields<'de, T: Deserialize<'de>>() -> String {
+    let mut recorder = FieldRecorder::default();
+    let _ = T::deserialize(&mut recorder);
+    recorder.recorded.expect("a struct or an enum")
+}
+
+fn schema() -> String {
+    [
+        fields::<BuildParameters>(),
+        fields::<Metadata>(),
+        fields::<Define>(),
+        fields::<Occurrences>(),
+        fields::<AppOnly>(),
+        fields::<CompilerInfo>(),
+        fields::<Toolchain>(),
+        fields::<MemoryLayout>(),
+        fields::<Display>(),
+        fields::<AppFlags>(),
+        fields::<ApiLevelCheck>(),
+        fields::<OverriddenWarning>(),
+        fields::<DefineOverride>(),
+        fields::<ParseIssue>(),
+    ]
+    .join("\n")
+}
+
+#[test]
+fn schema_changes_bump_the_schema_version() {
+    let digest = Sha256::digest(schema().as_bytes()).iter().map(|b| format!("{:02x}", b)).collect::<String>();
+    let versions = SCHEMA_HISTORY.iter().map(|(version, _)| *version).collect::<Vec<_>>();
+    assert!(versions.windows(2).all(|w| w[0] < w[1]), "the versions of SCHEMA_HISTORY have to increase");
+    let &(version, recorded) = SCHEMA_HISTORY.last().unwrap();
+    assert_eq!(version, SCHEMA_VERSION, "the last entry of SCHEMA_HISTORY is not the current SCHEMA_VERSION");
+    assert!(
+        recorded == digest,
+        "The schema changed since version {}, bump SCHEMA_VERSION and append ({}, \"{}\") to SCHEMA_HISTORY:\n{}",
+        version,
+        version + 1,
+        digest,
+        schema()
+    );
+}
@@ -0,0 +1,47 @@
+use std::{path::Path, sync::Arc};
+
+use cbpx::{
+    backend::SavedTrace,
+    device::find_device,
+    extract::{extract, ExtractError, ExtractOptions},
+    files::Define,
+    scope::Scope,
+    unit::Strategy,
+};
+
+fn options(trace: &str) -> ExtractOptions {
+    let mut options = ExtractOptions::new("/sdk");
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(trace);
+    options.backend = Arc::new(SavedTrace { path });
+    options.strategy = Strategy::Union;
+    options.scope = Scope::App;
+    options
+}
+
+fn app() -> std::path::PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/app")
+}
+
+#[test]
+fn app_parameters_are_the_ones_sdk_sources_lack() {
+    let params = extract(find_device("stax").unwrap(), &app(), &options("app_scope_trace.txt")).unwrap();
+    let app_only = params.app_only.unwrap();
+    let define = |name: &str, value: Option<&str>| Define { name: name.to_string(), value: value.map(str::to_string) };
+    assert_eq!(app_only.defines, [define("HAVE_APP_FEATURE", None), define("APP_VERSION", Some("\"1.2.0\""))]);
+    assert_eq!(app_only.cflags, ["-Wshadow"]);
+    assert_eq!(app_only.includes, ["src/plugins"]);
+    // The combined parameters are left as they are
+    assert!(params.defines.iter().any(|d| d.name == "HAVE_NBGL"));
+
+    let mut options = options("app_scope_trace.txt");
+    options.scope = Scope::All;
+    assert!(extract(find_device("stax").unwrap(), &app(), &options).unwrap().app_only.is_none());
+}
+
+#[test]
+fn app_parameters_need_sdk_compile_lines() {
+    match extract(find_device("stax").unwrap(), &app(), &options("warnings_trace.txt")) {
+        Err(ExtractError::Scope(message)) => assert!(message.contains("1 compile line(s) of app sources and 0 of SDK sources"), "{}", message),
+        other => panic!("unexpected result {:?}", other.map(|p| p.device)),
+    }
+}
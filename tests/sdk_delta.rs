@@ -0,0 +1,31 @@
+use std::{path::Path, sync::Arc};
+
+use cbpx::{
+    backend::SavedTrace, device::parse_device, diff::CompareOptions, extract::ExtractOptions, sdk_delta::sdk_delta,
+};
+
+// Extraction with `sdk` replaying a recorded trace
+fn options(sdk: &str, trace: &str) -> ExtractOptions {
+    let mut options = ExtractOptions::new(sdk);
+    options.backend = Arc::new(SavedTrace { path: Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(trace) });
+    options
+}
+
+#[test]
+fn both_sdks_are_extracted_and_compared() {
+    let device = parse_device("stax").unwrap();
+    let app = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/app");
+    let old = options("/sdk-old", "gcc_trace.txt");
+
+    let same = sdk_delta(device, &app, &old, &options("/sdk-new", "gcc_trace.txt"), &CompareOptions::default()).unwrap();
+    assert!(same.diff.is_empty());
+    assert_eq!(same.header(), "stax: old SDK /sdk-old (not a git checkout) → new SDK /sdk-new (not a git checkout)");
+
+    let new = options("/sdk-new", "warnings_trace.txt");
+    let delta = sdk_delta(device, &app, &old, &new, &CompareOptions::default()).unwrap();
+    assert!(!delta.diff.is_empty());
+    assert_eq!((delta.old.sdk.as_str(), delta.new.sdk.as_str()), ("/sdk-old", "/sdk-new"));
+
+    let missing = options("/sdk-new", "missing_trace.txt");
+    assert!(sdk_delta(device, &app, &old, &missing, &CompareOptions::default()).is_err_and(|e| e.starts_with("With the new SDK")));
+}
@@ -0,0 +1,25 @@
+use std::fs;
+
+use cbpx::{device::find_device, sdk_layout::missing_sdk_files};
+
+#[test]
+fn partial_checkouts_are_reported_with_what_is_missing() {
+    let sdk = std::env::temp_dir().join(format!("cbpx-sdk-layout-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&sdk);
+    fs::create_dir_all(sdk.join("target/stax")).unwrap();
+    fs::create_dir_all(sdk.join("lib_bagl")).unwrap();
+    fs::write(sdk.join("Makefile.defines"), "").unwrap();
+
+    let stax = find_device("stax").unwrap();
+    assert_eq!(missing_sdk_files(stax, &sdk), ["Makefile.rules (the build rules)", "lib_nbgl/ (the NBGL UI library)"]);
+    // BAGL is enough on the Nano devices
+    assert_eq!(
+        missing_sdk_files(find_device("nanosplus").unwrap(), &sdk),
+        ["Makefile.rules (the build rules)", "target/nanos2/ (the target directory of the device)"]
+    );
+
+    fs::write(sdk.join("Makefile.rules"), "").unwrap();
+    fs::create_dir_all(sdk.join("lib_nbgl")).unwrap();
+    assert!(missing_sdk_files(stax, &sdk).is_empty());
+    fs::remove_dir_all(&sdk).unwrap();
+}
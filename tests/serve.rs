@@ -0,0 +1,70 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{BufRead, BufReader, Write},
+    process::{Command, Stdio},
+};
+
+use serde_json::{json, Value};
+
+#[test]
+fn requests_are_answered_over_stdio_until_eof() {
+    let root = std::env::temp_dir().join(format!("cbpx-serve-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    let app = root.join("app");
+    fs::create_dir_all(&app).unwrap();
+    for dir in ["sdk/target/stax", "sdk/target/flex", "sdk/lib_nbgl"] {
+        fs::create_dir_all(root.join(dir)).unwrap();
+    }
+    for file in ["sdk/Makefile.defines", "sdk/Makefile.rules"] {
+        fs::write(root.join(file), "").unwrap();
+    }
+    fs::write(
+        app.join("Makefile"),
+        "include $(BOLOS_SDK)/Makefile.defines\nall:\n\tclang -c -DHAVE_X -DTARGET_$(TARGET) -Oz -o build/main.o src/main.c\n",
+    )
+    .unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_cbpx"))
+        .args(["serve", "--stdio"])
+        .env("STAX_SDK", root.join("sdk"))
+        .env_remove("FLEX_SDK")
+        .env_remove("BOLOS_SDK")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let requests = [
+        json!({"id": "early", "cmd": "extract", "device": "stax", "app_path": app}),
+        json!({"id": "hello", "cmd": "hello", "protocol": 1}),
+        json!({"id": "stax", "cmd": "extract", "device": "stax", "app_path": app}),
+        json!({"id": "flex", "cmd": "extract", "device": "flex", "app_path": app, "sdk": root.join("sdk")}),
+        json!({"id": "nope", "cmd": "extract", "device": "nope", "app_path": app}),
+    ];
+    let mut stdin = child.stdin.take().unwrap();
+    for request in &requests {
+        writeln!(stdin, "{}", request).unwrap();
+    }
+    // The end of the input shuts the server down once everything is answered
+    drop(stdin);
+
+    let responses = BufReader::new(child.stdout.take().unwrap())
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(&line.unwrap()).unwrap())
+        .map(|response| (response["id"].as_str().unwrap().to_string(), response))
+        .collect::<BTreeMap<_, _>>();
+    assert!(child.wait().unwrap().success());
+    assert_eq!(responses.len(), requests.len());
+
+    assert_eq!(responses["early"]["error"]["kind"], "protocol");
+    assert_eq!(responses["hello"], json!({"id": "hello", "ok": true, "protocol": 1, "version": env!("CARGO_PKG_VERSION")}));
+    for device in ["stax", "flex"] {
+        let response = &responses[device];
+        assert_eq!(response["ok"], true, "{}", response);
+        assert_eq!(response["params"]["device"], device);
+    }
+    assert!(responses["stax"]["params"]["defines"].as_array().unwrap().iter().any(|d| d["name"] == "HAVE_X"));
+    assert_eq!(responses["nope"]["ok"], false);
+    assert_eq!(responses["nope"]["error"]["kind"], "request");
+    fs::remove_dir_all(&root).unwrap();
+}
@@ -0,0 +1,20 @@
+use cbpx::shrink::ShrinkThresholds;
+
+#[test]
+fn counts_well_below_the_reference_are_suspicious() {
+    let thresholds = ShrinkThresholds::default();
+    assert_eq!(
+        thresholds.check("defines", 12, Some(90)).as_deref(),
+        Some("12 defines against 90 in the reference, less than 50%")
+    );
+    assert_eq!(thresholds.check("defines", 45, Some(90)), None);
+    assert_eq!(thresholds.check("cflags", 3, None), None);
+}
+
+#[test]
+fn thresholds_are_configurable() {
+    let thresholds: ShrinkThresholds = serde_json::from_str(r#"{"min_ratio": 0.9, "min_cflags": 10}"#).unwrap();
+    assert!(thresholds.check("defines", 80, Some(90)).unwrap().ends_with("less than 90%"));
+    assert_eq!(thresholds.check("cflags", 3, None).as_deref(), Some("3 cflags instead of at least 10"));
+    assert!(serde_json::from_str::<ShrinkThresholds>(r#"{"ratio": 0.9}"#).is_err());
+}
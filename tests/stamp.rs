@@ -0,0 +1,132 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .args(["-c", "user.name=cbpx", "-c", "user.email=cbpx@example.com"])
+        .args(args)
+        .current_dir(dir)
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+// An app and an SDK which is a git checkout, as the stamps record its commit,
+// with just the layout checked before running make
+fn setup(name: &str) -> (PathBuf, PathBuf, PathBuf) {
+    let root = std::env::temp_dir().join(format!("cbpx-stamp-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    let (sdk, app) = (root.join("sdk"), root.join("app"));
+    for dir in [sdk.join("target/stax"), sdk.join("lib_nbgl"), app.clone()] {
+        fs::create_dir_all(dir).unwrap();
+    }
+    for file in ["Makefile.defines", "Makefile.rules", "target/stax/.keep", "lib_nbgl/.keep"] {
+        fs::write(sdk.join(file), "").unwrap();
+    }
+    git(&sdk, &["init", "-q"]);
+    git(&sdk, &["add", "-A"]);
+    git(&sdk, &["commit", "-q", "-m", "SDK"]);
+    fs::copy(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/app/Makefile"), app.join("Makefile")).unwrap();
+    (root, sdk, app)
+}
+
+#[test]
+fn unchanged_inputs_skip_make() {
+    let (root, sdk, app) = setup("make");
+    let run = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_cbpx"))
+            .args(["--app-path", "app", "--device", "stax", "--reference-dir", "refs", "--stamp-file", "stamps.json"])
+            .args(args)
+            .current_dir(&root)
+            .env("STAX_SDK", &sdk)
+            .env_remove("BOLOS_SDK")
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).contains("app stax: unchanged (stamp)")
+    };
+    // Updating the references never skips make nor stamps
+    assert!(!run(&["--update-references"]));
+    assert!(!root.join("stamps.json").exists());
+
+    assert!(!run(&[]));
+    let stamps: serde_json::Value = serde_json::from_str(&fs::read_to_string(root.join("stamps.json")).unwrap()).unwrap();
+    assert_eq!(stamps["entries"].as_object().unwrap().len(), 1);
+    assert!(run(&[]));
+    assert!(!run(&["--no-stamp"]));
+
+    // Any input change invalidates the stamp: the app Makefile and the
+    // fragments it includes, the SDK working tree, its commit and the options
+    fs::write(app.join("Makefile"), fs::read_to_string(app.join("Makefile")).unwrap() + "-include extra.mk\n").unwrap();
+    assert!(!run(&[]));
+    assert!(run(&[]));
+    fs::write(app.join("extra.mk"), "# changed\n").unwrap();
+    assert!(!run(&[]));
+    assert!(run(&[]));
+    fs::write(sdk.join("Makefile.rules"), "# changed\n").unwrap();
+    assert!(!run(&[]));
+    assert!(run(&[]));
+    git(&sdk, &["commit", "-q", "-am", "SDK update"]);
+    assert!(!run(&[]));
+    assert!(run(&[]));
+    assert!(!run(&["--keep-large-defines"]));
+    assert!(!run(&["--allow-shrink"]));
+    assert!(!run(&["--require-clean-sdk"]));
+
+    // Post hooks run every time, the stamps being ignored
+    assert!(run(&["--require-clean-sdk"]));
+    assert!(!run(&["--require-clean-sdk", "--post-hook", "true"]));
+
+    // A mismatching run removes its stamp
+    fs::write(root.join("refs/c_sdk_build_stax.defines"), "#define HAVE_Y\n").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_cbpx"))
+        .args(["--app-path", "app", "--device", "stax", "--reference-dir", "refs", "--stamp-file", "stamps.json"])
+        .current_dir(&root)
+        .env("STAX_SDK", &sdk)
+        .env_remove("BOLOS_SDK")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stamps: serde_json::Value = serde_json::from_str(&fs::read_to_string(root.join("stamps.json")).unwrap()).unwrap();
+    assert!(stamps["entries"].as_object().unwrap().is_empty());
+    fs::remove_dir_all(&root).unwrap();
+}
+
+// The recorded trace replaces make, its contents being an input
+#[test]
+fn changed_trace_file_invalidates_the_stamp() {
+    let (root, sdk, _) = setup("trace");
+    let line = "clang -c -Oz -DHAVE_NBGL -Iinclude -o build/obj/main.o src/main.c\n";
+    fs::write(root.join("a.trace"), line).unwrap();
+    fs::write(root.join("b.trace"), line.replace("-DHAVE_NBGL", "-DHAVE_NBGL -DHAVE_EVIL")).unwrap();
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_cbpx"))
+            .args(["--app-path", "app", "--device", "stax", "--reference-dir", "refs", "--stamp-file", "stamps.json"])
+            .args(args)
+            .current_dir(&root)
+            .env("STAX_SDK", &sdk)
+            .env_remove("BOLOS_SDK")
+            .output()
+            .unwrap()
+    };
+    let output = run(&["--trace-file", "a.trace", "--update-references"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let output = run(&["--trace-file", "a.trace"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let output = run(&["--trace-file", "a.trace"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("app stax: unchanged (stamp)"));
+
+    let output = run(&["--trace-file", "b.trace"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("+ define HAVE_EVIL"));
+
+    // Neither is the stamp of the trace used when running make
+    let output = run(&["--trace-file", "a.trace"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let output = run(&[]);
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("unchanged (stamp)"));
+    fs::remove_dir_all(&root).unwrap();
+}
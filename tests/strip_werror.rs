@@ -0,0 +1,52 @@
+use std::{fs, process::Command};
+
+use cbpx::flags::strip_werror;
+
+#[test]
+fn werror_flags_are_split_out() {
+    let cflags = ["-Wall", "-Werror", "-Werror=int-to-pointer-cast", "-Wno-error=int-conversion", "-Oz"].map(String::from);
+    let (kept, stripped) = strip_werror(&cflags);
+    assert_eq!(kept, ["-Wall", "-Wno-error=int-conversion", "-Oz"]);
+    assert_eq!(stripped, ["-Werror", "-Werror=int-to-pointer-cast"]);
+}
+
+#[test]
+fn only_the_consumer_outputs_are_stripped() {
+    let root = std::env::temp_dir().join(format!("cbpx-strip-werror-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    let app = root.join("app");
+    fs::create_dir_all(&app).unwrap();
+    for dir in ["sdk/target/stax", "sdk/lib_nbgl"] {
+        fs::create_dir_all(root.join(dir)).unwrap();
+    }
+    for file in ["sdk/Makefile.defines", "sdk/Makefile.rules"] {
+        fs::write(root.join(file), "").unwrap();
+    }
+    fs::write(
+        app.join("Makefile"),
+        "include $(BOLOS_SDK)/Makefile.defines\n\
+         all:\n\tclang -c -DHAVE_X -Wall -Werror -Werror=int-to-pointer-cast -Oz -o build/main.o src/main.c\n",
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_cbpx"))
+        .args(["--app-path", "app", "--device", "stax", "--reference-dir", "refs", "--update-references"])
+        .args(["--format", "properties", "--strip-werror"])
+        .current_dir(&root)
+        .env("STAX_SDK", root.join("sdk"))
+        .env_remove("BOLOS_SDK")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let canonical = "-c\n-Wall\n-Werror\n-Werror=int-to-pointer-cast\n-Oz\n-o\n";
+    assert_eq!(fs::read_to_string(root.join("c_sdk_build_stax.cflags")).unwrap(), canonical);
+    assert_eq!(fs::read_to_string(root.join("refs/c_sdk_build_stax.cflags")).unwrap(), canonical);
+    let properties = fs::read_to_string(root.join("c_sdk_build_stax.properties")).unwrap();
+    assert!(properties.contains("cflag.1=-Wall\ncflag.2=-Oz\n"), "{}", properties);
+    assert!(!properties.contains("-Werror"), "{}", properties);
+    let json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(root.join("c_sdk_build_stax.json")).unwrap()).unwrap();
+    assert_eq!(json["metadata"]["werror_stripped"], serde_json::json!(["-Werror", "-Werror=int-to-pointer-cast"]));
+    fs::remove_dir_all(&root).unwrap();
+}
@@ -0,0 +1,21 @@
+use cbpx::{
+    device::find_device,
+    target_id::{check_target, TargetExpectation},
+};
+
+#[test]
+fn matching_target_defines_are_not_reported() {
+    let stax = find_device("stax").unwrap();
+    assert!(check_target(stax, None, Some("0x33200004"), Some("\\\"TARGET_STAX\\\"")).is_empty());
+    assert!(check_target(stax, None, None, None).is_empty());
+}
+
+#[test]
+fn deviations_from_the_expectation_are_reported() {
+    let stax = find_device("stax").unwrap();
+    let expectation = TargetExpectation { target_id: Some("0x33200005".to_string()), target_name: None };
+    let warnings = check_target(stax, Some(&expectation), Some("0x33200004"), Some("TARGET_stax"));
+    assert_eq!(warnings.len(), 2);
+    assert!(warnings[0].starts_with("TARGET_ID of stax is 0x33200004 instead of 0x33200005"));
+    assert!(warnings[1].starts_with("TARGET_NAME of stax is TARGET_stax instead of TARGET_STAX"));
+}
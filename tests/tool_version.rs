@@ -0,0 +1,67 @@
+use std::{fs, path::Path, process::Command};
+
+use cbpx::tool_version::{predates, read_tool_version, TOOL_VERSION_FILE};
+
+#[test]
+fn older_versions_predate_the_current_one() {
+    assert!(predates("0.0.9", "0.1.0"));
+    assert!(predates("0.1.0-rc.1", "0.2.0"));
+    assert!(predates("unknown", "0.1.0"));
+    assert!(!predates("0.1.0", "0.1.0"));
+    assert!(!predates("1.0.0", "0.1.0"));
+}
+
+// Extract an app into the references, then break its defines reference so
+// that the next run mismatches, returning the standard error of that run
+fn mismatch_with_references_of(root: &Path, recorded: Option<&str>) -> String {
+    let _ = fs::remove_dir_all(root);
+    let app = root.join("app");
+    fs::create_dir_all(&app).unwrap();
+    for dir in ["sdk/target/stax", "sdk/lib_nbgl"] {
+        fs::create_dir_all(root.join(dir)).unwrap();
+    }
+    for file in ["sdk/Makefile.defines", "sdk/Makefile.rules"] {
+        fs::write(root.join(file), "").unwrap();
+    }
+    fs::write(
+        app.join("Makefile"),
+        "include $(BOLOS_SDK)/Makefile.defines\nall:\n\tclang -c -DHAVE_X -Oz -o build/main.o src/main.c\n",
+    )
+    .unwrap();
+    let run = |update: bool| {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_cbpx"));
+        command.args(["--app-path", "app", "--device", "stax", "--reference-dir", "refs", "--allow-shrink"]);
+        if update {
+            command.arg("--update-references");
+        }
+        command.current_dir(root).env("STAX_SDK", root.join("sdk")).env_remove("BOLOS_SDK").output().unwrap()
+    };
+
+    assert!(run(true).status.success());
+    assert_eq!(read_tool_version(&root.join("refs")).as_deref(), Some(env!("CARGO_PKG_VERSION")));
+    if let Some(version) = recorded {
+        fs::write(root.join("refs").join(TOOL_VERSION_FILE), format!("{}\n", version)).unwrap();
+    }
+    fs::write(root.join("refs/c_sdk_build_stax.defines"), "#define HAVE_Y\n").unwrap();
+    let output = run(false);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    fs::remove_dir_all(root).unwrap();
+    stderr
+}
+
+#[test]
+fn mismatch_with_older_references_suggests_rebaselining() {
+    let root = std::env::temp_dir().join(format!("cbpx-tool-version-gap-{}", std::process::id()));
+    let stderr = mismatch_with_references_of(&root, Some("0.0.1"));
+    assert!(stderr.contains("were written by cbpx 0.0.1, before parser changes"), "{}", stderr);
+    assert!(stderr.contains("--update-references"), "{}", stderr);
+}
+
+#[test]
+fn mismatch_with_current_references_blames_nothing_else() {
+    let root = std::env::temp_dir().join(format!("cbpx-tool-version-same-{}", std::process::id()));
+    let stderr = mismatch_with_references_of(&root, None);
+    assert!(stderr.contains("does not match reference"), "{}", stderr);
+    assert!(!stderr.contains("parser changes"), "{}", stderr);
+}
@@ -0,0 +1,123 @@
+use cbpx::{
+    files::Define,
+    trace::{define_overrides, find_compile_lines, parse_compile_line, DefineOverride},
+    unit::{merge_units, occurrences, parse_compile_unit, Strategy},
+};
+
+const TRACE: &str = "\
+Makefile:10: target 'build/stax/obj/app/src/main.o' does not exist
+clang -c -Oz -fropi -DHAVE_NBGL -DAPI_LEVEL=22 -Iinclude -I sdk/include -o build/stax/obj/app/src/main.o src/main.c
+clang -c -Oz -fropi -Wno-shadow -DHAVE_NBGL -DAPI_LEVEL=22 -Iinclude -o build/stax/obj/sdk/ux.o sdk/lib_ux/ux.c
+clang -c -Os -fropi -DHAVE_NBGL -DAPI_LEVEL=21 -DDEBUG=1 -Iinclude -MF build/stax/dep/io.d -o build/stax/obj/sdk/io.o sdk/io.S
+";
+
+fn units() -> Vec<cbpx::unit::CompileUnit> {
+    find_compile_lines(TRACE.as_bytes(), false).unwrap().iter().map(|l| parse_compile_unit(l)).collect()
+}
+
+fn names(defines: &[Define]) -> Vec<String> {
+    defines.iter().map(|d| format!("{}={}", d.name, d.value.as_deref().unwrap_or(""))).collect()
+}
+
+#[test]
+fn sources_and_objects_are_associated() {
+    let units = units();
+    let pairs = units.iter().map(|u| (u.source.as_deref(), u.object.as_deref())).collect::<Vec<_>>();
+    assert_eq!(
+        pairs,
+        vec![
+            (Some("src/main.c"), Some("build/stax/obj/app/src/main.o")),
+            (Some("sdk/lib_ux/ux.c"), Some("build/stax/obj/sdk/ux.o")),
+            (Some("sdk/io.S"), Some("build/stax/obj/sdk/io.o")),
+        ]
+    );
+    assert_eq!(units[1].cflags, vec!["-c", "-Oz", "-fropi", "-Wno-shadow", "-o"]);
+    assert_eq!(units[0].includes, vec!["include", "sdk/include"]);
+}
+
+#[test]
+fn first_strategy_keeps_the_first_unit() {
+    let (defines, cflags, includes) = merge_units(&units(), Strategy::First);
+    assert_eq!(names(&defines), vec!["HAVE_NBGL=", "API_LEVEL=22"]);
+    assert_eq!(cflags, vec!["-c", "-Oz", "-fropi", "-o"]);
+    assert_eq!(includes, vec!["include", "sdk/include"]);
+}
+
+#[test]
+fn intersection_strategy_keeps_common_parameters() {
+    let (defines, cflags, includes) = merge_units(&units(), Strategy::Intersection);
+    assert_eq!(names(&defines), vec!["HAVE_NBGL="]);
+    assert_eq!(cflags, vec!["-c", "-fropi", "-o"]);
+    assert_eq!(includes, vec!["include"]);
+}
+
+#[test]
+fn union_strategy_keeps_every_parameter_once() {
+    let (defines, cflags, includes) = merge_units(&units(), Strategy::Union);
+    assert_eq!(names(&defines), vec!["HAVE_NBGL=", "API_LEVEL=22", "DEBUG=1"]);
+    assert_eq!(cflags, vec!["-c", "-Oz", "-fropi", "-o", "-Wno-shadow", "-Os", "-MF"]);
+    assert_eq!(includes, vec!["include", "sdk/include"]);
+}
+
+#[test]
+fn continuation_lines_are_joined() {
+    let trace = "clang -c -DHAVE_NBGL \\\n  -DAPI_LEVEL=22 -o main.o src/main.c\nclang -o app.elf main.o\n";
+    let lines = find_compile_lines(trace.as_bytes(), false).unwrap();
+    assert_eq!(lines, ["clang -c -DHAVE_NBGL   -DAPI_LEVEL=22 -o main.o src/main.c"]);
+}
+
+#[test]
+fn fragments_of_interleaved_commands_are_skipped() {
+    let trace = include_str!("fixtures/interleaved_trace.txt");
+    let lines = find_compile_lines(trace.as_bytes(), false).unwrap();
+    assert_eq!(lines, ["clang -c -Oz -fropi -DHAVE_NBGL -DAPI_LEVEL=22 -Iinclude -o build/stax/obj/sdk/io.o sdk/io.c"]);
+}
+
+#[test]
+fn undefs_apply_in_command_line_order() {
+    let (defines, cflags) = parse_compile_line("clang -c -DHAVE_X -DHAVE_Y=1 -UHAVE_X -UHAVE_Y -DHAVE_Y=2 -o a.o a.c");
+    assert_eq!(names(&defines), ["HAVE_Y=2"]);
+    assert_eq!(cflags, ["-c", "-o"]);
+    let (defines, _) = parse_compile_line("clang -c -UHAVE_X -DHAVE_X -o a.o a.c");
+    assert_eq!(names(&defines), ["HAVE_X="]);
+}
+
+#[test]
+fn occurrences_tell_outliers_from_flags_applied_everywhere() {
+    let units = units();
+    let counted = occurrences(&units, None);
+    assert_eq!(counted.units, 3);
+    assert_eq!(counted.cflags["-fropi"].count, 3);
+    assert_eq!(counted.partial_cflag("-fropi"), None);
+    assert_eq!(counted.partial_cflag("-Wno-shadow"), Some((1, 3)));
+    assert_eq!(counted.partial_define("API_LEVEL"), None);
+    assert_eq!(counted.partial_define("DEBUG"), Some((1, 3)));
+    assert!(counted.cflags["-Oz"].units.is_empty());
+
+    let names = units.iter().map(|u| u.source.clone().unwrap()).collect::<Vec<_>>();
+    let listed = occurrences(&units, Some(&names));
+    assert_eq!(listed.cflags["-Oz"].units, ["src/main.c", "sdk/lib_ux/ux.c"]);
+}
+
+#[test]
+fn undef_sequences_are_recorded() {
+    let define_override = |name: &str, sequence: &[&str], defined| DefineOverride {
+        name: name.to_string(),
+        sequence: sequence.iter().map(|w| w.to_string()).collect(),
+        defined,
+    };
+    assert_eq!(
+        define_overrides("clang -c -DHAVE_X -DHAVE_Z -UHAVE_Y -UHAVE_X -DHAVE_Y=2 -o a.o a.c"),
+        [define_override("HAVE_Y", &["-UHAVE_Y", "-DHAVE_Y=2"], true), define_override("HAVE_X", &["-DHAVE_X", "-UHAVE_X"], false)]
+    );
+    assert!(define_overrides("clang -c -DHAVE_X -o a.o a.c").is_empty());
+}
+
+#[test]
+fn carriage_returns_never_reach_the_defines() {
+    let trace = b"clang -c -DHAVE_X \\\r\n  -DNAME=a\\\r -DQUOTED=\"b\r\" -o a.o a.c\r\n";
+    let lines = find_compile_lines(trace, false).unwrap();
+    assert_eq!(lines.len(), 1);
+    let (defines, _) = parse_compile_line(&lines[0]);
+    assert_eq!(names(&defines), ["HAVE_X=", "NAME=a\\", "QUOTED=\"b"]);
+}